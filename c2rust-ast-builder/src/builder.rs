@@ -1163,6 +1163,20 @@ impl Builder {
         })
     }
 
+    pub fn tuple_struct_pat<Pa, Ps>(self, path: Pa, pats: Vec<Ps>) -> P<Pat>
+    where
+        Pa: Make<Path>,
+        Ps: Make<P<Pat>>,
+    {
+        let path = path.make(&self);
+        let pats: Vec<P<Pat>> = pats.into_iter().map(|x| x.make(&self)).collect();
+        P(Pat {
+            id: self.id,
+            kind: PatKind::TupleStruct(path, pats),
+            span: self.span,
+        })
+    }
+
     pub fn qpath_pat<Pa>(self, qself: Option<QSelf>, path: Pa) -> P<Pat>
     where
         Pa: Make<Path>,
@@ -1657,6 +1671,21 @@ impl Builder {
         )
     }
 
+    /// Create a `macro_rules!` definition
+    pub fn macro_def_item<I, Ts>(self, name: I, tts: Ts) -> P<Item>
+    where
+        I: Make<Ident>,
+        Ts: Make<TokenStream>,
+    {
+        let name = name.make(&self);
+        let tts = tts.make(&self);
+        let kind = ItemKind::MacroDef(MacroDef {
+            tokens: tts,
+            legacy: true,
+        });
+        Self::item(name, self.attrs, self.vis, self.span, self.id, kind)
+    }
+
     pub fn variant<I>(self, name: I, dat: VariantData) -> Variant
     where
         I: Make<Ident>,
@@ -4,8 +4,11 @@ extern crate cmake;
 extern crate env_logger;
 
 use cmake::Config;
+use std::collections::hash_map::DefaultHasher;
 use std::env;
 use std::ffi::OsStr;
+use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::process::{self, Command, Stdio};
 
@@ -18,6 +21,7 @@ fn main() {
 
     // Build the exporter library and link it (and its dependencies)
     build_native(&llvm_info);
+    emit_build_id(&llvm_info);
 
     // Generate ast_tags and ExportResult bindings
     if let Err(e) = generate_bindings() {
@@ -61,6 +65,26 @@ binary.",
     Ok(())
 }
 
+/// Identify this build of the exporter by its C++ sources and the LLVM it
+/// links against, so that ASTs cached by one build are not used by another
+fn emit_build_id(llvm_info: &LLVMInfo) {
+    let mut sources = fs::read_dir("src")
+        .expect("Could not list exporter sources")
+        .map(|entry| entry.expect("Could not list exporter sources").path())
+        .filter(|path| path.extension() != Some(OsStr::new("rs")))
+        .collect::<Vec<_>>();
+    sources.sort();
+
+    let mut hasher = DefaultHasher::new();
+    for path in &sources {
+        path.hash(&mut hasher);
+        fs::read(path).expect("Could not read exporter source").hash(&mut hasher);
+    }
+    llvm_info.lib_dir.hash(&mut hasher);
+    llvm_info.libs.hash(&mut hasher);
+    println!("cargo:rustc-env=C2RUST_AST_EXPORTER_BUILD_ID={:016x}", hasher.finish());
+}
+
 fn generate_bindings() -> Result<(), &'static str> {
     // The bindgen::Builder is the main entry point
     // to bindgen, and lets you build up options for
@@ -79,6 +103,7 @@ fn generate_bindings() -> Result<(), &'static str> {
         .rustified_enum("TypeTag")
         .rustified_enum("StringTypeTag")
         .rustified_enum("BuiltinVaListKind")
+        .rustified_enum("LongDoubleFormat")
         // Tell bindgen we are processing c++
         .clang_arg("-xc++")
         // Finish the builder and generate the bindings.
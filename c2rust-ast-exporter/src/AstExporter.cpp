@@ -47,6 +47,15 @@ using clang::ASTContext;
 using clang::QualType;
 using std::string;
 
+// Apply a custom category to all command-line options so that they are the
+// only ones displayed.
+static llvm::cl::OptionCategory MyToolCategory("my-tool options");
+
+static llvm::cl::opt<bool> ExportFnMacros(
+    "export-fn-macros",
+    llvm::cl::desc("Export function-like macros and the arguments of their expansions"),
+    llvm::cl::cat(MyToolCategory));
+
 namespace {
 // Encode a string object assuming that it is valid UTF-8 encoded text
 void cbor_encode_string(CborEncoder *encoder, const std::string &str) {
@@ -78,6 +87,16 @@ std::string make_realpath(std::string const &path) {
         abort();
     }
 }
+
+LongDoubleFormat getLongDoubleFormat(const llvm::fltSemantics &semantics) {
+    if (&semantics == &llvm::APFloat::IEEEdouble())
+        return IEEEDoubleLongDouble;
+    if (&semantics == &llvm::APFloat::x87DoubleExtended())
+        return X87LongDouble;
+    if (&semantics == &llvm::APFloat::IEEEquad())
+        return IEEEQuadLongDouble;
+    return OtherLongDouble;
+}
 } // namespace
 
 class TranslateASTVisitor;
@@ -132,6 +151,12 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
 
         auto i = uintptr_t(s.Ty);
 
+        // Types are at least 16-byte aligned, which leaves a spare low bit
+        // to mark _Atomic(T) as T with an atomic qualifier.
+        if (auto AT = dyn_cast<AtomicType>(s.Ty)) {
+            i = encodeQualType(AT->getValueType()) | 8;
+        }
+
         if (t.isConstQualified()) {
             i |= 1;
         }
@@ -417,6 +442,12 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         VisitQualType(t);
     }
 
+    // _Atomic(T) is encoded as T with the atomic qualifier bit set (see
+    // encodeQualType), so only the value type needs to be exported.
+    void VisitAtomicType(const AtomicType *T) {
+        VisitQualType(T->getValueType());
+    }
+
     void VisitElaboratedType(const ElaboratedType *T) {
         auto t = T->desugar();
         auto qt = encodeQualType(t);
@@ -444,6 +475,10 @@ class TranslateASTVisitor final
 
         /// Expressions that we have seen this macro expand to
         SmallPtrSet<Expr*, 10> Expressions;
+
+        /// For function-like macros, the expressions substituted for each
+        /// use of each parameter, keyed by expansion expression.
+        DenseMap<Expr*, std::vector<std::vector<Expr*>>> Arguments;
     };
 
     ASTContext *Context;
@@ -638,6 +673,93 @@ class TranslateASTVisitor final
         return true;
     }
 
+    SourceRange getImmediateExpansionRange(SourceLocation loc) const {
+        auto &Mgr = Context->getSourceManager();
+#if CLANG_VERSION_MAJOR < 7
+        auto Range = Mgr.getImmediateExpansionRange(loc);
+        return SourceRange(Range.first, Range.second);
+#else // CLANG_VERSION_MAJOR >= 7
+        return Mgr.getImmediateExpansionRange(loc).getAsRange();
+#endif
+    }
+
+    // Find the use of a macro parameter in the macro expansion `ExpansionFile`
+    // that the token at `loc` was substituted for. Returns an invalid location
+    // if `loc` did not come from an argument of that expansion.
+    SourceLocation getMacroParamUse(SourceLocation loc, FileID ExpansionFile,
+                                    bool isEnd) const {
+        auto &Mgr = Context->getSourceManager();
+        while (loc.isMacroID() && Mgr.getFileID(loc) != ExpansionFile) {
+            if (Mgr.isMacroArgExpansion(loc)) {
+                auto Use = getImmediateExpansionRange(loc).getBegin();
+                if (Mgr.getFileID(Use) == ExpansionFile)
+                    return Use;
+                loc = Mgr.getImmediateSpellingLoc(loc);
+            } else {
+                // The token came from the body of a macro that was itself
+                // invoked in an argument, so follow the invocation.
+                auto Range = getImmediateExpansionRange(loc);
+                loc = isEnd ? Range.getEnd() : Range.getBegin();
+            }
+        }
+        return SourceLocation();
+    }
+
+    // Collect the expressions that the expansion `E` of the function-like
+    // macro `mac` substituted for each use of each macro parameter. `args` is
+    // indexed by parameter number. Returns false if a parameter use does not
+    // correspond to a complete subexpression of `E`, in which case the macro
+    // cannot be translated as a function.
+    bool getMacroArguments(MacroInfo *mac, Expr *E, FileID ExpansionFile,
+                           std::vector<std::vector<Expr*>> &args) {
+        auto &Mgr = Context->getSourceManager();
+
+        unsigned numUses = 0;
+        for (auto &tok : mac->tokens()) {
+            // Stringified or pasted parameters are not expressions
+            if (tok.isOneOf(tok::hash, tok::hashhash))
+                return false;
+            if (tok.getIdentifierInfo() &&
+                mac->getParameterNum(tok.getIdentifierInfo()) >= 0)
+                numUses++;
+        }
+
+        args.assign(mac->getNumParams(), std::vector<Expr*>());
+        std::unordered_set<unsigned> foundUses;
+        std::vector<Stmt*> worklist{E};
+        while (!worklist.empty()) {
+            auto S = worklist.back();
+            worklist.pop_back();
+            if (!S)
+                continue;
+
+            if (auto Arg = dyn_cast<Expr>(S)) {
+                auto Range = Arg->getSourceRange();
+                auto Use = getMacroParamUse(Range.getBegin(), ExpansionFile, false);
+                if (Use.isValid() &&
+                    Use == getMacroParamUse(Range.getEnd(), ExpansionFile, true)) {
+                    Token Result;
+                    if (Lexer::getRawToken(Mgr.getSpellingLoc(Use), Result,
+                                           Mgr, Context->getLangOpts(), false))
+                        return false;
+                    if (Result.is(tok::raw_identifier))
+                        PP.LookUpIdentifierInfo(Result);
+                    auto Param = mac->getParameterNum(Result.getIdentifierInfo());
+                    if (Param < 0 || !foundUses.insert(Use.getRawEncoding()).second)
+                        return false;
+                    args[Param].push_back(Arg);
+                    continue;
+                }
+            }
+
+            // Visit children in reverse so that uses are found in order
+            std::vector<Stmt*> children(S->child_begin(), S->child_end());
+            worklist.insert(worklist.end(), children.rbegin(), children.rend());
+        }
+
+        return foundUses.size() == numUses;
+    }
+
     static bool isScalarAsmType(QualType ty) {
         ty = ty.getCanonicalType();
         switch (ty->getTypeClass()) {
@@ -708,8 +830,39 @@ class TranslateASTVisitor final
 
             auto range = SourceRange(Mac->getDefinitionLoc(), Mac->getDefinitionEndLoc());
             encode_entry_raw(Mac, tag, range, QualType(), false,
-                             false, false, childIds, [Name](CborEncoder *local) {
+                             false, false, childIds, [&](CborEncoder *local) {
                                  cbor_encode_string(local, Name.str());
+                                 if (!Mac->isFunctionLike())
+                                     return;
+
+                                 // Parameter names
+                                 CborEncoder arrayEnc;
+                                 cbor_encoder_create_array(local, &arrayEnc,
+                                                           Mac->getNumParams());
+                                 for (auto Param : Mac->params())
+                                     cbor_encode_string(&arrayEnc, Param->getName().str());
+                                 cbor_encoder_close_container(local, &arrayEnc);
+
+                                 // Argument uses for each expansion, in the
+                                 // same order as the expansions
+                                 cbor_encoder_create_array(local, &arrayEnc,
+                                                           childIds.size());
+                                 for (auto child : childIds) {
+                                     auto &args = Info.Arguments[static_cast<Expr*>(child)];
+                                     CborEncoder paramEnc;
+                                     cbor_encoder_create_array(&arrayEnc, &paramEnc,
+                                                               args.size());
+                                     for (auto &uses : args) {
+                                         CborEncoder useEnc;
+                                         cbor_encoder_create_array(&paramEnc, &useEnc,
+                                                                   uses.size());
+                                         for (auto use : uses)
+                                             cbor_encode_uint(&useEnc, uintptr_t(use));
+                                         cbor_encoder_close_container(&paramEnc, &useEnc);
+                                     }
+                                     cbor_encoder_close_container(&arrayEnc, &paramEnc);
+                                 }
+                                 cbor_encoder_close_container(local, &arrayEnc);
                              });
 
         }
@@ -829,11 +982,9 @@ class TranslateASTVisitor final
     }
 
     bool VisitIndirectGotoStmt(IndirectGotoStmt *IGS) {
-        std::string msg =
-            "the GNU C labels-as-values extension is not supported. Aborting.";
-
-        printError(msg, IGS);
-        abort();
+        std::vector<void *> childIds = {IGS->getTarget()};
+        encode_entry(IGS, TagIndirectGotoStmt, childIds);
+        return true;
     }
 
     bool VisitLabelStmt(LabelStmt *LS) {
@@ -962,6 +1113,9 @@ class TranslateASTVisitor final
     //   List of input constraints
     //   List of output constraints
     //   List of clobbers
+    //   Assembler template as written in the source
+    //   List of input operand names
+    //   List of output operand names
     //
     // The number of input and output expressions in the child id list will
     // match the length of the corresponding constraint arrays.
@@ -1019,6 +1173,17 @@ class TranslateASTVisitor final
             cbor_encode_string_array(local, ArrayRef<std::string>(inputs));
             cbor_encode_string_array(local, ArrayRef<std::string>(outputs));
             cbor_encode_string_array(local, ArrayRef<std::string>(clobbers));
+
+            std::vector<std::string> input_names, output_names;
+            for (unsigned i = 0, num = E->getNumInputs(); i < num; ++i) {
+                input_names.emplace_back(E->getInputName(i));
+            }
+            for (unsigned i = 0, num = E->getNumOutputs(); i < num; ++i) {
+                output_names.emplace_back(E->getOutputName(i));
+            }
+            cbor_encode_string(local, E->getAsmString()->getString().str());
+            cbor_encode_string_array(local, ArrayRef<std::string>(input_names));
+            cbor_encode_string_array(local, ArrayRef<std::string>(output_names));
         });
         return true;
     }
@@ -1059,9 +1224,11 @@ class TranslateASTVisitor final
     bool VisitExpr(Expr *E) {
         curMacroExpansionStack.clear();
 
-        // We only translate constant macro objects to Rust consts, so this
-        // expression must be constant.
-        if (!E->isConstantInitializer(*Context, false))
+        // We only translate constant macro objects to Rust consts, so their
+        // expansions must be constant. Function-like macros may expand to
+        // arbitrary expressions.
+        bool isConstant = E->isConstantInitializer(*Context, false);
+        if (!isConstant && !ExportFnMacros)
             return true;
 
         auto &Mgr = Context->getSourceManager();
@@ -1083,15 +1250,9 @@ class TranslateASTVisitor final
         // starts with literal replacement and works it's way to the macro call
         // that was replaced.
         while (Begin.isMacroID()) {
-#if CLANG_VERSION_MAJOR < 7
-            auto ExpansionRange = Mgr.getImmediateExpansionRange(Begin);
-            auto ExpansionBegin = ExpansionRange.first;
-            auto ExpansionEnd = ExpansionRange.second;
-#else // CLANG_VERSION_MAJOR >= 7
-            auto ExpansionRange = Mgr.getImmediateExpansionRange(Begin).getAsRange();
+            auto ExpansionRange = getImmediateExpansionRange(Begin);
             auto ExpansionBegin = ExpansionRange.getBegin();
             auto ExpansionEnd = ExpansionRange.getEnd();
-#endif
             StringRef name;
             MacroInfo *mac = getMacroInfo(ExpansionBegin, name);
 
@@ -1106,11 +1267,20 @@ class TranslateASTVisitor final
                 Mgr.getSpellingLoc(End) != ReplacementEnd)
                 return true;
 
+            auto ExpansionFile = Mgr.getFileID(Begin);
             Begin = ExpansionBegin;
             End = ExpansionEnd;
 
-            if (mac->isObjectLike() && VisitMacro(name, Begin, mac, E)) {
-                curMacroExpansionStack.push_back(mac);
+            if (mac->isObjectLike()) {
+                if (isConstant && VisitMacro(name, Begin, mac, E))
+                    curMacroExpansionStack.push_back(mac);
+            } else if (ExportFnMacros && !mac->isVariadic()) {
+                std::vector<std::vector<Expr*>> args;
+                if (getMacroArguments(mac, E, ExpansionFile, args) &&
+                    VisitMacro(name, Begin, mac, E)) {
+                    macros[mac].Arguments[E] = std::move(args);
+                    curMacroExpansionStack.push_back(mac);
+                }
             }
         }
         return true;
@@ -1545,7 +1715,26 @@ class TranslateASTVisitor final
         for (auto x : CE->arguments()) {
             childIds.push_back(x);
         }
-        encode_entry(CE, TagCallExpr, childIds);
+        encode_entry(CE, TagCallExpr, childIds, [CE, this](CborEncoder *extra) {
+            // Clang folds `__builtin_constant_p` to a constant, so we export
+            // its result rather than guessing one in the translator
+            if (CE->getBuiltinCallee() != Builtin::BI__builtin_constant_p)
+                return;
+
+#if CLANG_VERSION_MAJOR < 8
+            APSInt eval_result;
+#else
+            Expr::EvalResult eval_result;
+#endif // CLANG_VERSION_MAJOR
+            if (!CE->EvaluateAsInt(eval_result, *Context))
+                return;
+#if CLANG_VERSION_MAJOR < 8
+            APSInt value = eval_result;
+#else
+            APSInt value = eval_result.Val.getInt();
+#endif // CLANG_VERSION_MAJOR
+            cbor_encode_uint(extra, value.getZExtValue());
+        });
         return true;
     }
 
@@ -1598,7 +1787,8 @@ class TranslateASTVisitor final
     }
 
     bool VisitAddrLabelExpr(AddrLabelExpr *E) {
-        printWarning("Cannot translate GNU address of label expression", E);
+        std::vector<void *> childIds = {E->getLabel()->getStmt()};
+        encode_entry(E, TagAddrLabelExpr, childIds);
         return true;
     }
 
@@ -1959,6 +2149,22 @@ class TranslateASTVisitor final
         return true;
     }
 
+    bool VisitStaticAssertDecl(StaticAssertDecl *D) {
+        std::vector<void *> childIds = {D->getAssertExpr()};
+        encode_entry(D, TagStaticAssertDecl, childIds, QualType(),
+                     [D](CborEncoder *local) {
+                         // C2x allows the message to be omitted
+                         auto message = D->getMessage();
+                         if (message && message->getCharByteWidth() == 1) {
+                             auto str = message->getString().str();
+                             cbor_encode_string(local, str);
+                         } else {
+                             cbor_encode_null(local);
+                         }
+                     });
+        return true;
+    }
+
     bool VisitFieldDecl(FieldDecl *D) {
         if (!D->isCanonicalDecl()) {
             // Emit non-canonical decl so we have a placeholder to attach comments to
@@ -2119,6 +2325,14 @@ class TranslateASTVisitor final
                          auto lit = L->getValueAsApproximateDouble();
                          cbor_encode_double(array, lit);
                          cbor_encode_string(array, lexeme);
+
+                         // Exact bits of literals wider than a double, i.e.
+                         // long doubles, low word first
+                         auto bits = L->getValue().bitcastToAPInt();
+                         if (bits.getBitWidth() > 64) {
+                             cbor_encode_uint(array, bits.getRawData()[0]);
+                             cbor_encode_uint(array, bits.getRawData()[1]);
+                         }
                      });
         return true;
     }
@@ -2272,7 +2486,7 @@ class TranslateConsumer : public clang::ASTConsumer {
             cbor_encoder_init(&encoder, buffer, len, 0);
 
             CborEncoder outer;
-            cbor_encoder_create_array(&encoder, &outer, 5);
+            cbor_encoder_create_array(&encoder, &outer, 7);
 
             CborEncoder array;
 
@@ -2352,6 +2566,12 @@ class TranslateConsumer : public clang::ASTConsumer {
             // 5. Target VaList type as BuiltiVaListKind
             cbor_encode_uint(&outer, static_cast<std::uintptr_t>(Context.getTargetInfo().getBuiltinVaListKind()));
 
+            // 6. Target long double format as LongDoubleFormat
+            cbor_encode_uint(&outer, getLongDoubleFormat(Context.getTargetInfo().getLongDoubleFormat()));
+
+            // 7. Target triple
+            cbor_encode_string(&outer, Context.getTargetInfo().getTriple().str());
+
             cbor_encoder_close_container(&encoder, &outer);
         };
 
@@ -2386,10 +2606,6 @@ class TranslateAction : public clang::ASTFrontendAction {
     }
 };
 
-// Apply a custom category to all command-line options so that they are the
-// only ones displayed.
-static llvm::cl::OptionCategory MyToolCategory("my-tool options");
-
 // Added in C++ 17
 template <class _Tp, size_t _Sz>
 constexpr size_t size(const _Tp (&)[_Sz]) noexcept {
@@ -26,6 +26,8 @@ enum ASTEntryTag {
     TagMacroObjectDef,
     TagMacroFunctionDef,
 
+    TagStaticAssertDecl,
+
     TagCompoundStmt = 100,
     TagReturnStmt,
     TagIfStmt,
@@ -46,6 +48,7 @@ enum ASTEntryTag {
 
     TagAsmStmt,
     TagAttributedStmt,
+    TagIndirectGotoStmt,
 
     TagBinaryOperator = 200,
     TagUnaryOperator,
@@ -82,6 +85,7 @@ enum ASTEntryTag {
     TagChooseExpr,
 
     TagAtomicExpr,
+    TagAddrLabelExpr,
 
     TagIntegerLiteral = 300,
     TagStringLiteral,
@@ -191,4 +195,17 @@ enum BuiltinVaListKind {
     SystemZBuiltinVaList
 };
 
+// Floating-point format of the target's long double
+enum LongDoubleFormat {
+    IEEEDoubleLongDouble = 0,
+
+    /// 80-bit x87 extended precision
+    X87LongDouble,
+
+    IEEEQuadLongDouble,
+
+    /// Formats with no Rust counterpart, e.g. PowerPC's double-double
+    OtherLongDouble
+};
+
 #endif /* ast_tags_h */
@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum LRValue {
     LValue,
     RValue,
@@ -22,14 +22,14 @@ impl LRValue {
     }
 }
 
-#[derive(Copy, Debug, Clone, PartialOrd, PartialEq, Ord, Eq)]
+#[derive(Copy, Debug, Clone, PartialOrd, PartialEq, Ord, Eq, Serialize, Deserialize)]
 pub struct SrcLoc {
     pub fileid: u64,
     pub line: u64,
     pub column: u64,
 }
 
-#[derive(Copy, Debug, Clone, PartialOrd, PartialEq, Ord, Eq)]
+#[derive(Copy, Debug, Clone, PartialOrd, PartialEq, Ord, Eq, Serialize, Deserialize)]
 pub struct SrcSpan {
     pub fileid: u64,
     pub begin_line: u64,
@@ -94,7 +94,7 @@ pub struct CommentNode {
     pub string: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SrcFile {
     pub path: Option<PathBuf>,
     pub include_loc: Option<SrcLoc>,
@@ -102,10 +102,11 @@ pub struct SrcFile {
 
 impl TypeNode {
     // Masks used to decode the IDs given to type nodes
-    pub const ID_MASK: u64 = !0b111;
-    pub const CONST_MASK: u64 = 0b001;
-    pub const RESTRICT_MASK: u64 = 0b010;
-    pub const VOLATILE_MASK: u64 = 0b100;
+    pub const ID_MASK: u64 = !0b1111;
+    pub const CONST_MASK: u64 = 0b0001;
+    pub const RESTRICT_MASK: u64 = 0b0010;
+    pub const VOLATILE_MASK: u64 = 0b0100;
+    pub const ATOMIC_MASK: u64 = 0b1000;
 }
 
 #[derive(Debug, Clone)]
@@ -116,6 +117,8 @@ pub struct AstContext {
     pub comments: Vec<CommentNode>,
     pub files: Vec<SrcFile>,
     pub va_list_kind: BuiltinVaListKind,
+    pub long_double_format: LongDoubleFormat,
+    pub target_triple: String,
 }
 
 pub fn expect_opt_str(val: &Value) -> Option<Option<&str>> {
@@ -152,20 +155,79 @@ fn import_va_list_kind(tag: u64) -> BuiltinVaListKind {
     }
 }
 
+fn import_long_double_format(tag: u64) -> LongDoubleFormat {
+    unsafe {
+        return std::mem::transmute::<u32, LongDoubleFormat>(tag as u32);
+    }
+}
+
+/// Serde support for `BuiltinVaListKind`, for use with `#[serde(with = ...)]`
+/// since bindgen cannot derive it.
+pub mod serde_va_list_kind {
+    use super::BuiltinVaListKind;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S: Serializer>(
+        kind: &BuiltinVaListKind,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(*kind as u32)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BuiltinVaListKind, D::Error> {
+        let tag = u32::deserialize(deserializer)?;
+        if tag > BuiltinVaListKind::SystemZBuiltinVaList as u32 {
+            return Err(D::Error::custom(format!("invalid va_list kind {}", tag)));
+        }
+        Ok(super::import_va_list_kind(tag as u64))
+    }
+}
+
+/// Serde support for `LongDoubleFormat`, for use with `#[serde(with = ...)]`
+/// since bindgen cannot derive it.
+pub mod serde_long_double_format {
+    use super::LongDoubleFormat;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S: Serializer>(
+        format: &LongDoubleFormat,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(*format as u32)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<LongDoubleFormat, D::Error> {
+        let tag = u32::deserialize(deserializer)?;
+        if tag > LongDoubleFormat::OtherLongDouble as u32 {
+            return Err(D::Error::custom(format!("invalid long double format {}", tag)));
+        }
+        Ok(super::import_long_double_format(tag as u64))
+    }
+}
+
 pub fn process(items: Value) -> error::Result<AstContext> {
     let mut asts: HashMap<u64, AstNode> = HashMap::new();
     let mut types: HashMap<u64, TypeNode> = HashMap::new();
     let mut comments: Vec<CommentNode> = vec![];
 
-    let (all_nodes, top_nodes, files, raw_comments, va_list_kind): (
+    let (all_nodes, top_nodes, files, raw_comments, va_list_kind, long_double_format, target_triple): (
         Vec<Vec<Value>>,
         Vec<u64>,
         Vec<(String, Option<(u64, u64, u64)>)>,
         Vec<(u64, u64, u64, ByteBuf)>,
         u64,
+        u64,
+        String,
     ) = from_value(items)?;
 
     let va_list_kind = import_va_list_kind(va_list_kind);
+    let long_double_format = import_long_double_format(long_double_format);
 
     for (fileid, line, column, bytes) in raw_comments {
         comments.push(CommentNode {
@@ -247,5 +309,7 @@ pub fn process(items: Value) -> error::Result<AstContext> {
         comments,
         files,
         va_list_kind,
+        long_double_format,
+        target_triple,
     })
 }
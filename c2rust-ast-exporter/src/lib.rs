@@ -1,7 +1,10 @@
 #![allow(non_camel_case_types)]
 extern crate libc;
+extern crate serde;
 extern crate serde_bytes;
 extern crate serde_cbor;
+#[macro_use]
+extern crate serde_derive;
 
 use serde_cbor::{from_slice, Value};
 use std::collections::HashMap;
@@ -12,6 +15,16 @@ use std::slice;
 
 pub mod clang_ast;
 
+/// Identifies the exporter sources and the LLVM libraries this crate was
+/// built with
+pub const BUILD_ID: &str = env!("C2RUST_AST_EXPORTER_BUILD_ID");
+
+/// Full version string of the clang the exporter is linked against
+pub fn get_clang_version() -> String {
+    let s = unsafe { CStr::from_ptr(clang_version()) };
+    s.to_string_lossy().into_owned()
+}
+
 pub fn get_clang_major_version() -> Option<u32> {
     let s = unsafe { CStr::from_ptr(clang_version()) };
     s.to_str()
@@ -28,19 +41,33 @@ pub fn get_untyped_ast(
     cc_db: &Path,
     extra_args: &[&str],
     debug: bool,
+    fn_macros: bool,
 ) -> Result<clang_ast::AstContext, Error> {
-    let cbors = get_ast_cbors(file_path, cc_db, extra_args, debug);
-    let buffer = cbors.values().next().ok_or(Error::new(
+    let buffer = get_ast_cbor(file_path, cc_db, extra_args, debug, fn_macros)?;
+    untyped_ast_from_cbor(&buffer)
+}
+
+/// Run the exporter on `file_path` and return the CBOR encoding of its AST,
+/// which `untyped_ast_from_cbor` decodes. Function-like macros are only
+/// exported if `fn_macros` is set.
+pub fn get_ast_cbor(
+    file_path: &Path,
+    cc_db: &Path,
+    extra_args: &[&str],
+    debug: bool,
+    fn_macros: bool,
+) -> Result<Vec<u8>, Error> {
+    let mut cbors = get_ast_cbors(file_path, cc_db, extra_args, debug, fn_macros);
+    cbors.drain().map(|(_, buffer)| buffer).next().ok_or(Error::new(
         ErrorKind::InvalidData,
         "Could not parse input file",
-    ))?;
-
-    // let cbor_path = file_path.with_extension("cbor");
-    // let mut cbor_file = File::create(&cbor_path)?;
-    // cbor_file.write_all(&buffer[..])?;
-    // eprintln!("Dumped CBOR to {}", cbor_path.to_string_lossy());
+    ))
+}
 
-    let items: Value = from_slice(&buffer[..]).unwrap();
+/// Decode an AST exported by `get_ast_cbor`.
+pub fn untyped_ast_from_cbor(buffer: &[u8]) -> Result<clang_ast::AstContext, Error> {
+    let items: Value = from_slice(buffer)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:}", e)))?;
 
     match clang_ast::process(items) {
         Ok(cxt) => Ok(cxt),
@@ -53,6 +80,7 @@ fn get_ast_cbors(
     cc_db: &Path,
     extra_args: &[&str],
     debug: bool,
+    fn_macros: bool,
 ) -> HashMap<String, Vec<u8>> {
     let mut res = 0;
 
@@ -60,6 +88,9 @@ fn get_ast_cbors(
     args_owned.push(CString::new(file_path.to_str().unwrap()).unwrap());
     args_owned.push(CString::new("-p").unwrap());
     args_owned.push(CString::new(cc_db.to_str().unwrap()).unwrap());
+    if fn_macros {
+        args_owned.push(CString::new("-export-fn-macros").unwrap());
+    }
 
     for &arg in extra_args {
         args_owned.push(CString::new(["-extra-arg=", arg].join("")).unwrap())
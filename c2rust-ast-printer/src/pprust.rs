@@ -118,7 +118,8 @@ pub struct State<'a> {
     pub s: pp::Printer,
     comments: Option<Comments<'a>>,
     ann: &'a (dyn PpAnn+'a),
-    is_expanded: bool
+    is_expanded: bool,
+    in_macro_def: bool,
 }
 
 crate const INDENT_UNIT: usize = 4;
@@ -137,6 +138,7 @@ pub fn print_crate<'a>(cm: &'a SourceMap,
         comments: Some(Comments::parse(cm, sess, filename, input)),
         ann,
         is_expanded,
+        in_macro_def: false,
     };
 
     if is_expanded && sess.injected_crate_name.try_get().is_some() {
@@ -174,7 +176,8 @@ pub fn to_string<F>(f: F) -> String where
         s: pp::mk_printer(),
         comments: None,
         ann: &NoAnn,
-        is_expanded: false
+        is_expanded: false,
+        in_macro_def: false,
     };
     f(&mut printer);
     printer.s.eof()
@@ -187,7 +190,8 @@ pub fn to_string_with_comments<'a, F>(comments: Comments<'a>, f: F) -> String wh
         s: pp::mk_printer(),
         comments: Some(comments),
         ann: &NoAnn,
-        is_expanded: false
+        is_expanded: false,
+        in_macro_def: false,
     };
     f(&mut printer);
     printer.s.eof()
@@ -206,6 +210,21 @@ fn tt_prepend_space(tt: &TokenTree) -> bool {
     }
 }
 
+// Macro definitions additionally keep metavariables such as `$x:expr`
+// together, and don't put spaces before the `;` separating their rules.
+fn macro_def_tt_prepend_space(tt: &TokenTree, prev: &TokenTree) -> bool {
+    if let TokenTree::Token(Token { kind: token::Dollar, .. }) = prev {
+        return false;
+    }
+    match tt {
+        TokenTree::Token(token) => match token.kind {
+            token::Colon | token::Semi => false,
+            _ => tt_prepend_space(tt),
+        }
+        _ => true,
+    }
+}
+
 fn binop_to_string(op: BinOpToken) -> &'static str {
     match op {
         token::Plus     => "+",
@@ -483,6 +502,7 @@ impl std::ops::DerefMut for State<'_> {
 
 pub trait PrintState<'a>: std::ops::Deref<Target = pp::Printer> + std::ops::DerefMut {
     fn comments(&mut self) -> &mut Option<Comments<'a>>;
+    fn in_macro_def(&self) -> bool;
     fn print_ident(&mut self, ident: ast::Ident);
     fn print_generic_args(&mut self, args: &ast::GenericArgs, colons_before_params: bool);
 
@@ -749,11 +769,20 @@ pub trait PrintState<'a>: std::ops::Deref<Target = pp::Printer> + std::ops::Dere
     }
 
     fn print_tts(&mut self, tts: tokenstream::TokenStream, convert_dollar_crate: bool) {
-        for (i, tt) in tts.into_trees().enumerate() {
-            if i != 0 && tt_prepend_space(&tt) {
-                self.space();
+        let mut prev = None;
+        for tt in tts.into_trees() {
+            if let Some(ref prev) = prev {
+                let space = if self.in_macro_def() {
+                    macro_def_tt_prepend_space(&tt, prev)
+                } else {
+                    tt_prepend_space(&tt)
+                };
+                if space {
+                    self.space();
+                }
             }
-            self.print_tt(tt, convert_dollar_crate);
+            self.print_tt(tt.clone(), convert_dollar_crate);
+            prev = Some(tt);
         }
     }
 
@@ -872,6 +901,10 @@ impl<'a> PrintState<'a> for State<'a> {
         &mut self.comments
     }
 
+    fn in_macro_def(&self) -> bool {
+        self.in_macro_def
+    }
+
     fn print_ident(&mut self, ident: ast::Ident) {
         self.s.word(ast_ident_to_string(ident, ident.is_raw_guess()));
         self.ann.post(self, AnnNode::Ident(&ident))
@@ -1417,6 +1450,7 @@ impl<'a> State<'a> {
                     self.print_visibility(&item.vis);
                     ("macro", false)
                 };
+                self.in_macro_def = true;
                 self.print_mac_common(
                     Some(MacHeader::Keyword(kw)),
                     has_bang,
@@ -1426,6 +1460,7 @@ impl<'a> State<'a> {
                     true,
                     item.span,
                 );
+                self.in_macro_def = false;
             }
         }
         self.ann.post(self, AnnNode::Item(item))
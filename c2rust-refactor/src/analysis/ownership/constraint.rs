@@ -1,6 +1,7 @@
 //! `ConstraintSet` and related definitions.
 use std::cmp;
 use std::collections::btree_set::{self, BTreeSet};
+use std::collections::BTreeMap;
 use std::collections::Bound;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -396,6 +397,41 @@ impl<'lty, 'tcx> ConstraintSet<'lty> {
         bound
     }
 
+    /// Find a chain of constraints `c <= p_1 <= ... <= p` showing that the lower bound of `p` is
+    /// at least `min`.  The returned chain starts with the concrete permission `c` and ends with
+    /// `p`, or is `None` if `lower_bound(p)` is less than `min`.
+    ///
+    /// Like `lower_bound`, this doesn't look inside `Min`s.
+    pub fn explain_lower_bound(&self, p: Perm<'lty>, min: ConcretePerm) -> Option<Vec<Perm<'lty>>> {
+        let mut parent = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(p);
+
+        while let Some(cur) = queue.pop_front() {
+            if let Perm::Concrete(c) = cur {
+                if c < min {
+                    continue;
+                }
+                let mut chain = vec![cur];
+                let mut cur = cur;
+                while let Some(&next) = parent.get(&cur) {
+                    chain.push(next);
+                    cur = next;
+                }
+                return Some(chain);
+            }
+
+            for &(_, prev) in self.greater.range(perm_range(cur)) {
+                if prev != p && !parent.contains_key(&prev) {
+                    parent.insert(prev, cur);
+                    queue.push_back(prev);
+                }
+            }
+        }
+
+        None
+    }
+
     /// Given an assignment of concrete permission values to a subset of the variables, check
     /// whether any constraints are violated under the partial assignment.  Returns `false` if a
     /// constraint is violated, or `true` if all constraints appear to be satisfiable.
@@ -404,6 +440,15 @@ impl<'lty, 'tcx> ConstraintSet<'lty> {
     /// (strictly) partial assignments, it may report that a satisfying assignment is possible when
     /// it's not, but never the other way around.
     pub fn check_partial_assignment<F>(&self, eval: F) -> bool
+    where
+        F: Fn(Perm<'lty>) -> Option<ConcretePerm>,
+    {
+        self.find_violation(eval).is_none()
+    }
+
+    /// Like `check_partial_assignment`, but return the first constraint violated by the partial
+    /// assignment, if there is one.
+    pub fn find_violation<F>(&self, eval: F) -> Option<(Perm<'lty>, Perm<'lty>)>
     where
         F: Fn(Perm<'lty>) -> Option<ConcretePerm>,
     {
@@ -438,9 +483,9 @@ impl<'lty, 'tcx> ConstraintSet<'lty> {
             }
         }
 
-        for &(a, b) in &self.less {
-            let (a, a_any, a_all) = eval_rec(a, &eval);
-            let (b, _b_any, b_all) = eval_rec(b, &eval);
+        for &(pa, pb) in &self.less {
+            let (a, a_any, a_all) = eval_rec(pa, &eval);
+            let (b, _b_any, b_all) = eval_rec(pb, &eval);
 
             if a <= b {
                 continue;
@@ -461,10 +506,10 @@ impl<'lty, 'tcx> ConstraintSet<'lty> {
 
             debug!("Constraint {:?} <= {:?} is not satisfiable", a, b);
 
-            return false;
+            return Some((pa, pb));
         }
 
-        true
+        None
     }
 
     /// Obtain an editing cursor for this constraint set.
@@ -690,8 +735,9 @@ impl<'lty, 'tcx> ConstraintSet<'lty> {
         F: Fn(Perm<'lty>) -> bool,
     {
         // Collect all atomic permissions that appear in the constraint set.
-        let mut atomic_perms = HashSet::new();
-        fn collect_atomic<'lty, 'tcx>(p: Perm<'lty>, dest: &mut HashSet<Perm<'lty>>) {
+        // (Visit them in a fixed order, so the result doesn't depend on hashing.)
+        let mut atomic_perms = BTreeSet::new();
+        fn collect_atomic<'lty, 'tcx>(p: Perm<'lty>, dest: &mut BTreeSet<Perm<'lty>>) {
             match p {
                 Perm::Min(ps) => {
                     for &p in ps {
@@ -20,9 +20,8 @@
 //! don't currently have them.  Mono summaries aren't created on-demand because we never query a
 //! mono that might not exist.
 
-use std::collections::hash_map::{self, Entry, HashMap};
-
 use arena::SyncDroplessArena;
+use indexmap::map::{self, Entry, IndexMap};
 use log::Level;
 use rustc::hir::def_id::DefId;
 use rustc::ty::{Ty, TyCtxt, TyKind};
@@ -99,21 +98,23 @@ pub struct Instantiation {
     pub first_inst_var: u32,
 }
 
+/// Summaries are kept in `IndexMap`s, so that iterating over them visits functions in the order
+/// they were first seen.  This makes the analysis (and its failures) reproducible from run to run.
 pub struct Ctxt<'lty, 'tcx> {
     pub tcx: TyCtxt<'tcx>,
     pub lcx: LabeledTyCtxt<'lty, Option<PermVar>>,
     pub arena: &'lty SyncDroplessArena,
 
     /// Types of non-`fn` definitions.  This includes `static`s and also `struct` fields.
-    pub static_summ: HashMap<DefId, LTy<'lty, 'tcx>>,
+    pub static_summ: IndexMap<DefId, LTy<'lty, 'tcx>>,
 
     /// Assignment of permission values to static vars.  This is only here because this is a
     /// convenient way to communicate it from `annot` to `inter`.
     pub static_assign: IndexVec<Var, ConcretePerm>,
 
-    funcs: HashMap<DefId, FuncSumm<'lty, 'tcx>>,
-    variants: HashMap<DefId, VariantSumm<'lty>>,
-    monos: HashMap<(DefId, usize), MonoSumm>,
+    funcs: IndexMap<DefId, FuncSumm<'lty, 'tcx>>,
+    variants: IndexMap<DefId, VariantSumm<'lty>>,
+    monos: IndexMap<(DefId, usize), MonoSumm>,
 }
 
 impl<'lty, 'a: 'lty, 'tcx: 'a> Ctxt<'lty, 'tcx> {
@@ -126,12 +127,12 @@ impl<'lty, 'a: 'lty, 'tcx: 'a> Ctxt<'lty, 'tcx> {
             lcx: LabeledTyCtxt::new(&arena),
             arena: arena,
 
-            static_summ: HashMap::new(),
+            static_summ: IndexMap::new(),
             static_assign: IndexVec::new(),
 
-            funcs: HashMap::new(),
-            variants: HashMap::new(),
-            monos: HashMap::new(),
+            funcs: IndexMap::new(),
+            variants: IndexMap::new(),
+            monos: IndexMap::new(),
         }
     }
 
@@ -156,8 +157,8 @@ impl<'lty, 'a: 'lty, 'tcx: 'a> Ctxt<'lty, 'tcx> {
     }
 
     fn func_summ_impl<'b>(
-        funcs: &'b mut HashMap<DefId, FuncSumm<'lty, 'tcx>>,
-        variants: &mut HashMap<DefId, VariantSumm<'lty>>,
+        funcs: &'b mut IndexMap<DefId, FuncSumm<'lty, 'tcx>>,
+        variants: &mut IndexMap<DefId, VariantSumm<'lty>>,
         tcx: TyCtxt<'tcx>,
         lcx: &mut LabeledTyCtxt<'lty, Option<PermVar>>,
         did: DefId,
@@ -239,7 +240,7 @@ impl<'lty, 'a: 'lty, 'tcx: 'a> Ctxt<'lty, 'tcx> {
         }
     }
 
-    pub fn funcs_mut(&mut self) -> hash_map::IterMut<DefId, FuncSumm<'lty, 'tcx>> {
+    pub fn funcs_mut(&mut self) -> map::IterMut<DefId, FuncSumm<'lty, 'tcx>> {
         self.funcs.iter_mut()
     }
 
@@ -256,8 +257,8 @@ impl<'lty, 'a: 'lty, 'tcx: 'a> Ctxt<'lty, 'tcx> {
     }
 
     fn add_variant_impl<'b>(
-        funcs: &'b mut HashMap<DefId, FuncSumm<'lty, 'tcx>>,
-        variants: &'b mut HashMap<DefId, VariantSumm<'lty>>,
+        funcs: &'b mut IndexMap<DefId, FuncSumm<'lty, 'tcx>>,
+        variants: &'b mut IndexMap<DefId, VariantSumm<'lty>>,
         tcx: TyCtxt<'tcx>,
         lcx: &'b mut LabeledTyCtxt<'lty, Option<PermVar>>,
         func_did: DefId,
@@ -332,9 +333,45 @@ impl<'lty, 'a: 'lty, 'tcx: 'a> Ctxt<'lty, 'tcx> {
         (func, variant, mono)
     }
 
+    fn take_monos(
+        monos: &mut IndexMap<(DefId, usize), MonoSumm>,
+        func_did: DefId,
+        num_monos: usize,
+    ) -> Vec<MonoSumm> {
+        (0..num_monos)
+            .map(|idx| monos.swap_remove(&(func_did, idx)).unwrap())
+            .collect()
+    }
+
+    /// Remove monomorphization `mono_idx` of a single-variant function.  The indices of later
+    /// monos are shifted down by one.
+    pub fn remove_mono(&mut self, func_did: DefId, mono_idx: usize) -> MonoSumm {
+        let func = self.funcs.get_mut(&func_did).unwrap();
+        assert!(func.variant_ids.len() == 1, "can't remove a mono of multi-variant fn {:?}", func_did);
+        let mut monos = Self::take_monos(&mut self.monos, func_did, func.num_monos);
+        let mono = monos.remove(mono_idx);
+        func.num_monos -= 1;
+        for (idx, m) in monos.into_iter().enumerate() {
+            self.monos.insert((func_did, idx), m);
+        }
+        mono
+    }
+
+    /// Insert a monomorphization of a single-variant function at index `mono_idx`, undoing
+    /// `remove_mono`.
+    pub fn insert_mono(&mut self, func_did: DefId, mono_idx: usize, mono: MonoSumm) {
+        let func = self.funcs.get_mut(&func_did).unwrap();
+        let mut monos = Self::take_monos(&mut self.monos, func_did, func.num_monos);
+        monos.insert(mono_idx, mono);
+        func.num_monos += 1;
+        for (idx, m) in monos.into_iter().enumerate() {
+            self.monos.insert((func_did, idx), m);
+        }
+    }
+
     fn variant_summ_impl<'b>(
-        funcs: &'b mut HashMap<DefId, FuncSumm<'lty, 'tcx>>,
-        variants: &'b mut HashMap<DefId, VariantSumm<'lty>>,
+        funcs: &'b mut IndexMap<DefId, FuncSumm<'lty, 'tcx>>,
+        variants: &'b mut IndexMap<DefId, VariantSumm<'lty>>,
         tcx: TyCtxt<'tcx>,
         lcx: &'b mut LabeledTyCtxt<'lty, Option<PermVar>>,
         variant_did: DefId,
@@ -367,6 +404,10 @@ impl<'lty, 'a: 'lty, 'tcx: 'a> Ctxt<'lty, 'tcx> {
         &self.variants[&did]
     }
 
+    pub fn opt_variant_summ(&self, did: DefId) -> Option<&VariantSumm<'lty>> {
+        self.variants.get(&did)
+    }
+
     pub fn variant_func_sig(&mut self, variant_did: DefId) -> LFnSig<'lty, 'tcx> {
         self.variant_summ(variant_did).0.sig
     }
@@ -453,7 +494,7 @@ fn preload_constraints<'lty, 'tcx>(
 }
 
 pub struct FuncIds<'a, 'lty, 'tcx> {
-    inner: hash_map::Keys<'a, DefId, FuncSumm<'lty, 'tcx>>,
+    inner: map::Keys<'a, DefId, FuncSumm<'lty, 'tcx>>,
 }
 
 impl<'a, 'lty, 'tcx> Iterator for FuncIds<'a, 'lty, 'tcx> {
@@ -465,7 +506,7 @@ impl<'a, 'lty, 'tcx> Iterator for FuncIds<'a, 'lty, 'tcx> {
 }
 
 pub struct VariantIds<'a, 'lty> {
-    inner: hash_map::Keys<'a, DefId, VariantSumm<'lty>>,
+    inner: map::Keys<'a, DefId, VariantSumm<'lty>>,
 }
 
 impl<'a, 'lty> Iterator for VariantIds<'a, 'lty> {
@@ -477,7 +518,7 @@ impl<'a, 'lty> Iterator for VariantIds<'a, 'lty> {
 }
 
 pub struct MonoIds<'a> {
-    inner: hash_map::Keys<'a, (DefId, usize), MonoSumm>,
+    inner: map::Keys<'a, (DefId, usize), MonoSumm>,
 }
 
 impl<'a> Iterator for MonoIds<'a> {
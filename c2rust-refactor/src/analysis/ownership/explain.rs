@@ -0,0 +1,183 @@
+//! Explanations of the permissions inferred by the analysis.
+//!
+//! The lower bound of a signature variable is always the result of a chain of constraints
+//! `c <= p_1 <= ... <= p_n <= sig_var`, starting from a concrete permission.  The intermediate
+//! permissions may be local variables of the function, variables of callee signatures at some call
+//! site, or static variables (field and `static` types).  To explain a lower bound, we rebuild the
+//! constraint set that `inter` simplified to produce the signature, and search it for such a chain.
+
+use std::collections::{BTreeSet, HashMap};
+
+use rustc::hir::def_id::DefId;
+
+use super::constraint::{ConstraintSet, Perm};
+use super::context::{Ctxt, FuncSumm, VariantSumm};
+use super::{ConcretePerm, LFnSig, LTy, PermVar, Var};
+
+/// Describe the position of each signature variable in `sig`, like "argument 0 of `f`".
+fn sig_var_positions(sig: LFnSig, fn_name: &str) -> HashMap<Var, String> {
+    fn walk(ty: LTy, desc: String, out: &mut HashMap<Var, String>) {
+        let inner = match ty.label {
+            Some(PermVar::Sig(v)) => {
+                out.insert(v, desc.clone());
+                format!("target of {}", desc)
+            }
+            _ => desc,
+        };
+        for &arg in ty.args {
+            walk(arg, inner.clone(), out);
+        }
+    }
+
+    let mut out = HashMap::new();
+    for (i, &input) in sig.inputs.iter().enumerate() {
+        walk(input, format!("argument {} of `{}`", i, fn_name), &mut out);
+    }
+    walk(sig.output, format!("return value of `{}`", fn_name), &mut out);
+    out
+}
+
+struct Describer<'a, 'lty: 'a, 'tcx: 'lty> {
+    cx: &'a Ctxt<'lty, 'tcx>,
+    variant: &'a VariantSumm<'lty>,
+    sig_positions: HashMap<Var, String>,
+    static_names: HashMap<Var, String>,
+}
+
+impl<'a, 'lty, 'tcx> Describer<'a, 'lty, 'tcx> {
+    fn new(
+        cx: &'a Ctxt<'lty, 'tcx>,
+        func: &'a FuncSumm<'lty, 'tcx>,
+        variant: &'a VariantSumm<'lty>,
+    ) -> Describer<'a, 'lty, 'tcx> {
+        let fn_name = cx.tcx.def_path_str(variant.func_id);
+
+        let mut static_names = HashMap::new();
+        for (&did, &lty) in &cx.static_summ {
+            let name = cx.tcx.def_path_str(did);
+            lty.for_each_label(&mut |&label| {
+                if let Some(PermVar::Static(v)) = label {
+                    static_names.insert(v, format!("type of `{}`", name));
+                }
+            });
+        }
+
+        Describer {
+            cx,
+            variant,
+            sig_positions: sig_var_positions(func.sig, &fn_name),
+            static_names,
+        }
+    }
+
+    fn describe(&self, p: Perm<'lty>) -> String {
+        match p {
+            Perm::Concrete(c) => format!("{:?}", c).to_uppercase(),
+            Perm::SigVar(v) => self.sig_positions.get(&v).cloned()
+                .unwrap_or_else(|| format!("signature var {}", v.0)),
+            Perm::StaticVar(v) => self.static_names.get(&v).cloned()
+                .unwrap_or_else(|| format!("static var {}", v.0)),
+            Perm::LocalVar(v) => format!("local var {}", v.0),
+            Perm::InstVar(v) => {
+                for (idx, inst) in self.variant.insts.iter().enumerate() {
+                    let callee = self.cx.get_func_summ(inst.callee);
+                    if inst.first_inst_var <= v.0 && v.0 < inst.first_inst_var + callee.num_sig_vars {
+                        let callee_name = self.cx.tcx.def_path_str(inst.callee);
+                        let sig_var = Var(v.0 - inst.first_inst_var);
+                        let pos = sig_var_positions(callee.sig, &callee_name)
+                            .remove(&sig_var)
+                            .unwrap_or_else(|| format!("var {} of `{}`", sig_var.0, callee_name));
+                        return match inst.span {
+                            Some(span) => format!("{} (call #{} at {:?})", pos, idx, span),
+                            None => format!("{} (call #{})", pos, idx),
+                        };
+                    }
+                }
+                format!("instantiation var {}", v.0)
+            }
+            Perm::Min(ps) => {
+                let ps = ps.iter().map(|&p| self.describe(p)).collect::<Vec<_>>();
+                format!("min({})", ps.join(", "))
+            }
+        }
+    }
+}
+
+/// Rebuild the constraints relating the variables of `variant` to its signature, before `inter`
+/// simplified away everything but the signature variables.
+fn full_cset<'lty, 'tcx>(
+    cx: &Ctxt<'lty, 'tcx>,
+    func: &FuncSumm<'lty, 'tcx>,
+    variant: &VariantSumm<'lty>,
+) -> ConstraintSet<'lty> {
+    if func.cset_provided {
+        return func.sig_cset.clone();
+    }
+
+    let mut cset = variant.inst_cset.clone();
+    for inst in &variant.insts {
+        let callee = cx.get_func_summ(inst.callee);
+        cset.import_substituted(&callee.sig_cset, cx.arena, |p| match p {
+            Perm::SigVar(v) => Perm::InstVar(Var(v.0 + inst.first_inst_var)),
+            p => p,
+        });
+    }
+
+    let mut used_statics = BTreeSet::new();
+    cset.for_each_perm(|p| {
+        if let Perm::StaticVar(v) = p {
+            used_statics.insert(v);
+        }
+    });
+    for v in used_statics {
+        cset.add(Perm::Concrete(cx.static_assign[v]), Perm::StaticVar(v));
+    }
+
+    cset
+}
+
+/// Explain the lower bound of each pointer in the signature of `did` that must be at least
+/// `WRITE`.  Returns one line for each such pointer, showing the chain of constraints that forces
+/// its permission.
+pub fn explain_sig<'lty, 'tcx>(cx: &Ctxt<'lty, 'tcx>, did: DefId) -> Vec<String> {
+    let variant = match cx.opt_variant_summ(did) {
+        Some(x) => x,
+        None => return vec![format!("`{}` was not analyzed", cx.tcx.def_path_str(did))],
+    };
+    let func = cx.get_func_summ(variant.func_id);
+    let cset = full_cset(cx, func, variant);
+    let describer = Describer::new(cx, func, variant);
+
+    let mut lines = Vec::new();
+    for i in 0..func.num_sig_vars {
+        let p = Perm::SigVar(Var(i));
+        let bound = func.sig_cset.lower_bound(p);
+        if bound < ConcretePerm::Write {
+            continue;
+        }
+
+        let chain = cset.explain_lower_bound(p, bound)
+            .or_else(|| func.sig_cset.explain_lower_bound(p, bound));
+        let chain = match chain {
+            Some(chain) => chain.into_iter()
+                .map(|p| describer.describe(p))
+                .collect::<Vec<_>>()
+                .join(" <= "),
+            None => "no constraint chain found".to_owned(),
+        };
+        lines.push(format!(
+            "{} is at least {}: {}",
+            describer.describe(p),
+            describer.describe(Perm::Concrete(bound)),
+            chain
+        ));
+    }
+
+    if lines.is_empty() {
+        lines.push(format!(
+            "no pointer in the signature of `{}` must be WRITE or MOVE",
+            cx.tcx.def_path_str(variant.func_id)
+        ));
+    }
+    lines
+}
@@ -12,8 +12,23 @@ use super::constraint::ConstraintSet;
 use super::context::{Ctxt, Instantiation, VariantSumm};
 use super::{ConcretePerm, Perm, Var};
 
+/// Maximum number of candidate monomorphizations `do_solve` will try for the calls in a single
+/// function before giving up.  The search is exponential in the worst case, and without a bound
+/// an unsatisfiable function can backtrack effectively forever.
+const MAX_SOLVE_STEPS: usize = 1_000_000;
+
+/// The deepest point reached by a failed search: the call for which no callee monomorphization
+/// was compatible with the monomorphizations already selected for the earlier calls.
+struct InstFailure<'lty> {
+    inst_idx: usize,
+    /// For each callee mono, the first constraint it violated.
+    violations: Vec<Option<(Perm<'lty>, Perm<'lty>)>>,
+}
+
 pub struct InstCtxt<'lty, 'tcx> {
     cx: &'lty Ctxt<'lty, 'tcx>,
+    func_did: DefId,
+    mono_idx: usize,
 
     insts: &'lty [Instantiation],
     cset: ConstraintSet<'lty>,
@@ -23,6 +38,10 @@ pub struct InstCtxt<'lty, 'tcx> {
 
     /// Assignment to inst vars for the current mono.
     inst_assign: IndexVec<Var, Option<ConcretePerm>>,
+
+    /// Number of candidate monos tried so far.
+    steps: usize,
+    failure: Option<InstFailure<'lty>>,
 }
 
 impl<'lty, 'tcx> InstCtxt<'lty, 'tcx> {
@@ -37,14 +56,30 @@ impl<'lty, 'tcx> InstCtxt<'lty, 'tcx> {
 
         InstCtxt {
             cx: cx,
+            func_did: func_did,
+            mono_idx: mono_idx,
             insts: &variant.insts,
             cset: cset,
             inst_sel: Vec::new(),
             inst_assign: IndexVec::new(),
+            steps: 0,
+            failure: None,
         }
     }
 
     pub fn solve_instantiations(&mut self) -> Vec<usize> {
+        match self.try_solve_instantiations() {
+            Some(inst_sel) => inst_sel,
+            None => {
+                self.report_failure();
+                vec![]
+            }
+        }
+    }
+
+    /// Select a callee mono for each instantiation, or return `None` if there is no solution.
+    /// Unlike `solve_instantiations`, this doesn't report failures.
+    pub fn try_solve_instantiations(&mut self) -> Option<Vec<usize>> {
         self.inst_sel = iter::repeat(None)
             .take(self.insts.len())
             .collect::<Vec<_>>();
@@ -61,16 +96,16 @@ impl<'lty, 'tcx> InstCtxt<'lty, 'tcx> {
 
         let ok = self.do_solve(0);
         if !ok {
-            warn!("found no solution for call to {:?}", self.insts);
-            return vec![];
+            return None;
         }
 
         let inst_sel = mem::replace(&mut self.inst_sel, Vec::new());
-        inst_sel.into_iter().map(|i| i.unwrap()).collect::<Vec<_>>()
+        Some(inst_sel.into_iter().map(|i| i.unwrap()).collect::<Vec<_>>())
     }
 
     /// Simple brute-force search of all combinations of instantiations.  Surprisingly, this works
     /// pretty well, even on pathological cases like `json_tokener_parse_ex` (with its 452 calls).
+    /// The search gives up after `MAX_SOLVE_STEPS` candidates.
     fn do_solve(&mut self, inst_idx: usize) -> bool {
         if inst_idx >= self.inst_sel.len() {
             return true;
@@ -78,8 +113,15 @@ impl<'lty, 'tcx> InstCtxt<'lty, 'tcx> {
 
         // The search is biased toward higher-numbered monomorphizations.  Currently higher indexes
         // correspond to lower output permissions.
+        let mut any_compat = false;
         for mono_idx in (0..self.num_mono_sigs(inst_idx)).rev() {
+            if self.steps >= MAX_SOLVE_STEPS {
+                break;
+            }
+            self.steps += 1;
+
             if self.check_mono_compat(inst_idx, mono_idx) {
+                any_compat = true;
                 self.select(inst_idx, mono_idx);
                 if self.do_solve(inst_idx + 1) {
                     return true;
@@ -87,16 +129,72 @@ impl<'lty, 'tcx> InstCtxt<'lty, 'tcx> {
             }
         }
 
+        let deepest = self.failure.as_ref().map_or(true, |f| inst_idx > f.inst_idx);
+        if !any_compat && self.steps < MAX_SOLVE_STEPS && deepest {
+            let violations = (0..self.num_mono_sigs(inst_idx))
+                .map(|mono_idx| self.find_mono_violation(inst_idx, mono_idx))
+                .collect();
+            self.failure = Some(InstFailure {
+                inst_idx,
+                violations,
+            });
+        }
+
         self.deselect(inst_idx);
         false
     }
 
+    /// Explain why no instantiation could be found: report the call that the search got stuck
+    /// on, and which constraint rules out each monomorphization of its callee.
+    fn report_failure(&self) {
+        let tcx = self.cx.tcx;
+        let gave_up = if self.steps >= MAX_SOLVE_STEPS {
+            format!(" (gave up after {} steps)", self.steps)
+        } else {
+            String::new()
+        };
+        warn!(
+            "found no solution for the calls in {} (mono #{}){}",
+            tcx.def_path_str(self.func_did),
+            self.mono_idx,
+            gave_up
+        );
+
+        let failure = match self.failure {
+            Some(ref f) => f,
+            None => return,
+        };
+        let inst = &self.insts[failure.inst_idx];
+        warn!(
+            "  no monomorphization of {} fits call #{} (at {:?})",
+            tcx.def_path_str(inst.callee),
+            failure.inst_idx,
+            inst.span
+        );
+        for (mono_idx, violation) in failure.violations.iter().enumerate() {
+            match *violation {
+                Some((a, b)) => warn!("    mono #{}: violates {:?} <= {:?}", mono_idx, a, b),
+                None => warn!("    mono #{}: unsatisfiable with earlier calls", mono_idx),
+            }
+        }
+    }
+
     fn num_mono_sigs(&self, inst_idx: usize) -> usize {
         let inst = &self.insts[inst_idx];
         self.cx.get_func_summ(inst.callee).num_monos
     }
 
     fn check_mono_compat(&self, inst_idx: usize, mono_idx: usize) -> bool {
+        self.find_mono_violation(inst_idx, mono_idx).is_none()
+    }
+
+    /// Find a constraint violated by selecting `mono_idx` for the call `inst_idx`, given the
+    /// current selections for the other calls.
+    fn find_mono_violation(
+        &self,
+        inst_idx: usize,
+        mono_idx: usize,
+    ) -> Option<(Perm<'lty>, Perm<'lty>)> {
         let inst = &self.insts[inst_idx];
         let callee = inst.callee;
         let mono_assign = &self.cx.get_mono_summ(callee, mono_idx).assign;
@@ -106,7 +204,7 @@ impl<'lty, 'tcx> InstCtxt<'lty, 'tcx> {
         let first_var = Var(inst.first_inst_var);
         let last_var = Var(inst.first_inst_var + callee_summ.num_sig_vars);
 
-        self.cset.check_partial_assignment(|p| {
+        self.cset.find_violation(|p| {
             let v = match p {
                 Perm::InstVar(v) => v,
                 _ => return None,
@@ -1,9 +1,10 @@
 //! Interprocedural part of the analysis.
 
 use std::collections::hash_map::{Entry, HashMap};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::collections::VecDeque;
 
+use indexmap::IndexSet;
 use log::Level;
 use rustc::hir::def_id::DefId;
 
@@ -47,9 +48,10 @@ pub struct InterCtxt<'c, 'lty, 'tcx> {
     complete_cset: HashMap<DefId, ConstraintSet<'lty>>,
 
     work_list: WorkList,
-    rev_deps: HashMap<DefId, HashSet<DefId>>,
+    // The dependents are `IndexSet`s so that they're added to the work list in a consistent order.
+    rev_deps: HashMap<DefId, IndexSet<DefId>>,
 
-    static_rev_deps: HashMap<Var, HashSet<DefId>>,
+    static_rev_deps: HashMap<Var, IndexSet<DefId>>,
 }
 
 impl<'c, 'lty, 'tcx> InterCtxt<'c, 'lty, 'tcx> {
@@ -76,7 +78,7 @@ impl<'c, 'lty, 'tcx> InterCtxt<'c, 'lty, 'tcx> {
         };
 
         // Add constraints for all used static vars.
-        let mut used_statics = HashSet::new();
+        let mut used_statics = BTreeSet::new();
         cset.for_each_perm(|p| match p {
             Perm::StaticVar(v) => {
                 used_statics.insert(v);
@@ -88,7 +90,7 @@ impl<'c, 'lty, 'tcx> InterCtxt<'c, 'lty, 'tcx> {
             cset.add(Perm::Concrete(self.cx.static_assign[v]), Perm::StaticVar(v));
             self.static_rev_deps
                 .entry(v)
-                .or_insert_with(HashSet::new)
+                .or_insert_with(IndexSet::new)
                 .insert(def_id);
         }
 
@@ -107,7 +109,7 @@ impl<'c, 'lty, 'tcx> InterCtxt<'c, 'lty, 'tcx> {
 
             self.rev_deps
                 .entry(inst.callee)
-                .or_insert_with(HashSet::new)
+                .or_insert_with(IndexSet::new)
                 .insert(def_id);
         }
 
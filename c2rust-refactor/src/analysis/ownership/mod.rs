@@ -14,11 +14,11 @@
 //! runs interprocedurally to a fixed point, on each function plugging in the complete summaries of
 //! its callees and simplifying to produce a complete summary for the current function.
 
-use std::collections::HashMap;
 use std::fmt;
 use std::u32;
 
 use arena::SyncDroplessArena;
+use indexmap::IndexMap;
 use log::Level;
 use rustc::hir;
 use rustc::hir::def_id::{DefId, LOCAL_CRATE};
@@ -37,13 +37,12 @@ use crate::RefactorCtxt;
 mod annot;
 pub mod constraint;
 mod context;
+mod explain;
 mod inst;
 mod inter;
 mod intra;
 mod mono;
-/*
 mod mono_filter;
-*/
 mod debug;
 
 use self::annot::{handle_attrs, handle_marks};
@@ -53,9 +52,7 @@ use self::inst::find_instantiations;
 use self::inter::InterCtxt;
 use self::intra::IntraCtxt;
 use self::mono::compute_all_mono_sigs;
-/*
-use self::mono_filter::filter_suspicious_monos;
-*/
+use self::mono_filter::prune_monos;
 use self::debug::*;
 
 /// A variable index.
@@ -170,7 +167,11 @@ fn analyze_intra<'a, 'tcx, 'lty>(
     hir_map: &HirMap<'a, 'tcx>,
     tcx: TyCtxt<'tcx>,
 ) {
-    for &def_id in tcx.mir_keys(LOCAL_CRATE).iter() {
+    // `mir_keys` is a hash set.  Sort it so that summaries (and static vars) are created in the
+    // same order on every run.
+    let mut def_ids = tcx.mir_keys(LOCAL_CRATE).iter().cloned().collect::<Vec<_>>();
+    def_ids.sort();
+    for def_id in def_ids {
         // We currently don't process `static` bodies, even though they do have MIR.
         if !is_fn(hir_map, def_id) {
             continue;
@@ -271,12 +272,12 @@ fn register_std_constraints<'a, 'tcx, 'lty>(
     }
 }
 
-/// Run the analysis.
-pub fn analyze<'lty, 'a: 'lty, 'tcx: 'a>(
+/// Run the analysis up to the point where the polymorphic signature of every function is known.
+fn analyze_sigs<'lty, 'a: 'lty, 'tcx: 'a>(
     st: &CommandState,
     dcx: &RefactorCtxt<'a, 'tcx>,
     arena: &'lty SyncDroplessArena,
-) -> AnalysisResult<'lty, 'tcx> {
+) -> Ctxt<'lty, 'tcx> {
     let mut cx = Ctxt::new(dcx.ty_ctxt(), arena);
 
     // Process the annotations and marks provided by the user.
@@ -291,6 +292,17 @@ pub fn analyze<'lty, 'a: 'lty, 'tcx: 'a>(
     register_std_constraints(&mut cx, dcx.ty_ctxt());
     analyze_inter(&mut cx);
 
+    cx
+}
+
+/// Run the analysis.
+pub fn analyze<'lty, 'a: 'lty, 'tcx: 'a>(
+    st: &CommandState,
+    dcx: &RefactorCtxt<'a, 'tcx>,
+    arena: &'lty SyncDroplessArena,
+) -> AnalysisResult<'lty, 'tcx> {
+    let mut cx = analyze_sigs(st, dcx, arena);
+
     // Compute monomorphic signatures and select instantiations in each function
     compute_all_mono_sigs(&mut cx);
     find_instantiations(&mut cx);
@@ -299,6 +311,36 @@ pub fn analyze<'lty, 'a: 'lty, 'tcx: 'a>(
     cx.into()
 }
 
+/// Run the analysis, then remove monomorphizations that can't be implemented under the Rust type
+/// system, such as `WRITE -> MOVE` monos of generic accessors.  Returns the results along with the
+/// function and sig var assignment of each removed mono.
+pub fn analyze_pruned<'lty, 'a: 'lty, 'tcx: 'a>(
+    st: &CommandState,
+    dcx: &RefactorCtxt<'a, 'tcx>,
+    arena: &'lty SyncDroplessArena,
+) -> (AnalysisResult<'lty, 'tcx>, Vec<(DefId, IndexVec<Var, ConcretePerm>)>) {
+    let mut cx = analyze_sigs(st, dcx, arena);
+
+    compute_all_mono_sigs(&mut cx);
+    find_instantiations(&mut cx);
+    let pruned = prune_monos(&mut cx);
+
+    (cx.into(), pruned)
+}
+
+/// Run the analysis, and explain why each pointer in the signatures of `def_ids` must be `WRITE`
+/// or `MOVE`.  Returns one line for each such pointer, showing the chain of constraints that
+/// forces its permission.
+pub fn explain<'lty, 'a: 'lty, 'tcx: 'a>(
+    st: &CommandState,
+    dcx: &RefactorCtxt<'a, 'tcx>,
+    arena: &'lty SyncDroplessArena,
+    def_ids: &[DefId],
+) -> Vec<String> {
+    let cx = analyze_sigs(st, dcx, arena);
+    def_ids.iter().flat_map(|&did| explain::explain_sig(&cx, did)).collect()
+}
+
 /// A type where pointers are labeled with variables.
 pub type VTy<'lty, 'tcx> = LabeledTy<'lty, 'tcx, Option<Var>>;
 /// A signature where pointers are labeled with variables.
@@ -313,20 +355,20 @@ pub type PFnSig<'lty, 'tcx> = FnSig<'lty, 'tcx, Option<ConcretePerm>>;
 pub struct AnalysisResult<'lty, 'tcx> {
     /// The permission-labeled type of every non-fn item.  This includes statics, consts, and
     /// struct/enum fields.
-    pub statics: HashMap<DefId, PTy<'lty, 'tcx>>,
+    pub statics: IndexMap<DefId, PTy<'lty, 'tcx>>,
 
     /// Results for to each (analysis-level) function.  Note that only the primary variant of each
     /// variant group will have its `DefId` present in this table - look up `variants[&id].func_id`
     /// first if you aren't sure whether a `fn` is a primary variant.
-    pub funcs: HashMap<DefId, FunctionResult<'lty, 'tcx>>,
+    pub funcs: IndexMap<DefId, FunctionResult<'lty, 'tcx>>,
 
     /// Results for to each variant `fn`.  Every `fn` that was analyzed should have an entry
     /// in this table.
-    pub variants: HashMap<DefId, VariantResult>,
+    pub variants: IndexMap<DefId, VariantResult>,
 
     /// Results for each monomorphization of each analysis-level function, indexed by function ID
     /// and monomorphization index.
-    pub monos: HashMap<(DefId, usize), MonoResult>,
+    pub monos: IndexMap<(DefId, usize), MonoResult>,
 
     /// Arena used to allocate all type wrappers
     arena: &'lty SyncDroplessArena,
@@ -419,10 +461,10 @@ impl<'lty, 'tcx> AnalysisResult<'lty, 'tcx> {
 impl<'lty, 'tcx> From<Ctxt<'lty, 'tcx>> for AnalysisResult<'lty, 'tcx> {
     /// Extract the useful information from the `Ctxt`, and collect it into an `AnalysisResult`.
     fn from(cx: Ctxt<'lty, 'tcx>) -> AnalysisResult<'lty, 'tcx> {
-        let mut statics = HashMap::new();
-        let mut funcs = HashMap::new();
-        let mut variants = HashMap::new();
-        let mut monos = HashMap::new();
+        let mut statics = IndexMap::new();
+        let mut funcs = IndexMap::new();
+        let mut variants = IndexMap::new();
+        let mut monos = IndexMap::new();
 
         // statics
 
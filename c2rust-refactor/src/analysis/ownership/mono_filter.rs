@@ -1,114 +1,185 @@
-//! Filtering of monomorphizations.  We classify some monomorphizations as "suspicious", and skip
-//! generating code for them if they aren't used.  This module examines the monomorphizations and
-//! their call sites, and generates the list of monomorphizations to skip.
+//! Filtering of monomorphizations.  We classify some monomorphizations as "suspicious", and remove
+//! them if the rest of the program can do without them.  This runs as a post-pass after `inst`:
+//! removing monos too early (before instantiations are selected) causes `inst` to fail.
+//!
+//! Removing a mono changes the monos available at every call to its function, so each removal
+//! re-runs `inst` for the callers.  A removal is kept only if every caller mono that previously
+//! had a solution still has one.  Monos for which `inst` found no solution at all are also
+//! removed, since they can't be implemented either.  The process is repeated until no more monos
+//! can be removed.
 
-use std::collections::HashMap;
 use std::collections::HashSet;
-use std::collections::VecDeque;
 
+use indexmap::{IndexMap, IndexSet};
 use rustc::hir::def_id::DefId;
-use rustc_data_structures::indexed_vec::IndexVec;
+use rustc_index::vec::IndexVec;
 
-use super::{Var, ConcretePerm, Perm};
 use super::context::Ctxt;
+use super::inst::InstCtxt;
+use super::{ConcretePerm, Var};
 
+/// A mono, identified by its function and its assignment to sig vars.  Unlike mono indices, this
+/// doesn't change when other monos of the function are removed.
+type MonoKey = (DefId, Vec<ConcretePerm>);
 
-pub fn filter_suspicious_monos(
-    cx: &Ctxt,
-    all_mono_sigs: &HashMap<DefId, Vec<IndexVec<Var, ConcretePerm>>>,
-    all_inst_sels: &HashMap<(DefId, usize), Vec<usize>>)
-    -> HashSet<(DefId, usize)> {
-
-
-    // (1) Find suspicious mono sigs.
-    //
-    // It's common for accessor functions to get inferred signatures `READ -> READ`, `WRITE ->
-    // WRITE`, and `WRITE -> MOVE`.  This last one is suspicious because the presence of the `READ
-    // -> READ` sig suggests that no actual updates are happening to record the removal of the
-    // accessed element.
-    //
-    // The heuristic we currently use to find these cases is to look for fns where one mono sig has
-    // a `MOVE` output and the other has a `READ` output in the same position.  Note this doesn't
-    // actually look for a `READ` input like the description above said.
-
-    let mut suspicious = HashSet::new();
-    let mut all_monos = Vec::new();
-
-    for (&def_id, mono_sigs) in all_mono_sigs {
-        let summ = cx.get_fn_summ_imm(def_id).unwrap();
-
-        // Only look at inferred monos - ones provided by attributes are exempt.
-        if summ.attr_monos.is_none() {
-            let outputs = super::mono::infer_outputs(summ);
-
-            for (v, is_output) in outputs.iter_enumerated() {
-                if !is_output {
-                    continue;
-                }
+fn mono_key(cx: &Ctxt, func_did: DefId, mono_idx: usize) -> MonoKey {
+    let assign = &cx.get_mono_summ(func_did, mono_idx).assign;
+    (func_did, assign.iter().cloned().collect())
+}
 
-                if !mono_sigs.iter().any(|assign| assign[v] == ConcretePerm::Read) {
-                    continue;
-                }
+fn find_mono_idx(cx: &Ctxt, key: &MonoKey) -> Option<usize> {
+    let func = cx.get_func_summ(key.0);
+    (0..func.num_monos).find(|&idx| mono_key(cx, key.0, idx) == *key)
+}
 
-                for (i, assign) in mono_sigs.iter().enumerate() {
-                    if assign[v] == ConcretePerm::Move {
-                        suspicious.insert((def_id, i));
-                        eprintln!("found suspicious mono: {:?} #{}", def_id, i);
-                    }
-                }
-            }
-        }
+/// Can monos of this function be removed at all?  Monos provided by attributes are exempt, and
+/// monos of multi-variant functions correspond to separate source-level functions.
+fn is_prunable(cx: &Ctxt, func_did: DefId) -> bool {
+    let func = cx.get_func_summ(func_did);
+    !func.monos_provided && func.variant_ids.len() == 1
+}
 
-        for i in 0 .. mono_sigs.len() {
-            all_monos.push((def_id, i));
-        }
-    }
+fn is_solved(cx: &Ctxt, func_did: DefId, mono_idx: usize) -> bool {
+    let variant = cx.get_mono_variant_summ(func_did, mono_idx);
+    cx.get_mono_summ(func_did, mono_idx).callee_mono_idxs.len() == variant.insts.len()
+}
 
+/// Find the monos that are candidates for removal, in a deterministic order.
+fn find_candidates(cx: &Ctxt) -> Vec<MonoKey> {
+    let mut candidates = Vec::new();
 
-    // (2) Build the call graph.  Suspicious monos that have no calls from non-suspicious monos
-    // will be filtered out.
+    for def_id in cx.func_ids() {
+        if !is_prunable(cx, def_id) {
+            continue;
+        }
+        let summ = cx.get_func_summ(def_id);
 
-    let mut call_graph = HashMap::new();
-    let mut caller_count = HashMap::new();
+        // (1) Monos for which `inst` found no solution.
+        for idx in 0..summ.num_monos {
+            if !is_solved(cx, def_id, idx) {
+                candidates.push(mono_key(cx, def_id, idx));
+            }
+        }
 
-    for (&(def_id, src_idx), inst_sel) in all_inst_sels {
-        let summ = cx.get_fn_summ_imm(def_id).unwrap();
+        // (2) Suspicious mono sigs.
+        //
+        // It's common for accessor functions to get inferred signatures `READ -> READ`, `WRITE ->
+        // WRITE`, and `WRITE -> MOVE`.  This last one is suspicious because the presence of the
+        // `READ -> READ` sig suggests that no actual updates are happening to record the removal
+        // of the accessed element.
+        //
+        // The heuristic we currently use to find these cases is to look for fns where one mono
+        // sig has a `MOVE` output and the other has a `READ` output in the same position.  Note
+        // this doesn't actually look for a `READ` input like the description above said.
+        let outputs = super::mono::infer_outputs(summ);
+        let assigns = (0..summ.num_monos)
+            .map(|idx| &cx.get_mono_summ(def_id, idx).assign)
+            .collect::<Vec<_>>();
+        for (v, &is_output) in outputs.iter_enumerated() {
+            if !is_output || !assigns.iter().any(|assign| assign[v] == ConcretePerm::Read) {
+                continue;
+            }
 
-        for (inst, &dest_idx) in summ.insts.iter().zip(inst_sel.iter()) {
-            let src = (def_id, src_idx);
-            let dest = (inst.callee, dest_idx);
-            call_graph.entry(src).or_insert_with(HashSet::new).insert(dest);
-            *caller_count.entry(dest).or_insert(0) += 1;
+            for (idx, assign) in assigns.iter().enumerate() {
+                if assign[v] == ConcretePerm::Move {
+                    debug!("found suspicious mono: {:?} #{}", def_id, idx);
+                    let key = mono_key(cx, def_id, idx);
+                    if !candidates.contains(&key) {
+                        candidates.push(key);
+                    }
+                }
+            }
         }
     }
 
+    candidates
+}
 
-    // (3) Search the callgraph for suspicious monos with only suspicious callers.
-
-    let mut queue = VecDeque::new();
-    let mut filter = HashSet::new();
+/// Build a map from each function to the functions that call it.
+fn build_callers(cx: &Ctxt) -> IndexMap<DefId, IndexSet<DefId>> {
+    let mut callers = IndexMap::new();
+    for var_id in cx.variant_ids() {
+        let variant = cx.get_variant_summ(var_id);
+        for inst in &variant.insts {
+            callers
+                .entry(inst.callee)
+                .or_insert_with(IndexSet::new)
+                .insert(variant.func_id);
+        }
+    }
+    callers
+}
 
-    for &src in &all_monos {
-        let count = caller_count.get(&src).map(|&x| x).unwrap_or(0);
-        eprintln!("{:?}: {} callers, suspicious? {}", src, count, suspicious.contains(&src));
-        if suspicious.contains(&src) && count == 0 {
-            queue.push_back(src);
+/// Re-run `inst` for every mono of the functions in `callers`.  Returns the new callee mono
+/// selections, or `None` if some mono that previously had a solution no longer has one.
+fn resolve_callers(
+    cx: &Ctxt,
+    callers: &IndexSet<DefId>,
+) -> Option<Vec<((DefId, usize), Vec<usize>)>> {
+    let mut new_sels = Vec::new();
+    for &func_did in callers {
+        for mono_idx in 0..cx.get_func_summ(func_did).num_monos {
+            let was_solved = is_solved(cx, func_did, mono_idx);
+            let inst_sel = InstCtxt::new(cx, func_did, mono_idx).try_solve_instantiations();
+            match inst_sel {
+                Some(inst_sel) => new_sels.push(((func_did, mono_idx), inst_sel)),
+                None if was_solved => {
+                    debug!("  caller {:?} #{} has no solution", func_did, mono_idx);
+                    return None;
+                }
+                None => new_sels.push(((func_did, mono_idx), vec![])),
+            }
         }
     }
+    Some(new_sels)
+}
 
-    while let Some(src) = queue.pop_front() {
-        filter.insert(src);
+/// Remove unsolvable and suspicious monos, as long as every caller can still select an
+/// instantiation without them.  A function always keeps at least one mono.  Returns the function
+/// and sig var assignment of each removed mono.
+pub fn prune_monos(cx: &mut Ctxt) -> Vec<(DefId, IndexVec<Var, ConcretePerm>)> {
+    let callers = build_callers(cx);
+    let no_callers = IndexSet::new();
+
+    let mut pruned = Vec::new();
+    loop {
+        // Monos whose removal was tried and undone in this round.  Removing other monos may
+        // change the outcome, so these get another chance in the next round.
+        let mut rejected = HashSet::new();
+        let mut changed = false;
+
+        for key in find_candidates(cx) {
+            let func_did = key.0;
+            let mono_idx = match find_mono_idx(cx, &key) {
+                Some(x) => x,
+                None => continue,
+            };
+            if rejected.contains(&key) || cx.get_func_summ(func_did).num_monos <= 1 {
+                continue;
+            }
 
-        // `src` has been filtered out.  Decrement the caller count for each of its callees.
-        for &dest in call_graph.entry(src).or_insert_with(HashSet::new).iter() {
-            let count = caller_count.get_mut(&dest).unwrap();
-            *count -= 1;
-            if *count == 0 {
-                queue.push_back(dest);
+            debug!("try removing mono {:?} #{}", func_did, mono_idx);
+            let mono = cx.remove_mono(func_did, mono_idx);
+            let fn_callers = callers.get(&func_did).unwrap_or(&no_callers);
+            match resolve_callers(cx, fn_callers) {
+                Some(new_sels) => {
+                    for ((caller, idx), inst_sel) in new_sels {
+                        cx.mono_summ(caller, idx).2.callee_mono_idxs = inst_sel;
+                    }
+                    pruned.push((func_did, mono.assign));
+                    changed = true;
+                }
+                None => {
+                    cx.insert_mono(func_did, mono_idx, mono);
+                    rejected.insert(key);
+                }
             }
         }
-    }
 
+        if !changed {
+            break;
+        }
+    }
 
-    filter
+    pruned
 }
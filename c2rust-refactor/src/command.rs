@@ -641,8 +641,9 @@ pub trait Command {
     fn run(&mut self, state: &mut RefactorState);
 }
 
-/// A command builder is a function that takes some string arguments and produces a `Command`.
-pub type Builder = dyn FnMut(&[String]) -> Box<dyn Command> + Send;
+/// A command builder is a function that takes some string arguments and produces a `Command`, or
+/// an error message if the arguments are invalid.
+pub type Builder = dyn FnMut(&[String]) -> Result<Box<dyn Command>, String> + Send;
 
 /// Tracks known refactoring command builders, and allows invoking them by name.
 pub struct Registry {
@@ -656,9 +657,17 @@ impl Registry {
         }
     }
 
-    pub fn register<B>(&mut self, name: &str, builder: B)
+    pub fn register<B>(&mut self, name: &str, mut builder: B)
     where
         B: FnMut(&[String]) -> Box<dyn Command> + 'static + Send,
+    {
+        self.register_checked(name, move |args| Ok(builder(args)));
+    }
+
+    /// Register a command whose builder validates its arguments.
+    pub fn register_checked<B>(&mut self, name: &str, builder: B)
+    where
+        B: FnMut(&[String]) -> Result<Box<dyn Command>, String> + 'static + Send,
     {
         self.commands.insert(name.to_owned(), Box::new(builder));
     }
@@ -668,7 +677,7 @@ impl Registry {
             Some(command) => command,
             None => return Err(format!("Invalid command: {:#?}", name)),
         };
-        Ok(builder(args))
+        builder(args)
     }
 }
 
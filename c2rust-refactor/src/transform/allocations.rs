@@ -0,0 +1,756 @@
+use std::collections::{HashMap, HashSet};
+
+use rustc::hir::def_id::DefId;
+use rustc::ty;
+use rustc_target::spec::abi::Abi;
+use syntax::ast::*;
+use syntax::ptr::P;
+use syntax::symbol::Symbol;
+use syntax_pos::Span;
+
+use c2rust_ast_builder::{mk, IntoSymbol};
+use crate::ast_manip::{visit_nodes, AstEquiv, FlatMapNodes, MutVisitNodes};
+use crate::ast_manip::lr_expr::{self, fold_exprs_with_context};
+use crate::command::{CommandState, Registry};
+use crate::driver::{Phase, parse_expr, parse_ty};
+use crate::matcher::{Bindings, Subst};
+use crate::transform::Transform;
+use crate::transform::ptr_idioms::{
+    alloc_size, callee_name, field_def_id, is_copy, is_null_ptr, is_size_of, offset_base,
+    peel_casts, struct_field_def_id, zero_value, AllocSize,
+};
+use crate::RefactorCtxt;
+
+
+/// # `convert_allocations` Command
+///
+/// Usage: `convert_allocations [MARK]`
+///
+/// Marks: reads `MARK`/`target`
+///
+/// Replace `malloc`, `calloc`, `realloc` and `free` of the `*mut T` locals and
+/// struct fields bearing `MARK` (default: `target`) with safe allocations.
+/// Marking a struct selects all of its fields.  The new type of each pointer
+/// depends on the values assigned to it:
+///
+///  * If it is assigned single objects, `malloc(size_of::<T>())` or
+///    `calloc(1, size_of::<T>())`, it becomes `Box<T>`.  It becomes
+///    `Option<Box<T>>` if it is also assigned a null pointer, or if it is a
+///    field that gets freed.
+///  * If it is assigned arrays, `malloc(n * size_of::<T>())` or
+///    `calloc(n, size_of::<T>())`, it becomes `c2rust_runtime::CArray<T>`.
+///    If it is also grown with `p = realloc(p, n * size_of::<T>())`, or `T` is
+///    not an integer, float, `bool` or raw pointer type (for which `CArray`
+///    can't safely zero-initialize its elements), it becomes `Vec<T>` instead.
+///    Null pointers become empty arrays.
+///
+/// A `Box` or `Vec` field is wrapped in `Option` unless every value of its
+/// struct is built by a struct literal.  Values made from raw memory, such as
+/// `mem::zeroed()`, a `calloc` cast to the struct's pointer type, a pointer
+/// passed to `memset`, or a struct passed through a non-Rust function, could
+/// otherwise hold a null `Box` or `Vec`.  `CArray` is valid as all zeros.
+///
+/// Allocations become `Box::new`, `Vec` and `CArray::alloc` calls, and every
+/// new object or element is zero-initialized, as with `calloc`: `Box` and
+/// `Vec` elements start out as the zero value of `T` (`0`, a null pointer, or
+/// a struct literal of those), and a pointer to a type with no such value,
+/// like an enum, is left unchanged.  `realloc` becomes `Vec::resize_with`.  Dereferences become their safe equivalents,
+/// `*p.offset(i)` on an array becomes `p[i]`, `is_null` checks become
+/// emptiness checks, and `free(p)` becomes a drop.  A pointer passed to a
+/// function is lent to it as a raw pointer.
+///
+/// A pointer assigned anything other than a new allocation or null, or used as
+/// a raw pointer in some other way, keeps its raw pointer type, and a warning
+/// reports the use that prevented the conversion.  Fields of `Copy` structs are
+/// never converted.  The crate must import `c2rust_runtime` (for example, with
+/// `create_item 'extern crate c2rust_runtime;' inside`) before `CArray` can be
+/// used.
+pub struct ConvertAllocations {
+    pub label: Symbol,
+}
+
+impl Transform for ConvertAllocations {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        let mut reports = Vec::new();
+
+        // (1) Find the marked pointers.
+        let mut cands: HashMap<AllocDef, AllocPtr> = HashMap::new();
+
+        visit_nodes(&*krate, |l: &Local| {
+            if !st.marked(l.id, self.label) && !st.marked(l.pat.id, self.label) {
+                return;
+            }
+            let ident = match_or!([l.pat.kind] PatKind::Ident(BindingMode::ByValue(_), ident, None)
+                                  => ident; return);
+            let ty = match_or!([l.ty] Some(ref ty) => ty; return);
+            let desc = format!("local `{}`", ident);
+            if let Some(ptr) = AllocPtr::new(cx, ty, cx.opt_node_type(l.pat.id), desc) {
+                cands.insert(AllocDef::Local(l.pat.id), ptr);
+            }
+        });
+
+        visit_nodes(&*krate, |i: &Item| {
+            let fields = match i.kind {
+                ItemKind::Struct(VariantData::Struct(ref fields, _), _) => fields,
+                _ => return,
+            };
+            let struct_marked = st.marked(i.id, self.label);
+            for f in fields {
+                if !struct_marked && !st.marked(f.id, self.label) {
+                    continue;
+                }
+                let name = match_or!([f.ident] Some(x) => x; continue);
+                let did = cx.node_def_id(f.id);
+                let desc = format!("field `{}::{}`", i.ident, name);
+                let mut ptr = match_or!([AllocPtr::new(cx, &f.ty, Some(cx.def_type(did)), desc)]
+                                        Some(x) => x; continue);
+                ptr.owner = Some(cx.node_def_id(i.id));
+                if is_copy(cx, cx.node_def_id(i.id)) {
+                    reports.push((f.span, format!(
+                        "the struct is `Copy`; keeping {} as a raw pointer", ptr.desc)));
+                    continue;
+                }
+                cands.insert(AllocDef::Field(did), ptr);
+            }
+        });
+
+        // (2) Find every use of the marked pointers, and every value assigned to them.
+        let mut scan = AllocScan {
+            cx,
+            cands: &cands,
+            uses: HashMap::new(),
+            values: HashMap::new(),
+            free_calls: HashSet::new(),
+            unproven: HashSet::new(),
+        };
+        fold_exprs_with_context(krate, |e, _ectx| scan.visit_expr(e));
+        visit_nodes(&*krate, |l: &Local| {
+            let def = AllocDef::Local(l.pat.id);
+            if let (true, Some(init)) = (scan.cands.contains_key(&def), l.init.as_ref()) {
+                scan.add_value(l.id, def, init, None);
+            }
+        });
+        visit_nodes(&*krate, |i: &Item| {
+            if let ItemKind::Fn(_, ref header, _, _) = i.kind {
+                if header.abi != Abi::Rust {
+                    scan.add_foreign_sig(cx.node_def_id(i.id));
+                }
+            }
+        });
+        visit_nodes(&*krate, |i: &ForeignItem| {
+            if let ForeignItemKind::Fn(..) = i.kind {
+                scan.add_foreign_sig(cx.node_def_id(i.id));
+            }
+        });
+        let AllocScan { uses, values, free_calls, unproven, .. } = scan;
+
+        // (3) Pick the new type of each pointer.
+        let mut ptrs = HashMap::new();
+        for (&def, cand) in &cands {
+            match choose_alloc(def, cand, &uses, &values, &unproven) {
+                Ok(ptr) => {
+                    ptrs.insert(def, ptr);
+                },
+                Err((span, reason)) => reports.push((span, format!(
+                    "{}; keeping {} as a raw pointer", reason, cand.desc))),
+            }
+        }
+
+        reports.sort_by_key(|&(span, _)| span.lo());
+        for (span, msg) in &reports {
+            warn!("convert_allocations: {}: {}",
+                  cx.session().source_map().span_to_string(*span), msg);
+        }
+
+        // (4) Rewrite the crate.
+        let rw = AllocRewriter { st, cx };
+        let retyped = |id: NodeId| uses.get(&id).and_then(|u| Some((u, ptrs.get(&u.def)?)));
+
+        fold_exprs_with_context(krate, |e, ectx| {
+            if let Some(a) = values.get(&e.id) {
+                let ptr = match_or!([ptrs.get(&a.def)] Some(x) => x; return);
+                if let Value::Realloc(_, ref n) = a.value {
+                    let lhs = match_or!([e.kind] ExprKind::Assign(ref lhs, _) => lhs.clone(); return);
+                    *e = rw.resize(lhs, n.clone(), ptr);
+                } else if let ExprKind::Assign(_, ref mut rhs) = e.kind {
+                    *rhs = rw.value(&a.value, ptr);
+                }
+                return;
+            }
+
+            if let ExprKind::Struct(_, ref mut fields, _) = e.kind {
+                for f in fields {
+                    let a = match_or!([values.get(&f.id)] Some(x) => x; continue);
+                    if let Some(ptr) = ptrs.get(&a.def) {
+                        f.expr = rw.value(&a.value, ptr);
+                    }
+                }
+                return;
+            }
+
+            let new_expr = match e.kind {
+                ExprKind::Unary(UnOp::Deref, ref ptr) => match offset_base(ptr) {
+                    Some((base, idx)) => {
+                        retyped(base.id).map(|(_, p)| rw.index(base.clone(), idx.clone(), p, ectx))
+                    },
+                    None => retyped(ptr.id).and_then(|(_, p)| rw.deref(ptr.clone(), p, ectx)),
+                },
+                ExprKind::MethodCall(ref seg, ref args)
+                        if seg.ident.name == "is_null".into_symbol() && args.len() == 1 => {
+                    retyped(args[0].id).map(|(_, p)| rw.is_null(args[0].clone(), p))
+                },
+                ExprKind::Call(_, ref args) if free_calls.contains(&e.id) => {
+                    let ptr = peel_casts(&args[0]);
+                    retyped(ptr.id).map(|(u, p)| rw.free(ptr.clone(), u.def, p))
+                },
+                _ => None,
+            };
+            if let Some(new_expr) = new_expr {
+                *e = new_expr;
+            } else if let Some((u, p)) = retyped(e.id) {
+                if let UseKind::Arg = u.kind {
+                    *e = rw.to_raw(e.clone(), p);
+                }
+            }
+        });
+
+        MutVisitNodes::visit(krate, |l: &mut P<Local>| {
+            let ptr = match_or!([ptrs.get(&AllocDef::Local(l.pat.id))] Some(x) => x; return);
+            if let Some(a) = values.get(&l.id) {
+                l.init = Some(rw.value(&a.value, ptr));
+            }
+            l.ty = Some(rw.new_ty(ptr));
+            if let PatKind::Ident(ref mut mode, _, _) = l.pat.kind {
+                *mode = BindingMode::ByValue(Mutability::Mutable);
+            }
+        });
+
+        FlatMapNodes::visit(krate, |mut sf: StructField| {
+            if let Some(ptr) = ptrs.get(&AllocDef::Field(cx.node_def_id(sf.id))) {
+                sf.ty = rw.new_ty(ptr);
+            }
+            smallvec![sf]
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// A local or struct field holding a pointer that `convert_allocations` retypes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum AllocDef {
+    /// A local, identified by the `NodeId` of its binding pattern.
+    Local(NodeId),
+    Field(DefId),
+}
+
+/// A marked pointer, before its new type is chosen.
+struct AllocPtr {
+    pointee: P<Ty>,
+    /// Whether all zeros is a valid `pointee`, as `CArray` requires.
+    pointee_nullable: bool,
+    /// The zero value of `pointee`, which initializes new `Box` and `Vec` elements.
+    zero: Option<P<Expr>>,
+    /// For a field, the struct it belongs to.
+    owner: Option<DefId>,
+    span: Span,
+    /// Description used in reports, such as "local `p`".
+    desc: String,
+}
+
+impl AllocPtr {
+    fn new<'tcx>(cx: &RefactorCtxt<'_, 'tcx>,
+                 ty: &P<Ty>,
+                 rty: Option<ty::Ty<'tcx>>,
+                 desc: String) -> Option<AllocPtr> {
+        let mty = match_or!([ty.kind] TyKind::Ptr(ref mty) => mty; return None);
+        if mty.mutbl != Mutability::Mutable {
+            return None;
+        }
+        // Pointers to `c_void` don't say what they point to.
+        if let TyKind::Path(None, ref path) = mty.ty.kind {
+            if path.segments.last().map_or(false, |seg| &*seg.ident.as_str() == "c_void") {
+                return None;
+            }
+        }
+        let pointee_rty = match rty.map(|rty| &rty.kind) {
+            Some(ty::TyKind::RawPtr(mt)) => Some(mt.ty),
+            _ => None,
+        };
+        Some(AllocPtr {
+            pointee: mty.ty.clone(),
+            pointee_nullable: pointee_rty.map_or(false, is_nullable),
+            zero: pointee_rty.and_then(|rty| zero_value(cx, rty)),
+            owner: None,
+            span: ty.span,
+            desc,
+        })
+    }
+}
+
+/// The safe type replacing a raw pointer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum AllocKind {
+    Box,
+    Vec,
+    CArray,
+}
+
+struct SafeAlloc {
+    kind: AllocKind,
+    /// Whether a `Box` or `Vec` is wrapped in `Option`.  Otherwise, arrays use the empty array
+    /// for null.
+    nullable: bool,
+    pointee: P<Ty>,
+    zero: Option<P<Expr>>,
+}
+
+/// A value assigned to one of the marked pointers.
+#[derive(Clone, Debug)]
+enum Value {
+    Null,
+    /// A `malloc` or `calloc` of a single object
+    Single,
+    /// A `malloc` or `calloc` of an array with the given number of elements
+    Array(P<Expr>),
+    /// A `realloc` of the pointer itself, with the `NodeId` of the reallocated pointer expr
+    /// and the new number of elements
+    Realloc(NodeId, P<Expr>),
+    Other,
+}
+
+struct Assign {
+    def: AllocDef,
+    value: Value,
+    span: Span,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum UseKind {
+    Deref,
+    /// The base pointer of `*p.offset(i)`
+    Index,
+    IsNull,
+    Free,
+    AssignLhs,
+    ReallocArg,
+    /// An argument to a call, which can borrow the allocation as a raw pointer
+    Arg,
+    Other,
+}
+
+struct Use {
+    def: AllocDef,
+    kind: UseKind,
+    span: Span,
+}
+
+/// Functions that make a value of their return type from raw memory.
+const RAW_INITS: &[&str] = &["zeroed", "uninitialized", "transmute"];
+
+struct AllocScan<'a, 'tcx: 'a> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    cands: &'a HashMap<AllocDef, AllocPtr>,
+
+    uses: HashMap<NodeId, Use>,
+    /// Values assigned by `let` initializers, assignments and struct literal fields, by the
+    /// `NodeId` of the `Local`, `Assign` expr or `ast::Field`
+    values: HashMap<NodeId, Assign>,
+    free_calls: HashSet<NodeId>,
+    /// Structs whose values may be made from raw memory rather than a struct literal
+    unproven: HashSet<DefId>,
+}
+
+impl<'a, 'tcx> AllocScan<'a, 'tcx> {
+    fn def_of(&self, e: &Expr) -> Option<AllocDef> {
+        let def = match e.kind {
+            ExprKind::Path(..) => {
+                let hir_id = self.cx.try_resolve_expr_to_hid(e)?;
+                AllocDef::Local(self.cx.hir_map().hir_to_node_id(hir_id))
+            },
+            ExprKind::Field(ref obj, ident) => AllocDef::Field(field_def_id(self.cx, obj, ident)?),
+            _ => return None,
+        };
+        if self.cands.contains_key(&def) {
+            Some(def)
+        } else {
+            None
+        }
+    }
+
+    /// Change the kind of a recorded use.  Exprs are visited bottom-up, so the parent
+    /// expression changes the kind if it handles the pointer itself.
+    fn consume(&mut self, id: NodeId, kind: UseKind) {
+        if let Some(u) = self.uses.get_mut(&id) {
+            u.kind = kind;
+        }
+    }
+
+    fn add_value(&mut self, id: NodeId, def: AllocDef, e: &P<Expr>, lhs: Option<&P<Expr>>) {
+        let value = classify(self.cx, e, lhs);
+        if let Value::Realloc(ptr_id, _) = value {
+            self.consume(ptr_id, UseKind::ReallocArg);
+        }
+        self.values.insert(id, Assign { def, value, span: e.span });
+    }
+
+    /// Record that values of `ty` may be made from raw memory, so any `Box` or `Vec` field of a
+    /// struct stored inline in `ty` may be null.
+    fn add_unproven(&mut self, ty: ty::Ty<'tcx>) {
+        match ty.kind {
+            ty::TyKind::Adt(adt, substs) => {
+                if self.unproven.insert(adt.did) {
+                    let tcx = self.cx.ty_ctxt();
+                    for f in adt.all_fields() {
+                        self.add_unproven(f.ty(tcx, substs));
+                    }
+                }
+            },
+            ty::TyKind::Array(elem, _) | ty::TyKind::Slice(elem) => self.add_unproven(elem),
+            _ => {},
+        }
+    }
+
+    /// Structs passed to or returned from a non-Rust function, by value or through pointers,
+    /// may be built by foreign code.
+    fn add_foreign_sig(&mut self, did: DefId) {
+        let sig = self.cx.ty_ctxt().fn_sig(did);
+        let sig = sig.skip_binder();
+        for &ty in sig.inputs().iter().chain(Some(sig.output()).iter()) {
+            let mut ty = ty;
+            while let Some(pointee) = pointee_ty(ty) {
+                ty = pointee;
+            }
+            self.add_unproven(ty);
+        }
+    }
+
+    fn visit_expr(&mut self, e: &Expr) {
+        match e.kind {
+            ExprKind::Path(..) | ExprKind::Field(..) => {
+                if let Some(def) = self.def_of(e) {
+                    self.uses.insert(e.id, Use { def, kind: UseKind::Other, span: e.span });
+                }
+            },
+
+            ExprKind::Unary(UnOp::Deref, ref ptr) => match offset_base(ptr) {
+                Some((base, _)) => self.consume(base.id, UseKind::Index),
+                None => self.consume(ptr.id, UseKind::Deref),
+            },
+
+            ExprKind::MethodCall(ref seg, ref args)
+                    if seg.ident.name == "is_null".into_symbol() && args.len() == 1 => {
+                self.consume(args[0].id, UseKind::IsNull);
+            },
+
+            ExprKind::Call(_, ref args) => {
+                let callee = callee_name(self.cx, e);
+                if args.len() == 1 && callee.as_ref().map_or(false, |n| n == "free") {
+                    self.consume(peel_casts(&args[0]).id, UseKind::Free);
+                    self.free_calls.insert(e.id);
+                } else {
+                    for arg in args {
+                        self.consume(peel_casts(arg).id, UseKind::Arg);
+                    }
+                }
+                if callee.as_ref().map_or(false, |n| RAW_INITS.contains(&n.as_str())) {
+                    if let Some(ty) = self.cx.opt_node_type(e.id) {
+                        self.add_unproven(ty);
+                    }
+                }
+            },
+
+            // A cast between pointers to different types, like `calloc(..) as *mut S` or
+            // `&mut s as *mut S as *mut c_void` passed to `memset`, lets raw memory be written
+            // as either type.
+            ExprKind::Cast(ref inner, _) => {
+                let from = self.cx.opt_node_type(inner.id).and_then(pointee_ty);
+                let to = self.cx.opt_node_type(e.id).and_then(pointee_ty);
+                if let (Some(from), Some(to)) = (from, to) {
+                    if from != to {
+                        self.add_unproven(from);
+                        self.add_unproven(to);
+                    }
+                }
+            },
+
+            ExprKind::Assign(ref lhs, ref rhs) => {
+                if let Some(def) = self.uses.get(&lhs.id).map(|u| u.def) {
+                    self.consume(lhs.id, UseKind::AssignLhs);
+                    self.add_value(e.id, def, rhs, Some(lhs));
+                }
+            },
+
+            ExprKind::Struct(_, ref fields, _) => {
+                for f in fields {
+                    let did = match_or!([struct_field_def_id(self.cx, e, f.ident)]
+                                        Some(x) => x; continue);
+                    if self.cands.contains_key(&AllocDef::Field(did)) {
+                        self.add_value(f.id, AllocDef::Field(did), &f.expr, None);
+                    }
+                }
+            },
+
+            _ => {},
+        }
+    }
+}
+
+/// Pick the new type of `cand` from the values assigned to it, or give the reason it has to
+/// stay a raw pointer.
+fn choose_alloc(def: AllocDef,
+                cand: &AllocPtr,
+                uses: &HashMap<NodeId, Use>,
+                values: &HashMap<NodeId, Assign>,
+                unproven: &HashSet<DefId>) -> Result<SafeAlloc, (Span, &'static str)> {
+    let mut def_values = values.values().filter(|a| a.def == def).collect::<Vec<_>>();
+    def_values.sort_by_key(|a| a.span.lo());
+    let mut def_uses = uses.values().filter(|u| u.def == def).collect::<Vec<_>>();
+    def_uses.sort_by_key(|u| u.span.lo());
+
+    let (mut null, mut single, mut array, mut realloc) = (false, false, false, false);
+    for a in def_values {
+        match a.value {
+            Value::Null => null = true,
+            Value::Single => single = true,
+            Value::Array(_) => array = true,
+            Value::Realloc(..) => realloc = true,
+            Value::Other =>
+                return Err((a.span, "it is assigned a pointer that isn't a new allocation")),
+        }
+    }
+
+    let kind = match (single, array || realloc) {
+        (true, false) => AllocKind::Box,
+        (false, true) if realloc || !cand.pointee_nullable => AllocKind::Vec,
+        (false, true) => AllocKind::CArray,
+        (true, true) => return Err((cand.span, "it holds both single objects and arrays")),
+        (false, false) =>
+            return Err((cand.span, "it is never assigned a `malloc` or `calloc` allocation")),
+    };
+    if kind != AllocKind::CArray && cand.zero.is_none() {
+        return Err((cand.span, "the pointee type has no zero value to initialize new objects with"));
+    }
+
+    let mut freed_field = false;
+    for u in def_uses {
+        match u.kind {
+            UseKind::Other => return Err((u.span, "it is used as a raw pointer")),
+            UseKind::Index if kind == AllocKind::Box =>
+                return Err((u.span, "it is offset like an array, but points to a single object")),
+            UseKind::Free if matches!([def] AllocDef::Field(_)) => freed_field = true,
+            _ => {},
+        }
+    }
+
+    // An all-zeros `Box` or `Vec` is invalid, so a field of a struct that may be made from raw
+    // memory needs an `Option`.
+    let unproven_field = cand.owner.map_or(false, |did| unproven.contains(&did));
+    let nullable = match kind {
+        AllocKind::Box => null || freed_field || unproven_field,
+        AllocKind::Vec => unproven_field,
+        AllocKind::CArray => false,
+    };
+
+    Ok(SafeAlloc {
+        kind,
+        nullable,
+        pointee: cand.pointee.clone(),
+        zero: cand.zero.clone(),
+    })
+}
+
+/// Builds the expressions replacing allocations and uses of the retyped pointers.
+struct AllocRewriter<'a, 'tcx: 'a> {
+    st: &'a CommandState,
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+}
+
+impl<'a, 'tcx> AllocRewriter<'a, 'tcx> {
+    /// Substitute the pointee type of `ptr` for `__t`, its zero value for `__z`, and each of
+    /// `exprs`, in an expression template.
+    fn subst(&self, template: &str, ptr: &SafeAlloc, exprs: &[(&str, P<Expr>)]) -> P<Expr> {
+        let mut bnd = Bindings::new();
+        bnd.add("__t", ptr.pointee.clone());
+        if let Some(ref zero) = ptr.zero {
+            bnd.add("__z", zero.clone());
+        }
+        for (name, e) in exprs {
+            bnd.add(*name, e.clone());
+        }
+        parse_expr(self.cx.session(), template).subst(self.st, self.cx, &bnd)
+    }
+
+    fn new_ty(&self, ptr: &SafeAlloc) -> P<Ty> {
+        let ty = match (ptr.kind, ptr.nullable) {
+            (AllocKind::Box, false) => "Box<__t>",
+            (AllocKind::Box, true) => "Option<Box<__t>>",
+            (AllocKind::Vec, false) => "Vec<__t>",
+            (AllocKind::Vec, true) => "Option<Vec<__t>>",
+            (AllocKind::CArray, _) => "::c2rust_runtime::CArray<__t>",
+        };
+        let mut bnd = Bindings::new();
+        bnd.add("__t", ptr.pointee.clone());
+        parse_ty(self.cx.session(), ty).subst(self.st, self.cx, &bnd)
+    }
+
+    /// Build the new value of a pointer assigned `value`.
+    fn value(&self, value: &Value, ptr: &SafeAlloc) -> P<Expr> {
+        match (value, ptr.kind) {
+            (Value::Null, AllocKind::Box) => self.subst("None", ptr, &[]),
+            (Value::Null, AllocKind::Vec) if ptr.nullable => self.subst("None", ptr, &[]),
+            (Value::Null, AllocKind::Vec) => self.subst("Vec::new()", ptr, &[]),
+            (Value::Null, AllocKind::CArray) =>
+                self.subst("::c2rust_runtime::CArray::empty()", ptr, &[]),
+            (Value::Single, _) if ptr.nullable => self.subst("Some(Box::new(__z))", ptr, &[]),
+            (Value::Single, _) => self.subst("Box::new(__z)", ptr, &[]),
+            (Value::Array(n), AllocKind::Vec) if ptr.nullable => self.subst(
+                "Some(::std::iter::repeat_with(|| __z).take(__n as usize).collect::<Vec<__t>>())",
+                ptr, &[("__n", n.clone())]),
+            (Value::Array(n), AllocKind::Vec) => self.subst(
+                "::std::iter::repeat_with(|| __z).take(__n as usize).collect::<Vec<__t>>()",
+                ptr, &[("__n", n.clone())]),
+            (Value::Array(n), _) =>
+                self.subst("::c2rust_runtime::CArray::alloc(__n as usize)",
+                           ptr, &[("__n", n.clone())]),
+            _ => panic!("can't convert {:?} to {:?}", value, ptr.kind),
+        }
+    }
+
+    /// Replace `e = realloc(e, ...)` with an in-place resize of `e`.
+    fn resize(&self, e: P<Expr>, n: P<Expr>, ptr: &SafeAlloc) -> P<Expr> {
+        let template = if ptr.nullable {
+            "__e.get_or_insert_with(Vec::new).resize_with(__n as usize, || __z)"
+        } else {
+            "__e.resize_with(__n as usize, || __z)"
+        };
+        self.subst(template, ptr, &[("__e", e), ("__n", n)])
+    }
+
+    /// Dereference a retyped pointer.  Returns `None` if `*e` needs no change.
+    fn deref(&self, e: P<Expr>, ptr: &SafeAlloc, ectx: lr_expr::Context) -> Option<P<Expr>> {
+        let template = match (ptr.kind, ptr.nullable, ectx) {
+            (AllocKind::Box, false, _) => return None,
+            (AllocKind::Box, true, lr_expr::Context::LvalueMut) => "**__e.as_mut().unwrap()",
+            (AllocKind::Box, true, _) => "**__e.as_ref().unwrap()",
+            (AllocKind::Vec, true, lr_expr::Context::LvalueMut) => "__e.as_mut().unwrap()[0]",
+            (AllocKind::Vec, true, _) => "__e.as_ref().unwrap()[0]",
+            (_, _, _) => "__e[0]",
+        };
+        Some(self.subst(template, ptr, &[("__e", e)]))
+    }
+
+    /// Replace `*e.offset(idx)` with indexing.
+    fn index(&self, e: P<Expr>, idx: P<Expr>, ptr: &SafeAlloc, ectx: lr_expr::Context)
+             -> P<Expr> {
+        let template = match (ptr.nullable, ectx) {
+            (false, _) => "__e[__i as usize]",
+            (true, lr_expr::Context::LvalueMut) => "__e.as_mut().unwrap()[__i as usize]",
+            (true, _) => "__e.as_ref().unwrap()[__i as usize]",
+        };
+        self.subst(template, ptr, &[("__e", e), ("__i", peel_casts(&idx).clone())])
+    }
+
+    fn is_null(&self, e: P<Expr>, ptr: &SafeAlloc) -> P<Expr> {
+        let template = match (ptr.kind, ptr.nullable) {
+            (AllocKind::Box, false) => return mk().lit_expr(mk().bool_lit(false)),
+            (AllocKind::Box, true) => "__e.is_none()",
+            (AllocKind::Vec, false) => "__e.is_empty()",
+            (AllocKind::Vec, true) => "__e.as_ref().map_or(true, |v| v.is_empty())",
+            (AllocKind::CArray, _) => "__e.len() == 0",
+        };
+        self.subst(template, ptr, &[("__e", e)])
+    }
+
+    fn free(&self, e: P<Expr>, def: AllocDef, ptr: &SafeAlloc) -> P<Expr> {
+        let template = match (ptr.kind, ptr.nullable, def) {
+            (AllocKind::Box, true, _) | (AllocKind::Vec, true, _) => "::std::mem::drop(__e.take())",
+            (_, _, AllocDef::Local(_)) => "::std::mem::drop(__e)",
+            // A field can't be moved out of, so leave an empty array in its place.
+            (AllocKind::Vec, _, _) => "::std::mem::drop(::std::mem::replace(&mut __e, Vec::new()))",
+            (_, _, _) => "::std::mem::drop(::std::mem::replace(&mut __e, \
+                          ::c2rust_runtime::CArray::empty()))",
+        };
+        self.subst(template, ptr, &[("__e", e)])
+    }
+
+    /// Lend a retyped pointer to a call as a raw pointer.
+    fn to_raw(&self, e: P<Expr>, ptr: &SafeAlloc) -> P<Expr> {
+        let template = match (ptr.kind, ptr.nullable) {
+            (AllocKind::Box, false) => "&mut *__e as *mut __t",
+            (AllocKind::Box, true) =>
+                "__e.as_mut().map_or(::std::ptr::null_mut(), |r| &mut **r as *mut __t)",
+            (AllocKind::Vec, false) => "__e.as_mut_ptr()",
+            (AllocKind::Vec, true) =>
+                "__e.as_mut().map_or(::std::ptr::null_mut(), |v| v.as_mut_ptr())",
+            (AllocKind::CArray, _) => "__e.as_array_ptr()",
+        };
+        self.subst(template, ptr, &[("__e", e)])
+    }
+}
+
+/// Classify a value assigned to a pointer.  `lhs` is the assigned place, if the value comes from
+/// an assignment expression; only those can be `realloc`s of the pointer itself.
+fn classify(cx: &RefactorCtxt, e: &P<Expr>, lhs: Option<&P<Expr>>) -> Value {
+    if is_null_ptr(e) {
+        return Value::Null;
+    }
+    let e = peel_casts(e);
+    let args = match_or!([e.kind] ExprKind::Call(_, ref args) => args; return Value::Other);
+    match callee_name(cx, e).as_ref().map(String::as_str) {
+        Some("malloc") if args.len() == 1 => match alloc_size(&args[0]) {
+            Some(AllocSize::One) => Value::Single,
+            Some(AllocSize::Count(n)) => Value::Array(n),
+            None => Value::Other,
+        },
+        Some("calloc") if args.len() == 2 && is_size_of(&args[1]) => {
+            let n = peel_casts(&args[0]);
+            if matches!([n.kind] ExprKind::Lit(Lit { kind: LitKind::Int(1, _), .. })) {
+                Value::Single
+            } else {
+                Value::Array(n.clone())
+            }
+        },
+        Some("realloc") if args.len() == 2 => {
+            let ptr = peel_casts(&args[0]);
+            if !lhs.map_or(false, |lhs| ptr.ast_equiv(lhs)) {
+                return Value::Other;
+            }
+            match alloc_size(&args[1]) {
+                Some(AllocSize::Count(n)) => Value::Realloc(ptr.id, n),
+                _ => Value::Other,
+            }
+        },
+        _ => Value::Other,
+    }
+}
+
+/// Can an all-zero value of `ty` be stored in a `CArray`?  These are the types the runtime
+/// implements `Nullable` for.
+fn is_nullable(ty: ty::Ty) -> bool {
+    match ty.kind {
+        ty::TyKind::Int(_) | ty::TyKind::Uint(_) | ty::TyKind::Float(_) | ty::TyKind::Bool |
+        ty::TyKind::RawPtr(_) => true,
+        _ => false,
+    }
+}
+
+/// Get the type pointed to by a raw pointer or reference type.
+fn pointee_ty(ty: ty::Ty) -> Option<ty::Ty> {
+    match ty.kind {
+        ty::TyKind::RawPtr(mt) => Some(mt.ty),
+        ty::TyKind::Ref(_, ty, _) => Some(ty),
+        _ => None,
+    }
+}
+
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("convert_allocations", |args| mk(ConvertAllocations {
+        label: args.get(0).map_or("target", |x| x).into_symbol(),
+    }));
+}
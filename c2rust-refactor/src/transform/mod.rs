@@ -48,7 +48,10 @@ macro_rules! transform_modules {
     };
 }
 
+mod ptr_idioms;
+
 transform_modules! {
+    allocations,
     canonicalize_refs,
     casts,
     char_literals,
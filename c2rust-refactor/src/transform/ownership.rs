@@ -3,26 +3,35 @@ use std::collections::HashSet;
 
 use arena::SyncDroplessArena;
 use rustc::hir::def_id::DefId;
+use rustc::ty;
 use rustc_index::vec::IndexVec;
 use syntax::ast::*;
+use syntax::attr;
 use syntax::source_map::DUMMY_SP;
 use syntax::mut_visit::{self, MutVisitor};
 use syntax::parse::token::{self, Token, TokenKind, DelimToken};
 use syntax::ptr::P;
 use syntax::symbol::Symbol;
 use syntax::tokenstream::{TokenTree, TokenStream, DelimSpan};
+use syntax_pos::Span;
 use smallvec::SmallVec;
 
-use crate::ast_manip::{MutVisitNodes, MutVisit};
-use crate::ast_manip::fn_edit::flat_map_fns;
+use crate::ast_manip::{fold_output_exprs, visit_nodes, FlatMapNodes, MutVisitNodes, MutVisit};
+use crate::ast_manip::fn_edit::{flat_map_fns, mut_visit_fns, visit_fns, FnKind};
+use crate::ast_manip::lr_expr::{self, fold_exprs_with_context};
 use crate::analysis::labeled_ty::LabeledTyCtxt;
 use crate::analysis::ownership::{self, ConcretePerm, Var, PTy};
 use crate::analysis::ownership::constraint::{ConstraintSet, Perm};
 use crate::command::{CommandState, Registry, DriverCommand};
 use crate::context::HirMap;
-use crate::driver::{Phase};
+use crate::driver::{Phase, parse_expr, parse_ty};
+use crate::matcher::{Bindings, Subst};
 use crate::type_map;
 use crate::RefactorCtxt;
+use crate::transform::ptr_idioms::{
+    callee_name, field_def_id, is_copy, is_null_ptr, is_size_of, peel_casts, peel_casts_expr,
+    struct_field_def_id, zero_value,
+};
 use c2rust_ast_builder::{mk, IntoSymbol};
 
 pub fn register_commands(reg: &mut Registry) {
@@ -47,6 +56,31 @@ pub fn register_commands(reg: &mut Registry) {
             do_mark_pointers(st, cx);
         }))
     });
+
+    reg.register("ownership_prune_monos", |_args| {
+        Box::new(DriverCommand::new(Phase::Phase3, move |st, cx| {
+            do_prune_monos(st, cx);
+        }))
+    });
+
+    reg.register_checked("ownership_explain", |args| {
+        let name = match args.get(0) {
+            Some(name) => name.clone(),
+            None => return Err("Usage: ownership_explain FUNC".to_owned()),
+        };
+
+        Ok(Box::new(DriverCommand::new(Phase::Phase3, move |st, cx| {
+            do_explain(st, cx, &name);
+        })))
+    });
+
+    reg.register("ownership_retype", |args| {
+        let label = args.get(0).map_or("target", |x| x).into_symbol();
+
+        Box::new(DriverCommand::new(Phase::Phase3, move |st, cx| {
+            do_retype(st, cx, label);
+        }))
+    });
 }
 
 /// # `ownership_annotate` Command
@@ -490,6 +524,57 @@ fn callee_new_name(cx: &RefactorCtxt,
 }
 
 
+/// Type source providing the monomorphic signatures inferred by the ownership analysis.
+struct AnalysisTypeSource<'lty, 'tcx: 'lty> {
+    ana: &'lty ownership::AnalysisResult<'lty, 'tcx>,
+}
+
+impl<'lty, 'tcx> type_map::TypeSource for AnalysisTypeSource<'lty, 'tcx> {
+    type Type = ownership::PTy<'lty, 'tcx>;
+    type Signature = ownership::PFnSig<'lty, 'tcx>;
+
+    fn def_type(&mut self, did: DefId) -> Option<Self::Type> {
+        self.ana.statics.get(&did).cloned()
+    }
+
+    fn fn_sig(&mut self, did: DefId) -> Option<Self::Signature> {
+        let (fr, vr) = self.ana.fn_results(did);
+        // Only provide signatures for monomorphic fns.
+        if fr.variants.is_none() && fr.num_monos > 1 {
+            return None;
+        }
+
+        // Only one variant?  Use mono #0 (which is the only one, by the check above).
+        // Multiple variants?  Use the mono for the current variant.
+        let mono_idx =
+            if fr.variants.is_none() { 0 }
+            else { vr.index };
+
+        let mr = &self.ana.monos[&(vr.func_id, mono_idx)];
+
+        let lcx = LabeledTyCtxt::new(self.ana.arena());
+
+        let sig = {
+            let mut f = |l: &Option<_>| {
+                if let Some(v) = *l {
+                    Some(mr.assign[v])
+                } else {
+                    None
+                }
+            };
+            ownership::FnSig {
+                inputs: lcx.relabel_slice(fr.sig.inputs, &mut f),
+                output: lcx.relabel(fr.sig.output, &mut f),
+            }
+        };
+
+        Some(sig)
+    }
+
+    fn closure_sig(&mut self, _did: DefId) -> Option<Self::Signature> { None }
+}
+
+
 /// # `ownership_mark_pointers` Command
 ///
 /// Usage: `ownership_mark_pointers [MARK]`
@@ -505,75 +590,876 @@ fn do_mark_pointers(st: &CommandState, cx: &RefactorCtxt) {
     let arena = SyncDroplessArena::default();
     let ana = ownership::analyze(&st, &cx, &arena);
 
-    struct AnalysisTypeSource<'lty, 'tcx: 'lty> {
-        ana: &'lty ownership::AnalysisResult<'lty, 'tcx>,
+    let source = AnalysisTypeSource {
+        ana: &ana,
+    };
+
+    let s_ref = "ref".into_symbol();
+    let s_mut = "mut".into_symbol();
+    let s_box = "box".into_symbol();
+
+    type_map::map_types(&cx.hir_map(), source, &st.krate(), |_source, ast_ty, lty| {
+        let p = match lty.label {
+            Some(x) => x,
+            None => return,
+        };
+
+        let label = match p {
+            ConcretePerm::Read => s_ref,
+            ConcretePerm::Write => s_mut,
+            ConcretePerm::Move => s_box,
+        };
+
+        st.add_mark(ast_ty.id, label);
+    });
+}
+
+
+/// # `ownership_prune_monos` Command
+///
+/// Usage: `ownership_prune_monos`
+///
+/// Run ownership analysis, then remove monomorphizations that can't be
+/// implemented under the Rust type system, such as `WRITE -> MOVE` monos of
+/// generic accessors that also have a `READ -> READ` mono, and monos for which
+/// no instantiation of the callees could be found.  A mono is only removed if
+/// all of its function's callers can still select monos for their calls
+/// without it, and the removals are repeated until no more monos can be
+/// removed.
+///
+/// Each function that lost a mono gets `#[ownership_mono]` attributes for its
+/// remaining monos, so that later commands such as `ownership_split_variants`
+/// only generate the useful variants.
+fn do_prune_monos(st: &CommandState, cx: &RefactorCtxt) {
+    let arena = SyncDroplessArena::default();
+    let (ana, pruned) = ownership::analyze_pruned(&st, &cx, &arena);
+
+    let mut changed = HashSet::new();
+    for &(def_id, ref assign) in &pruned {
+        info!("removed mono {:?} of {}", assign, cx.ty_ctxt().def_path_str(def_id));
+        changed.insert(def_id);
     }
 
-    impl<'lty, 'tcx> type_map::TypeSource for AnalysisTypeSource<'lty, 'tcx> {
-        type Type = ownership::PTy<'lty, 'tcx>;
-        type Signature = ownership::PFnSig<'lty, 'tcx>;
+    st.map_krate(|krate| {
+        mut_visit_fns(krate, |fl| {
+            let def_id = match_or!([cx.hir_map().opt_local_def_id_from_node_id(fl.id)]
+                                   Some(x) => x; return);
+            if !changed.contains(&def_id) {
+                return;
+            }
 
-        fn def_type(&mut self, did: DefId) -> Option<Self::Type> {
-            self.ana.statics.get(&did).cloned()
+            fl.attrs.retain(|a| !a.check_name("ownership_mono".into_symbol()));
+            for idx in 0..ana.funcs[&def_id].num_monos {
+                let mr = &ana.monos[&(def_id, idx)];
+                fl.attrs.push(build_mono_attr(&mr.suffix, &mr.assign));
+            }
+        });
+    });
+}
+
+/// # `ownership_explain` Command
+///
+/// Usage: `ownership_explain FUNC`
+///
+/// Run ownership analysis, and explain why each pointer in the signature of the
+/// function `FUNC` must be `WRITE` or `MOVE`.  `FUNC` is either the name of the
+/// function or its full path.  For each such pointer, this logs (at level
+/// `info`) the chain of constraints that forces its permission, through locals,
+/// the arguments of other functions at each call site, and the types of statics
+/// and struct fields.
+fn do_explain(st: &CommandState, cx: &RefactorCtxt, name: &str) {
+    let mut def_ids = Vec::new();
+    visit_fns(&*st.krate(), |fl| {
+        if fl.block.is_none() {
+            return;
+        }
+        let def_id = match_or!([cx.hir_map().opt_local_def_id_from_node_id(fl.id)]
+                               Some(x) => x; return);
+        if &*fl.ident.as_str() == name || cx.ty_ctxt().def_path_str(def_id) == name {
+            def_ids.push(def_id);
         }
+    });
+    if def_ids.is_empty() {
+        warn!("ownership_explain: found no function named `{}`", name);
+        return;
+    }
 
-        fn fn_sig(&mut self, did: DefId) -> Option<Self::Signature> {
-            let (fr, vr) = self.ana.fn_results(did);
-            // Only provide signatures for monomorphic fns.
-            if fr.variants.is_none() && fr.num_monos > 1 {
-                return None;
+    let arena = SyncDroplessArena::default();
+    for line in ownership::explain(&st, &cx, &arena, &def_ids) {
+        info!("{}", line);
+    }
+}
+
+/// # `ownership_retype` Command
+///
+/// Usage: `ownership_retype [MARK]`
+///
+/// Marks: reads `MARK`/`target`
+///
+/// Run ownership analysis, then rewrite the raw pointers in the signatures of
+/// functions bearing `MARK` (default: `target`) and in the fields of marked
+/// structs to safe pointer types.  `READ` pointers become `&T`, `WRITE`
+/// pointers become `&mut T`, and `MOVE` pointers become `Box<T>`.  Each new
+/// type is wrapped in `Option` unless the argument or field has a `#[nonnull]`
+/// attribute.  Fields are only rewritten to `Box<T>`, as references would need
+/// a lifetime parameter on the struct.
+///
+/// Arguments at call sites, assigned values, struct literal fields and return
+/// values are converted to the new types, and any other use is converted back
+/// to the original raw pointer.  Dereferences and `is_null` checks become
+/// their safe equivalents, `malloc` or `calloc` of a single object becomes
+/// `Box::new` of the allocated type's zero value (`0`, a null pointer, or a
+/// struct literal of those), and `free` becomes a drop.
+///
+/// Functions with several monomorphizations must first be split with
+/// `ownership_split_variants`.  A pointer that can't be converted at one of
+/// its uses, such as a `Box` escaping as a raw pointer or a non-null pointer
+/// being assigned null, keeps its raw pointer type, and a warning reports the
+/// use that prevented the conversion.
+/// See `analysis/ownership/README.md` for details on ownership inference.
+fn do_retype(st: &CommandState, cx: &RefactorCtxt, label: Symbol) {
+    let arena = SyncDroplessArena::default();
+    let ana = ownership::analyze(&st, &cx, &arena);
+
+    let mut perms = HashMap::new();
+    let source = AnalysisTypeSource {
+        ana: &ana,
+    };
+    type_map::map_types(&cx.hir_map(), source, &st.krate(), |_source, ast_ty, lty| {
+        if let Some(p) = lty.label {
+            perms.insert(ast_ty.id, p);
+        }
+    });
+
+    let mut reports = Vec::new();
+
+    // (1) Pick the pointers to retype: arguments and return values of marked fns, and `MOVE`
+    // fields of marked structs.
+    let mut ptrs: HashMap<PtrDef, SafePtr> = HashMap::new();
+    // Retyped arguments of each fn, by index.
+    let mut fn_args: HashMap<DefId, Vec<Option<PtrDef>>> = HashMap::new();
+
+    visit_fns(&*st.krate(), |fl| {
+        if !matches!([fl.kind] FnKind::Normal) || fl.block.is_none() || !st.marked(fl.id, label) {
+            return;
+        }
+        let def_id = cx.node_def_id(fl.id);
+        let (fr, _) = ana.fn_results(def_id);
+        if fr.variants.is_none() && fr.num_monos > 1 {
+            reports.push((fl.span, format!(
+                "`{}` has {} monomorphizations; run `ownership_split_variants` first",
+                fl.ident, fr.num_monos)));
+            return;
+        }
+
+        let mut args = Vec::with_capacity(fl.decl.inputs.len());
+        for arg in &fl.decl.inputs {
+            let name = match arg.pat.kind {
+                PatKind::Ident(_, ident, None) => ident,
+                _ => {
+                    args.push(None);
+                    continue;
+                }
+            };
+            let nullable = !attr::contains_name(&arg.attrs, "nonnull".into_symbol());
+            let desc = format!("argument `{}` of `{}`", name, fl.ident);
+            let ptr = SafePtr::new(&arg.ty, perms.get(&arg.ty.id).cloned(), nullable, desc);
+            args.push(ptr.map(|ptr| {
+                let def = PtrDef::Arg(arg.pat.id);
+                ptrs.insert(def, ptr);
+                def
+            }));
+        }
+        fn_args.insert(def_id, args);
+
+        if let FunctionRetTy::Ty(ref ty) = fl.decl.output {
+            let desc = format!("return value of `{}`", fl.ident);
+            if let Some(ptr) = SafePtr::new(ty, perms.get(&ty.id).cloned(), true, desc) {
+                ptrs.insert(PtrDef::Ret(def_id), ptr);
             }
+        }
+    });
 
-            // Only one variant?  Use mono #0 (which is the only one, by the check above).
-            // Multiple variants?  Use the mono for the current variant.
-            let mono_idx =
-                if fr.variants.is_none() { 0 }
-                else { vr.index };
+    visit_nodes(&*st.krate(), |i: &Item| {
+        let fields = match i.kind {
+            ItemKind::Struct(VariantData::Struct(ref fields, _), _) => fields,
+            _ => return,
+        };
+        let struct_marked = st.marked(i.id, label);
+        for f in fields {
+            if !struct_marked && !st.marked(f.id, label) {
+                continue;
+            }
+            let name = match f.ident {
+                Some(name) => name,
+                None => continue,
+            };
+            let nullable = !attr::contains_name(&f.attrs, "nonnull".into_symbol());
+            let desc = format!("field `{}::{}`", i.ident, name);
+            let ptr = match SafePtr::new(&f.ty, perms.get(&f.ty.id).cloned(), nullable, desc) {
+                Some(x) => x,
+                None => continue,
+            };
+            let reason = if ptr.kind != SafeKind::Box {
+                Some("a borrowed pointer field would need a lifetime parameter")
+            } else if is_copy(cx, cx.node_def_id(i.id)) {
+                Some("the struct is `Copy`, but `Box` is not")
+            } else {
+                None
+            };
+            match reason {
+                Some(reason) => reports.push((f.span, format!(
+                    "{}; keeping {} as a raw pointer", reason, ptr.desc))),
+                None => {
+                    ptrs.insert(PtrDef::Field(cx.node_def_id(f.id)), ptr);
+                }
+            }
+        }
+    });
 
-            let mr = &self.ana.monos[&(vr.func_id, mono_idx)];
+    // (2) Find every use of the selected pointers, and every value flowing into them.
+    let mut scan = UseScan {
+        cx,
+        ptrs: &ptrs,
+        fn_args: &fn_args,
+        ptr_of: HashMap::new(),
+        uses: HashMap::new(),
+        flows: Vec::new(),
+        call_flows: HashMap::new(),
+        assign_flows: HashMap::new(),
+        field_flows: HashMap::new(),
+        output_flows: HashMap::new(),
+        free_calls: HashMap::new(),
+        callees: HashSet::new(),
+        fn_refs: Vec::new(),
+    };
+    let mut krate = st.krate().clone();
+    fold_exprs_with_context(&mut krate, |e, ectx| scan.visit_expr(e, ectx));
+    mut_visit_fns(&mut krate, |fl| {
+        let def_id = match_or!([cx.hir_map().opt_local_def_id_from_node_id(fl.id)]
+                               Some(x) => x; return);
+        if !scan.ptrs.contains_key(&PtrDef::Ret(def_id)) {
+            return;
+        }
+        fl.block.as_mut().map(|b| fold_output_exprs(b, true, |e| {
+            let idx = scan.add_flow(PtrDef::Ret(def_id), e, false);
+            scan.output_flows.insert(e.id, idx);
+        }));
+    });
+    let UseScan {
+        ptr_of, uses, flows, call_flows, assign_flows, field_flows, output_flows, free_calls,
+        callees, fn_refs, ..
+    } = scan;
+
+    // (3) Drop pointers that can't be converted at some use, until the remaining ones are
+    // consistent with each other.
+    let mut fallback = |ptrs: &mut HashMap<PtrDef, SafePtr>, def, span, reason: &str| {
+        if let Some(ptr) = ptrs.remove(&def) {
+            reports.push((span, format!("{}; keeping {} as a raw pointer", reason, ptr.desc)));
+        }
+    };
 
-            let lcx = LabeledTyCtxt::new(self.ana.arena());
+    for &(id, def_id, span) in &fn_refs {
+        if callees.contains(&id) {
+            continue;
+        }
+        let defs = fn_args[&def_id].iter().filter_map(|&def| def)
+            .chain(Some(PtrDef::Ret(def_id)));
+        for def in defs {
+            fallback(&mut ptrs, def, span, "the function is used as a function pointer");
+        }
+    }
 
-            let sig = {
-                let mut f = |l: &Option<_>| {
-                    if let Some(v) = *l {
-                        Some(mr.assign[v])
-                    } else {
-                        None
+    loop {
+        let mut failed = Vec::new();
+
+        for u in uses.values() {
+            let ptr = match_or!([ptrs.get(&u.def)] Some(x) => x; continue);
+            let kind = match u.kind {
+                UseKind::Flow(idx) if !ptrs.contains_key(&flows[idx].target) => UseKind::Rvalue,
+                kind => kind,
+            };
+            let reason = match kind {
+                UseKind::Place => Some("its address is taken"),
+                UseKind::Rvalue if ptr.kind == SafeKind::Box =>
+                    Some("the owned pointer escapes as a raw pointer"),
+                UseKind::Free if ptr.kind == SafeKind::Box && !ptr.nullable &&
+                                 matches!([u.def] PtrDef::Field(_)) =>
+                    Some("a non-null `Box` can't be moved out of a field to free it"),
+                _ => None,
+            };
+            if let Some(reason) = reason {
+                failed.push((u.def, u.span, reason));
+            }
+        }
+
+        for flow in &flows {
+            let target = match_or!([ptrs.get(&flow.target)] Some(x) => x; continue);
+            let reason = match flow.source {
+                Source::Null if !target.nullable => Some("it is assigned a null pointer"),
+                Source::Def(src) if target.kind == SafeKind::Box => {
+                    match ptrs.get(&src) {
+                        Some(p) if p.kind == SafeKind::Box && (target.nullable || !p.nullable) &&
+                                   !matches!([src] PtrDef::Field(_)) => None,
+                        _ => Some("it is assigned a pointer it can't take ownership of"),
                     }
-                };
-                ownership::FnSig {
-                    inputs: lcx.relabel_slice(fr.sig.inputs, &mut f),
-                    output: lcx.relabel(fr.sig.output, &mut f),
+                },
+                Source::Raw if target.kind == SafeKind::Box =>
+                    Some("it is assigned a pointer it can't take ownership of"),
+                Source::Alloc if target.kind == SafeKind::Box && flow.init.is_none() =>
+                    Some("the allocated type has no zero value to initialize the `Box` with"),
+                _ => None,
+            };
+            if let Some(reason) = reason {
+                failed.push((flow.target, flow.span, reason));
+            }
+        }
+
+        // A returned reference borrows from the only reference argument.
+        for (&def_id, args) in &fn_args {
+            let ret = match_or!([ptrs.get(&PtrDef::Ret(def_id))] Some(x) => x; continue);
+            if ret.kind == SafeKind::Box {
+                continue;
+            }
+            let num_refs = args.iter()
+                .filter_map(|&def| def.and_then(|def| ptrs.get(&def)))
+                .filter(|p| p.kind != SafeKind::Box)
+                .count();
+            if num_refs != 1 {
+                failed.push((PtrDef::Ret(def_id), ret.span,
+                             "a returned reference must borrow from exactly one reference argument"));
+            }
+        }
+
+        if failed.is_empty() {
+            break;
+        }
+        for (def, span, reason) in failed {
+            fallback(&mut ptrs, def, span, reason);
+        }
+    }
+
+    reports.sort_by_key(|&(span, _)| span.lo());
+    for (span, msg) in &reports {
+        warn!("ownership_retype: {}: {}", cx.session().source_map().span_to_string(*span), msg);
+    }
+
+    // Uses handled together with their parent expression, rather than by converting the pointer
+    // back to its raw type.
+    let deferred = uses.iter().filter(|&(_, u)| {
+        let ptr = match_or!([ptrs.get(&u.def)] Some(x) => x; return false);
+        match u.kind {
+            UseKind::Deref | UseKind::IsNull | UseKind::AssignLhs => true,
+            UseKind::Free => ptr.kind == SafeKind::Box,
+            UseKind::Flow(idx) => ptrs.contains_key(&flows[idx].target),
+            _ => false,
+        }
+    }).map(|(&id, _)| id).collect::<HashSet<_>>();
+
+    // (4) Rewrite the crate.
+    let rw = PtrRewriter { st, cx, ptrs: &ptrs };
+    let retyped = |id: NodeId| ptr_of.get(&id).and_then(|def| ptrs.get(def));
+
+    st.map_krate(|krate| {
+        mut_visit_fns(krate, |fl| {
+            let def_id = match_or!([cx.hir_map().opt_local_def_id_from_node_id(fl.id)]
+                                   Some(x) => x; return);
+            if let Some(args) = fn_args.get(&def_id) {
+                for (arg, def) in fl.decl.inputs.iter_mut().zip(args) {
+                    let ptr = match_or!([def.and_then(|def| ptrs.get(&def))] Some(x) => x; continue);
+                    arg.ty = rw.new_ty(ptr);
+                    arg.attrs = remove_nonnull(arg.attrs.clone().into()).into();
+                    if ptr.needs_mut() {
+                        if let PatKind::Ident(ref mut mode, _, _) = arg.pat.kind {
+                            *mode = BindingMode::ByValue(Mutability::Mutable);
+                        }
+                    }
+                }
+            }
+            if let Some(ptr) = ptrs.get(&PtrDef::Ret(def_id)) {
+                fl.decl.output = FunctionRetTy::Ty(rw.new_ty(ptr));
+            }
+        });
+
+        FlatMapNodes::visit(krate, |mut sf: StructField| {
+            if let Some(ptr) = ptrs.get(&PtrDef::Field(cx.node_def_id(sf.id))) {
+                sf.ty = rw.new_ty(ptr);
+                sf.attrs = remove_nonnull(sf.attrs);
+            }
+            smallvec![sf]
+        });
+
+        // Convert values flowing into retyped pointers.  Flows are found through their parent
+        // expression, whose `NodeId` survives rewriting of its children.
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let id = e.id;
+            match e.kind {
+                ExprKind::Call(_, ref mut args) => {
+                    for &(i, idx) in call_flows.get(&id).into_iter().flatten() {
+                        if let Some(target) = ptrs.get(&flows[idx].target) {
+                            args[i] = rw.convert(args[i].clone(), &flows[idx], target);
+                        }
+                    }
+                },
+                ExprKind::Assign(_, ref mut rhs) => {
+                    if let Some(&idx) = assign_flows.get(&id) {
+                        if let Some(target) = ptrs.get(&flows[idx].target) {
+                            *rhs = rw.convert(rhs.clone(), &flows[idx], target);
+                        }
+                    }
+                },
+                ExprKind::Struct(_, ref mut fields, _) => {
+                    for f in fields {
+                        let idx = match_or!([field_flows.get(&f.id)] Some(&x) => x; continue);
+                        if let Some(target) = ptrs.get(&flows[idx].target) {
+                            f.expr = rw.convert(f.expr.clone(), &flows[idx], target);
+                        }
+                    }
+                },
+                _ => {},
+            }
+        });
+
+        mut_visit_fns(krate, |fl| {
+            let def_id = match_or!([cx.hir_map().opt_local_def_id_from_node_id(fl.id)]
+                                   Some(x) => x; return);
+            let target = match_or!([ptrs.get(&PtrDef::Ret(def_id))] Some(x) => x; return);
+            fl.block.as_mut().map(|b| fold_output_exprs(b, true, |e| {
+                if let Some(&idx) = output_flows.get(&e.id) {
+                    *e = rw.convert(e.clone(), &flows[idx], target);
                 }
+            }));
+        });
+
+        // Rewrite the remaining uses.
+        fold_exprs_with_context(krate, |e, ectx| {
+            let new_expr = match e.kind {
+                ExprKind::Unary(UnOp::Deref, ref ptr) => {
+                    retyped(ptr.id).and_then(|p| rw.deref(ptr.clone(), p, ectx))
+                },
+                ExprKind::MethodCall(ref seg, ref args)
+                        if seg.ident.name == "is_null".into_symbol() && args.len() == 1 => {
+                    retyped(args[0].id).map(|p| rw.is_null(args[0].clone(), p))
+                },
+                ExprKind::Call(_, ref args) if free_calls.contains_key(&e.id) => {
+                    let ptr = peel_casts(&args[0]);
+                    retyped(ptr.id)
+                        .filter(|p| p.kind == SafeKind::Box)
+                        .map(|p| rw.free(ptr.clone(), p))
+                },
+                _ => None,
             };
+            if let Some(new_expr) = new_expr {
+                *e = new_expr;
+            } else if !deferred.contains(&e.id) {
+                if let Some(p) = retyped(e.id) {
+                    *e = rw.to_raw(e.clone(), p);
+                }
+            }
+        });
+    });
+}
 
-            Some(sig)
+/// A pointer-typed argument, return value, or struct field that `ownership_retype` rewrites.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum PtrDef {
+    /// A function argument, identified by the `NodeId` of its binding pattern.
+    Arg(NodeId),
+    Ret(DefId),
+    Field(DefId),
+}
+
+/// The safe pointer type replacing a raw pointer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SafeKind {
+    Ref,
+    RefMut,
+    Box,
+}
+
+#[derive(Clone, Debug)]
+struct SafePtr {
+    kind: SafeKind,
+    /// Whether the new type is wrapped in `Option`.
+    nullable: bool,
+    pointee: P<Ty>,
+    old_ty: P<Ty>,
+    old_mutbl: Mutability,
+    span: Span,
+    /// Description used in reports, such as "argument `p` of `f`".
+    desc: String,
+}
+
+impl SafePtr {
+    fn new(ty: &P<Ty>, perm: Option<ConcretePerm>, nullable: bool, desc: String)
+           -> Option<SafePtr> {
+        let mty = match_or!([ty.kind] TyKind::Ptr(ref mty) => mty; return None);
+        let perm = perm?;
+        // Pointers to `c_void` don't say anything about what they point to.
+        if let TyKind::Path(None, ref path) = mty.ty.kind {
+            if path.segments.last().map_or(false, |seg| &*seg.ident.as_str() == "c_void") {
+                return None;
+            }
         }
+        let kind = match perm {
+            ConcretePerm::Read => SafeKind::Ref,
+            ConcretePerm::Write => SafeKind::RefMut,
+            ConcretePerm::Move => SafeKind::Box,
+        };
+        Some(SafePtr {
+            kind,
+            nullable,
+            pointee: mty.ty.clone(),
+            old_ty: ty.clone(),
+            old_mutbl: mty.mutbl,
+            span: ty.span,
+            desc,
+        })
+    }
 
-        fn closure_sig(&mut self, _did: DefId) -> Option<Self::Signature> { None }
+    /// Whether an argument of this type must be bound mutably to allow `as_mut` and `take`.
+    fn needs_mut(&self) -> bool {
+        self.kind == SafeKind::Box || (self.kind == SafeKind::RefMut && self.nullable)
     }
+}
 
-    let source = AnalysisTypeSource {
-        ana: &ana,
-    };
+/// What flows into a retyped pointer at a call, assignment, struct literal, or return.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Source {
+    Null,
+    /// A `malloc` or `calloc` of a single object
+    Alloc,
+    Def(PtrDef),
+    Raw,
+}
 
-    let s_ref = "ref".into_symbol();
-    let s_mut = "mut".into_symbol();
-    let s_box = "box".into_symbol();
+struct Flow {
+    target: PtrDef,
+    source: Source,
+    /// Whether the value is a call argument, which can be reborrowed instead of moved.
+    at_call: bool,
+    /// For `Source::Alloc`, the initial value of the new `Box`: a typed zero value of the
+    /// allocated type, if it has one.
+    init: Option<P<Expr>>,
+    span: Span,
+}
 
-    type_map::map_types(&cx.hir_map(), source, &st.krate(), |_source, ast_ty, lty| {
-        let p = match lty.label {
-            Some(x) => x,
-            None => return,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum UseKind {
+    Deref,
+    IsNull,
+    Free,
+    AssignLhs,
+    /// The source of the flow with the given index
+    Flow(usize),
+    /// An argument to a function whose argument type is unchanged
+    BorrowArg,
+    Rvalue,
+    Place,
+}
+
+struct Use {
+    def: PtrDef,
+    kind: UseKind,
+    span: Span,
+}
+
+struct UseScan<'a, 'tcx: 'a> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    ptrs: &'a HashMap<PtrDef, SafePtr>,
+    fn_args: &'a HashMap<DefId, Vec<Option<PtrDef>>>,
+
+    /// Exprs whose value is one of the retyped pointers
+    ptr_of: HashMap<NodeId, PtrDef>,
+    uses: HashMap<NodeId, Use>,
+    flows: Vec<Flow>,
+    /// Flows into the arguments of each call, as (argument index, flow index)
+    call_flows: HashMap<NodeId, Vec<(usize, usize)>>,
+    assign_flows: HashMap<NodeId, usize>,
+    /// Flows into struct literal fields, by the `NodeId` of the `ast::Field`
+    field_flows: HashMap<NodeId, usize>,
+    output_flows: HashMap<NodeId, usize>,
+    /// Calls to `free`, and the pointer expr they free
+    free_calls: HashMap<NodeId, NodeId>,
+    callees: HashSet<NodeId>,
+    /// Paths referring to fns with retyped signatures
+    fn_refs: Vec<(NodeId, DefId, Span)>,
+}
+
+impl<'a, 'tcx> UseScan<'a, 'tcx> {
+    /// Record the use of a retyped pointer.  Exprs are visited bottom-up, so the parent
+    /// expression later changes the use kind if it handles the pointer itself.
+    fn add_ptr(&mut self, e: &Expr, def: PtrDef, ectx: lr_expr::Context) {
+        let kind = match ectx {
+            lr_expr::Context::Rvalue => UseKind::Rvalue,
+            _ => UseKind::Place,
         };
+        self.ptr_of.insert(e.id, def);
+        self.uses.insert(e.id, Use { def, kind, span: e.span });
+    }
 
-        let label = match p {
-            ConcretePerm::Read => s_ref,
-            ConcretePerm::Write => s_mut,
-            ConcretePerm::Move => s_box,
+    fn consume(&mut self, id: NodeId, kind: UseKind) {
+        if let Some(u) = self.uses.get_mut(&id) {
+            u.kind = kind;
+        }
+    }
+
+    fn add_flow(&mut self, target: PtrDef, e: &Expr, at_call: bool) -> usize {
+        let source = if is_null_ptr(e) {
+            Source::Null
+        } else if is_single_alloc(self.cx, e) {
+            Source::Alloc
+        } else if let Some(&def) = self.ptr_of.get(&e.id) {
+            Source::Def(def)
+        } else {
+            Source::Raw
+        };
+        let init = match (source, self.cx.opt_node_type(e.id).map(|ty| &ty.kind)) {
+            (Source::Alloc, Some(ty::TyKind::RawPtr(mt))) => zero_value(self.cx, mt.ty),
+            _ => None,
         };
+        let idx = self.flows.len();
+        self.flows.push(Flow { target, source, at_call, init, span: e.span });
+        if let Source::Def(_) = source {
+            self.consume(e.id, UseKind::Flow(idx));
+        }
+        idx
+    }
 
-        st.add_mark(ast_ty.id, label);
-    });
+    fn visit_expr(&mut self, e: &Expr, ectx: lr_expr::Context) {
+        match e.kind {
+            ExprKind::Path(..) => {
+                if let Some(hir_id) = self.cx.try_resolve_expr_to_hid(e) {
+                    let def = PtrDef::Arg(self.cx.hir_map().hir_to_node_id(hir_id));
+                    if self.ptrs.contains_key(&def) {
+                        self.add_ptr(e, def, ectx);
+                    }
+                }
+                if let Some(def_id) = self.cx.try_resolve_expr(e) {
+                    if self.fn_args.contains_key(&def_id) {
+                        self.fn_refs.push((e.id, def_id, e.span));
+                    }
+                }
+            },
+
+            ExprKind::Field(ref obj, ident) => {
+                if let Some(did) = field_def_id(self.cx, obj, ident) {
+                    if self.ptrs.contains_key(&PtrDef::Field(did)) {
+                        self.add_ptr(e, PtrDef::Field(did), ectx);
+                    }
+                }
+            },
+
+            ExprKind::Call(ref func, ref args) => {
+                self.callees.insert(func.id);
+                let callee = self.cx.opt_callee(e);
+
+                if args.len() == 1 && callee_name(self.cx, e).map_or(false, |n| n == "free") {
+                    let ptr = peel_casts(&args[0]);
+                    self.consume(ptr.id, UseKind::Free);
+                    self.free_calls.insert(e.id, ptr.id);
+                } else {
+                    let params = callee.and_then(|did| self.fn_args.get(&did)).cloned();
+                    for (i, arg) in args.iter().enumerate() {
+                        let param = params.as_ref().and_then(|ps| ps.get(i).cloned()).and_then(|p| p);
+                        match param {
+                            Some(target) => {
+                                let idx = self.add_flow(target, arg, true);
+                                self.call_flows.entry(e.id).or_insert_with(Vec::new).push((i, idx));
+                            },
+                            None => self.consume(peel_casts(arg).id, UseKind::BorrowArg),
+                        }
+                    }
+                }
+
+                if let Some(did) = callee {
+                    if self.ptrs.contains_key(&PtrDef::Ret(did)) {
+                        self.add_ptr(e, PtrDef::Ret(did), ectx);
+                    }
+                }
+            },
+
+            ExprKind::MethodCall(ref seg, ref args) => {
+                if seg.ident.name == "is_null".into_symbol() && args.len() == 1 {
+                    self.consume(args[0].id, UseKind::IsNull);
+                }
+            },
+
+            ExprKind::Unary(UnOp::Deref, ref ptr) => {
+                self.consume(ptr.id, UseKind::Deref);
+            },
+
+            ExprKind::Assign(ref lhs, ref rhs) => {
+                if let Some(&def) = self.ptr_of.get(&lhs.id) {
+                    self.consume(lhs.id, UseKind::AssignLhs);
+                    let idx = self.add_flow(def, rhs, false);
+                    self.assign_flows.insert(e.id, idx);
+                }
+            },
+
+            ExprKind::Struct(_, ref fields, _) => {
+                for f in fields {
+                    let did = match_or!([struct_field_def_id(self.cx, e, f.ident)]
+                                        Some(x) => x; continue);
+                    if self.ptrs.contains_key(&PtrDef::Field(did)) {
+                        let idx = self.add_flow(PtrDef::Field(did), &f.expr, false);
+                        self.field_flows.insert(f.id, idx);
+                    }
+                }
+            },
+
+            _ => {},
+        }
+    }
+}
+
+/// Builds the expressions converting between raw pointers and their replacements.
+struct PtrRewriter<'a, 'tcx: 'a> {
+    st: &'a CommandState,
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    ptrs: &'a HashMap<PtrDef, SafePtr>,
+}
+
+impl<'a, 'tcx> PtrRewriter<'a, 'tcx> {
+    /// Substitute `e` for `__e`, and the pointee and original types of `ptr` for `__t` and
+    /// `__old_ty`, in an expression template.
+    fn subst(&self, template: &str, e: P<Expr>, ptr: &SafePtr) -> P<Expr> {
+        let mut bnd = Bindings::new();
+        bnd.add("__e", e);
+        bnd.add("__t", ptr.pointee.clone());
+        bnd.add("__old_ty", ptr.old_ty.clone());
+        parse_expr(self.cx.session(), template).subst(self.st, self.cx, &bnd)
+    }
+
+    fn new_ty(&self, ptr: &SafePtr) -> P<Ty> {
+        let ty = match ptr.kind {
+            SafeKind::Ref => "&__t",
+            SafeKind::RefMut => "&mut __t",
+            SafeKind::Box => "Box<__t>",
+        };
+        let ty = if ptr.nullable { format!("Option<{}>", ty) } else { ty.to_owned() };
+        let mut bnd = Bindings::new();
+        bnd.add("__t", ptr.pointee.clone());
+        parse_ty(self.cx.session(), &ty).subst(self.st, self.cx, &bnd)
+    }
+
+    /// Convert `e`, of the retyped type of `ptr`, back to the original raw pointer.
+    fn to_raw(&self, e: P<Expr>, ptr: &SafePtr) -> P<Expr> {
+        let (template, mutbl) = match (ptr.kind, ptr.nullable) {
+            (SafeKind::Ref, false) => ("__e as *const __t", Mutability::Immutable),
+            (SafeKind::Ref, true) =>
+                ("__e.map_or(::std::ptr::null(), |r| r as *const __t)", Mutability::Immutable),
+            (_, false) => ("&mut *__e as *mut __t", Mutability::Mutable),
+            (_, true) => ("__e.as_mut().map_or(::std::ptr::null_mut(), |r| &mut **r as *mut __t)",
+                          Mutability::Mutable),
+        };
+        if mutbl == ptr.old_mutbl {
+            self.subst(template, e, ptr)
+        } else {
+            self.subst(&format!("{} as __old_ty", template), e, ptr)
+        }
+    }
+
+    /// Convert `e`, a raw pointer, to the retyped type of `ptr`.
+    fn from_raw(&self, e: P<Expr>, ptr: &SafePtr) -> P<Expr> {
+        // References coerce to raw pointers, so `e` may not be a raw pointer yet.
+        let cast = matches!([e.kind] ExprKind::AddrOf(..)) ||
+            (ptr.kind != SafeKind::Ref && ptr.old_mutbl == Mutability::Immutable);
+        let raw = match (ptr.kind, cast) {
+            (_, false) => "__e",
+            (SafeKind::Ref, true) => "(__e as *const __t)",
+            (_, true) => "(__e as *mut __t)",
+        };
+        let template = match (ptr.kind, ptr.nullable) {
+            (SafeKind::Ref, false) => format!("&*{}", raw),
+            (SafeKind::Ref, true) => format!("{}.as_ref()", raw),
+            (SafeKind::RefMut, false) => format!("&mut *{}", raw),
+            (SafeKind::RefMut, true) => format!("{}.as_mut()", raw),
+            (SafeKind::Box, false) => format!("Box::from_raw({})", raw),
+            (SafeKind::Box, true) => format!("{}.as_mut().map(|r| Box::from_raw(r))", raw),
+        };
+        self.subst(&template, e, ptr)
+    }
+
+    /// Convert a value flowing into a retyped pointer to its new type.
+    fn convert(&self, e: P<Expr>, flow: &Flow, target: &SafePtr) -> P<Expr> {
+        match flow.source {
+            Source::Null => self.subst("None", e, target),
+            Source::Alloc if target.kind == SafeKind::Box => {
+                let template = if target.nullable { "Some(Box::new(__e))" } else { "Box::new(__e)" };
+                let init = flow.init.clone().expect("allocation without an initial value");
+                self.subst(template, init, target)
+            },
+            Source::Def(def) => match self.ptrs.get(&def) {
+                Some(src) if src.kind == target.kind && src.nullable == target.nullable => {
+                    // Reborrow mutable references passed to a call, so the caller can keep
+                    // using them.
+                    match (target.kind, target.nullable, flow.at_call) {
+                        (SafeKind::RefMut, false, true) => self.subst("&mut *__e", e, target),
+                        (SafeKind::RefMut, true, true) =>
+                            self.subst("__e.as_mut().map(|r| &mut **r)", e, target),
+                        _ => e,
+                    }
+                },
+                Some(src) if src.kind == SafeKind::Box && target.kind == SafeKind::Box => {
+                    self.subst("Some(__e)", e, target)
+                },
+                Some(src) => {
+                    let raw = self.to_raw(e, src);
+                    self.from_raw(raw, target)
+                },
+                None => self.from_raw(e, target),
+            },
+            _ => self.from_raw(e, target),
+        }
+    }
+
+    /// Dereference a retyped pointer.  Returns `None` if `*e` needs no change.
+    fn deref(&self, e: P<Expr>, ptr: &SafePtr, ectx: lr_expr::Context) -> Option<P<Expr>> {
+        if !ptr.nullable {
+            return None;
+        }
+        let template = match (ptr.kind, ectx) {
+            (SafeKind::Ref, _) => "*__e.unwrap()",
+            (_, lr_expr::Context::LvalueMut) => "**__e.as_mut().unwrap()",
+            (_, _) => "**__e.as_ref().unwrap()",
+        };
+        Some(self.subst(template, e, ptr))
+    }
+
+    fn is_null(&self, e: P<Expr>, ptr: &SafePtr) -> P<Expr> {
+        if ptr.nullable {
+            self.subst("__e.is_none()", e, ptr)
+        } else {
+            mk().lit_expr(mk().bool_lit(false))
+        }
+    }
+
+    fn free(&self, e: P<Expr>, ptr: &SafePtr) -> P<Expr> {
+        if ptr.nullable {
+            self.subst("::std::mem::drop(__e.take())", e, ptr)
+        } else {
+            self.subst("::std::mem::drop(__e)", e, ptr)
+        }
+    }
+}
+
+fn remove_nonnull(mut attrs: Vec<Attribute>) -> Vec<Attribute> {
+    attrs.retain(|a| !a.check_name("nonnull".into_symbol()));
+    attrs
+}
+
+/// Is `e` an allocation of a single object, `malloc(size_of::<T>())` or
+/// `calloc(1, size_of::<T>())`?
+fn is_single_alloc(cx: &RefactorCtxt, e: &Expr) -> bool {
+    let e = peel_casts_expr(e);
+    let args = match_or!([e.kind] ExprKind::Call(_, ref args) => args; return false);
+    match callee_name(cx, e).as_ref().map(String::as_str) {
+        Some("malloc") => args.len() == 1 && is_size_of(&args[0]),
+        Some("calloc") => {
+            args.len() == 2 && is_size_of(&args[1]) &&
+                matches!([peel_casts(&args[0]).kind] ExprKind::Lit(Lit { kind: LitKind::Int(1, _), .. }))
+        },
+        _ => false,
+    }
 }
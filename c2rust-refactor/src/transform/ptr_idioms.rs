@@ -0,0 +1,173 @@
+//! Recognizers for the raw pointer idioms that appear in translated C code, shared by the
+//! transforms that replace raw pointers with safe types (`ownership_retype`,
+//! `convert_allocations`, `ptr_to_slice`).
+use rustc::hir::def_id::DefId;
+use rustc::ty::{self, ParamEnv};
+use syntax::ast::*;
+use syntax::ptr::P;
+use syntax::source_map::DUMMY_SP;
+
+use c2rust_ast_builder::mk;
+use crate::reflect::reflect_def_path;
+use crate::RefactorCtxt;
+
+/// Remove any casts and parentheses wrapped around `e`.
+pub fn peel_casts(e: &P<Expr>) -> &P<Expr> {
+    match e.kind {
+        ExprKind::Cast(ref e, _) | ExprKind::Paren(ref e) => peel_casts(e),
+        _ => e,
+    }
+}
+
+/// Like `peel_casts`, but for an `Expr` that is not behind a `P`.
+pub fn peel_casts_expr(e: &Expr) -> &Expr {
+    match e.kind {
+        ExprKind::Cast(ref e, _) | ExprKind::Paren(ref e) => peel_casts_expr(e),
+        _ => e,
+    }
+}
+
+pub fn peel_parens(e: &Expr) -> &Expr {
+    match e.kind {
+        ExprKind::Paren(ref e) => peel_parens(e),
+        _ => e,
+    }
+}
+
+/// Is `e` a path whose last segment is one of `names`?
+pub fn is_path_to(e: &Expr, names: &[&str]) -> bool {
+    match e.kind {
+        ExprKind::Path(_, ref path) => path.segments.last()
+            .map_or(false, |seg| names.iter().any(|&n| &*seg.ident.as_str() == n)),
+        _ => false,
+    }
+}
+
+/// Is `e` a null pointer, such as `0 as *mut T` or `ptr::null_mut()`?
+pub fn is_null_ptr(e: &Expr) -> bool {
+    match peel_casts_expr(e).kind {
+        ExprKind::Lit(ref lit) => matches!([lit.kind] LitKind::Int(0, _)),
+        ExprKind::Call(ref func, ref args) => args.is_empty() && is_path_to(func, &["null", "null_mut"]),
+        _ => false,
+    }
+}
+
+/// Is `e` a call to `size_of::<T>()`, possibly with casts?
+pub fn is_size_of(e: &Expr) -> bool {
+    match peel_casts_expr(e).kind {
+        ExprKind::Call(ref func, ref args) => args.is_empty() && is_path_to(func, &["size_of"]),
+        _ => false,
+    }
+}
+
+/// Split `p.offset(i)` into `p` and `i`.
+pub fn offset_base(e: &P<Expr>) -> Option<(&P<Expr>, &P<Expr>)> {
+    match e.kind {
+        ExprKind::Paren(ref e) => offset_base(e),
+        ExprKind::MethodCall(ref seg, ref args)
+                if &*seg.ident.as_str() == "offset" && args.len() == 2 => Some((&args[0], &args[1])),
+        _ => None,
+    }
+}
+
+pub enum AllocSize {
+    One,
+    Count(P<Expr>),
+}
+
+/// Get the number of elements in an allocation of `size` bytes, for sizes of the form
+/// `size_of::<T>()`, `n * size_of::<T>()` or `size_of::<T>() * n`.  The translator emits the
+/// multiplication as `wrapping_mul`.
+pub fn alloc_size(size: &P<Expr>) -> Option<AllocSize> {
+    let size = peel_casts(size);
+    if is_size_of(size) {
+        return Some(AllocSize::One);
+    }
+    let (a, b) = match size.kind {
+        ExprKind::Binary(op, ref a, ref b) if op.node == BinOpKind::Mul => (a, b),
+        ExprKind::MethodCall(ref seg, ref args)
+                if &*seg.ident.as_str() == "wrapping_mul" && args.len() == 2 => (&args[0], &args[1]),
+        _ => return None,
+    };
+    if is_size_of(b) {
+        Some(AllocSize::Count(peel_casts(a).clone()))
+    } else if is_size_of(a) {
+        Some(AllocSize::Count(peel_casts(b).clone()))
+    } else {
+        None
+    }
+}
+
+/// Get the name of the function called by `e`, if `e` is a call to a known function.
+pub fn callee_name(cx: &RefactorCtxt, e: &Expr) -> Option<String> {
+    let did = cx.opt_callee(e)?;
+    let name = cx.ty_ctxt().def_path(did).data.last()?.data.get_opt_name()?;
+    Some(name.to_string())
+}
+
+fn adt_field_def_id(ty: ty::Ty, name: Ident) -> Option<DefId> {
+    let ty = match ty.kind {
+        ty::TyKind::Ref(_, ty, _) => ty,
+        _ => ty,
+    };
+    match ty.kind {
+        ty::TyKind::Adt(adt, _) if adt.is_struct() => {
+            adt.non_enum_variant().fields.iter().find(|f| f.ident == name).map(|f| f.did)
+        },
+        _ => None,
+    }
+}
+
+/// Get the `DefId` of the field accessed by `obj.name`.
+pub fn field_def_id(cx: &RefactorCtxt, obj: &Expr, name: Ident) -> Option<DefId> {
+    adt_field_def_id(cx.opt_adjusted_node_type(peel_parens(obj).id)?, name)
+}
+
+/// Get the `DefId` of field `name` initialized by struct literal `e`.
+pub fn struct_field_def_id(cx: &RefactorCtxt, e: &Expr, name: Ident) -> Option<DefId> {
+    adt_field_def_id(cx.opt_node_type(e.id)?, name)
+}
+
+/// Is the type of the item (usually a field) `did` `Copy`?
+pub fn is_copy(cx: &RefactorCtxt, did: DefId) -> bool {
+    let tcx = cx.ty_ctxt();
+    cx.def_type(did).is_copy_modulo_regions(tcx, tcx.param_env(did), DUMMY_SP)
+}
+
+/// Build a value of type `ty` whose representation is all zeros, such as `0`, `ptr::null_mut()`
+/// or a struct literal with every field zeroed.  This is the typed equivalent of
+/// `mem::zeroed()`, and of the contents of fresh `calloc` memory.  Returns `None` for types that
+/// have no such value, or whose zero value can't be written as a literal, like enums and unions.
+pub fn zero_value<'tcx>(cx: &RefactorCtxt<'_, 'tcx>, ty: ty::Ty<'tcx>) -> Option<P<Expr>> {
+    let tcx = cx.ty_ctxt();
+    Some(match ty.kind {
+        ty::TyKind::Bool => mk().lit_expr(mk().bool_lit(false)),
+        ty::TyKind::Char => mk().lit_expr(mk().char_lit('\0')),
+        ty::TyKind::Int(ity) => mk().lit_expr(mk().int_lit(0, ity)),
+        ty::TyKind::Uint(uty) => mk().lit_expr(mk().int_lit(0, uty)),
+        ty::TyKind::Float(fty) => mk().lit_expr(mk().float_lit("0", fty)),
+        ty::TyKind::RawPtr(mt) => {
+            let func = match mt.mutbl {
+                Mutability::Mutable => "null_mut",
+                Mutability::Immutable => "null",
+            };
+            mk().call_expr(mk().path_expr(vec!["", "std", "ptr", func]), Vec::<P<Expr>>::new())
+        },
+        ty::TyKind::Array(elem, len) => {
+            let len = len.eval_usize(tcx, ParamEnv::empty()) as u128;
+            mk().repeat_expr(zero_value(cx, elem)?, mk().lit_expr(mk().int_lit(len, "usize")))
+        },
+        ty::TyKind::Adt(adt, substs) if adt.is_struct() => {
+            let mut fields = Vec::new();
+            for f in &adt.non_enum_variant().fields {
+                fields.push(mk().field(f.ident, zero_value(cx, f.ty(tcx, substs))?));
+            }
+            let (qself, path) = reflect_def_path(tcx, adt.did);
+            if qself.is_some() {
+                return None;
+            }
+            mk().struct_expr(path, fields)
+        },
+        _ => return None,
+    })
+}
@@ -3,6 +3,7 @@ use std::ops::DerefMut;
 use rustc::hir;
 use rustc::hir::def_id::DefId;
 use rustc::ty::{self, TyKind, TyCtxt, ParamEnv};
+use syntax::ast;
 use syntax::ast::*;
 use syntax::mut_visit::{self, MutVisitor};
 use syntax::parse::PResult;
@@ -14,7 +15,7 @@ use syntax_pos::Span;
 use smallvec::SmallVec;
 
 use c2rust_ast_builder::{mk, IntoSymbol};
-use crate::ast_manip::{FlatMapNodes, MutVisit, MutVisitNodes, fold_output_exprs};
+use crate::ast_manip::{FlatMapNodes, MutVisit, MutVisitNodes, fold_output_exprs, visit_nodes};
 use crate::ast_manip::fn_edit::{mut_visit_fns, visit_fns};
 use crate::ast_manip::lr_expr::{self, fold_expr_with_context, fold_exprs_with_context};
 use crate::command::{Command, CommandState, RefactorState, Registry, TypeckLoopResult};
@@ -23,6 +24,7 @@ use crate::illtyped::{IlltypedFolder, fold_illtyped};
 use crate::matcher::{Bindings, MatchCtxt, Subst, mut_visit_match};
 use crate::reflect::{self, reflect_tcx_ty};
 use crate::transform::Transform;
+use crate::transform::ptr_idioms::{is_null_ptr, offset_base, peel_casts_expr};
 use crate::RefactorCtxt;
 
 /// # `retype_argument` Command
@@ -77,19 +79,11 @@ impl Transform for RetypeArgument {
 
             // An argument was changed, so we need to rewrite uses of that argument inside the
             // function body.
-
-            // `mut_visit_nodes` does a preorder traversal, so if we replace `x` with `wrap(x)`, we will
-            // see `x` again in the recursive call.  We keep track of which nodes have already been
-            // rewritten so that we don't end up with a stack overflow.
-            let mut rewritten_nodes = HashSet::new();
-            fl.block.as_mut().map(|b| MutVisitNodes::visit(b, |e: &mut P<Expr>| {
-                if let Some(hir_id) = cx.try_resolve_expr_to_hid(&e) {
-                    if changed_args.contains(&hir_id) && !rewritten_nodes.contains(&e.id) {
-                        rewritten_nodes.insert(e.id);
-                        let mut bnd = Bindings::new();
-                        bnd.add("__new", e.clone());
-                        *e = unwrap.clone().subst(st, cx, &bnd);
-                    }
+            fl.block.as_mut().map(|b| unwrap_arg_uses(b, st, cx, &HashSet::new(), |hir_id| {
+                if changed_args.contains(&hir_id) {
+                    Some(unwrap.clone())
+                } else {
+                    None
                 }
             }));
         });
@@ -98,19 +92,295 @@ impl Transform for RetypeArgument {
 
         // We don't need any protection against infinite recursion here, because it doesn't make
         // sense for `wrap` to call the function whose args we're changing.
-        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
-            let callee = match_or!([cx.opt_callee(&e)] Some(x) => x; return);
-            let mod_args = match_or!([mod_fns.get(&callee)] Some(x) => x; return);
-            let args: &mut [P<Expr>] = match e.kind {
-                ExprKind::Call(_, ref mut args) => args,
-                ExprKind::MethodCall(_, ref mut args) => args,
-                _ => panic!("expected Call or MethodCall"),
+        wrap_call_args(krate, st, cx, |callee, _args| {
+            mod_fns.get(&callee).into_iter().flatten()
+                .map(|&idx| (idx, wrap.clone(), Bindings::new()))
+                .collect()
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Replace each use of a retyped argument in `block` with its `UNWRAP` template, which
+/// `unwrap` returns given the argument's `HirId`.  The use is bound to `__new`.  Exprs in
+/// `skip` are left unchanged.
+fn unwrap_arg_uses<F>(block: &mut P<Block>,
+                      st: &CommandState,
+                      cx: &RefactorCtxt,
+                      skip: &HashSet<NodeId>,
+                      mut unwrap: F)
+    where F: FnMut(hir::HirId) -> Option<P<Expr>>
+{
+    // `mut_visit_nodes` does a preorder traversal, so if we replace `x` with `unwrap(x)`, we will
+    // see `x` again in the recursive call.  We keep track of which nodes have already been
+    // rewritten so that we don't end up with a stack overflow.
+    let mut rewritten_nodes = skip.clone();
+    MutVisitNodes::visit(block, |e: &mut P<Expr>| {
+        let hir_id = match_or!([cx.try_resolve_expr_to_hid(&e)] Some(x) => x; return);
+        if rewritten_nodes.contains(&e.id) {
+            return;
+        }
+        if let Some(unwrap) = unwrap(hir_id) {
+            rewritten_nodes.insert(e.id);
+            let mut bnd = Bindings::new();
+            bnd.add("__new", e.clone());
+            *e = unwrap.subst(st, cx, &bnd);
+        }
+    });
+}
+
+/// Convert the arguments of every call to a function with retyped arguments.  `wrap` is given
+/// the callee and the arguments of each call, and returns the index of each argument to convert,
+/// with its `WRAP` template and any extra bindings for it.  The argument is bound to `__old`.
+fn wrap_call_args<F>(krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt, mut wrap: F)
+    where F: FnMut(DefId, &[P<Expr>]) -> Vec<(usize, P<Expr>, Bindings)>
+{
+    MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+        let callee = match_or!([cx.opt_callee(&e)] Some(x) => x; return);
+        let args: &mut [P<Expr>] = match e.kind {
+            ExprKind::Call(_, ref mut args) => args,
+            ExprKind::MethodCall(_, ref mut args) => args,
+            _ => return,
+        };
+        for (idx, template, mut bnd) in wrap(callee, &*args) {
+            bnd.add("__old", args[idx].clone());
+            args[idx] = template.subst(st, cx, &bnd);
+        }
+    });
+}
+
+
+/// # `ptr_to_slice` Command
+/// 
+/// Usage: `ptr_to_slice [LEN]`
+/// 
+/// Marks: `target`, `len`
+/// 
+/// For each `*const T` or `*mut T` argument marked `target`, change the type of
+/// the argument to `&[T]` or `&mut [T]`.  The length of the slice is given by the
+/// argument of the same function marked `len`, or, if no argument is marked
+/// `len`, by the `usize` expression `LEN`, which can refer to the function's
+/// arguments by name.
+/// 
+/// Within the function body, `*p.offset(i)` becomes `p[i]`, `*p` becomes `p[0]`,
+/// `p = p.offset(n)` becomes `p = &p[n..]`, and `p.is_null()` becomes
+/// `p.is_empty()`, since callers pass an empty slice for a null pointer.  (This
+/// also treats a non-null pointer with a length of zero as null.)  As with the `UNWRAP` expression
+/// of `retype_argument`, `p.as_ptr()` or `p.as_mut_ptr()` converts every other
+/// use of `p` back to the original pointer.  A slice can't be indexed backwards,
+/// so an argument offset by anything but an integer literal or an unsigned value
+/// is left unchanged, as is an argument whose address is taken or which is
+/// assigned anything other than an offset of itself.
+/// 
+/// At call sites, as with the `WRAP` expression of `retype_argument`, the pointer
+/// is wrapped in `std::slice::from_raw_parts` (or `from_raw_parts_mut`) with the
+/// length computed from the call's arguments.  A null pointer becomes an empty
+/// slice: literal nulls are replaced directly, and any pointer not made by `&x`
+/// or `v.as_ptr()` is checked for null before the conversion.  The arguments used
+/// in the length are evaluated a second time, so they should be free of side
+/// effects.
+pub struct PtrToSlice {
+    pub len: Option<String>,
+}
+
+/// A function whose arguments were changed by `ptr_to_slice`.
+struct SliceFn {
+    /// Index and mutability of each retyped argument
+    args: Vec<(usize, Mutability)>,
+    /// Names of all the function's arguments, for substituting into `len`
+    arg_names: Vec<Option<Ident>>,
+    /// The slice length, in terms of the function's arguments
+    len: P<Expr>,
+}
+
+impl Transform for PtrToSlice {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Change argument types and rewrite function bodies.
+
+        let unwrap_imm = parse_expr(cx.session(), "__new.as_ptr()");
+        let unwrap_mut = parse_expr(cx.session(), "__new.as_mut_ptr()");
+
+        let mut mod_fns: HashMap<DefId, SliceFn> = HashMap::new();
+
+        mut_visit_fns(krate, |fl| {
+            // Retyped arguments, by `HirId`, with their index and mutability.
+            let mut slice_args = HashMap::new();
+            for (i, arg) in fl.decl.inputs.iter().enumerate() {
+                if !st.marked(arg.id, "target") {
+                    continue;
+                }
+                let mty = match_or!([arg.ty.kind] ast::TyKind::Ptr(ref mty) => mty; continue);
+                slice_args.insert(cx.hir_map().node_to_hir_id(arg.pat.id), (i, mty.mutbl));
+            }
+            if slice_args.is_empty() || fl.block.is_none() {
+                return;
+            }
+
+            let arg_names = fl.decl.inputs.iter().map(|arg| match arg.pat.kind {
+                PatKind::Ident(_, ident, None) => Some(ident),
+                _ => None,
+            }).collect::<Vec<_>>();
+            let len_arg = fl.decl.inputs.iter().position(|arg| st.marked(arg.id, "len"));
+            let len = match (len_arg.and_then(|i| arg_names[i]), &self.len) {
+                (Some(name), _) => parse_expr(cx.session(), &format!("{} as usize", name)),
+                (None, Some(len)) => parse_expr(cx.session(), len),
+                (None, None) => {
+                    warn!("ptr_to_slice: {}: `{}` has no argument marked `len`, and no LEN was given",
+                          cx.session().source_map().span_to_string(fl.span), fl.ident);
+                    return;
+                },
             };
-            for &idx in mod_args {
+
+            // Find the uses of each argument that are rewritten together with their parent
+            // expression, and the arguments that can't be retyped at all.  For each offset
+            // pointer, also record how many casts to peel off the offset to reach its
+            // non-negative part.
+            let mut handled = HashMap::new();
+            let mut offsets = HashMap::new();
+            let mut bad = Vec::new();
+            {
+                let arg_of = |e: &Expr| {
+                    cx.try_resolve_expr_to_hid(e).filter(|id| slice_args.contains_key(id))
+                };
+                let block = fl.block.as_ref().unwrap();
+                visit_nodes(&**block, |e: &Expr| match e.kind {
+                    ExprKind::Unary(UnOp::Deref, ref ptr) => match offset_base(ptr) {
+                        Some((base, idx)) => {
+                            let id = match_or!([arg_of(base)] Some(x) => x; return);
+                            match nonneg_offset(cx, idx) {
+                                Some(depth) => {
+                                    handled.insert(base.id, id);
+                                    offsets.insert(base.id, depth);
+                                },
+                                None => bad.push((id, e.span, "it is offset by an amount that may be negative")),
+                            }
+                        },
+                        None => if let Some(id) = arg_of(ptr) {
+                            handled.insert(ptr.id, id);
+                        },
+                    },
+                    ExprKind::Assign(ref lhs, ref rhs) => {
+                        let id = match_or!([arg_of(lhs)] Some(x) => x; return);
+                        match offset_base(rhs) {
+                            Some((base, n)) if arg_of(base) == Some(id) => match nonneg_offset(cx, n) {
+                                Some(depth) => {
+                                    handled.insert(lhs.id, id);
+                                    handled.insert(base.id, id);
+                                    offsets.insert(lhs.id, depth);
+                                },
+                                None => bad.push((id, e.span, "it is offset by an amount that may be negative")),
+                            },
+                            _ => bad.push((id, e.span, "it is assigned a pointer that isn't an offset of itself")),
+                        }
+                    },
+                    ExprKind::AddrOf(_, ref ptr) => {
+                        if let Some(id) = arg_of(ptr) {
+                            bad.push((id, e.span, "its address is taken"));
+                        }
+                    },
+                    ExprKind::MethodCall(ref seg, ref args) if &*seg.ident.as_str() == "is_null" => {
+                        if let Some(id) = arg_of(&args[0]) {
+                            handled.insert(args[0].id, id);
+                        }
+                    },
+                    _ => {},
+                });
+            }
+            for (id, span, reason) in bad {
+                if let Some((i, _)) = slice_args.remove(&id) {
+                    warn!("ptr_to_slice: {}: {}; leaving argument {} of `{}` unchanged",
+                          cx.session().source_map().span_to_string(span), reason, i, fl.ident);
+                }
+            }
+            if slice_args.is_empty() {
+                return;
+            }
+
+            for arg in fl.decl.inputs.iter_mut() {
+                let id = cx.hir_map().node_to_hir_id(arg.pat.id);
+                let mutbl = match_or!([slice_args.get(&id)] Some(&(_, m)) => m; continue);
+                let elem_ty = match_or!([arg.ty.kind] ast::TyKind::Ptr(ref mty) => mty.ty.clone(); continue);
+                let new_ty = match mutbl {
+                    Mutability::Mutable => "&mut [__t]",
+                    Mutability::Immutable => "&[__t]",
+                };
+                let mut bnd = Bindings::new();
+                bnd.add("__t", elem_ty);
+                arg.ty = parse_ty(cx.session(), new_ty).subst(st, cx, &bnd);
+            }
+
+            let subst = |template: &str, p: &P<Expr>, i: Option<(&P<Expr>, usize)>| {
                 let mut bnd = Bindings::new();
-                bnd.add("__old", args[idx].clone());
-                args[idx] = wrap.clone().subst(st, cx, &bnd);
+                bnd.add("__p", p.clone());
+                if let Some((i, depth)) = i {
+                    bnd.add("__i", peel_layers(i, depth).clone());
+                }
+                parse_expr(cx.session(), template).subst(st, cx, &bnd)
+            };
+            let live = |id: NodeId| handled.get(&id).and_then(|hid| slice_args.get(hid));
+
+            fl.block.as_mut().map(|b| fold_exprs_with_context(b, |e, _ectx| {
+                let new_expr = match e.kind {
+                    ExprKind::Unary(UnOp::Deref, ref ptr) => match offset_base(ptr) {
+                        Some((base, idx)) => live(base.id).map(|_| {
+                            subst("__p[__i as usize]", base, Some((idx, offsets[&base.id])))
+                        }),
+                        None => live(ptr.id).map(|_| subst("__p[0]", ptr, None)),
+                    },
+                    ExprKind::Assign(ref lhs, ref rhs) => match (live(lhs.id), offset_base(rhs)) {
+                        (Some(&(_, Mutability::Mutable)), Some((_, n))) => Some(subst(
+                            "__p = &mut ::std::mem::replace(&mut __p, &mut [])[__i as usize..]",
+                            lhs, Some((n, offsets[&lhs.id])))),
+                        (Some(_), Some((_, n))) => Some(subst(
+                            "__p = &__p[__i as usize..]", lhs, Some((n, offsets[&lhs.id])))),
+                        _ => None,
+                    },
+                    ExprKind::MethodCall(ref seg, ref args) if &*seg.ident.as_str() == "is_null" => {
+                        live(args[0].id).map(|_| subst("__p.is_empty()", &args[0], None))
+                    },
+                    _ => None,
+                };
+                if let Some(new_expr) = new_expr {
+                    *e = new_expr;
+                }
+            }));
+
+            // Every other use converts the slice back to a raw pointer.
+            let skip = handled.keys().cloned().collect::<HashSet<_>>();
+            fl.block.as_mut().map(|b| unwrap_arg_uses(b, st, cx, &skip, |hir_id| {
+                match slice_args.get(&hir_id) {
+                    Some(&(_, Mutability::Mutable)) => Some(unwrap_mut.clone()),
+                    Some(_) => Some(unwrap_imm.clone()),
+                    None => None,
+                }
+            }));
+
+            let mut args = slice_args.values().cloned().collect::<Vec<_>>();
+            args.sort_by_key(|&(i, _)| i);
+            mod_fns.insert(cx.node_def_id(fl.id), SliceFn { args, arg_names, len });
+        });
+
+        // (2) Rewrite callsites of modified functions.
+
+        wrap_call_args(krate, st, cx, |callee, args| {
+            let sf = match_or!([mod_fns.get(&callee)] Some(x) => x; return Vec::new());
+
+            let mut bnd = Bindings::new();
+            for (name, arg) in sf.arg_names.iter().zip(args.iter()) {
+                if let Some(name) = name {
+                    bnd.add(name.name, arg.clone());
+                }
             }
+            let len = sf.len.clone().subst(st, cx, &bnd);
+
+            sf.args.iter().map(|&(idx, mutbl)| {
+                let mut bnd = Bindings::new();
+                bnd.add("__len", len.clone());
+                (idx, parse_expr(cx.session(), slice_wrap(&args[idx], mutbl)), bnd)
+            }).collect()
         });
     }
 
@@ -119,6 +389,61 @@ impl Transform for RetypeArgument {
     }
 }
 
+/// Check that the pointer offset `idx` can't be negative: under any casts, it is an integer
+/// literal or has an unsigned type.  Returns the number of casts and parentheses wrapping the
+/// non-negative part.
+fn nonneg_offset(cx: &RefactorCtxt, idx: &Expr) -> Option<usize> {
+    let mut e = idx;
+    let mut depth = 0;
+    loop {
+        if matches!([e.kind] ExprKind::Lit(Lit { kind: LitKind::Int(..), .. })) ||
+           matches!([cx.opt_node_type(e.id).map(|ty| &ty.kind)] Some(TyKind::Uint(_))) {
+            return Some(depth);
+        }
+        e = match e.kind {
+            ExprKind::Cast(ref e, _) | ExprKind::Paren(ref e) => e,
+            _ => return None,
+        };
+        depth += 1;
+    }
+}
+
+/// Remove `depth` layers of casts and parentheses from `e`.
+fn peel_layers(e: &P<Expr>, depth: usize) -> &P<Expr> {
+    match e.kind {
+        ExprKind::Cast(ref e, _) | ExprKind::Paren(ref e) if depth > 0 => peel_layers(e, depth - 1),
+        _ => e,
+    }
+}
+
+/// Pick the `WRAP` template converting the pointer `arg` to a slice of length `__len`.
+/// `from_raw_parts` requires a non-null pointer, so a pointer that isn't known to be non-null is
+/// checked first.
+fn slice_wrap(arg: &Expr, mutbl: Mutability) -> &'static str {
+    let nonnull = match peel_casts_expr(arg).kind {
+        ExprKind::AddrOf(..) => true,
+        ExprKind::MethodCall(ref seg, _) => {
+            let name = seg.ident.as_str();
+            &*name == "as_ptr" || &*name == "as_mut_ptr"
+        },
+        _ => false,
+    };
+    match (is_null_ptr(arg), nonnull, mutbl) {
+        (true, _, Mutability::Mutable) => "&mut []",
+        (true, _, Mutability::Immutable) => "&[]",
+        (false, true, Mutability::Mutable) => "::std::slice::from_raw_parts_mut(__old, __len)",
+        (false, true, Mutability::Immutable) => "::std::slice::from_raw_parts(__old, __len)",
+        (false, false, Mutability::Mutable) => "{
+            let __ptr = __old;
+            if __ptr.is_null() { &mut [] } else { ::std::slice::from_raw_parts_mut(__ptr, __len) }
+        }",
+        (false, false, Mutability::Immutable) => "{
+            let __ptr = __old;
+            if __ptr.is_null() { &[] } else { ::std::slice::from_raw_parts(__ptr, __len) }
+        }",
+    }
+}
+
 
 /// # `retype_return` Command
 /// 
@@ -1420,6 +1745,10 @@ pub fn register_commands(reg: &mut Registry) {
         unwrap: args[2].clone(),
     }));
 
+    reg.register("ptr_to_slice", |args| mk(PtrToSlice {
+        len: args.get(0).cloned(),
+    }));
+
     reg.register("retype_return", |args| mk(RetypeReturn {
         new_ty: args[0].clone(),
         wrap: args[1].clone(),
@@ -0,0 +1,65 @@
+#![feature(rustc_private)]
+extern crate libc;
+
+extern "C" {
+    #[no_mangle]
+    fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn calloc(_: libc::c_ulong, _: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn realloc(_: *mut libc::c_void, _: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn free(_: *mut libc::c_void);
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Point {
+    pub x: libc::c_int,
+    pub y: libc::c_int,
+}
+
+// Only built by struct literals, so its fields don't need an `Option`.
+pub struct Pair {
+    pub first: Box<Point>,
+    pub rest: Vec<Point>,
+}
+
+// Built by `mem::zeroed()`, so its `Box` field must be nullable.
+pub struct Raw {
+    pub p: Option<Box<Point>>,
+}
+
+unsafe fn pairs(n: libc::c_ulong) -> libc::c_int {
+    let mut pair: Pair = Pair {
+        first: Box::new(crate::Point { x: 0i32, y: 0i32 }),
+        rest: ::std::iter::repeat_with(|| crate::Point { x: 0i32, y: 0i32 })
+            .take(n as usize)
+            .collect::<Vec<Point>>(),
+    };
+    (*pair.first).x = 1;
+    (pair.rest[1 as usize]).y = 2;
+    (*pair.first).x + (pair.rest[1 as usize]).y
+}
+
+unsafe fn raw() -> libc::c_int {
+    let mut r: Raw = ::std::mem::zeroed();
+    r.p = Some(Box::new(crate::Point { x: 0i32, y: 0i32 }));
+    (**r.p.as_mut().unwrap()).x = 3;
+    let x = (**r.p.as_ref().unwrap()).x;
+    ::std::mem::drop(r.p.take());
+    x
+}
+
+unsafe fn grow() -> libc::c_int {
+    let mut buf: Vec<libc::c_int> = ::std::iter::repeat_with(|| 0i32)
+        .take(4i32 as usize)
+        .collect::<Vec<libc::c_int>>();
+    buf.resize_with(8i32 as usize, || 0i32);
+    buf[7 as usize] = 4;
+    let x = buf[7 as usize];
+    ::std::mem::drop(buf);
+    x
+}
+
+fn main() {}
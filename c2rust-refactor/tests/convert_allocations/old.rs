@@ -0,0 +1,68 @@
+#![feature(rustc_private)]
+extern crate libc;
+
+extern "C" {
+    #[no_mangle]
+    fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn calloc(_: libc::c_ulong, _: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn realloc(_: *mut libc::c_void, _: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn free(_: *mut libc::c_void);
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Point {
+    pub x: libc::c_int,
+    pub y: libc::c_int,
+}
+
+// Only built by struct literals, so its fields don't need an `Option`.
+pub struct Pair {
+    pub first: *mut Point,
+    pub rest: *mut Point,
+}
+
+// Built by `mem::zeroed()`, so its `Box` field must be nullable.
+pub struct Raw {
+    pub p: *mut Point,
+}
+
+unsafe fn pairs(n: libc::c_ulong) -> libc::c_int {
+    let mut pair: Pair = Pair {
+        first: malloc(::std::mem::size_of::<Point>() as libc::c_ulong) as *mut Point,
+        rest: calloc(n, ::std::mem::size_of::<Point>() as libc::c_ulong) as *mut Point,
+    };
+    (*pair.first).x = 1;
+    (*pair.rest.offset(1)).y = 2;
+    (*pair.first).x + (*pair.rest.offset(1)).y
+}
+
+unsafe fn raw() -> libc::c_int {
+    let mut r: Raw = ::std::mem::zeroed();
+    r.p = malloc(::std::mem::size_of::<Point>() as libc::c_ulong) as *mut Point;
+    (*r.p).x = 3;
+    let x = (*r.p).x;
+    free(r.p as *mut libc::c_void);
+    x
+}
+
+unsafe fn grow() -> libc::c_int {
+    let mut buf: *mut libc::c_int = malloc(
+        (4i32 as libc::c_ulong)
+            .wrapping_mul(::std::mem::size_of::<libc::c_int>() as libc::c_ulong),
+    ) as *mut libc::c_int;
+    buf = realloc(
+        buf as *mut libc::c_void,
+        (8i32 as libc::c_ulong)
+            .wrapping_mul(::std::mem::size_of::<libc::c_int>() as libc::c_ulong),
+    ) as *mut libc::c_int;
+    *buf.offset(7) = 4;
+    let x = *buf.offset(7);
+    free(buf as *mut libc::c_void);
+    x
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    select target 'crate; desc((struct && name("Pair|Raw")) || match_pat(mut buf));' \; \
+    convert_allocations \
+    -- old.rs $rustflags
@@ -0,0 +1,28 @@
+#![feature(custom_attribute)]
+
+use std::os::raw::c_void;
+
+extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+unsafe fn set(p: *mut i32, v: i32) {
+    *p = v;
+}
+
+unsafe fn release(p: *mut i32) {
+    free(p as *mut c_void);
+}
+
+unsafe fn update(p: *mut i32, q: *mut i32) {
+    set(p, 1);
+    release(q);
+}
+
+// `release` needs a MOVE pointer, so this mono has no valid instantiation.
+#[ownership_mono("", READ)]
+unsafe fn release_borrowed(p: *mut i32) {
+    release(p);
+}
+
+fn main() {}
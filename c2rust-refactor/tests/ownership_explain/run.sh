@@ -0,0 +1,27 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    ownership_explain update \; \
+    ownership_mark_pointers \
+    -- old.rs $rustflags 2>explain.log || exit 1
+
+# Neither command rewrites the crate
+[ -f old.new ] || cp old.rs old.new
+
+expect() {
+    if ! grep -q "$1" explain.log; then
+        echo "missing from the log: $1"
+        cat explain.log
+        exit 1
+    fi
+}
+
+expect 'argument 0 of `update` is at least WRITE: WRITE <= argument 0 of `set` (call #0 at old.rs:18:.* <= argument 0 of `update`$'
+expect 'argument 1 of `update` is at least MOVE: MOVE <= argument 0 of `release` (call #1 at old.rs:19:.* <= argument 1 of `update`$'
+expect 'found no solution for the calls in release_borrowed (mono #0)'
+expect 'no monomorphization of release fits call #0 (at Some(old.rs:25:'
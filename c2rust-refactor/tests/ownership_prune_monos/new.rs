@@ -0,0 +1,47 @@
+//! `get_children` and `take_children` both get the suspicious monos `WRITE -> MOVE`, alongside
+//! `WRITE -> WRITE` and `READ -> READ`.  The `MOVE` mono of `get_children` can't be removed at
+//! first, since the `MOVE` mono of its caller `take_children` needs it.  Once that caller mono is
+//! removed, `take_children` is re-resolved to use the remaining monos of `get_children`, and the
+//! `MOVE` mono of `get_children` is removed in the next round.
+
+#![feature(custom_attribute, attr_literals)]
+
+use std::ptr;
+
+struct Forest {
+    #[ownership_static(MOVE)]
+    tree: *mut Tree,
+    #[ownership_static(MOVE)]
+    next: *mut Forest,
+}
+
+struct Tree {
+    #[ownership_static()]
+    data: i32,
+    #[ownership_static(MOVE)]
+    children: *mut Forest,
+}
+
+#[ownership_constraints(le(min(WRITE, _1), _0))]
+#[ownership_mono("mut", WRITE, WRITE)]
+#[ownership_mono("", READ, READ)]
+unsafe fn get_children(parent: *mut Tree) -> *mut Forest {
+    (*parent).children
+}
+
+#[ownership_constraints(le(min(WRITE, _1), _0))]
+#[ownership_mono("mut", WRITE, WRITE)]
+#[ownership_mono("", READ, READ)]
+unsafe fn take_children(parent: *mut Tree) -> *mut Forest {
+    get_children(parent)
+}
+
+fn main() {
+    let mut t = Tree {
+        data: 0,
+        children: ptr::null_mut(),
+    };
+    unsafe {
+        take_children(&mut t);
+    }
+}
@@ -0,0 +1,42 @@
+#![feature(rustc_private, custom_attribute, param_attrs)]
+extern crate libc;
+
+extern "C" {
+    #[no_mangle]
+    fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn free(_: *mut libc::c_void);
+}
+
+#[ownership_mono("", READ)]
+unsafe fn get(p: Option<&i32>) -> i32 {
+    if p.is_none() {
+        return 0;
+    }
+    *p.unwrap()
+}
+
+#[ownership_mono("", WRITE)]
+unsafe fn set(p: &mut i32, v: i32) {
+    *p = v;
+}
+
+#[ownership_mono("", MOVE)]
+unsafe fn make() -> Option<Box<i32>> {
+    Some(Box::new(0i32))
+}
+
+#[ownership_mono("", MOVE)]
+unsafe fn destroy(mut p: Option<Box<i32>>) {
+    ::std::mem::drop(p.take());
+}
+
+unsafe fn client() -> i32 {
+    let mut x = 1;
+    set(&mut *(&mut x as *mut i32), 2);
+    destroy(make());
+    destroy(None);
+    get((&x as *const i32).as_ref()) + get(None)
+}
+
+fn main() {}
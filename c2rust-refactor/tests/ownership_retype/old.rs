@@ -0,0 +1,42 @@
+#![feature(rustc_private, custom_attribute, param_attrs)]
+extern crate libc;
+
+extern "C" {
+    #[no_mangle]
+    fn malloc(_: libc::c_ulong) -> *mut libc::c_void;
+    #[no_mangle]
+    fn free(_: *mut libc::c_void);
+}
+
+#[ownership_mono("", READ)]
+unsafe fn get(p: *const i32) -> i32 {
+    if p.is_null() {
+        return 0;
+    }
+    *p
+}
+
+#[ownership_mono("", WRITE)]
+unsafe fn set(#[nonnull] p: *mut i32, v: i32) {
+    *p = v;
+}
+
+#[ownership_mono("", MOVE)]
+unsafe fn make() -> *mut i32 {
+    malloc(::std::mem::size_of::<i32>() as libc::c_ulong) as *mut i32
+}
+
+#[ownership_mono("", MOVE)]
+unsafe fn destroy(p: *mut i32) {
+    free(p as *mut libc::c_void);
+}
+
+unsafe fn client() -> i32 {
+    let mut x = 1;
+    set(&mut x, 2);
+    destroy(make());
+    destroy(0 as *mut i32);
+    get(&x) + get(0 as *const i32)
+}
+
+fn main() {}
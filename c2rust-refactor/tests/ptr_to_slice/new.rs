@@ -0,0 +1,54 @@
+unsafe fn sum(p: &[i32], n: u32) -> i32 {
+    if p.is_empty() {
+        return 0;
+    }
+    let mut total = 0;
+    let mut i = 0u32;
+    while i < n {
+        total += p[i as usize];
+        i += 1;
+    }
+    total
+}
+
+unsafe fn fill(mut q: &mut [i32], n: u32, x: i32) {
+    let mut i = 0;
+    while i < n {
+        q[0] = x;
+        q = &mut ::std::mem::replace(&mut q, &mut [])[1 as usize..];
+        i += 1;
+    }
+}
+
+// The offset may be negative, so `p` can't become a slice.
+unsafe fn last(p: *const i32, n: i32) -> i32 {
+    *p.offset((n - 1) as isize)
+}
+
+fn main() {
+    let mut buf = [0i32; 4];
+    let v = vec![1, 2, 3];
+    unsafe {
+        fill(::std::slice::from_raw_parts_mut(buf.as_mut_ptr(), 4 as usize), 4, 7);
+        fill(&mut [], 0, 7);
+        let p: *const i32 = &buf[0];
+        let a = sum(
+            {
+                let __ptr = p;
+                if __ptr.is_null() {
+                    &[]
+                } else {
+                    ::std::slice::from_raw_parts(__ptr, 4 as usize)
+                }
+            },
+            4,
+        );
+        let b = sum(
+            ::std::slice::from_raw_parts(v.as_ptr(), v.len() as u32 as usize),
+            v.len() as u32,
+        );
+        let c = last(buf.as_ptr(), 4);
+        let d = sum(&[], 4);
+        println!("{} {} {} {}", a, b, c, d);
+    }
+}
@@ -0,0 +1,41 @@
+unsafe fn sum(p: *const i32, n: u32) -> i32 {
+    if p.is_null() {
+        return 0;
+    }
+    let mut total = 0;
+    let mut i = 0u32;
+    while i < n {
+        total += *p.offset(i as isize);
+        i += 1;
+    }
+    total
+}
+
+unsafe fn fill(mut q: *mut i32, n: u32, x: i32) {
+    let mut i = 0;
+    while i < n {
+        *q = x;
+        q = q.offset(1);
+        i += 1;
+    }
+}
+
+// The offset may be negative, so `p` can't become a slice.
+unsafe fn last(p: *const i32, n: i32) -> i32 {
+    *p.offset((n - 1) as isize)
+}
+
+fn main() {
+    let mut buf = [0i32; 4];
+    let v = vec![1, 2, 3];
+    unsafe {
+        fill(buf.as_mut_ptr(), 4, 7);
+        fill(0 as *mut i32, 0, 7);
+        let p: *const i32 = &buf[0];
+        let a = sum(p, 4);
+        let b = sum(v.as_ptr(), v.len() as u32);
+        let c = last(buf.as_ptr(), 4);
+        let d = sum(0 as *const i32, 4);
+        println!("{} {} {} {}", a, b, c, d);
+    }
+}
@@ -0,0 +1,11 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    select target 'crate; desc(arg && (any_child(match_pat(p)) || any_child(match_pat(mut q))));' \; \
+    select len 'crate; desc(arg && any_child(match_pat(n)));' \; \
+    ptr_to_slice -- old.rs $rustflags
@@ -0,0 +1,192 @@
+//! On-disk cache of typed C ASTs, which lets a transpiler run skip clang for
+//! translation units that have not changed since they were last parsed.
+//!
+//! Each entry holds the CBOR-serialized `TypedAstContext` of one translation
+//! unit. Entries are keyed by a hash of the clang version and exporter build,
+//! the compile command, the extra clang arguments, whether function-like macros
+//! were exported and the contents of the main source file, and additionally record
+//! a digest of every file the AST refers to so that header edits invalidate
+//! them as well.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use c2rust_ast_exporter as ast_exporter;
+use failure::Error;
+use sha1::{Digest, Sha1};
+
+use crate::c_ast::TypedAstContext;
+use crate::compile_cmds::CompileCmd;
+
+/// Version of the cache entry layout. Bump this whenever the serialized
+/// form of `TypedAstContext` changes.
+const FORMAT_VERSION: u32 = 7;
+
+/// Files the cached AST was built from, along with digests of their contents
+type Inputs = Vec<(PathBuf, String)>;
+
+pub struct AstCache {
+    dir: PathBuf,
+}
+
+impl AstCache {
+    pub fn new(dir: &Path) -> Result<AstCache, Error> {
+        fs::create_dir_all(dir)?;
+        Ok(AstCache {
+            dir: dir.to_owned(),
+        })
+    }
+
+    /// Compute the key of the cache entry for the translation unit built by `cmd`.
+    pub fn key(
+        &self,
+        cmd: &CompileCmd,
+        extra_clang_args: &[&str],
+        fn_macros: bool,
+    ) -> Result<String, Error> {
+        let mut hasher = Sha1::new();
+        hasher.input(env!("CARGO_PKG_VERSION"));
+        hasher.input(FORMAT_VERSION.to_le_bytes());
+        // A different clang or exporter may produce a different AST
+        hasher.input(ast_exporter::get_clang_version());
+        hasher.input([0u8]);
+        hasher.input(ast_exporter::BUILD_ID);
+        hasher.input([0u8]);
+        hasher.input(serde_json::to_vec(cmd)?);
+        for arg in extra_clang_args {
+            hasher.input(arg);
+            hasher.input([0u8]);
+        }
+        hasher.input([fn_macros as u8]);
+        hasher.input(fs::read(cmd.abs_file())?);
+        Ok(hex(&hasher.result()))
+    }
+
+    /// Load the AST stored under `key`, provided none of the files it was
+    /// built from have changed since.
+    pub fn load(&self, key: &str) -> Option<TypedAstContext> {
+        let file = File::open(self.entry_path(key)).ok()?;
+        let (inputs, context): (Inputs, TypedAstContext) =
+            match serde_cbor::from_reader(BufReader::new(file)) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Ignoring unreadable AST cache entry {}: {}", key, e);
+                    return None;
+                }
+            };
+
+        let unchanged = inputs
+            .iter()
+            .all(|(path, digest)| file_digest(path).ok().as_ref() == Some(digest));
+        if unchanged {
+            Some(context)
+        } else {
+            None
+        }
+    }
+
+    /// Store `context` under `key`.
+    pub fn store(&self, key: &str, context: &TypedAstContext) -> Result<(), Error> {
+        let inputs = context
+            .iter_file_paths()
+            .map(|path| Ok((path.to_owned(), file_digest(path)?)))
+            .collect::<io::Result<Inputs>>()?;
+
+        // Write to a temporary file first so that concurrent runs never
+        // observe a partially written entry
+        let tmp_path = self.dir.join(format!("{}.{}.tmp", key, process::id()));
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            serde_cbor::to_writer(&mut writer, &(&inputs, context))?;
+        }
+        fs::rename(&tmp_path, self.entry_path(key))?;
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension("cbor")
+    }
+}
+
+fn file_digest(path: &Path) -> io::Result<String> {
+    Ok(hex(&Sha1::digest(&fs::read(path)?)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use c2rust_ast_exporter::clang_ast::SrcFile;
+
+    /// A scratch directory holding a cache, a source file and a header
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("c2rust-ast-cache-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.c"), "#include \"main.h\"\nint x;\n").unwrap();
+        fs::write(dir.join("main.h"), "extern int x;\n").unwrap();
+        dir
+    }
+
+    fn compile_cmd(dir: &Path) -> CompileCmd {
+        serde_json::from_value(serde_json::json!({
+            "directory": dir,
+            "file": dir.join("main.c"),
+            "arguments": ["cc", "-c", "main.c"],
+        }))
+        .unwrap()
+    }
+
+    fn context(dir: &Path) -> TypedAstContext {
+        let file = |name: &str| SrcFile {
+            path: Some(dir.join(name)),
+            include_loc: None,
+        };
+        TypedAstContext::new(&[file("main.c"), file("main.h")])
+    }
+
+    #[test]
+    fn hit_and_miss() {
+        let dir = scratch_dir("hit");
+        let cache = AstCache::new(&dir.join("cache")).unwrap();
+        let cmd = compile_cmd(&dir);
+
+        let key = cache.key(&cmd, &[], false).unwrap();
+        assert!(cache.load(&key).is_none());
+        cache.store(&key, &context(&dir)).unwrap();
+        assert!(cache.load(&key).is_some());
+
+        // Other clang arguments, exported macros or sources make for other entries
+        let other_args = cache.key(&cmd, &["-DFOO"], false).unwrap();
+        assert_ne!(key, other_args);
+        assert!(cache.load(&other_args).is_none());
+        let fn_macros = cache.key(&cmd, &[], true).unwrap();
+        assert_ne!(key, fn_macros);
+        assert!(cache.load(&fn_macros).is_none());
+        fs::write(dir.join("main.c"), "#include \"main.h\"\nint x = 1;\n").unwrap();
+        let other_source = cache.key(&cmd, &[], false).unwrap();
+        assert_ne!(key, other_source);
+        assert!(cache.load(&other_source).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn header_edits_invalidate() {
+        let dir = scratch_dir("header");
+        let cache = AstCache::new(&dir.join("cache")).unwrap();
+        let key = cache.key(&compile_cmd(&dir), &[], false).unwrap();
+        cache.store(&key, &context(&dir)).unwrap();
+        assert!(cache.load(&key).is_some());
+
+        fs::write(dir.join("main.h"), "extern int x, y;\n").unwrap();
+        assert!(cache.load(&key).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
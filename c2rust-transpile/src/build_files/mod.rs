@@ -11,8 +11,8 @@ use self::handlebars::Handlebars;
 use self::pathdiff::diff_paths;
 use serde_json::json;
 
-use super::TranspilerConfig;
-use super::compile_cmds::LinkCmd;
+use super::{AsmStyle, TranspilerConfig};
+use super::compile_cmds::{provides_lib, LinkCmd};
 use crate::CrateSet;
 use crate::PragmaSet;
 use crate::get_module_name;
@@ -64,6 +64,15 @@ pub struct CrateConfig<'lcmd> {
     pub pragmas: PragmaSet,
     pub crates: CrateSet,
     pub link_cmd: &'lcmd LinkCmd,
+    pub dependencies: Vec<CrateDependency<'lcmd>>,
+}
+
+/// Another crate of the workspace whose library is linked into a crate
+pub struct CrateDependency<'lcmd> {
+    pub crate_name: String,
+    /// Path to the dependency, relative to the dependent crate
+    pub path: PathBuf,
+    pub link_cmd: &'lcmd LinkCmd,
 }
 
 /// Emit `Cargo.toml` and `lib.rs` for a library or `main.rs` for a binary.
@@ -96,8 +105,13 @@ pub fn emit_build_files<'lcmd>(
         emit_rust_toolchain(tcfg, &build_dir);
     }
     crate_cfg.and_then(|ccfg| {
-        emit_build_rs(tcfg, &reg, &build_dir, ccfg.link_cmd);
-        emit_lib_rs(tcfg, &reg, &build_dir, ccfg.modules, ccfg.pragmas, &ccfg.crates)
+        emit_build_rs(tcfg, &reg, &build_dir, ccfg.link_cmd, &ccfg.dependencies);
+        let dependencies = ccfg
+            .dependencies
+            .iter()
+            .map(|dep| dep.crate_name.replace('-', "_"))
+            .collect();
+        emit_lib_rs(tcfg, &reg, &build_dir, ccfg.modules, ccfg.pragmas, &ccfg.crates, dependencies)
     })
 }
 
@@ -191,15 +205,28 @@ fn get_lib_rs_file_name(tcfg: &TranspilerConfig) -> &str {
     }
 }
 
-/// Emit `build.rs` to make it easier to link in native libraries
+/// Emit `build.rs` to make it easier to link in native libraries. Libraries
+/// provided by other crates of the workspace are linked through Cargo
+/// dependencies instead.
 fn emit_build_rs(
     tcfg: &TranspilerConfig,
     reg: &Handlebars,
     build_dir: &Path,
     link_cmd: &LinkCmd,
+    dependencies: &[CrateDependency],
 ) -> Option<PathBuf> {
+    let libraries = link_cmd
+        .libs
+        .iter()
+        .filter(|lib| {
+            !dependencies.iter().any(|dep| {
+                dep.link_cmd.output.as_ref().map_or(false, |output| provides_lib(output, lib))
+            })
+        })
+        .collect::<Vec<_>>();
     let json = json!({
-        "libraries": link_cmd.libs,
+        "libraries": libraries,
+        "library_dirs": link_cmd.lib_dirs,
     });
     let output = reg.render("build.rs", &json).unwrap();
     let output_path = build_dir.join("build.rs");
@@ -215,6 +242,7 @@ fn emit_lib_rs(
     modules: Vec<PathBuf>,
     pragmas: PragmaSet,
     crates: &CrateSet,
+    dependencies: Vec<String>,
 ) -> Option<PathBuf> {
     let plugin_args = tcfg
         .cross_check_configs
@@ -236,6 +264,7 @@ fn emit_lib_rs(
         "modules": modules,
         "pragmas": pragmas,
         "crates": crates,
+        "dependencies": dependencies,
     });
 
     let output_path = build_dir.join(file_name);
@@ -244,11 +273,25 @@ fn emit_lib_rs(
     maybe_write_to_file(&output_path, output, tcfg.overwrite_existing)
 }
 
+/// The oldest nightly that accepts the `asm!` operand syntax emitted with
+/// `--asm-style operands`.
+pub const ASM_OPERANDS_TOOLCHAIN: &str = "nightly-2020-06-01";
+
+/// A nightly with `std::process::ExitCode` (Rust 1.61), which `main` returns
+/// with `--main-exit-code`. It also accepts the `asm!` operand syntax.
+pub const MAIN_EXIT_CODE_TOOLCHAIN: &str = "nightly-2022-06-01";
+
 /// If we translate variadic functions, the output will only compile
 /// on a nightly toolchain until the `c_variadics` feature is stable.
 fn emit_rust_toolchain(tcfg: &TranspilerConfig, build_dir: &Path) {
     let output_path = build_dir.join("rust-toolchain");
-    let output = include_str!("../../rust-toolchain").to_string();
+    let output = if tcfg.main_exit_code {
+        format!("{}\n", MAIN_EXIT_CODE_TOOLCHAIN)
+    } else if tcfg.translate_asm && tcfg.asm_style == AsmStyle::Operands {
+        format!("{}\n", ASM_OPERANDS_TOOLCHAIN)
+    } else {
+        include_str!("../../rust-toolchain").to_string()
+    };
     maybe_write_to_file(&output_path, output, tcfg.overwrite_existing);
 }
 
@@ -268,6 +311,32 @@ fn emit_cargo_toml<'lcmd>(
     });
     if let Some(ccfg) = crate_cfg {
         let binaries = convert_module_list(tcfg, build_dir, ccfg.modules.to_owned(), ModuleSubset::Binaries);
+        let dependencies = ccfg
+            .dependencies
+            .iter()
+            .map(|dep| json!({
+                "name": dep.crate_name,
+                "path": dep.path,
+            }))
+            .collect::<Vec<_>>();
+        // Configuration features are forwarded to the crates we depend on,
+        // whose items may be guarded by them as well
+        let features = tcfg
+            .configurations
+            .iter()
+            .map(|config| {
+                let forwarded = ccfg
+                    .dependencies
+                    .iter()
+                    .map(|dep| format!("\"{}/{}\"", dep.crate_name, config.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                json!({
+                    "name": config.name,
+                    "forwarded": forwarded,
+                })
+            })
+            .collect::<Vec<_>>();
         let crate_json = json!({
             "crate_name": ccfg.crate_name,
             "crate_rust_name": ccfg.crate_name.replace('-', "_"),
@@ -279,8 +348,13 @@ fn emit_cargo_toml<'lcmd>(
             "cross_check_backend": tcfg.cross_check_backend,
             "c2rust_bitfields": ccfg.crates.contains("c2rust_bitfields"),
             "c2rust_asm_casts": ccfg.crates.contains("c2rust_asm_casts"),
+            "c2rust_x87": ccfg.crates.contains("c2rust_x87"),
             "f128": ccfg.crates.contains("f128"),
             "num_traits": ccfg.crates.contains("num_traits"),
+            "num_complex": ccfg.crates.contains("num_complex"),
+            "dependencies": dependencies,
+            "features": features,
+            "default_feature": tcfg.configurations.first().map(|config| &config.name),
         });
         json.as_object_mut()
             .unwrap()
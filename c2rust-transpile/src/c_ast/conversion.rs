@@ -151,6 +151,7 @@ fn parse_cast_kind(kind: &str) -> CastKind {
         "FloatingComplexToReal" => CastKind::FloatingComplexToReal,
         "FloatingComplexCast" => CastKind::FloatingComplexCast,
         "FloatingComplexToIntegralComplex" => CastKind::FloatingComplexToIntegralComplex,
+        "FloatingComplexToBoolean" => CastKind::FloatingComplexToBoolean,
         "IntegralRealToComplex" => CastKind::IntegralRealToComplex,
         "IntegralComplexToReal" => CastKind::IntegralComplexToReal,
         "IntegralComplexToBoolean" => CastKind::IntegralComplexToBoolean,
@@ -159,6 +160,9 @@ fn parse_cast_kind(kind: &str) -> CastKind {
         "BuiltinFnToFnPtr" => CastKind::BuiltinFnToFnPtr,
         "ConstCast" => CastKind::ConstCast,
         "VectorSplat" => CastKind::VectorSplat,
+        // `_Atomic(T)` is exported as an atomic-qualified `T`, so these do not
+        // change the representation; accesses are made atomic by the lvalue.
+        "AtomicToNonAtomic" | "NonAtomicToAtomic" => CastKind::NoOp,
         k => panic!("Unsupported implicit cast: {}", k),
     }
 }
@@ -344,6 +348,7 @@ impl ConversionContext {
             is_const: node_id & TypeNode::CONST_MASK != 0,
             is_restrict: node_id & TypeNode::RESTRICT_MASK != 0,
             is_volatile: node_id & TypeNode::VOLATILE_MASK != 0,
+            is_atomic: node_id & TypeNode::ATOMIC_MASK != 0,
         };
         let ctype = self.visit_type(node_id);
 
@@ -457,6 +462,8 @@ impl ConversionContext {
         }
 
         self.typed_context.va_list_kind = untyped_context.va_list_kind;
+        self.typed_context.long_double_format = untyped_context.long_double_format;
+        self.typed_context.target_triple = untyped_context.target_triple.clone();
     }
 
     /// Visit one node.
@@ -890,6 +897,16 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_STMT);
                 }
 
+                ASTEntryTag::TagIndirectGotoStmt if expected_ty & OTHER_STMT != 0 => {
+                    let target_old = node.children[0].expect("Indirect goto target not found");
+                    let target = self.visit_expr(target_old);
+
+                    let goto_stmt = CStmtKind::IndirectGoto(target);
+
+                    self.add_stmt(new_id, located(node, goto_stmt));
+                    self.processed_nodes.insert(new_id, OTHER_STMT);
+                }
+
                 ASTEntryTag::TagNullStmt if expected_ty & OTHER_STMT != 0 => {
                     let null_stmt = CStmtKind::Empty;
 
@@ -1005,18 +1022,27 @@ impl ConversionContext {
                     let raw_inputs = node.extras[2].as_array().expect("input constraints array");
                     let raw_outputs = node.extras[3].as_array().expect("output constraints array");
                     let raw_clobbers = node.extras[4].as_array().expect("clobber array");
+                    let gcc_asm = node.extras[5]
+                        .as_string()
+                        .expect("assembler template")
+                        .to_owned();
+                    let raw_input_names = node.extras[6].as_array().expect("input names array");
+                    let raw_output_names = node.extras[7].as_array().expect("output names array");
 
                     let (input_children, output_children) =
                         node.children.split_at(raw_inputs.len());
 
                     let inputs: Vec<AsmOperand> = raw_inputs
                         .iter()
+                        .zip(raw_input_names)
                         .zip(input_children)
-                        .map(|(c, e)| {
+                        .map(|((c, n), e)| {
                             let constraints = c.as_string().expect("constraint string").to_owned();
+                            let name = n.as_string().expect("operand name");
                             let expression = self.visit_expr(e.expect("expression"));
                             AsmOperand {
                                 constraints,
+                                name: if name.is_empty() { None } else { Some(name.to_owned()) },
                                 expression,
                             }
                         })
@@ -1024,12 +1050,15 @@ impl ConversionContext {
 
                     let outputs: Vec<AsmOperand> = raw_outputs
                         .iter()
+                        .zip(raw_output_names)
                         .zip(output_children)
-                        .map(|(c, e)| {
+                        .map(|((c, n), e)| {
                             let constraints = c.as_string().expect("constraint string").to_owned();
+                            let name = n.as_string().expect("operand name");
                             let expression = self.visit_expr(e.expect("expression"));
                             AsmOperand {
                                 constraints,
+                                name: if name.is_empty() { None } else { Some(name.to_owned()) },
                                 expression,
                             }
                         })
@@ -1043,6 +1072,7 @@ impl ConversionContext {
                     let stmt = CStmtKind::Asm {
                         is_volatile,
                         asm,
+                        gcc_asm,
                         inputs,
                         outputs,
                         clobbers,
@@ -1146,10 +1176,18 @@ impl ConversionContext {
                         .as_string()
                         .expect("Expected float literal string")
                         .to_owned();
+                    let bits = match (node.extras.get(2), node.extras.get(3)) {
+                        (Some(low), Some(high)) => Some((
+                            low.as_u64().expect("Expected long double literal bits"),
+                            high.as_u64().expect("Expected long double literal bits"),
+                        )),
+                        _ => None,
+                    };
                     let ty_old = node.type_id.expect("Expected expression to have type");
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let floating_literal = CExprKind::Literal(ty, CLiteral::Floating(value, c_str));
+                    let floating_literal =
+                        CExprKind::Literal(ty, CLiteral::Floating(value, c_str, bits));
 
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, floating_literal);
                 }
@@ -1244,23 +1282,29 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagCallExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let func_old = node.children[0].expect("Expected function for function call");
-                    let func = self.visit_expr(func_old);
-
-                    let args: Vec<CExprId> = node
-                        .children
-                        .iter()
-                        .skip(1)
-                        .map(|id| {
-                            let arg_id = id.expect("Expected call expression argument");
-                            self.visit_expr(arg_id)
-                        })
-                        .collect();
-
                     let ty_old = node.type_id.expect("Expected expression to have type");
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let call = CExprKind::Call(ty, func, args);
+                    // Calls to `__builtin_constant_p` come with the result clang folded them to
+                    let call = if let Some(value) = node.extras.get(0).and_then(Value::as_u64) {
+                        CExprKind::Literal(ty, CLiteral::Integer(value, IntBase::Dec))
+                    } else {
+                        let func_old =
+                            node.children[0].expect("Expected function for function call");
+                        let func = self.visit_expr(func_old);
+
+                        let args: Vec<CExprId> = node
+                            .children
+                            .iter()
+                            .skip(1)
+                            .map(|id| {
+                                let arg_id = id.expect("Expected call expression argument");
+                                self.visit_expr(arg_id)
+                            })
+                            .collect();
+
+                        CExprKind::Call(ty, func, args)
+                    };
 
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, call);
                 }
@@ -1555,6 +1599,18 @@ impl ConversionContext {
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, stmt_expr)
                 }
 
+                ASTEntryTag::TagAddrLabelExpr => {
+                    let label_old = node.children[0].expect("Expected label ID");
+                    let label = CStmtId(self.visit_node_type(label_old, LABEL_STMT));
+
+                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty = self.visit_qualified_type(ty_old);
+
+                    let addr_label = CExprKind::AddrLabel(ty, label);
+
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, addr_label)
+                }
+
                 ASTEntryTag::TagVAArgExpr => {
                     let child_id = node.children[0].expect("Expected subexpression");
                     let child = self.visit_expr(child_id);
@@ -1604,19 +1660,15 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagConstantExpr => {
-                    let kids: Vec<CExprId> = node
-                        .children
-                        .iter()
-                        .map(|id| {
-                            let child_id = id.expect("Missing constant subexpr");
-                            self.visit_expr(child_id)
-                        })
-                        .collect();
+                    // A `ConstantExpr` only records that its subexpression was evaluated
+                    // as a constant, so it is as transparent to us as parentheses.
+                    let child_id = node.children[0].expect("Missing constant subexpr");
+                    let child = self.visit_expr(child_id);
 
                     let ty_old = node.type_id.expect("Expected expression to have type");
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let e = CExprKind::ConvertVector(ty, kids);
+                    let e = CExprKind::Paren(ty, child);
 
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
                 }
@@ -1944,6 +1996,9 @@ impl ConversionContext {
                     let has_def = node.extras[1]
                         .as_boolean()
                         .expect("Expected has_def flag on struct");
+                    let platform_byte_size = node.extras[5].as_u64().expect("Expected union size");
+                    let platform_alignment =
+                        node.extras[6].as_u64().expect("Expected union alignment");
                     let fields: Option<Vec<CDeclId>> = if has_def {
                         Some(
                             node.children
@@ -1960,7 +2015,12 @@ impl ConversionContext {
                         None
                     };
 
-                    let record = CDeclKind::Union { name, fields };
+                    let record = CDeclKind::Union {
+                        name,
+                        fields,
+                        platform_byte_size,
+                        platform_alignment,
+                    };
 
                     self.add_decl(new_id, located(node, record));
                     self.processed_nodes.insert(new_id, RECORD_DECL);
@@ -2018,6 +2078,74 @@ impl ConversionContext {
                     self.typed_context.c_decls_top.push(CDeclId(new_id));
                 }
 
+                ASTEntryTag::TagMacroFunctionDef if expected_ty & MACRO_DECL != 0 => {
+                    let name = node.extras[0]
+                        .as_string()
+                        .expect("Macros must have a name")
+                        .to_owned();
+
+                    let parameters = node.extras[1]
+                        .as_array()
+                        .expect("Expected macro parameter array")
+                        .iter()
+                        .map(|x| x.as_string().expect("Macro parameters must be named").to_owned())
+                        .collect();
+
+                    let replacements = node
+                        .children
+                        .iter()
+                        .map(|id| {
+                            let expr_id = id.expect("Macro replacement expr not found");
+                            self.visit_expr(expr_id)
+                        })
+                        .collect();
+
+                    let arguments = node.extras[2]
+                        .as_array()
+                        .expect("Expected macro argument array")
+                        .iter()
+                        .map(|expansion| {
+                            expansion
+                                .as_array()
+                                .expect("Expected macro expansion arguments")
+                                .iter()
+                                .map(|uses| {
+                                    uses.as_array()
+                                        .expect("Expected macro parameter uses")
+                                        .iter()
+                                        .map(|id| {
+                                            let id = id.as_u64().expect("Macro argument expr not found");
+                                            self.visit_expr(id)
+                                        })
+                                        .collect()
+                                })
+                                .collect()
+                        })
+                        .collect();
+
+                    let mac_function = CDeclKind::MacroFunction {
+                        name,
+                        parameters,
+                        replacements,
+                        arguments,
+                    };
+                    self.add_decl(new_id, located(node, mac_function));
+                    self.processed_nodes.insert(new_id, MACRO_DECL);
+
+                    self.typed_context.c_decls_top.push(CDeclId(new_id));
+                }
+
+                ASTEntryTag::TagStaticAssertDecl if expected_ty & DECL != 0 => {
+                    let assert_expr = node.children[0].expect("Expected static assert condition");
+                    let assert_expr = self.visit_expr(assert_expr);
+                    let message = node.extras[0].as_string().cloned();
+
+                    let static_assert = CDeclKind::StaticAssert { assert_expr, message };
+
+                    self.add_decl(new_id, located(node, static_assert));
+                    self.processed_nodes.insert(new_id, OTHER_DECL);
+                }
+
                 ASTEntryTag::TagNonCanonicalDecl if expected_ty & DECL != 0 => {
                     let canonical_decl = node.children[0]
                         .expect("NonCanonicalDecl must point to a canonical decl");
@@ -44,6 +44,7 @@ fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
         ShuffleVector(..) | ConvertVector(..) => vec![],
         OffsetOf(..) | Literal(..) | ImplicitValueInit(..) => vec![],
         DeclRef(..) => vec![], // don't follow references back!
+        AddrLabel(..) => vec![], // don't follow the reference to the label
         Unary(_ty, _op, subexpr, _) => intos![subexpr],
         UnaryType(_ty, _op, opt_expr_id, _) => opt_expr_id.iter().map(|&x| x.into()).collect(),
         Binary(_ty, _op, lhs, rhs, _, _) => intos![lhs, rhs],
@@ -55,8 +56,9 @@ fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
             res
         }
         ArraySubscript(_, l, r, _) => intos![l, r],
-        Conditional(_, c, t, e)
-        | Choose(_, c, t, e, _) => intos![c, t, e],
+        Conditional(_, c, t, e) => intos![c, t, e],
+        // Only the chosen expression is part of the program
+        Choose(_, _, t, e, is_cond_true) => intos![if is_cond_true { t } else { e }],
         BinaryConditional(_, c, t) => intos![c, t],
         InitList(_, ref xs, _, _) => xs.iter().map(|&x| x.into()).collect(),
         Atomic { ptr, order, val1, order_fail, val2, weak, ..} => {
@@ -90,6 +92,7 @@ fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
         OffsetOf(_, OffsetOfKind::Variable(qty, _, _)) => intos![qty.ctype],
         OffsetOf(..) | Literal(..) | ImplicitValueInit(..) => vec![],
         DeclRef(..) => vec![], // don't follow references back!
+        AddrLabel(..) => vec![], // don't follow the reference to the label
         Unary(_ty, _op, subexpr, _) => intos![subexpr],
         UnaryType(_ty, _op, opt_expr_id, qty) => {
             let mut res = intos![qty.ctype];
@@ -107,8 +110,9 @@ fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
             res
         }
         ArraySubscript(_, l, r, _) => intos![l, r],
-        Conditional(_, c, t, e)
-        | Choose(_, c, t, e, _) => intos![c, t, e],
+        Conditional(_, c, t, e) => intos![c, t, e],
+        // Only the chosen expression is part of the program
+        Choose(_, _, t, e, is_cond_true) => intos![if is_cond_true { t } else { e }],
         BinaryConditional(_, c, t) => intos![c, t],
         InitList(_, ref xs, _, _) => xs.iter().map(|&x| x.into()).collect(),
         Atomic { ptr, order, val1, order_fail, val2, weak, ..} => {
@@ -175,6 +179,10 @@ fn immediate_decl_children(kind: &CDeclKind) -> Vec<SomeId> {
         MacroObject {
             ref replacements, ..
         } => replacements.iter().map(|&x| x.into()).collect(),
+        MacroFunction {
+            ref replacements, ..
+        } => replacements.iter().map(|&x| x.into()).collect(),
+        StaticAssert { assert_expr, .. } => intos![assert_expr],
         NonCanonicalDecl { canonical_decl } => intos![canonical_decl],
     }
 }
@@ -234,6 +242,7 @@ fn immediate_stmt_children(kind: &CStmtKind) -> Vec<SomeId> {
             res
         }
         Goto(_) => vec![], // Don't follow the reference to the label
+        IndirectGoto(e) => intos![e],
         Break => vec![],
         Continue => vec![],
         Return(ref opt_e) => opt_e.iter().map(|&x| x.into()).collect(),
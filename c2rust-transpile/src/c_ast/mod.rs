@@ -8,18 +8,20 @@ use std::mem;
 use std::ops::Index;
 use std::path::{Path, PathBuf};
 
-pub use c2rust_ast_exporter::clang_ast::{SrcFile, SrcLoc, SrcSpan, BuiltinVaListKind};
+pub use c2rust_ast_exporter::clang_ast::{
+    BuiltinVaListKind, LongDoubleFormat, SrcFile, SrcLoc, SrcSpan,
+};
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct CTypeId(pub u64);
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct CExprId(pub u64);
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct CDeclId(pub u64);
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct CStmtId(pub u64);
 
 // These are references into particular variants of AST nodes
@@ -42,7 +44,7 @@ mod print;
 use iterators::{DFNodes, SomeId};
 
 /// AST context containing all of the nodes in the Clang AST
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypedAstContext {
     c_types: HashMap<CTypeId, CType>,
     c_exprs: HashMap<CExprId, CExpr>,
@@ -75,7 +77,14 @@ pub struct TypedAstContext {
     // and the value is the decl id to the corresponding structure
     pub prenamed_decls: IndexMap<CDeclId, CDeclId>,
 
+    #[serde(with = "c2rust_ast_exporter::clang_ast::serde_va_list_kind")]
     pub va_list_kind: BuiltinVaListKind,
+
+    #[serde(with = "c2rust_ast_exporter::clang_ast::serde_long_double_format")]
+    pub long_double_format: LongDoubleFormat,
+
+    /// The target triple clang parsed the sources for
+    pub target_triple: String,
 }
 
 /// Comments associated with a typed AST context
@@ -90,6 +99,16 @@ pub struct DisplaySrcSpan {
     loc: SrcSpan,
 }
 
+impl DisplaySrcSpan {
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_ref().map(|p| p.as_path())
+    }
+
+    pub fn span(&self) -> SrcSpan {
+        self.loc
+    }
+}
+
 impl Display for DisplaySrcSpan {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(ref file) = self.file {
@@ -103,7 +122,7 @@ impl Display for DisplaySrcSpan {
 pub type FileId = usize;
 
 /// Represents some AST node possibly with source location information bundled with it
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Located<T> {
     pub loc: Option<SrcSpan>,
     pub kind: T,
@@ -166,6 +185,8 @@ impl TypedAstContext {
             comments: vec![],
             prenamed_decls: IndexMap::new(),
             va_list_kind: BuiltinVaListKind::CharPtrBuiltinVaList,
+            long_double_format: LongDoubleFormat::X87LongDouble,
+            target_triple: String::new(),
         }
     }
 
@@ -186,6 +207,10 @@ impl TypedAstContext {
         self.files[id].path.as_ref().map(|p| p.as_path())
     }
 
+    /// Paths of all the source files this AST refers to.
+    pub fn iter_file_paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().filter_map(|file| file.path.as_ref().map(PathBuf::as_path))
+    }
 
     pub fn compare_src_locs(&self, a: &SrcLoc, b: &SrcLoc) -> Ordering {
         /// Compare `self` with `other`, without regard to file id
@@ -243,6 +268,14 @@ impl TypedAstContext {
         self.c_decls.iter_mut()
     }
 
+    pub fn iter_exprs(&self) -> impl Iterator<Item = (&CExprId, &CExpr)> {
+        self.c_exprs.iter()
+    }
+
+    pub fn iter_stmts(&self) -> impl Iterator<Item = (&CStmtId, &CStmt)> {
+        self.c_stmts.iter()
+    }
+
     pub fn get_decl(&self, key: &CDeclId) -> Option<&CDecl> {
         self.c_decls.get(key)
     }
@@ -465,6 +498,7 @@ impl TypedAstContext {
 
             CExprKind::Literal(_, _) |
             CExprKind::DeclRef(_, _, _) |
+            CExprKind::AddrLabel(_, _) |
             CExprKind::UnaryType(_, _, _, _) |
             CExprKind::OffsetOf(..) => true,
 
@@ -482,7 +516,7 @@ impl TypedAstContext {
             CExprKind::ArraySubscript(_, lhs, rhs, _) => self.is_expr_pure(lhs) && self.is_expr_pure(rhs),
             CExprKind::Conditional(_, c, lhs, rhs) => self.is_expr_pure(c) && self.is_expr_pure(lhs) && self.is_expr_pure(rhs),
             CExprKind::BinaryConditional(_, c, rhs) => self.is_expr_pure(c) && self.is_expr_pure(rhs),
-            CExprKind::Choose(_, c, lhs, rhs, _) => self.is_expr_pure(c) && self.is_expr_pure(lhs) && self.is_expr_pure(rhs),
+            CExprKind::Choose(_, _, lhs, rhs, is_cond_true) => self.is_expr_pure(if is_cond_true { lhs } else { rhs }),
         }
     }
 
@@ -510,45 +544,80 @@ impl TypedAstContext {
     }
 
     pub fn prune_unused_decls(&mut self) {
-        // Starting from a set of root declarations, walk each one to find declarations it
-        // depends on. Then walk each of those, recursively.
-
-        // Declarations we still need to walk.  Everything in here is also in `used`.
-        let mut to_walk: Vec<CDeclId> = Vec::new();
-        // Declarations accessible from a root.
-        let mut used: HashSet<CDeclId> = HashSet::new();
-
-        // Mark all the roots as used.  Roots are all top-level functions and variables that might
-        // be visible from another compilation unit.
-        for &decl_id in &self.c_decls_top {
-            let decl = self.index(decl_id);
-            match decl.kind {
+        // Roots are all top-level functions and variables that might be visible from another
+        // compilation unit.
+        let roots = self
+            .c_decls_top
+            .iter()
+            .copied()
+            .filter(|&decl_id| match self.index(decl_id).kind {
                 CDeclKind::Function {
                     body: Some(_),
                     is_global: true,
                     is_inline: false,
                     ..
-                } => {
-                    to_walk.push(decl_id);
-                    used.insert(decl_id);
-                }
+                } => true,
                 CDeclKind::Variable {
                     is_defn: true,
                     is_externally_visible: true,
                     ..
-                } => {
-                    to_walk.push(decl_id);
-                    used.insert(decl_id);
-                }
-                CDeclKind::Variable { ref attrs, .. } | CDeclKind::Function { ref attrs, .. }
-                    if attrs.contains(&Attribute::Used) =>
-                {
-                    to_walk.push(decl_id);
-                    used.insert(decl_id);
+                } => true,
+                CDeclKind::Variable { ref attrs, .. } | CDeclKind::Function { ref attrs, .. } => {
+                    attrs.contains(&Attribute::Used)
                 }
-                _ => {}
-            }
-        }
+                CDeclKind::StaticAssert { .. } => true,
+                _ => false,
+            })
+            .collect();
+        self.retain_reachable_decls(roots);
+    }
+
+    /// Prune the context down to what is needed to bind to the declarations of the main file
+    /// and the headers it includes directly. Declarations from headers included further down
+    /// are only kept if the bound declarations refer to them.
+    pub fn prune_to_bindings(&mut self) {
+        let roots = self
+            .c_decls_top
+            .iter()
+            .copied()
+            .filter(|&decl_id| {
+                let decl = self.index(decl_id);
+                let selected = self
+                    .file_id(decl)
+                    .map_or(false, |file| self.include_map[file].len() <= 1);
+                selected
+                    && match decl.kind {
+                        CDeclKind::Function {
+                            is_global: true,
+                            is_inline: false,
+                            is_implicit: false,
+                            ..
+                        } => true,
+                        CDeclKind::Variable {
+                            is_externally_visible: true,
+                            ..
+                        } => true,
+                        CDeclKind::Struct { .. }
+                        | CDeclKind::Union { .. }
+                        | CDeclKind::Enum { .. }
+                        | CDeclKind::Typedef { .. }
+                        | CDeclKind::MacroObject { .. } => true,
+                        _ => false,
+                    }
+            })
+            .collect();
+        self.retain_reachable_decls(roots);
+    }
+
+    /// Prune every declaration that is not reachable from `roots`.
+    fn retain_reachable_decls(&mut self, roots: Vec<CDeclId>) {
+        // Starting from a set of root declarations, walk each one to find declarations it
+        // depends on. Then walk each of those, recursively.
+
+        // Declarations accessible from a root.
+        let mut used: HashSet<CDeclId> = roots.iter().copied().collect();
+        // Declarations we still need to walk.  Everything in here is also in `used`.
+        let mut to_walk: Vec<CDeclId> = roots;
 
         // Add all referenced macros to the set of used decls
         // used.extend(self.macro_expansions.values().flatten());
@@ -849,7 +918,7 @@ pub type CStmt = Located<CStmtKind>;
 pub type CExpr = Located<CExprKind>;
 pub type CType = Located<CTypeKind>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, IntoStaticStr)]
 pub enum CDeclKind {
     // http://clang.llvm.org/doxygen/classclang_1_1FunctionDecl.html
     Function {
@@ -910,6 +979,8 @@ pub enum CDeclKind {
     Union {
         name: Option<String>,
         fields: Option<Vec<CFieldId>>,
+        platform_byte_size: u64,
+        platform_alignment: u64,
     },
 
     // Field
@@ -926,6 +997,20 @@ pub enum CDeclKind {
         replacements: Vec<CExprId>,
     },
 
+    MacroFunction {
+        name: String,
+        parameters: Vec<String>,
+        replacements: Vec<CExprId>,
+        /// For each replacement, the expressions substituted for each use of
+        /// each parameter, indexed by parameter.
+        arguments: Vec<Vec<Vec<CExprId>>>,
+    },
+
+    StaticAssert {
+        assert_expr: CExprId,
+        message: Option<String>,
+    },
+
     NonCanonicalDecl {
         canonical_decl: CDeclId,
     }
@@ -949,13 +1034,14 @@ impl CDeclKind {
             } => Some(i),
             &CDeclKind::Field { name: ref i, .. } => Some(i),
             &CDeclKind::MacroObject { ref name, .. } => Some(name),
+            &CDeclKind::MacroFunction { ref name, .. } => Some(name),
             _ => None,
         }
     }
 }
 
 /// An OffsetOf Expr may or may not be a constant
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OffsetOfKind {
     /// An Integer Constant Expr
     Constant(u64),
@@ -972,7 +1058,7 @@ pub enum OffsetOfKind {
 /// are given.
 ///
 /// As per the C standard, qualifiers on types make sense only on lvalues.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, IntoStaticStr)]
 pub enum CExprKind {
     // Literals
     Literal(CQualTypeId, CLiteral),
@@ -1065,10 +1151,13 @@ pub enum CExprKind {
         weak: Option<CExprId>,
     },
 
+    // GNU address of label expr (`&&label`)
+    AddrLabel(CQualTypeId, CLabelId),
+
     BadExpr,
 }
 
-#[derive(Copy, Debug, Clone)]
+#[derive(Copy, Debug, Clone, Serialize, Deserialize)]
 pub enum MemberKind {
     Arrow,
     Dot,
@@ -1114,7 +1203,8 @@ impl CExprKind {
             | CExprKind::ConvertVector(ty, _)
             | CExprKind::DesignatedInitExpr(ty, _, _) => Some(ty),
             | CExprKind::Choose(ty, _, _, _, _)
-            | CExprKind::Atomic{typ: ty, ..} => Some(ty),
+            | CExprKind::Atomic{typ: ty, ..}
+            | CExprKind::AddrLabel(ty, _) => Some(ty),
         }
     }
 
@@ -1132,7 +1222,7 @@ impl CExprKind {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CastKind {
     BitCast,
     LValueToRValue,
@@ -1156,6 +1246,7 @@ pub enum CastKind {
     FloatingComplexToReal,
     FloatingComplexCast,
     FloatingComplexToIntegralComplex,
+    FloatingComplexToBoolean,
     IntegralRealToComplex,
     IntegralComplexToReal,
     IntegralComplexToBoolean,
@@ -1167,7 +1258,7 @@ pub enum CastKind {
 }
 
 /// Represents a unary operator in C (6.5.3 Unary operators) and GNU C extensions
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum UnOp {
     AddressOf,     // &x
     Deref,         // *x
@@ -1186,7 +1277,7 @@ pub enum UnOp {
 }
 
 /// Represents a unary type operator in C
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum UnTypeOp {
     SizeOf,
     AlignOf,
@@ -1205,7 +1296,7 @@ impl UnOp {
 }
 
 /// Represents a binary operator in C (6.5.5 Multiplicative operators - 6.5.14 Logical OR operator)
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BinOp {
     Multiply,     // *
     Divide,       // /
@@ -1281,18 +1372,18 @@ impl BinOp {
     }
 }
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum IntBase {
     Dec,
     Hex,
     Oct,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CLiteral {
     Integer(u64, IntBase), // value and base
     Character(u64),
-    Floating(f64, String),
+    Floating(f64, String, Option<(u64, u64)>), // value, spelling and exact long double bits
     String(Vec<u8>, u8), // Literal bytes and unit byte width
 }
 
@@ -1302,14 +1393,14 @@ impl CLiteral {
         match *self {
             CLiteral::Integer(x, _) => x != 0u64,
             CLiteral::Character(x) => x != 0u64,
-            CLiteral::Floating(x, _) => x != 0f64,
+            CLiteral::Floating(x, _, _) => x != 0f64,
             _ => true,
         }
     }
 }
 
 /// Represents a constant integer expression as used in a case expression
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ConstIntExpr {
     U(u64),
     I(i64),
@@ -1318,7 +1409,7 @@ pub enum ConstIntExpr {
 /// Represents a statement in C (6.8 Statements)
 ///
 /// Reflects the types in <http://clang.llvm.org/doxygen/classclang_1_1Stmt.html>
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, IntoStaticStr)]
 pub enum CStmtKind {
     // Labeled statements (6.8.1)
     //
@@ -1363,6 +1454,7 @@ pub enum CStmtKind {
 
     // Jump statements (6.8.6)
     Goto(CLabelId),
+    IndirectGoto(CExprId),
     Break,
     Continue,
     Return(Option<CExprId>),
@@ -1372,7 +1464,10 @@ pub enum CStmtKind {
 
     // GCC inline assembly
     Asm {
+        /// Assembler template in LLVM syntax
         asm: String,
+        /// Assembler template as written in the GCC source
+        gcc_asm: String,
         inputs: Vec<AsmOperand>,
         outputs: Vec<AsmOperand>,
         clobbers: Vec<String>,
@@ -1380,14 +1475,16 @@ pub enum CStmtKind {
     },
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AsmOperand {
     pub constraints: String,
+    /// Symbolic name of the operand, as used by `%[name]` in the template
+    pub name: Option<String>,
     pub expression: CExprId,
 }
 
 /// Type qualifiers (6.7.3)
-#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Qualifiers {
     /// The `const` qualifier, which marks lvalues as non-assignable.
     ///
@@ -1409,6 +1506,11 @@ pub struct Qualifiers {
     /// To see where we use `volatile`, check the call-sites of `Translation::volatile_write` and
     /// `Translation::volatile_read`.
     pub is_volatile: bool,
+
+    /// The `_Atomic` type specifier or qualifier (6.7.2.4), exported as a qualifier on the value
+    /// type. Plain reads, writes and read-modify-writes through such lvalues are sequentially
+    /// consistent atomic operations, which we translate to the `atomic_*` intrinsics.
+    pub is_atomic: bool,
 }
 
 impl Qualifiers {
@@ -1418,12 +1520,13 @@ impl Qualifiers {
             is_const: self.is_const || other.is_const,
             is_restrict: self.is_restrict || other.is_restrict,
             is_volatile: self.is_volatile || other.is_volatile,
+            is_atomic: self.is_atomic || other.is_atomic,
         }
     }
 }
 
 /// Qualified type
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CQualTypeId {
     pub qualifiers: Qualifiers,
     pub ctype: CTypeId,
@@ -1446,7 +1549,7 @@ impl CQualTypeId {
 /// Represents a type in C (6.2.5 Types)
 ///
 /// Reflects the types in <http://clang.llvm.org/doxygen/classclang_1_1Type.html>
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, IntoStaticStr)]
 pub enum CTypeKind {
     Void,
 
@@ -1539,7 +1642,7 @@ pub enum CTypeKind {
     Half,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Designator {
     Index(u64),
     Range(u64, u64),
@@ -1547,7 +1650,7 @@ pub enum Designator {
 }
 
 /// Enumeration of supported attributes for Declarations
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum Attribute {
     /// __attribute__((alias("foo"), __alias__("foo")))
     Alias(String),
@@ -252,6 +252,10 @@ impl<W: Write> Printer<W> {
                 self.writer.write_all(b")")
             }
 
+            Some(&CExprKind::AddrLabel(_, label)) => {
+                self.writer.write_fmt(format_args!("&&label_{}", label.0))
+            }
+
             None => panic!("Could not find expression with ID {:?}", expr_id),
             // _ => unimplemented!("Printer::print_expr"),
         }
@@ -316,10 +320,10 @@ impl<W: Write> Printer<W> {
     pub fn print_lit(&mut self, lit: &CLiteral, _context: &TypedAstContext) -> Result<()> {
         match *lit {
             CLiteral::Integer(i, _) => self.writer.write_fmt(format_args!("{}", i)),
-            CLiteral::Floating(f, ref str) if str.is_empty() => {
+            CLiteral::Floating(f, ref str, _) if str.is_empty() => {
                 self.writer.write_fmt(format_args!("{}", f))
             }
-            CLiteral::Floating(_, ref str) if str.is_empty() => {
+            CLiteral::Floating(_, ref str, _) if str.is_empty() => {
                 self.writer.write_fmt(format_args!("{}", str))
             }
             _ => unimplemented!("Printer::print_lit"),
@@ -728,6 +732,37 @@ impl<W: Write> Printer<W> {
                 Ok(())
             }
 
+            Some(&CDeclKind::MacroFunction {
+                ref name,
+                ref parameters,
+                ref replacements,
+                ..
+            }) => {
+                self.writer.write_fmt(format_args!("#define {}({}) ", name, parameters.join(", ")))?;
+                for replacement in replacements {
+                    self.print_expr(*replacement, context)?;
+                }
+
+                Ok(())
+            }
+
+            Some(&CDeclKind::StaticAssert {
+                assert_expr,
+                ref message,
+            }) => {
+                self.writer.write_all(b"_Static_assert(")?;
+                self.print_expr(assert_expr, context)?;
+                if let Some(message) = message {
+                    self.writer.write_fmt(format_args!(", {:?}", message))?;
+                }
+                self.writer.write_all(b")")?;
+                if newline {
+                    self.writer.write_all(b"\n")?;
+                }
+
+                Ok(())
+            }
+
             Some(&CDeclKind::NonCanonicalDecl {
                 ref canonical_decl,
             }) => {
@@ -848,6 +883,7 @@ impl<W: Write> Printer<W> {
             is_const,
             is_restrict,
             is_volatile,
+            is_atomic,
         } = type_id.qualifiers;
 
         self.print_type(type_id.ctype, ident, context)?;
@@ -861,6 +897,9 @@ impl<W: Write> Printer<W> {
         if is_volatile {
             self.writer.write_all(b" volatile")?
         }
+        if is_atomic {
+            self.writer.write_all(b" _Atomic")?
+        }
 
         Ok(())
     }
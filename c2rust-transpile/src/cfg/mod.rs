@@ -436,6 +436,8 @@ impl GenTerminator<StructureLabel<StmtOrDecl>> {
 pub struct SwitchCases {
     cases: Vec<(P<Pat>, Label)>,
     default: Option<Label>,
+    /// Enum translated into a Rust enum whose variants the cases match on
+    rust_enum: Option<CEnumId>,
 }
 
 /// A Rust statement, or a C declaration, or a comment
@@ -580,7 +582,37 @@ impl Cfg<Label, StmtOrDecl> {
                 .insert(x);
         }
 
-        let mut cfg_builder = CfgBuilder::new(c_label_to_goto);
+        // A `goto *ptr` can jump to any label whose address is taken in this function, so we
+        // treat it as a `goto` to each of those labels.
+        let mut addr_labels: IndexSet<CLabelId> = IndexSet::new();
+        let mut indirect_gotos: IndexSet<CStmtId> = IndexSet::new();
+        for node in stmt_ids
+            .iter()
+            .flat_map(|&stmt_id| DFExpr::new(&translator.ast_context, stmt_id.into()))
+        {
+            match node {
+                SomeId::Expr(x) => {
+                    if let CExprKind::AddrLabel(_, target) = translator.ast_context[x].kind {
+                        addr_labels.insert(target);
+                    }
+                }
+                SomeId::Stmt(x) => {
+                    if let CStmtKind::IndirectGoto(_) = translator.ast_context[x].kind {
+                        indirect_gotos.insert(x);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for &target in &addr_labels {
+            c_label_to_goto
+                .entry(target)
+                .or_insert(IndexSet::new())
+                .extend(indirect_gotos.iter().cloned());
+        }
+        translator.assign_label_tags(&addr_labels);
+
+        let mut cfg_builder = CfgBuilder::new(c_label_to_goto, addr_labels);
         let entry = cfg_builder.entry;
         cfg_builder.per_stmt_stack.push(PerStmt::new(
             stmt_ids.get(0).cloned(),
@@ -811,6 +843,9 @@ struct CfgBuilder {
     /// Global (immutable) mapping of `CLabelId` -> ID of pointing gotos (basically, reverse the dir
     /// of the goto)
     c_label_to_goto: IndexMap<CLabelId, IndexSet<CStmtId>>,
+    /// Global (immutable) set of labels whose address is taken, in the order of their tags. These
+    /// are the possible targets of every `goto *ptr`.
+    addr_labels: IndexSet<CLabelId>,
 
     // Book-keeping information to build up the `loops` and `multiples` fields in `graph`.
     /// Loops we are currently in. Every time we enter a loop, we push a new vector onto this field.
@@ -1307,7 +1342,10 @@ impl CfgBuilder {
     }
 
     /// Create a new `CfgBuilder` with a single entry label.
-    fn new(c_label_to_goto: IndexMap<CLabelId, IndexSet<CStmtId>>) -> CfgBuilder {
+    fn new(
+        c_label_to_goto: IndexMap<CLabelId, IndexSet<CStmtId>>,
+        addr_labels: IndexSet<CLabelId>,
+    ) -> CfgBuilder {
         let entry = Label::Synthetic(0);
 
         CfgBuilder {
@@ -1319,6 +1357,7 @@ impl CfgBuilder {
             prev_loop_id: 0,
 
             c_label_to_goto,
+            addr_labels,
 
             break_labels: vec![],
             continue_labels: vec![],
@@ -1379,6 +1418,20 @@ impl CfgBuilder {
 
         // Entry label
         entry: Label,
+    ) -> Result<Option<Label>, TranslationError> {
+        self.convert_stmt_inner(translator, ctx, stmt_id, in_tail, entry)
+            .map_err(|e| {
+                e.add_node("CStmtKind", (&translator.ast_context.index(stmt_id).kind).into())
+            })
+    }
+
+    fn convert_stmt_inner(
+        &mut self,
+        translator: &Translation,
+        ctx: ExprContext,
+        stmt_id: CStmtId,
+        in_tail: Option<ImplicitReturnType>,
+        entry: Label,
     ) -> Result<Option<Label>, TranslationError> {
         // Add to the per_stmt_stack
         let live_in: IndexSet<CDeclId> = self.currently_live.last().unwrap().clone();
@@ -1408,7 +1461,7 @@ impl CfgBuilder {
                 }
 
                 CStmtKind::Return(expr) => {
-                    let val = match expr.map(|i| translator.convert_expr(ctx.used(), i)) {
+                    let val = match expr.map(|i| translator.convert_return_value(ctx, i)) {
                         Some(r) => Some(r?),
                         None => None,
                     };
@@ -1426,7 +1479,25 @@ impl CfgBuilder {
                     scrutinee,
                     true_variant,
                     false_variant,
-                } => {
+                } => 'case_blk: {
+                    // `if (setjmp(env) == 0) { body } else { handler }` runs `body` as part of the
+                    // condition, which is then whether to run `handler`
+                    let setjmp = translator.match_setjmp_if(scrutinee, true_variant, false_variant);
+                    let (true_variant, false_variant) = match setjmp {
+                        Some(setjmp) => match setjmp.handler {
+                            Some(handler) => (handler, None),
+                            None => {
+                                let (stmts, val) = translator
+                                    .convert_setjmp_if(ctx, scrutinee, &setjmp)?
+                                    .discard_unsafe();
+                                wip.extend(stmts);
+                                wip.push_stmt(mk().semi_stmt(val));
+                                break 'case_blk Ok(Some(wip));
+                            }
+                        },
+                        None => (true_variant, false_variant),
+                    };
+
                     let next_entry = self.fresh_label();
                     let then_entry = self.fresh_label();
                     let else_entry = if false_variant.is_none() {
@@ -1436,7 +1507,11 @@ impl CfgBuilder {
                     };
 
                     // Condition
-                    let (stmts, val) = translator.convert_condition(ctx, true, scrutinee)?.discard_unsafe();
+                    let (stmts, val) = match setjmp {
+                        Some(ref setjmp) => translator.convert_setjmp_if(ctx, scrutinee, setjmp)?,
+                        None => translator.convert_condition(ctx, true, scrutinee)?,
+                    }
+                    .discard_unsafe();
                     wip.extend(stmts);
 
                     let cond_val = translator.ast_context[scrutinee].kind.get_bool();
@@ -1687,6 +1762,42 @@ impl CfgBuilder {
                     Ok(None)
                 }
 
+                CStmtKind::IndirectGoto(target) => {
+                    // Label addresses are translated as integer tags, so dispatch on the tag
+                    let (stmts, val) = translator
+                        .convert_expr(ctx.used(), target)?
+                        .discard_unsafe();
+                    wip.extend(stmts);
+                    let val = mk().cast_expr(val, mk().path_ty(vec!["usize"]));
+
+                    let mut cases = vec![];
+                    for &label_id in &self.addr_labels.clone() {
+                        let tag = translator.label_tag(label_id)?;
+                        let pat = mk().lit_pat(
+                            mk().lit_expr(mk().int_lit(tag as u128, LitIntType::Unsuffixed)),
+                        );
+                        cases.push((pat, Label::FromC(label_id)));
+                        self.last_per_stmt_mut()
+                            .c_labels_used
+                            .entry(label_id)
+                            .or_insert(IndexSet::new())
+                            .insert(stmt_id);
+                    }
+
+                    // Jumping to anything other than the address of a label is undefined
+                    let invalid_label = self.fresh_label();
+                    let mut invalid_wip = self.new_wip_block(invalid_label);
+                    invalid_wip.push_stmt(mk().semi_stmt(
+                        translator.panic("Indirect goto to an invalid label address"),
+                    ));
+                    self.add_wip_block(invalid_wip, End);
+                    cases.push((mk().wild_pat(), invalid_label));
+
+                    self.add_wip_block(wip, Switch { expr: val, cases });
+
+                    Ok(None)
+                }
+
                 CStmtKind::Compound(ref comp_stmts) => {
                     let comp_entry = self.fresh_label();
                     self.add_wip_block(wip, Jump(comp_entry));
@@ -1760,6 +1871,11 @@ impl CfgBuilder {
                     let this_label = Label::FromC(stmt_id);
                     self.add_wip_block(wip, Jump(this_label));
 
+                    let rust_enum = self.switch_expr_cases
+                        .last()
+                        .and_then(|cases| cases.rust_enum);
+                    let enum_pat = rust_enum.and_then(|enum_id| translator.rust_enum_case_pat(enum_id, cie));
+
                     // Case
                     let branch = match cie {
                         ConstIntExpr::U(n) => {
@@ -1782,7 +1898,7 @@ impl CfgBuilder {
                             stmt_id,
                         ))?
                         .cases
-                        .push((mk().lit_pat(branch), this_label));
+                        .push((enum_pat.unwrap_or_else(|| mk().lit_pat(branch)), this_label));
 
                     // Sub stmt
                     let sub_stmt_next =
@@ -1815,6 +1931,11 @@ impl CfgBuilder {
                     let next_label = self.fresh_label();
                     let body_label = self.fresh_label();
 
+                    // Switches on enums translated into Rust enums match on
+                    // their variants rather than on the promoted integer value
+                    let rust_enum = translator.rust_enum_switch(scrutinee);
+                    let scrutinee = rust_enum.map_or(scrutinee, |(expr, _)| expr);
+
                     // Convert the condition
                     let (stmts, val) = translator
                         .convert_expr(ctx.used(), scrutinee)?
@@ -1829,7 +1950,10 @@ impl CfgBuilder {
                     let saw_unmatched_case = self.last_per_stmt_mut().saw_unmatched_case;
                     let saw_unmatched_default = self.last_per_stmt_mut().saw_unmatched_default;
                     self.break_labels.push(next_label);
-                    self.switch_expr_cases.push(SwitchCases::default());
+                    self.switch_expr_cases.push(SwitchCases {
+                        rust_enum: rust_enum.map(|(_, enum_id)| enum_id),
+                        ..SwitchCases::default()
+                    });
 
                     let body_stuff =
                         self.convert_stmt_help(translator, ctx, switch_body, in_tail, body_label)?;
@@ -1864,6 +1988,7 @@ impl CfgBuilder {
                 CStmtKind::Asm {
                     is_volatile,
                     ref asm,
+                    ref gcc_asm,
                     ref inputs,
                     ref outputs,
                     ref clobbers,
@@ -1873,6 +1998,7 @@ impl CfgBuilder {
                         DUMMY_SP,
                         is_volatile,
                         asm,
+                        gcc_asm,
                         inputs,
                         outputs,
                         clobbers,
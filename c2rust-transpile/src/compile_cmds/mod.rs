@@ -7,7 +7,7 @@ use std::rc::Rc;
 use failure::Error;
 use regex::Regex;
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct CompileCmd {
     /// The working directory of the compilation. All paths specified in the command
     /// or file fields must be either absolute or relative to this directory.
@@ -63,7 +63,7 @@ impl LinkType {
     pub fn as_cargo_types(&self) -> &str {
         match self {
             LinkType::Exe => "\"rlib\"",
-            LinkType::Shared => "\"cdylib\"",
+            LinkType::Shared => "\"cdylib\", \"rlib\"",
             LinkType::Static => "\"staticlib\", \"rlib\"",
         }
     }
@@ -87,6 +87,30 @@ pub struct LinkCmd {
     pub cmd_inputs: Vec<Rc<CompileCmd>>,
     #[serde(default)]
     pub top_level: bool,
+    /// Indices of the other link commands whose outputs are linked into this one
+    #[serde(default)]
+    pub deps: Vec<usize>,
+}
+
+impl LinkCmd {
+    /// Check whether `output` is linked in by this command, either directly
+    /// as an input or through a `-l` flag.
+    pub fn links_against(&self, output: &str) -> bool {
+        self.inputs.iter().any(|inp| inp == output)
+            || self.libs.iter().any(|lib| provides_lib(output, lib))
+    }
+}
+
+const LIB_EXTS: [&str; 3] = ["a", "so", "dylib"];
+
+/// Check whether `output` is a library that `-l<lib>` would resolve to.
+pub fn provides_lib(output: &str, lib: &str) -> bool {
+    match Path::new(output).file_name().and_then(OsStr::to_str) {
+        Some(file_name) => LIB_EXTS
+            .iter()
+            .any(|ext| file_name == format!("lib{}.{}", lib, ext)),
+        None => false,
+    }
 }
 
 /// Convert a linear vector of `CompileCmd`s into a DAG of `LinkCmd`s and `CompileCmd`s
@@ -108,6 +132,8 @@ fn build_link_commands(mut v: Vec<Rc<CompileCmd>>) -> Result<Vec<LinkCmd>, Error
         let mut lcmd: LinkCmd = serde_bencode::from_str(lcmd)?;
 
         lcmd.output = ccmd.output.clone();
+        // `-L` paths are relative to the directory the linker ran in
+        lcmd.lib_dirs = lcmd.lib_dirs.iter().map(|dir| ccmd.directory.join(dir)).collect();
         for inp in &lcmd.inputs {
             if let Some(ccmd_idx) = output_map.get(&inp) {
                 let inp_ccmd = Rc::clone(&v[*ccmd_idx]);
@@ -120,7 +146,20 @@ fn build_link_commands(mut v: Vec<Rc<CompileCmd>>) -> Result<Vec<LinkCmd>, Error
         seen_ccmds.insert(idx);
     }
 
-    // TODO: add binaries
+    // Link commands may consume the outputs of other link commands, e.g. an
+    // executable linking against a library built from the same project.
+    // Record these edges so the corresponding crates can depend on each other.
+    let lcmd_outputs = res.iter().map(|lcmd| lcmd.output.clone()).collect::<Vec<_>>();
+    for (idx, lcmd) in res.iter_mut().enumerate() {
+        lcmd.deps = lcmd_outputs
+            .iter()
+            .enumerate()
+            .filter(|&(dep_idx, output)| {
+                dep_idx != idx && output.as_ref().map_or(false, |o| lcmd.links_against(o))
+            })
+            .map(|(dep_idx, _)| dep_idx)
+            .collect();
+    }
 
     // Check if we have left-over compile commands; if we do,
     // bind them to the crate itself (which becomes a `staticlib` or `rlib`)
@@ -136,6 +175,7 @@ fn build_link_commands(mut v: Vec<Rc<CompileCmd>>) -> Result<Vec<LinkCmd>, Error
             r#type: LinkType::Static,
             cmd_inputs: v,
             top_level: true,
+            deps: vec![],
         };
         res.push(lcmd);
     }
@@ -143,6 +183,27 @@ fn build_link_commands(mut v: Vec<Rc<CompileCmd>>) -> Result<Vec<LinkCmd>, Error
     Ok(res)
 }
 
+/// Order the link commands so that every command comes after the commands
+/// whose outputs it links against.
+pub fn dependency_order(lcmds: &[LinkCmd]) -> Vec<usize> {
+    fn visit(idx: usize, lcmds: &[LinkCmd], visited: &mut HashSet<usize>, order: &mut Vec<usize>) {
+        if !visited.insert(idx) {
+            return;
+        }
+        for &dep in &lcmds[idx].deps {
+            visit(dep, lcmds, visited, order);
+        }
+        order.push(idx);
+    }
+
+    let mut visited = HashSet::new();
+    let mut order = vec![];
+    for idx in 0..lcmds.len() {
+        visit(idx, lcmds, &mut visited, &mut order);
+    }
+    order
+}
+
 ///GNU GCC treats all of the following extensions as C++
 const CPP_EXTS: [&str; 7] = ["C", "cc", "cpp", "CPP", "c++", "cp", "cxx"];
 const ASM_EXTS: [&str; 3] = ["S", "s", "asm"];
@@ -212,3 +273,32 @@ pub fn get_compile_commands(
 
     Ok(lcmds)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link_cmd(directory: &str, lcmd: &str, output: &str) -> Rc<CompileCmd> {
+        Rc::new(CompileCmd {
+            directory: PathBuf::from(directory),
+            file: PathBuf::from(format!("/c2rust/link/{}", lcmd)),
+            output: Some(output.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn lib_dirs_are_absolute() {
+        let cmds = vec![link_cmd(
+            "/src/build",
+            "d6:inputsle8:lib_dirsl3:lib8:/opt/libe4:libsl1:ze4:type3:exee",
+            "prog",
+        )];
+        let lcmds = build_link_commands(cmds).unwrap();
+        assert_eq!(lcmds.len(), 1);
+        assert_eq!(
+            lcmds[0].lib_dirs,
+            vec![PathBuf::from("/src/build/lib"), PathBuf::from("/opt/lib")]
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! Translation of the same C sources under several preprocessor
+//! configurations. Each configuration is translated separately and the
+//! printed outputs are then merged item by item: items that are the same in
+//! every configuration are emitted once, all others are guarded with
+//! `#[cfg(feature = "...")]` attributes naming the configurations they belong
+//! to.
+
+use std::str::FromStr;
+
+use indexmap::{IndexMap, IndexSet};
+
+/// A named set of extra clang arguments, typically `-D` options, under which
+/// every translation unit is translated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Configuration {
+    /// Name of the configuration, which is also the name of the cargo feature
+    /// enabling the items only found in this configuration
+    pub name: String,
+    pub clang_args: Vec<String>,
+}
+
+impl FromStr for Configuration {
+    type Err = String;
+
+    /// Parse a configuration given as `NAME=ARG,ARG,...`
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (name, args) = match s.find('=') {
+            Some(idx) => (&s[..idx], &s[idx + 1..]),
+            None => (s, ""),
+        };
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(format!("Invalid configuration name `{}`", name));
+        }
+
+        let clang_args = args
+            .split(',')
+            .filter(|arg| !arg.is_empty())
+            .map(String::from)
+            .collect();
+        Ok(Configuration {
+            name: name.to_owned(),
+            clang_args,
+        })
+    }
+}
+
+/// A top-level item of a translated file together with the comments and
+/// attributes preceding it
+struct Chunk<'a> {
+    lines: Vec<&'a str>,
+    /// Index of the first line that is not a comment, where a `#[cfg]`
+    /// attribute can be inserted
+    attrs_start: Option<usize>,
+    has_item: bool,
+}
+
+impl<'a> Chunk<'a> {
+    fn new() -> Self {
+        Chunk {
+            lines: vec![],
+            attrs_start: None,
+            has_item: false,
+        }
+    }
+
+    /// Text of the chunk used to decide whether two configurations translated
+    /// an item the same way
+    fn key(&self) -> String {
+        let mut lines = self.lines.iter().map(|line| line.trim_end()).collect::<Vec<_>>();
+        while lines.last().map_or(false, |line| line.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+
+    fn is_inner_attr(&self) -> bool {
+        self.has_item && self.lines.iter().any(|line| line.starts_with("#!["))
+    }
+}
+
+/// Split pretty-printed Rust source into its top-level items. Items start at
+/// the first column, while everything nested in them is indented or closes a
+/// delimiter opened by the item.
+fn split_items(source: &str) -> Vec<Chunk> {
+    let mut chunks = vec![];
+    let mut chunk = Chunk::new();
+    let mut in_block_comment = false;
+
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        let starts_block_comment = trimmed.starts_with("/*");
+        let continues = in_block_comment
+            || line.is_empty()
+            || line.starts_with(char::is_whitespace)
+            || line.starts_with(|c| c == '}' || c == ')' || c == ']');
+
+        if !continues {
+            let is_comment = line.starts_with("//") || starts_block_comment;
+            if chunk.has_item {
+                chunks.push(std::mem::replace(&mut chunk, Chunk::new()));
+            }
+            if !is_comment && chunk.attrs_start.is_none() {
+                chunk.attrs_start = Some(chunk.lines.len());
+            }
+            chunk.has_item = !is_comment && !line.starts_with("#[");
+        }
+        chunk.lines.push(line);
+
+        if starts_block_comment && !in_block_comment {
+            in_block_comment = !trimmed[2..].contains("*/");
+        } else if in_block_comment && line.contains("*/") {
+            in_block_comment = false;
+        }
+    }
+    if !chunk.lines.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Merge the inner attributes of all configurations, taking the union of the
+/// values of list attributes such as `#![feature(...)]` since they may not be
+/// repeated
+fn merge_inner_attrs(attrs: &[String]) -> Vec<String> {
+    let mut lists: IndexMap<&str, IndexSet<&str>> = IndexMap::new();
+    let mut others: IndexSet<&str> = IndexSet::new();
+    for attr in attrs {
+        let attr = attr.trim();
+        let list = Some(attr)
+            .filter(|attr| attr.starts_with("#![") && attr.ends_with(")]"))
+            .and_then(|attr| {
+                let attr = &attr[3..attr.len() - 2];
+                let idx = attr.find('(')?;
+                Some((&attr[..idx], &attr[idx + 1..]))
+            })
+            .filter(|(_, values)| !values.contains(|c| c == '(' || c == '"' || c == '='));
+        match list {
+            Some((key, values)) => {
+                lists
+                    .entry(key)
+                    .or_insert_with(IndexSet::new)
+                    .extend(values.split(',').map(str::trim).filter(|v| !v.is_empty()));
+            }
+            None => {
+                others.insert(attr);
+            }
+        }
+    }
+
+    lists
+        .into_iter()
+        .map(|(key, values)| {
+            let values = values.into_iter().collect::<Vec<_>>();
+            format!("#![{}({})]", key, values.join(", "))
+        })
+        .chain(others.into_iter().map(String::from))
+        .collect()
+}
+
+/// `#[cfg]` attribute enabling an item in the given configurations
+fn cfg_attr(names: &[&str]) -> String {
+    let features = names
+        .iter()
+        .map(|name| format!("feature = \"{}\"", name))
+        .collect::<Vec<_>>();
+    if features.len() == 1 {
+        format!("#[cfg({})]", features[0])
+    } else {
+        format!("#[cfg(any({}))]", features.join(", "))
+    }
+}
+
+/// Step of the alignment of two sequences of items
+#[derive(Copy, Clone)]
+enum Op {
+    Shared,
+    Old,
+    New,
+}
+
+/// Align the merged items with the items of another configuration along their
+/// longest common subsequence, recording `config` on the shared items and
+/// inserting the others where they appear.
+fn merge_into<'a>(
+    merged: Vec<(Chunk<'a>, Vec<usize>)>,
+    chunks: Vec<Chunk<'a>>,
+    config: usize,
+) -> Vec<(Chunk<'a>, Vec<usize>)> {
+    let old_keys = merged.iter().map(|(chunk, _)| chunk.key()).collect::<Vec<_>>();
+    let new_keys = chunks.iter().map(Chunk::key).collect::<Vec<_>>();
+
+    // Most items are the same in every configuration, so only the items
+    // between the common prefix and suffix need to be aligned
+    let prefix = old_keys
+        .iter()
+        .zip(&new_keys)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old_keys[prefix..]
+        .iter()
+        .rev()
+        .zip(new_keys[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_mid = &old_keys[prefix..old_keys.len() - suffix];
+    let new_mid = &new_keys[prefix..new_keys.len() - suffix];
+
+    // lcs[i][j] is the length of the longest common subsequence of
+    // old_mid[i..] and new_mid[j..]
+    let mut lcs = vec![vec![0u32; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = vec![Op::Shared; prefix];
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            ops.push(Op::Shared);
+            i += 1;
+            j += 1;
+        } else if j == new_mid.len() || (i < old_mid.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Old);
+            i += 1;
+        } else {
+            ops.push(Op::New);
+            j += 1;
+        }
+    }
+    ops.extend(vec![Op::Shared; suffix]);
+
+    let mut old = merged.into_iter();
+    let mut new = chunks.into_iter();
+    ops.into_iter()
+        .map(|op| match op {
+            Op::Shared => {
+                let (chunk, mut configs) = old.next().unwrap();
+                configs.push(config);
+                new.next();
+                (chunk, configs)
+            }
+            Op::Old => old.next().unwrap(),
+            Op::New => (new.next().unwrap(), vec![config]),
+        })
+        .collect()
+}
+
+/// Merge the translations of a file under each of `configs` into a single
+/// file, guarding items that are not the same in every configuration with
+/// `#[cfg(feature = "...")]` attributes
+pub fn merge_translations(configs: &[&str], translations: &[String]) -> String {
+    let mut inner_attrs = vec![];
+    let mut merged: Vec<(Chunk, Vec<usize>)> = vec![];
+    for (config, translation) in translations.iter().enumerate() {
+        let (attrs, chunks): (Vec<_>, Vec<_>) = split_items(translation)
+            .into_iter()
+            .partition(Chunk::is_inner_attr);
+        inner_attrs.extend(attrs.iter().map(|chunk| chunk.key().replace('\n', " ")));
+        merged = if config == 0 {
+            chunks.into_iter().map(|chunk| (chunk, vec![config])).collect()
+        } else {
+            merge_into(merged, chunks, config)
+        };
+    }
+
+    let mut output = String::new();
+    for attr in merge_inner_attrs(&inner_attrs) {
+        output.push_str(&attr);
+        output.push('\n');
+    }
+    for (chunk, in_configs) in merged {
+        let guard = if chunk.has_item && in_configs.len() < configs.len() {
+            let names = in_configs.iter().map(|&idx| configs[idx]).collect::<Vec<_>>();
+            Some(cfg_attr(&names))
+        } else {
+            None
+        };
+        // The attribute goes after the comments preceding the item
+        for (idx, line) in chunk.lines.iter().enumerate() {
+            if Some(idx) == chunk.attrs_start {
+                if let Some(ref guard) = guard {
+                    output.push_str(guard);
+                    output.push('\n');
+                }
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_configuration() {
+        let config = "linux=-D__linux__,-DHAVE_FOO=1".parse::<Configuration>().unwrap();
+        assert_eq!(config.name, "linux");
+        assert_eq!(config.clang_args, vec!["-D__linux__", "-DHAVE_FOO=1"]);
+
+        let config = "plain".parse::<Configuration>().unwrap();
+        assert!(config.clang_args.is_empty());
+
+        assert!("has space=-DX".parse::<Configuration>().is_err());
+        assert!("=-DX".parse::<Configuration>().is_err());
+    }
+
+    #[test]
+    fn merge_items() {
+        let a = "#![allow(dead_code)]\n\
+                 pub type T = i32;\n\
+                 // only in a\n\
+                 pub fn f() -> i32 {\n    1\n}\n\
+                 pub static X: i32 = 0;\n";
+        let b = "#![allow(dead_code, unused_mut)]\n\
+                 pub type T = i32;\n\
+                 pub fn f() -> i32 {\n    2\n}\n\
+                 pub fn g() {}\n\
+                 pub static X: i32 = 0;\n";
+        let merged = merge_translations(&["a", "b"], &[a.to_owned(), b.to_owned()]);
+        assert_eq!(
+            merged,
+            "#![allow(dead_code, unused_mut)]\n\
+             pub type T = i32;\n\
+             // only in a\n\
+             #[cfg(feature = \"a\")]\n\
+             pub fn f() -> i32 {\n    1\n}\n\
+             #[cfg(feature = \"b\")]\n\
+             pub fn f() -> i32 {\n    2\n}\n\
+             #[cfg(feature = \"b\")]\n\
+             pub fn g() {}\n\
+             pub static X: i32 = 0;\n"
+        );
+    }
+
+    #[test]
+    fn merge_three_configurations() {
+        let a = "pub const A: i32 = 1;\npub const B: i32 = 2;\n";
+        let b = "pub const A: i32 = 1;\n";
+        let c = "pub const B: i32 = 2;\n";
+        let merged = merge_translations(
+            &["a", "b", "c"],
+            &[a.to_owned(), b.to_owned(), c.to_owned()],
+        );
+        assert_eq!(
+            merged,
+            "#[cfg(any(feature = \"a\", feature = \"b\"))]\n\
+             pub const A: i32 = 1;\n\
+             #[cfg(any(feature = \"a\", feature = \"c\"))]\n\
+             pub const B: i32 = 2;\n"
+        );
+    }
+}
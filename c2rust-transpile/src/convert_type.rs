@@ -2,6 +2,7 @@ use crate::c_ast::CDeclId;
 use crate::c_ast::*;
 use crate::renamer::*;
 use crate::diagnostics::TranslationError;
+use crate::translator::LongDouble;
 use c2rust_ast_builder::mk;
 use std::collections::{HashMap, HashSet};
 use std::ops::Index;
@@ -16,10 +17,15 @@ enum FieldKey {
 
 pub struct TypeConverter {
     pub translate_valist: bool,
+    pub long_double: LongDouble,
+    /// Translate `<stdint.h>` and `<stddef.h>` typedefs to Rust's fixed-width
+    /// integer types rather than aliases of `libc` types
+    pub fixed_width_ints: bool,
     renamer: Renamer<CDeclId>,
     fields: HashMap<CDeclId, Renamer<FieldKey>>,
     suffix_names: HashMap<(CDeclId, &'static str), String>,
     features: HashSet<&'static str>,
+    extern_crates: HashSet<&'static str>,
     emit_no_std: bool,
 }
 
@@ -136,10 +142,13 @@ impl TypeConverter {
     pub fn new(emit_no_std: bool) -> TypeConverter {
         TypeConverter {
             translate_valist: false,
+            long_double: LongDouble::F128,
+            fixed_width_ints: false,
             renamer: Renamer::new(&RESERVED_NAMES),
             fields: HashMap::new(),
             suffix_names: HashMap::new(),
             features: HashSet::new(),
+            extern_crates: HashSet::new(),
             emit_no_std,
         }
     }
@@ -148,6 +157,10 @@ impl TypeConverter {
         &self.features
     }
 
+    pub fn extern_crates_used(&self) -> &HashSet<&'static str> {
+        &self.extern_crates
+    }
+
     pub fn declare_decl_name(&mut self, decl_id: CDeclId, name: &str) -> String {
         self.renamer
             .insert(decl_id, name)
@@ -162,6 +175,37 @@ impl TypeConverter {
         self.renamer.get(&decl_id)
     }
 
+    /// Rust integer type the typedef `decl_id` is translated to when fixed-width
+    /// integer types are enabled and it is one of the standard typedefs. The
+    /// underlying C type has to match the signedness and size of the Rust type,
+    /// in case some code defines its own types with these names.
+    pub fn fixed_width_int(&self, ctxt: &TypedAstContext, decl_id: CDeclId) -> Option<&'static str> {
+        if !self.fixed_width_ints {
+            return None;
+        }
+        let (name, typ) = match ctxt.index(decl_id).kind {
+            CDeclKind::Typedef { ref name, typ, .. } => (name, typ),
+            _ => return None,
+        };
+        let rust_ty = fixed_width_int_type(name)?;
+        let kind = &ctxt.resolve_type(typ.ctype).kind;
+        let matches = if rust_ty.starts_with('i') {
+            kind.is_signed_integral_type()
+        } else {
+            kind.is_unsigned_integral_type() && *kind != CTypeKind::Bool
+        };
+        let target = TargetInts::new(&ctxt.target_triple);
+        let same_size = match (target.c_int_bytes(kind), target.rust_int_bytes(rust_ty)) {
+            (Some(c_bytes), Some(rust_bytes)) => c_bytes == rust_bytes,
+            _ => false,
+        };
+        if matches && same_size {
+            Some(rust_ty)
+        } else {
+            None
+        }
+    }
+
     pub fn resolve_decl_suffix_name(&mut self, decl_id: CDeclId, suffix: &'static str) -> &str {
         let key = (decl_id, suffix);
         if !self.suffix_names.contains_key(&key) {
@@ -318,6 +362,15 @@ impl TypeConverter {
         &mut self,
         ctxt: &TypedAstContext,
         ctype: CTypeId,
+    ) -> Result<P<Ty>, TranslationError> {
+        self.convert_inner(ctxt, ctype)
+            .map_err(|e| e.add_node("CTypeKind", (&ctxt.index(ctype).kind).into()))
+    }
+
+    fn convert_inner(
+        &mut self,
+        ctxt: &TypedAstContext,
+        ctype: CTypeId,
     ) -> Result<P<Ty>, TranslationError> {
         if self.translate_valist && ctxt.is_va_list(ctype) {
             let std_or_core = if self.emit_no_std { "core" } else { "std" };
@@ -341,7 +394,26 @@ impl TypeConverter {
             CTypeKind::UChar => Ok(mk().path_ty(mk().path(vec!["libc", "c_uchar"]))),
             CTypeKind::Char => Ok(mk().path_ty(mk().path(vec!["libc", "c_char"]))),
             CTypeKind::Double => Ok(mk().path_ty(mk().path(vec!["libc", "c_double"]))),
-            CTypeKind::LongDouble => Ok(mk().path_ty(mk().path(vec!["f128", "f128"]))),
+            CTypeKind::LongDouble => match self.long_double {
+                LongDouble::F64 => Ok(mk().path_ty(mk().path(vec!["f64"]))),
+                LongDouble::X87 => {
+                    // `f80` has the size and alignment of `long double` on
+                    // x86-64 only; on i686 it is 12 bytes aligned to 4
+                    if !ctxt.target_triple.starts_with("x86_64") {
+                        return Err(format_err!(
+                            "long double can only be translated to c2rust_x87::f80 on x86-64, not `{}`",
+                            ctxt.target_triple,
+                        )
+                        .into());
+                    }
+                    self.extern_crates.insert("c2rust_x87");
+                    Ok(mk().path_ty(mk().path(vec!["c2rust_x87", "f80"])))
+                }
+                LongDouble::F128 => {
+                    self.extern_crates.insert("f128");
+                    Ok(mk().path_ty(mk().path(vec!["f128", "f128"])))
+                }
+            },
             CTypeKind::Float => Ok(mk().path_ty(mk().path(vec!["libc", "c_float"]))),
             CTypeKind::Int128 => Ok(mk().path_ty(mk().path(vec!["i128"]))),
             CTypeKind::UInt128 => Ok(mk().path_ty(mk().path(vec!["u128"]))),
@@ -370,6 +442,9 @@ impl TypeConverter {
             }
 
             CTypeKind::Typedef(decl_id) => {
+                if let Some(rust_ty) = self.fixed_width_int(ctxt, decl_id) {
+                    return Ok(mk().path_ty(mk().path(vec![rust_ty])));
+                }
                 let new_name = self.resolve_decl_name(decl_id).unwrap();
                 Ok(mk().path_ty(mk().path(vec![new_name])))
             }
@@ -415,6 +490,35 @@ impl TypeConverter {
 
             CTypeKind::TypeOf(ty) => self.convert(ctxt, ty),
 
+            // `num_complex::Complex<T>` is `#[repr(C)]` and laid out like C's `_Complex T`
+            CTypeKind::Complex(elt) => {
+                self.extern_crates.insert("num_complex");
+                let elt_ty = self.convert(ctxt, elt)?;
+                let args = mk().angle_bracketed_args(vec![elt_ty]);
+                Ok(mk().path_ty(vec![
+                    mk().path_segment("num_complex"),
+                    mk().path_segment_with_args("Complex", args),
+                ]))
+            }
+
+            // Vectors become the `core::arch` type with the same layout when there is
+            // one, and otherwise an array of their elements
+            CTypeKind::Vector(elt, len) => match x86_vector_type(ctxt, elt.ctype, len) {
+                Some(name) => {
+                    if name == "__m64" {
+                        self.features.insert("stdsimd");
+                    }
+                    Ok(mk().path_ty(mk().path(vec![name])))
+                }
+                None => {
+                    let ty = self.convert(ctxt, elt.ctype)?;
+                    Ok(mk().array_ty(
+                        ty,
+                        mk().lit_expr(mk().int_lit(len as u128, LitIntType::Unsuffixed)),
+                    ))
+                }
+            },
+
             ref t => Err(format_err!("Unsupported type {:?}", t).into()),
         }
     }
@@ -464,3 +568,111 @@ impl TypeConverter {
         }
     }
 }
+
+/// Name of the x86 `core::arch` type laid out like a vector of `len` elements of
+/// type `elt`, if there is one. There is none unless the sources were parsed for
+/// an x86 or x86-64 target.
+pub fn x86_vector_type(ctxt: &TypedAstContext, elt: CTypeId, len: usize) -> Option<&'static str> {
+    match ctxt.target_triple.split('-').next().unwrap_or("") {
+        "x86_64" | "i386" | "i486" | "i586" | "i686" => {}
+        _ => return None,
+    }
+
+    let int_width = match ctxt.resolve_type(elt).kind {
+        CTypeKind::Float => {
+            return match len {
+                4 => Some("__m128"),
+                8 => Some("__m256"),
+                _ => None,
+            }
+        }
+        CTypeKind::Double => {
+            return match len {
+                2 => Some("__m128d"),
+                4 => Some("__m256d"),
+                _ => None,
+            }
+        }
+        CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar => 1,
+        CTypeKind::Short | CTypeKind::UShort => 2,
+        CTypeKind::Int | CTypeKind::UInt => 4,
+        CTypeKind::LongLong | CTypeKind::ULongLong => 8,
+        _ => return None,
+    };
+
+    match int_width * len {
+        8 => Some("__m64"),
+        16 => Some("__m128i"),
+        32 => Some("__m256i"),
+        _ => None,
+    }
+}
+
+/// Integer sizes of the target clang parsed the sources for
+struct TargetInts {
+    pointer_bytes: u64,
+    long_bytes: u64,
+}
+
+impl TargetInts {
+    fn new(triple: &str) -> TargetInts {
+        let arch = triple.split('-').next().unwrap_or("");
+        let is_64_bit = arch.ends_with("64")
+            || arch.starts_with("aarch64")
+            || arch.starts_with("powerpc64")
+            || arch == "s390x"
+            || arch == "sparcv9";
+        // The x32 ABI has 32-bit pointers on x86-64
+        let pointer_bytes = if is_64_bit && !triple.ends_with("x32") { 8 } else { 4 };
+        // 64-bit Windows keeps `long` at 32 bits
+        let long_bytes = if triple.contains("windows") { 4 } else { pointer_bytes };
+        TargetInts {
+            pointer_bytes,
+            long_bytes,
+        }
+    }
+
+    /// Size in bytes of the C integer type `kind`
+    fn c_int_bytes(&self, kind: &CTypeKind) -> Option<u64> {
+        match *kind {
+            CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar => Some(1),
+            CTypeKind::Short | CTypeKind::UShort => Some(2),
+            CTypeKind::Int | CTypeKind::UInt => Some(4),
+            CTypeKind::Long | CTypeKind::ULong => Some(self.long_bytes),
+            CTypeKind::LongLong | CTypeKind::ULongLong => Some(8),
+            CTypeKind::Int128 | CTypeKind::UInt128 => Some(16),
+            _ => None,
+        }
+    }
+
+    /// Size in bytes of the Rust integer type `name` on the same target
+    fn rust_int_bytes(&self, name: &str) -> Option<u64> {
+        match name {
+            "i8" | "u8" => Some(1),
+            "i16" | "u16" => Some(2),
+            "i32" | "u32" => Some(4),
+            "i64" | "u64" => Some(8),
+            "isize" | "usize" => Some(self.pointer_bytes),
+            _ => None,
+        }
+    }
+}
+
+/// Rust type of the `<stdint.h>` or `<stddef.h>` typedef `name`. This includes
+/// the reserved names glibc and the BSDs define them in terms of.
+fn fixed_width_int_type(name: &str) -> Option<&'static str> {
+    let name = name.trim_start_matches('_');
+    match name {
+        "int8_t" => Some("i8"),
+        "int16_t" => Some("i16"),
+        "int32_t" => Some("i32"),
+        "int64_t" => Some("i64"),
+        "uint8_t" | "u_int8_t" => Some("u8"),
+        "uint16_t" | "u_int16_t" => Some("u16"),
+        "uint32_t" | "u_int32_t" => Some("u32"),
+        "uint64_t" | "u_int64_t" => Some("u64"),
+        "size_t" | "uintptr_t" => Some("usize"),
+        "ssize_t" | "ptrdiff_t" | "intptr_t" => Some("isize"),
+        _ => None,
+    }
+}
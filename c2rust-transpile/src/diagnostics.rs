@@ -13,12 +13,16 @@ use c2rust_ast_exporter::get_clang_major_version;
 
 const DEFAULT_WARNINGS: &[Diagnostic] = &[Diagnostic::ClangAst];
 
-#[derive(PartialEq, Eq, Hash, Debug, Display, EnumString, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Display, EnumString, Clone, Serialize)]
 #[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "kebab-case")]
 pub enum Diagnostic {
     All,
     Comments,
     ClangAst,
+    /// Category of report records for translation errors, which are always
+    /// logged as plain errors
+    Translation,
 }
 
 #[allow(unused_macros)]
@@ -71,6 +75,7 @@ pub fn init(mut enabled_warnings: HashSet<Diagnostic>, log_level: log::LevelFilt
 #[derive(Debug, Clone)]
 pub struct TranslationError {
     loc: Vec<DisplaySrcSpan>,
+    node: Option<(&'static str, &'static str)>,
     inner: Arc<Context<TranslationErrorKind>>,
 }
 
@@ -100,6 +105,19 @@ macro_rules! format_translation_err {
     }
 }
 
+impl TranslationErrorKind {
+    /// Short name of this kind of error, used in translation reports
+    pub fn name(&self) -> &'static str {
+        use self::TranslationErrorKind::*;
+        match self {
+            Generic => "generic",
+            OldLLVMSimd => "old-llvm-simd",
+            VaCopyNotImplemented => "va-copy-not-implemented",
+            InvalidClangAst(_) => "invalid-clang-ast",
+        }
+    }
+}
+
 impl Display for TranslationErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::TranslationErrorKind::*;
@@ -164,6 +182,7 @@ impl TranslationError {
         }
         TranslationError {
             loc: loc_stack,
+            node: None,
             inner: Arc::new(inner),
         }
     }
@@ -171,10 +190,31 @@ impl TranslationError {
     pub fn generic(msg: &'static str) -> Self {
         TranslationError {
             loc: vec![],
+            node: None,
             inner: Arc::new(err_msg(msg).context(TranslationErrorKind::Generic)),
         }
     }
 
+    /// Location of the innermost construct that failed to translate
+    pub fn loc(&self) -> Option<&DisplaySrcSpan> {
+        self.loc.first()
+    }
+
+    /// Kind of the innermost C expression, statement, or type that failed to
+    /// translate, e.g. `CExprKind::Call`
+    pub fn node_kind(&self) -> Option<String> {
+        self.node.map(|(node, kind)| format!("{}::{}", node, kind))
+    }
+
+    /// Record the kind of C node being translated. Only the innermost node is
+    /// kept, so enclosing nodes do not overwrite it.
+    pub fn add_node(mut self, node: &'static str, kind: &'static str) -> Self {
+        if self.node.is_none() {
+            self.node = Some((node, kind));
+        }
+        self
+    }
+
     pub fn add_loc(mut self, loc: Option<DisplaySrcSpan>) -> Self {
         if let Some(loc) = loc {
             self.loc.push(loc);
@@ -187,6 +227,7 @@ impl From<&'static str> for TranslationError {
     fn from(msg: &'static str) -> TranslationError {
         TranslationError {
             loc: vec![],
+            node: None,
             inner: Arc::new(err_msg(msg).context(TranslationErrorKind::Generic)),
         }
     }
@@ -196,6 +237,7 @@ impl From<Error> for TranslationError {
     fn from(e: Error) -> TranslationError {
         TranslationError {
             loc: vec![],
+            node: None,
             inner: Arc::new(e.context(TranslationErrorKind::Generic)),
         }
     }
@@ -205,6 +247,7 @@ impl From<TranslationErrorKind> for TranslationError {
     fn from(kind: TranslationErrorKind) -> TranslationError {
         TranslationError {
             loc: vec![],
+            node: None,
             inner: Arc::new(Context::new(kind)),
         }
     }
@@ -214,6 +257,7 @@ impl From<Context<TranslationErrorKind>> for TranslationError {
     fn from(ctx: Context<TranslationErrorKind>) -> TranslationError {
         TranslationError {
             loc: vec![],
+            node: None,
             inner: Arc::new(ctx),
         }
     }
@@ -15,10 +15,12 @@ extern crate serde_derive;
 extern crate c2rust_ast_builder;
 extern crate c2rust_ast_exporter;
 extern crate clap;
+extern crate crossbeam_utils;
 extern crate itertools;
 extern crate libc;
 extern crate regex;
 extern crate serde_json;
+extern crate sha1;
 #[macro_use]
 extern crate log;
 extern crate fern;
@@ -31,22 +33,28 @@ extern crate failure;
 #[macro_use]
 mod diagnostics;
 
+mod ast_cache;
 pub mod build_files;
 pub mod c_ast;
 pub mod cfg;
 mod compile_cmds;
+mod configurations;
 pub mod convert_type;
 pub mod renamer;
+mod report;
 pub mod rust_ast;
 pub mod translator;
 pub mod with_stmts;
 
 use std::collections::HashSet;
+use std::env;
 use std::fs::{self, File};
 use std::io;
 use std::io::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use failure::Error;
 use regex::Regex;
@@ -56,16 +64,21 @@ use crate::c_ast::*;
 pub use crate::diagnostics::Diagnostic;
 use c2rust_ast_exporter as ast_exporter;
 
-use crate::build_files::{emit_build_files, get_build_dir, CrateConfig};
-use crate::compile_cmds::get_compile_commands;
+use crate::ast_cache::AstCache;
+use crate::build_files::{emit_build_files, get_build_dir, CrateConfig, CrateDependency};
+use crate::compile_cmds::{dependency_order, get_compile_commands, CompileCmd, LinkCmd};
+pub use crate::configurations::Configuration;
+use crate::configurations::merge_translations;
 use crate::convert_type::RESERVED_NAMES;
-pub use crate::translator::ReplaceMode;
+pub use crate::report::ReportFormat;
+use crate::report::UnitReport;
+pub use crate::translator::{AsmStyle, EnumStyle, LongDouble, ReplaceMode, SignedOverflow};
 use std::prelude::v1::Vec;
 
 type PragmaVec = Vec<(&'static str, Vec<&'static str>)>;
 type PragmaSet = indexmap::IndexSet<(&'static str, &'static str)>;
 type CrateSet = indexmap::IndexSet<&'static str>;
-type TranspileResult = (PathBuf, Option<PragmaVec>, Option<CrateSet>);
+type TranspileResult = (PathBuf, Option<PragmaVec>, Option<CrateSet>, Vec<UnitReport>);
 
 /// Configuration settings for the translation process
 #[derive(Debug)]
@@ -91,6 +104,7 @@ pub struct TranspilerConfig {
     pub cross_check_configs: Vec<String>,
     pub prefix_function_names: Option<String>,
     pub translate_asm: bool,
+    pub asm_style: AsmStyle,
     pub use_c_loop_info: bool,
     pub use_c_multiple_info: bool,
     pub simplify_structures: bool,
@@ -98,16 +112,46 @@ pub struct TranspilerConfig {
     pub emit_modules: bool,
     pub fail_on_error: bool,
     pub replace_unsupported_decls: ReplaceMode,
+    pub enum_style: EnumStyle,
+    pub signed_overflow: SignedOverflow,
+    /// Rust type for `long double`, or `None` to match the target's format
+    pub long_double: Option<LongDouble>,
+    /// Translate `<stdint.h>` and `<stddef.h>` typedefs such as `uint32_t` and
+    /// `size_t` to `u32` and `usize` rather than aliases of `libc` types
+    pub fixed_width_ints: bool,
     pub translate_valist: bool,
     pub overwrite_existing: bool,
     pub reduce_type_annotations: bool,
     pub reorganize_definitions: bool,
     pub enabled_warnings: HashSet<Diagnostic>,
     pub emit_no_std: bool,
+    /// Emit compile-time assertions that translated structs and unions have
+    /// the size, alignment and field offsets clang computed for them
+    pub emit_layout_asserts: bool,
+    /// Only emit the foreign declarations, records, typedefs and constants
+    /// needed to call into the C code rather than translating it
+    pub bindings_only: bool,
+    /// Have the generated `main` return `std::process::ExitCode` rather than
+    /// call `std::process::exit`, so that destructors run
+    pub main_exit_code: bool,
     pub output_dir: Option<PathBuf>,
     pub translate_const_macros: bool,
+    pub translate_fn_macros: bool,
     pub disable_refactoring: bool,
     pub log_level: log::LevelFilter,
+    /// Directory in which typed C ASTs are cached between runs, so that
+    /// unchanged translation units do not need to be re-parsed by clang
+    pub ast_cache_dir: Option<PathBuf>,
+    /// Maximum number of translation units to translate concurrently. More
+    /// than one needs `run_exporter_worker`, see `transpile`
+    pub jobs: usize,
+    /// File to write a report of skipped and replaced declarations to
+    pub report_file: Option<PathBuf>,
+    pub report_format: ReportFormat,
+    /// Named sets of extra clang arguments to translate every file under.
+    /// Items that differ between them are guarded with
+    /// `#[cfg(feature = "<name>")]`.
+    pub configurations: Vec<Configuration>,
 
     // Options that control build files
     /// Emit `Cargo.toml` and `lib.rs`
@@ -179,11 +223,104 @@ fn get_module_name(
     file.to_str().map(String::from)
 }
 
+/// Environment variable through which `export_ast_in_subprocess` asks a child
+/// process to run the AST exporter
+const EXPORTER_WORKER_VAR: &str = "C2RUST_AST_EXPORTER_WORKER";
+
+/// Set once `run_exporter_worker` has been called, so that `transpile` knows
+/// its child processes will export ASTs instead of translating again
+static EXPORTER_WORKER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Stack size of the threads translating with `--jobs`, which need as much
+/// stack as the main thread for deeply nested C code
+const TRANSLATION_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// If this process was started by `export_ast_in_subprocess`, export the AST
+/// of the translation unit it was given and exit. With more than one job,
+/// `transpile` runs clang in child processes of the current executable, so
+/// binaries calling `transpile` must call this at the start of `main`.
+pub fn run_exporter_worker() {
+    EXPORTER_WORKER_INSTALLED.store(true, Ordering::SeqCst);
+    let debug = match env::var(EXPORTER_WORKER_VAR) {
+        Ok(mode) => mode == "debug",
+        Err(_) => return,
+    };
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() < 4 {
+        eprintln!("Error: AST exporter worker needs an output, input and compile commands path, \
+                   and whether to export function-like macros");
+        process::exit(1);
+    }
+    let fn_macros = args[3] == "1";
+    let extra_args = args[4..].iter().map(String::as_str).collect::<Vec<_>>();
+    let result = ast_exporter::get_ast_cbor(
+        Path::new(&args[1]),
+        Path::new(&args[2]),
+        &extra_args,
+        debug,
+        fn_macros,
+    )
+    .and_then(|buffer| fs::write(&args[0], buffer));
+    match result {
+        Ok(()) => process::exit(0),
+        Err(e) => {
+            eprintln!("Error: {:}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Export the AST of `input_path` in a child process running
+/// `run_exporter_worker`. The exporter keeps its command line options in
+/// LLVM's global state, so clang can only parse one translation unit at a
+/// time in each process.
+fn export_ast_in_subprocess(
+    input_path: &Path,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+    debug: bool,
+    fn_macros: bool,
+) -> io::Result<ast_exporter::clang_ast::AstContext> {
+    static NEXT_OUTPUT: AtomicUsize = AtomicUsize::new(0);
+    let output_path = env::temp_dir().join(format!(
+        "c2rust-ast-{}-{}.cbor",
+        process::id(),
+        NEXT_OUTPUT.fetch_add(1, Ordering::SeqCst),
+    ));
+    let status = process::Command::new(env::current_exe()?)
+        .env(EXPORTER_WORKER_VAR, if debug { "debug" } else { "1" })
+        .arg(&output_path)
+        .arg(input_path)
+        .arg(cc_db)
+        .arg(if fn_macros { "1" } else { "0" })
+        .args(extra_clang_args)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("AST exporter for {} failed: {}", input_path.display(), status),
+        ));
+    }
+    let buffer = fs::read(&output_path);
+    let _ = fs::remove_file(&output_path);
+    ast_exporter::untyped_ast_from_cbor(&buffer?)
+}
+
 /// Main entry point to transpiler. Called from CLI tools with the result of
 /// clap::App::get_matches().
-pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]) {
+///
+/// With `tcfg.jobs > 1`, clang runs in child processes of the current
+/// executable, which must call `run_exporter_worker` at the start of `main`.
+/// If it hasn't been called, the translation units are translated one at a
+/// time in this process instead.
+pub fn transpile(mut tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]) {
     diagnostics::init(tcfg.enabled_warnings.clone(), tcfg.log_level);
 
+    if tcfg.jobs > 1 && !EXPORTER_WORKER_INSTALLED.load(Ordering::SeqCst) {
+        warn!("run_exporter_worker was not called, translating one file at a time");
+        tcfg.jobs = 1;
+    }
+
     let lcmds = get_compile_commands(cc_db, &tcfg.filter).expect(&format!(
         "Could not parse compile commands from {}",
         cc_db.to_string_lossy()
@@ -194,23 +331,25 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
     let mut clang_args: Vec<&str> = clang_args.iter().map(AsRef::as_ref).collect();
     clang_args.extend_from_slice(extra_clang_args);
 
+    let ast_cache = tcfg.ast_cache_dir.as_ref().and_then(|dir| {
+        AstCache::new(dir)
+            .map_err(|e| warn!("Could not open AST cache {}: {}", dir.display(), e))
+            .ok()
+    });
+
     let mut top_level_ccfg = None;
     let mut workspace_members = vec![];
+    let mut reports = vec![];
     let build_dir = get_build_dir(&tcfg, cc_db);
-    for lcmd in &lcmds {
+    let lcmd_names = lcmds
+        .iter()
+        .map(|lcmd| link_cmd_name(&tcfg, lcmd))
+        .collect::<Vec<String>>();
+    // Translate libraries before the crates that link against them
+    for idx in dependency_order(&lcmds) {
+        let lcmd = &lcmds[idx];
+        let lcmd_name = lcmd_names[idx].clone();
         let cmds = &lcmd.cmd_inputs;
-        let lcmd_name = lcmd.output
-            .as_ref()
-            .map(|output| {
-                let output_path = Path::new(output);
-                output_path
-                    .file_stem()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_owned()
-            })
-            .unwrap_or_else(|| tcfg.crate_name());
         let build_dir = if lcmd.top_level {
             build_dir.to_path_buf()
         } else {
@@ -232,21 +371,23 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
                 .unwrap_or_else(PathBuf::new);
         }
 
-        let results = cmds
-            .iter()
-            .map(|cmd| transpile_single(&tcfg, cmd.abs_file(),
-                                        &ancestor_path,
-                                        &build_dir,
-                                        cc_db,
-                                        extra_clang_args))
-            .collect::<Vec<TranspileResult>>();
+        let cmds = cmds.iter().map(|cmd| &**cmd).collect::<Vec<&CompileCmd>>();
+        let results = transpile_all(tcfg.jobs, &cmds, |cmd| {
+            transpile_single(&tcfg, cmd,
+                             &ancestor_path,
+                             &build_dir,
+                             cc_db,
+                             extra_clang_args,
+                             ast_cache.as_ref())
+        });
         let mut modules = vec![];
         let mut modules_skipped = false;
         let mut pragmas = PragmaSet::new();
         let mut crates = CrateSet::new();
         for res in results {
-            let (module, pragma_vec, crate_set) = res;
+            let (module, pragma_vec, crate_set, report) = res;
             modules.push(module);
+            reports.extend(report);
 
             if let Some(pv) = pragma_vec {
                 for (key, vals) in pv {
@@ -269,15 +410,33 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
             if modules_skipped {
                 // If we skipped a file, we may not have collected all required pragmas
                 warn!("Can't emit build files after incremental transpiler run; skipped.");
+                write_report(&tcfg, &reports);
                 return;
             }
 
+            let dependencies = lcmd.deps
+                .iter()
+                .map(|&dep| {
+                    let dep_name = &lcmd_names[dep];
+                    let path = if lcmd.top_level {
+                        PathBuf::from(dep_name)
+                    } else {
+                        Path::new("..").join(dep_name)
+                    };
+                    CrateDependency {
+                        crate_name: dep_name.clone(),
+                        path,
+                        link_cmd: &lcmds[dep],
+                    }
+                })
+                .collect();
             let ccfg = CrateConfig {
                 crate_name: lcmd_name.clone(),
                 modules,
                 pragmas,
                 crates,
-                link_cmd: lcmd
+                link_cmd: lcmd,
+                dependencies,
             };
             if lcmd.top_level {
                 top_level_ccfg = Some(ccfg);
@@ -289,6 +448,7 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
             }
         }
     }
+    write_report(&tcfg, &reports);
     if tcfg.emit_build_files {
         let crate_file = emit_build_files(&tcfg, &build_dir, top_level_ccfg, Some(workspace_members));
         reorganize_definitions(&tcfg, &build_dir, crate_file)
@@ -296,6 +456,107 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
     }
 }
 
+/// Name of the crate generated for `lcmd`, taken from the name of the file
+/// it links
+fn link_cmd_name(tcfg: &TranspilerConfig, lcmd: &LinkCmd) -> String {
+    lcmd.output
+        .as_ref()
+        .and_then(|output| output_crate_name(output))
+        .unwrap_or_else(|| tcfg.crate_name())
+}
+
+/// Crate name for the linker output `output`, e.g. `foo` for
+/// `build/libfoo.so.1.2`
+fn output_crate_name(output: &str) -> Option<String> {
+    let file_name = Path::new(output).file_name()?.to_str()?;
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let name = if stem.starts_with("lib") && stem.len() > 3 {
+        &stem[3..]
+    } else {
+        stem
+    };
+    if name.is_empty() {
+        return None;
+    }
+    let name = str_to_ident(name);
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Some(format!("_{}", name))
+    } else {
+        Some(name)
+    }
+}
+
+/// Write the translation report requested with `--report`, if any.
+fn write_report(tcfg: &TranspilerConfig, reports: &[UnitReport]) {
+    if let Some(ref path) = tcfg.report_file {
+        report::write_report(path, tcfg.report_format, reports)
+            .unwrap_or_else(|e| warn!("Could not write report to {}: {}", path.display(), e));
+    }
+}
+
+/// Translate each of `cmds` with `transpile_one`, using up to `jobs`
+/// threads. Results are returned in the same order as `cmds` regardless of
+/// the order in which the translation units finish.
+///
+/// If any translation panics (e.g. because of `--fail-on-error`), no further
+/// translation units are started and the panic is propagated once the ones
+/// already in progress have finished.
+fn transpile_all<C, R, F>(jobs: usize, cmds: &[C], transpile_one: F) -> Vec<R>
+where
+    C: Sync,
+    R: Send,
+    F: Fn(&C) -> R + Sync,
+{
+    let jobs = jobs.max(1).min(cmds.len());
+    if jobs <= 1 {
+        return cmds.iter().map(|cmd| transpile_one(cmd)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let worker = || {
+        let mut results = vec![];
+        while !failed.load(Ordering::SeqCst) {
+            let idx = next.fetch_add(1, Ordering::SeqCst);
+            let cmd = match cmds.get(idx) {
+                Some(cmd) => cmd,
+                None => break,
+            };
+            match panic::catch_unwind(AssertUnwindSafe(|| transpile_one(cmd))) {
+                Ok(res) => results.push((idx, res)),
+                Err(e) => {
+                    failed.store(true, Ordering::SeqCst);
+                    panic::resume_unwind(e);
+                }
+            }
+        }
+        results
+    };
+
+    let outcomes = crossbeam_utils::thread::scope(|s| {
+        let handles = (0..jobs)
+            .map(|_| {
+                s.builder()
+                    .stack_size(TRANSLATION_STACK_SIZE)
+                    .spawn(|_| worker())
+                    .expect("could not spawn translation thread")
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().map(|h| h.join()).collect::<Vec<_>>()
+    })
+    .expect("all translation threads were joined");
+
+    let mut results = vec![];
+    for outcome in outcomes {
+        match outcome {
+            Ok(res) => results.extend(res),
+            Err(e) => panic::resume_unwind(e),
+        }
+    }
+    results.sort_by_key(|&(idx, _)| idx);
+    results.into_iter().map(|(_, res)| res).collect()
+}
+
 /// Ensure that clang can locate the system headers on macOS 10.14+.
 ///
 /// MacOS 10.14 does not have a `/usr/include` folder even if Xcode
@@ -389,16 +650,18 @@ fn reorganize_definitions(
 
 fn transpile_single(
     tcfg: &TranspilerConfig,
-    input_path: PathBuf,
+    cmd: &CompileCmd,
     ancestor_path: &Path,
     build_dir: &Path,
     cc_db: &Path,
     extra_clang_args: &[&str],
+    ast_cache: Option<&AstCache>,
 ) -> TranspileResult {
+    let input_path = cmd.abs_file();
     let output_path = get_output_path(tcfg, &input_path, ancestor_path, build_dir);
     if output_path.exists() && !tcfg.overwrite_existing {
         println!("Skipping existing file {}", output_path.display());
-        return (output_path, None, None);
+        return (output_path, None, None, vec![]);
     }
 
     let file = input_path.file_name().unwrap().to_str().unwrap();
@@ -410,36 +673,138 @@ fn transpile_single(
         );
     }
 
+    let translations = if tcfg.configurations.is_empty() {
+        vec![translate_unit(tcfg, cmd, cc_db, extra_clang_args, ast_cache)]
+    } else {
+        tcfg.configurations
+            .iter()
+            .map(|config| {
+                if tcfg.verbose {
+                    println!("Translating {} in configuration {}", file, config.name);
+                }
+                let mut clang_args = extra_clang_args.to_vec();
+                clang_args.extend(config.clang_args.iter().map(String::as_str));
+                translate_unit(tcfg, cmd, cc_db, &clang_args, ast_cache)
+            })
+            .collect()
+    };
+
+    let mut translated_strings = vec![];
+    let mut pragmas = PragmaVec::new();
+    let mut crates = CrateSet::new();
+    let mut reports = vec![];
+    for (translated_string, unit_pragmas, unit_crates, report) in translations {
+        translated_strings.push(translated_string);
+        pragmas.extend(unit_pragmas);
+        crates.extend(unit_crates);
+        reports.push(report);
+    }
+    let translated_string = if tcfg.configurations.is_empty() {
+        translated_strings.pop().unwrap()
+    } else {
+        let names = tcfg.configurations
+            .iter()
+            .map(|config| config.name.as_str())
+            .collect::<Vec<_>>();
+        merge_translations(&names, &translated_strings)
+    };
+
+    let mut file = match File::create(&output_path) {
+        Ok(file) => file,
+        Err(e) => panic!("Unable to open file {} for writing: {}", output_path.display(), e),
+    };
+
+    match file.write_all(translated_string.as_bytes()) {
+        Ok(()) => (),
+        Err(e) => panic!("Unable to write translation to file {}: {}", output_path.display(), e),
+    };
+
+    (output_path, Some(pragmas), Some(crates), reports)
+}
+
+/// Parse and translate the C file of `cmd` with the given extra clang
+/// arguments, returning the translated source
+fn translate_unit(
+    tcfg: &TranspilerConfig,
+    cmd: &CompileCmd,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+    ast_cache: Option<&AstCache>,
+) -> (String, PragmaVec, CrateSet, UnitReport) {
+    let input_path = cmd.abs_file();
+    let file = input_path.file_name().unwrap().to_str().unwrap();
+
     if tcfg.verbose {
         println!("Additional Clang arguments: {}", extra_clang_args.join(" "));
     }
 
-    // Extract the untyped AST from the CBOR file
-    let untyped_context = match ast_exporter::get_untyped_ast(
-        input_path.as_path(),
-        cc_db,
-        extra_clang_args,
-        tcfg.debug_ast_exporter,
-    ) {
-        Err(e) => {
-            eprintln!("Error: {:}", e);
-            process::exit(1);
+    // Dumping the untyped context requires running clang, so bypass the cache
+    let ast_cache = ast_cache.filter(|_| !tcfg.dump_untyped_context);
+    let cache_key = ast_cache.and_then(|cache| {
+        cache
+            .key(cmd, extra_clang_args, tcfg.translate_fn_macros)
+            .map_err(|e| warn!("Could not compute AST cache key for {}: {}", file, e))
+            .ok()
+    });
+    let cached_context = ast_cache
+        .and_then(|cache| cache.load(cache_key.as_ref()?));
+
+    let typed_context = match cached_context {
+        Some(typed_context) => {
+            if tcfg.verbose {
+                println!("Using cached Clang AST for {}", file);
+            }
+            typed_context
         }
-        Ok(cxt) => cxt,
-    };
+        None => {
+            // Extract the untyped AST from the CBOR file. Translation
+            // threads each run clang in a process of its own.
+            let untyped_ast = if tcfg.jobs > 1 {
+                export_ast_in_subprocess(
+                    input_path.as_path(),
+                    cc_db,
+                    extra_clang_args,
+                    tcfg.debug_ast_exporter,
+                    tcfg.translate_fn_macros,
+                )
+            } else {
+                ast_exporter::get_untyped_ast(
+                    input_path.as_path(),
+                    cc_db,
+                    extra_clang_args,
+                    tcfg.debug_ast_exporter,
+                    tcfg.translate_fn_macros,
+                )
+            };
+            let untyped_context = match untyped_ast {
+                Err(e) => {
+                    eprintln!("Error: {:}", e);
+                    process::exit(1);
+                }
+                Ok(cxt) => cxt,
+            };
 
-    if tcfg.dump_untyped_context {
-        println!("CBOR Clang AST");
-        println!("{:#?}", untyped_context);
-    }
+            if tcfg.dump_untyped_context {
+                println!("CBOR Clang AST");
+                println!("{:#?}", untyped_context);
+            }
+
+            // Convert this into a typed AST
+            let conv = ConversionContext::new(&untyped_context);
+            if conv.invalid_clang_ast && tcfg.fail_on_error {
+                panic!("Clang AST was invalid");
+            }
 
-    // Convert this into a typed AST
-    let typed_context = {
-        let conv = ConversionContext::new(&untyped_context);
-        if conv.invalid_clang_ast && tcfg.fail_on_error {
-            panic!("Clang AST was invalid");
+            // Only cache ASTs that clang produced without errors
+            if let (Some(cache), Some(key)) = (ast_cache, &cache_key) {
+                if !conv.invalid_clang_ast {
+                    cache
+                        .store(key, &conv.typed_context)
+                        .unwrap_or_else(|e| warn!("Could not cache AST for {}: {}", file, e));
+                }
+            }
+            conv.typed_context
         }
-        conv.typed_context
     };
 
     if tcfg.dump_typed_context {
@@ -453,20 +818,7 @@ fn transpile_single(
     }
 
     // Perform the translation
-    let (translated_string, pragmas, crates) =
-        translator::translate(typed_context, &tcfg, input_path);
-
-    let mut file = match File::create(&output_path) {
-        Ok(file) => file,
-        Err(e) => panic!("Unable to open file {} for writing: {}", output_path.display(), e),
-    };
-
-    match file.write_all(translated_string.as_bytes()) {
-        Ok(()) => (),
-        Err(e) => panic!("Unable to write translation to file {}: {}", output_path.display(), e),
-    };
-
-    (output_path, Some(pragmas), Some(crates))
+    translator::translate(typed_context, &tcfg, input_path)
 }
 
 fn get_output_path(
@@ -515,3 +867,36 @@ fn get_output_path(
         path_buf
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_results_in_order() {
+        use std::thread;
+        use std::time::Duration;
+
+        let cmds = (0..16u64).collect::<Vec<_>>();
+        // Later units finish first, so the workers complete out of order
+        let transpile_one = |&n: &u64| {
+            thread::sleep(Duration::from_millis(16 - n));
+            format!("unit {}", n)
+        };
+        let serial = transpile_all(1, &cmds, transpile_one);
+        let parallel = transpile_all(4, &cmds, transpile_one);
+        assert_eq!(serial, parallel);
+        assert_eq!(parallel[3], "unit 3");
+    }
+
+    #[test]
+    fn crate_names_of_outputs() {
+        assert_eq!(output_crate_name("prog"), Some("prog".to_owned()));
+        assert_eq!(output_crate_name("build/libfoo.a"), Some("foo".to_owned()));
+        assert_eq!(output_crate_name("/usr/lib/libz.so.1.2.11"), Some("z".to_owned()));
+        assert_eq!(output_crate_name("libfoo-bar.dylib"), Some("foo_bar".to_owned()));
+        assert_eq!(output_crate_name("lib2d.so"), Some("_2d".to_owned()));
+        assert_eq!(output_crate_name("lib.a"), Some("lib".to_owned()));
+        assert_eq!(output_crate_name(".hidden"), None);
+    }
+}
@@ -0,0 +1,353 @@
+//! Machine-readable reports of the declarations the translator skipped or
+//! replaced, written out when `--report` is given.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use failure::{Error, Fail};
+use serde_json::{json, Value};
+
+use crate::c_ast::DisplaySrcSpan;
+use crate::diagnostics::{Diagnostic, TranslationError, TranslationErrorKind};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Sarif,
+}
+
+/// C source span of a reported construct
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportLocation {
+    pub file: Option<PathBuf>,
+    pub begin_line: u64,
+    pub begin_column: u64,
+    pub end_line: u64,
+    pub end_column: u64,
+}
+
+impl<'a> From<&'a DisplaySrcSpan> for ReportLocation {
+    fn from(loc: &'a DisplaySrcSpan) -> Self {
+        let span = loc.span();
+        ReportLocation {
+            file: loc.file().map(Path::to_owned),
+            begin_line: span.begin_line,
+            begin_column: span.begin_column,
+            end_line: span.end_line,
+            end_column: span.end_column,
+        }
+    }
+}
+
+/// A declaration that was skipped, or replaced by an extern declaration,
+/// because it could not be translated
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRecord {
+    /// Location of the construct that failed to translate, falling back to
+    /// the location of the declaration containing it
+    pub location: Option<ReportLocation>,
+    pub declaration: Option<String>,
+    /// Kind of the declaration, e.g. `Function` or `Struct`
+    pub decl_kind: &'static str,
+    /// Kind of the innermost C node that failed to translate, e.g.
+    /// `CExprKind::Call`, or of the declaration itself
+    pub node_kind: String,
+    /// Kind of translation error, e.g. `generic` or `invalid-clang-ast`
+    pub category: &'static str,
+    /// Warning category the error is reported under
+    pub diagnostic: Diagnostic,
+    pub message: String,
+    /// Whether `ReplaceMode::Extern` substituted an extern declaration
+    pub replaced_with_extern: bool,
+}
+
+impl ReportRecord {
+    pub fn new(
+        loc: Option<&DisplaySrcSpan>,
+        declaration: Option<String>,
+        decl_kind: &'static str,
+        error: &TranslationError,
+        replaced_with_extern: bool,
+    ) -> Self {
+        let kind = error.kind();
+        let diagnostic = match kind {
+            TranslationErrorKind::InvalidClangAst(_) => Diagnostic::ClangAst,
+            _ => Diagnostic::Translation,
+        };
+        let node_kind = error
+            .node_kind()
+            .unwrap_or_else(|| format!("CDeclKind::{}", decl_kind));
+
+        let mut message = error.cause().map_or_else(String::new, |cause| cause.to_string());
+        let kind_message = kind.to_string();
+        if !kind_message.is_empty() {
+            if !message.is_empty() {
+                message.push_str(": ");
+            }
+            message.push_str(&kind_message);
+        }
+
+        ReportRecord {
+            location: loc.map(ReportLocation::from),
+            declaration,
+            decl_kind,
+            node_kind,
+            category: kind.name(),
+            diagnostic,
+            message,
+            replaced_with_extern,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportTotals {
+    /// Declarations the translator attempted to translate
+    pub declarations: usize,
+    pub translated: usize,
+    pub skipped: usize,
+    pub replaced: usize,
+    /// Number of records per failing node kind
+    pub by_node_kind: BTreeMap<String, usize>,
+}
+
+impl ReportTotals {
+    fn add(&mut self, other: &ReportTotals) {
+        self.declarations += other.declarations;
+        self.translated += other.translated;
+        self.skipped += other.skipped;
+        self.replaced += other.replaced;
+        for (node_kind, count) in &other.by_node_kind {
+            *self.by_node_kind.entry(node_kind.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+/// Report for a single translation unit
+#[derive(Debug, Clone, Serialize)]
+pub struct UnitReport {
+    pub file: PathBuf,
+    pub totals: ReportTotals,
+    pub records: Vec<ReportRecord>,
+}
+
+impl UnitReport {
+    pub fn new(file: PathBuf) -> Self {
+        UnitReport {
+            file,
+            totals: ReportTotals::default(),
+            records: vec![],
+        }
+    }
+
+    /// Note that translation of another declaration was attempted.
+    pub fn add_declaration(&mut self) {
+        self.totals.declarations += 1;
+    }
+
+    pub fn add_record(&mut self, record: ReportRecord) {
+        if record.replaced_with_extern {
+            self.totals.replaced += 1;
+        } else {
+            self.totals.skipped += 1;
+        }
+        *self
+            .totals
+            .by_node_kind
+            .entry(record.node_kind.clone())
+            .or_insert(0) += 1;
+        self.records.push(record);
+    }
+
+    /// Compute the number of successfully translated declarations.
+    pub fn finish(mut self) -> Self {
+        self.totals.translated = self
+            .totals
+            .declarations
+            .saturating_sub(self.totals.skipped + self.totals.replaced);
+        self
+    }
+}
+
+/// Write the reports of all translated units to `path`.
+pub fn write_report(path: &Path, format: ReportFormat, units: &[UnitReport]) -> Result<(), Error> {
+    let report = match format {
+        ReportFormat::Json => json_report(units),
+        ReportFormat::Sarif => sarif_report(units),
+    };
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, &report)?;
+    Ok(())
+}
+
+fn json_report(units: &[UnitReport]) -> Value {
+    let mut totals = ReportTotals::default();
+    for unit in units {
+        totals.add(&unit.totals);
+    }
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "totals": totals,
+        "translation_units": units,
+    })
+}
+
+/// Build a SARIF 2.1.0 log with one result per record.
+fn sarif_report(units: &[UnitReport]) -> Value {
+    let results = units
+        .iter()
+        .flat_map(|unit| &unit.records)
+        .map(|record| {
+            let locations = record.location.as_ref().map_or_else(Vec::new, |loc| {
+                let uri = loc.file.as_ref().map(|file| file.to_string_lossy().into_owned());
+                vec![json!({
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": uri },
+                        "region": {
+                            "startLine": loc.begin_line,
+                            "startColumn": loc.begin_column,
+                            "endLine": loc.end_line,
+                            "endColumn": loc.end_column,
+                        },
+                    },
+                })]
+            });
+            json!({
+                "ruleId": format!("{}/{}", record.category, record.node_kind),
+                "level": if record.replaced_with_extern { "warning" } else { "error" },
+                "message": { "text": record.message },
+                "locations": locations,
+                "properties": {
+                    "declaration": record.declaration,
+                    "declKind": record.decl_kind,
+                    "nodeKind": record.node_kind,
+                    "diagnostic": record.diagnostic,
+                    "replacedWithExtern": record.replaced_with_extern,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let unit_totals = units
+        .iter()
+        .map(|unit| json!({ "file": unit.file, "totals": unit.totals }))
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "c2rust-transpile",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://c2rust.com/",
+                },
+            },
+            "results": results,
+            "properties": { "translationUnits": unit_totals },
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::c_ast::ClangAstParseErrorKind;
+
+    fn call_error() -> TranslationError {
+        TranslationError::generic("unsupported call")
+            .add_node("CExprKind", "Call")
+            .add_node("CStmtKind", "Return")
+    }
+
+    fn units() -> Vec<UnitReport> {
+        let mut skipped = ReportRecord::new(None, Some("f".into()), "Function", &call_error(), false);
+        skipped.location = Some(ReportLocation {
+            file: Some(PathBuf::from("main.c")),
+            begin_line: 3,
+            begin_column: 12,
+            end_line: 3,
+            end_column: 20,
+        });
+        let ast_error = TranslationErrorKind::InvalidClangAst(ClangAstParseErrorKind::MissingType).into();
+        let replaced = ReportRecord::new(None, Some("g".into()), "Function", &ast_error, true);
+
+        let mut main = UnitReport::new(PathBuf::from("main.c"));
+        for _ in 0..3 {
+            main.add_declaration();
+        }
+        main.add_record(skipped);
+        main.add_record(replaced);
+
+        let mut other = UnitReport::new(PathBuf::from("other.c"));
+        other.add_declaration();
+        other.add_record(ReportRecord::new(None, None, "Struct", &call_error(), false));
+
+        vec![main.finish(), other.finish()]
+    }
+
+    #[test]
+    fn records_innermost_node() {
+        let record = ReportRecord::new(None, None, "Function", &call_error(), false);
+        assert_eq!(record.node_kind, "CExprKind::Call");
+        assert_eq!(record.category, "generic");
+        assert_eq!(record.diagnostic, Diagnostic::Translation);
+        assert_eq!(record.message, "unsupported call");
+
+        let error = TranslationError::generic("unsupported decl");
+        let record = ReportRecord::new(None, None, "Function", &error, false);
+        assert_eq!(record.node_kind, "CDeclKind::Function");
+    }
+
+    #[test]
+    fn json_output() {
+        let report = json_report(&units());
+        let totals = &report["totals"];
+        assert_eq!(totals["declarations"], 4);
+        assert_eq!(totals["translated"], 1);
+        assert_eq!(totals["skipped"], 2);
+        assert_eq!(totals["replaced"], 1);
+        assert_eq!(totals["by_node_kind"]["CExprKind::Call"], 2);
+        assert_eq!(totals["by_node_kind"]["CDeclKind::Function"], 1);
+
+        let unit = &report["translation_units"][0];
+        assert_eq!(unit["file"], "main.c");
+        assert_eq!(unit["totals"]["translated"], 1);
+        let records = &unit["records"];
+        assert_eq!(records[0]["declaration"], "f");
+        assert_eq!(records[0]["node_kind"], "CExprKind::Call");
+        assert_eq!(records[0]["diagnostic"], "translation");
+        assert_eq!(records[0]["location"]["begin_column"], 12);
+        assert_eq!(records[1]["category"], "invalid-clang-ast");
+        assert_eq!(records[1]["diagnostic"], "clang-ast");
+        assert_eq!(records[1]["replaced_with_extern"], true);
+        assert!(records[1]["location"].is_null());
+    }
+
+    #[test]
+    fn sarif_output() {
+        let report = sarif_report(&units());
+        assert_eq!(report["version"], "2.1.0");
+        let run = &report["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "c2rust-transpile");
+        assert_eq!(run["properties"]["translationUnits"][1]["file"], "other.c");
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["ruleId"], "generic/CExprKind::Call");
+        assert_eq!(results[0]["level"], "error");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "main.c");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["endColumn"], 20);
+
+        assert_eq!(results[1]["ruleId"], "invalid-clang-ast/CDeclKind::Function");
+        assert_eq!(results[1]["level"], "warning");
+        assert_eq!(results[1]["properties"]["diagnostic"], "clang-ast");
+        assert_eq!(results[1]["locations"].as_array().unwrap().len(), 0);
+        assert_eq!(results[2]["properties"]["nodeKind"], "CExprKind::Call");
+    }
+}
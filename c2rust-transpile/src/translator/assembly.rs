@@ -1,22 +1,32 @@
 #![deny(missing_docs)]
 //! This module provides basic support for converting inline assembly statements.
+//!
+//! Two backends are available. The LLVM backend emits the old
+//! `asm!("..." : outputs : inputs : clobbers)` syntax behind the `asm`
+//! feature gate, passing the template through in LLVM syntax. The operands
+//! backend rewrites the GCC template and constraints into the `asm!` syntax
+//! with `in(reg)`/`out(reg)` operands and `options(...)`. That syntax is
+//! newer than the toolchain translated crates are normally built with, so
+//! the build files pin a newer nightly when it is selected.
 
 use super::*;
 
 impl<'c> Translation<'c> {
     /// Convert an inline-assembly statement into one or more Rust statements.
     /// If inline assembly translation is not enabled this will result in an
-    /// error message instead of a conversion. Because the inline assembly syntax
-    /// used in C is different than the one used in Rust (Rust uses the LLVM syntax
-    /// directly) the resulting translated assembly statements will be unlikely to work
-    /// without further manual translation. The translator will properly translate
-    /// the arguments to the assembly statement, however.
+    /// error message instead of a conversion. With `AsmStyle::Llvm`, because
+    /// the inline assembly syntax used in C is different than the one used in
+    /// Rust (Rust uses the LLVM syntax directly) the resulting translated
+    /// assembly statements will be unlikely to work without further manual
+    /// translation. The translator will properly translate the arguments to the
+    /// assembly statement, however.
     pub fn convert_asm(
         &self,
         ctx: ExprContext,
         span: Span,
         is_volatile: bool,
         asm: &str,
+        gcc_asm: &str,
         inputs: &[AsmOperand],
         outputs: &[AsmOperand],
         clobbers: &[String],
@@ -29,6 +39,27 @@ impl<'c> Translation<'c> {
 
         self.use_feature("asm");
 
+        if self.tcfg.asm_style == AsmStyle::Operands {
+            let triple = &self.ast_context.target_triple;
+            let arch = AsmArch::for_target(triple).ok_or_else(|| {
+                format_translation_err!(
+                    None,
+                    "asm! operand translation is only supported on x86-64 and AArch64, not `{}`",
+                    triple,
+                )
+            })?;
+            return self.convert_asm_operands(
+                ctx,
+                span,
+                arch,
+                is_volatile,
+                gcc_asm,
+                inputs,
+                outputs,
+                clobbers,
+            );
+        }
+
         fn push_expr(tokens: &mut Vec<TokenTree>, expr: P<Expr>) {
             tokens.push(TokenTree::token(token::Interpolated(Rc::new(Nonterminal::NtExpr(expr))), DUMMY_SP));
         }
@@ -194,3 +225,819 @@ impl<'c> Translation<'c> {
         Ok(stmts)
     }
 }
+
+/// Target architecture whose constraints, operand modifiers and registers an
+/// `asm!` invocation is written for
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AsmArch {
+    /// x86-64, using AT&T syntax like GCC does
+    X86_64,
+    /// AArch64
+    Aarch64,
+}
+
+impl AsmArch {
+    /// The architecture of the target triple clang parsed the C sources for.
+    pub fn for_target(triple: &str) -> Option<Self> {
+        let arch = triple.split('-').next().unwrap_or("");
+        if arch == "x86_64" {
+            Some(AsmArch::X86_64)
+        } else if arch == "aarch64" || arch == "arm64" {
+            Some(AsmArch::Aarch64)
+        } else {
+            None
+        }
+    }
+
+    /// Map a single GCC constraint letter onto the place an `asm!` operand
+    /// with that constraint can live in.
+    fn constraint_place(self, letter: char) -> Option<AsmPlace> {
+        use self::AsmPlace::*;
+        let place = match self {
+            AsmArch::X86_64 => match letter {
+                'r' | 'R' | 'l' => Class("reg"),
+                'q' | 'Q' => Class("reg_abcd"),
+                'x' | 'v' => Class("xmm_reg"),
+                'k' => Class("kreg"),
+                // `b` is missing on purpose: LLVM reserves `rbx`, so it
+                // cannot be an `asm!` operand
+                'a' => Reg("ax".into()),
+                'c' => Reg("cx".into()),
+                'd' => Reg("dx".into()),
+                'S' => Reg("si".into()),
+                'D' => Reg("di".into()),
+                'm' | 'o' | 'V' | '<' | '>' => Memory,
+                'i' | 'n' | 'e' | 'Z' | 'I' | 'J' | 'K' | 'L' | 'M' | 'N' => Immediate,
+                _ => return None,
+            },
+            AsmArch::Aarch64 => match letter {
+                'r' => Class("reg"),
+                'w' => Class("vreg"),
+                'x' => Class("vreg_low16"),
+                'm' | 'o' | 'Q' => Memory,
+                'i' | 'n' | 'I' | 'J' | 'K' | 'L' | 'M' | 'N' | 'Y' | 'Z' => Immediate,
+                _ => return None,
+            },
+        };
+        Some(place)
+    }
+
+    /// Map a GCC operand modifier onto the `asm!` template modifier for an
+    /// operand in the register class `class`.
+    fn class_modifier(self, class: &str, modifier: char) -> Option<char> {
+        match (self, class, modifier) {
+            (AsmArch::X86_64, "reg", _) | (AsmArch::X86_64, "reg_abcd", _) => match modifier {
+                'b' => Some('l'),
+                'h' => Some('h'),
+                'w' => Some('x'),
+                'k' => Some('e'),
+                'q' => Some('r'),
+                _ => None,
+            },
+            (AsmArch::X86_64, "xmm_reg", _) => match modifier {
+                'x' => Some('x'),
+                't' => Some('y'),
+                'g' => Some('z'),
+                _ => None,
+            },
+            (AsmArch::Aarch64, "reg", 'w') | (AsmArch::Aarch64, "reg", 'x') => Some(modifier),
+            (AsmArch::Aarch64, "vreg", _) | (AsmArch::Aarch64, "vreg_low16", _) => match modifier {
+                'b' | 'h' | 's' | 'd' | 'q' => Some(modifier),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Spell out the explicit register `reg` holding an operand of `bytes`
+    /// bytes, or sized by the GCC operand modifier `modifier`.
+    fn register_name(self, reg: &str, bytes: Option<u32>, modifier: Option<char>) -> Option<String> {
+        match self {
+            AsmArch::X86_64 => {
+                let bytes = match modifier {
+                    None => bytes.unwrap_or(4),
+                    Some('b') | Some('h') => 1,
+                    Some('w') => 2,
+                    Some('k') => 4,
+                    Some('q') => 8,
+                    Some(_) => return None,
+                };
+                let name = match (reg, bytes) {
+                    ("ax", 1) | ("cx", 1) | ("dx", 1) => {
+                        let high = modifier == Some('h');
+                        format!("{}{}", &reg[..1], if high { 'h' } else { 'l' })
+                    }
+                    ("si", 1) | ("di", 1) => format!("{}l", reg),
+                    ("ax", _) | ("cx", _) | ("dx", _) | ("si", _) | ("di", _) => match bytes {
+                        2 => reg.to_owned(),
+                        4 => format!("e{}", reg),
+                        _ => format!("r{}", reg),
+                    },
+                    _ => reg.to_owned(),
+                };
+                Some(format!("%{}", name))
+            }
+            AsmArch::Aarch64 => {
+                let number = reg
+                    .trim_start_matches(|c| c == 'x' || c == 'w')
+                    .parse::<u32>();
+                match number {
+                    Ok(number) => {
+                        let narrow = match modifier {
+                            None => bytes.map_or(false, |bytes| bytes <= 4),
+                            Some('w') => true,
+                            Some('x') => false,
+                            Some(_) => return None,
+                        };
+                        Some(format!("{}{}", if narrow { 'w' } else { 'x' }, number))
+                    }
+                    Err(_) if modifier.is_none() => Some(reg.to_owned()),
+                    Err(_) => None,
+                }
+            }
+        }
+    }
+
+    /// Whether LLVM reserves `reg`, so that it can be neither an operand nor
+    /// a clobber of `asm!`.
+    fn is_reserved(self, reg: &str) -> bool {
+        match self {
+            AsmArch::X86_64 => match reg {
+                "bl" | "bh" | "bx" | "ebx" | "rbx" | "bp" | "ebp" | "rbp" | "sp" | "esp"
+                | "rsp" | "ip" | "eip" | "rip" => true,
+                _ => false,
+            },
+            AsmArch::Aarch64 => match reg {
+                "x19" | "w19" | "x29" | "w29" | "fp" | "sp" | "wsp" | "xzr" | "wzr" => true,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Place of an `asm!` operand
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AsmPlace {
+    /// Any register of the named register class
+    Class(&'static str),
+    /// The named register
+    Reg(String),
+    /// Memory, passed to `asm!` as a pointer in a general purpose register
+    Memory,
+    /// A constant
+    Immediate,
+}
+
+/// Operand constraint as produced by the AST exporter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct AsmConstraint {
+    /// `+`: the operand is both read and written
+    read_write: bool,
+    /// `&`: the output is written before all inputs are consumed
+    early_clobber: bool,
+    /// `*`: the exporter decided to pass the operand by address
+    indirect: bool,
+    /// Index of the output operand this input is tied to
+    tied_to: Option<usize>,
+    /// Places the operand may live in, in constraint order
+    places: Vec<AsmPlace>,
+    /// Constraint letters that have no `asm!` equivalent
+    unsupported: String,
+}
+
+impl AsmConstraint {
+    /// Parse the first alternative of `constraints`.
+    fn parse(arch: AsmArch, constraints: &str) -> Self {
+        let mut constraint = AsmConstraint::default();
+        let mut chars = constraints.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '=' | '%' => {}
+                '+' => constraint.read_write = true,
+                '&' => constraint.early_clobber = true,
+                '*' => constraint.indirect = true,
+                // Only the first alternative is considered
+                '|' => break,
+                '{' => {
+                    let reg: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    constraint.places.push(AsmPlace::Reg(reg));
+                }
+                '0'..='9' => {
+                    let mut digits = c.to_string();
+                    while let Some(&d) = chars.peek() {
+                        if !d.is_ascii_digit() {
+                            break;
+                        }
+                        digits.push(d);
+                        chars.next();
+                    }
+                    constraint.tied_to = digits.parse().ok();
+                }
+                _ => match arch.constraint_place(c) {
+                    Some(place) => {
+                        if !constraint.places.contains(&place) {
+                            constraint.places.push(place);
+                        }
+                    }
+                    None => constraint.unsupported.push(c),
+                },
+            }
+        }
+        constraint
+    }
+
+    /// Choose the place of an operand, preferring registers over immediates
+    /// over memory. Operands with a non-scalar type always live in memory.
+    fn select_place(&self, is_scalar: bool, is_output: bool) -> Option<AsmPlace> {
+        if !is_scalar {
+            return if self.indirect || self.places.contains(&AsmPlace::Memory) {
+                Some(AsmPlace::Memory)
+            } else {
+                None
+            };
+        }
+
+        let rank = |place: &AsmPlace| match place {
+            AsmPlace::Reg(_) => 0,
+            AsmPlace::Class(_) => 1,
+            AsmPlace::Immediate => 2,
+            AsmPlace::Memory => 3,
+        };
+        let place = self
+            .places
+            .iter()
+            .filter(|place| !is_output || **place != AsmPlace::Immediate)
+            .min_by_key(|place| rank(*place))
+            .cloned();
+        match place {
+            None if self.indirect => Some(AsmPlace::Memory),
+            place => place,
+        }
+    }
+}
+
+/// How a GCC operand is referred to from the rewritten template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplateOperand {
+    /// Positional `asm!` operand
+    Positional { index: usize, place: AsmPlace },
+    /// Explicit register, which `asm!` templates cannot refer to, so its name
+    /// is spelled out instead
+    Register { name: String, bytes: Option<u32> },
+}
+
+/// Rewrite a GCC assembler template into an `asm!` template string,
+/// replacing `%0`, `%k0` and `%[name]` with `asm!` operand references and
+/// `%%` with `%`. On x86-64 only the first (AT&T) alternative of each
+/// `{att|intel}` dialect group is kept.
+fn rewrite_asm_template(
+    arch: AsmArch,
+    template: &str,
+    operands: &[TemplateOperand],
+    names: &HashMap<String, usize>,
+) -> Result<String, TranslationError> {
+    let mut out = String::with_capacity(template.len());
+    let mut dialect: Option<usize> = None;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let emit = dialect.map_or(true, |alternative| alternative == 0);
+        match c {
+            '{' if arch == AsmArch::X86_64 => dialect = Some(0),
+            '|' if dialect.is_some() => dialect = dialect.map(|alternative| alternative + 1),
+            '}' if arch == AsmArch::X86_64 => dialect = None,
+            '{' if emit => out.push_str("{{"),
+            '}' if emit => out.push_str("}}"),
+            '%' => {
+                let mut modifier = None;
+                let operand = match chars.next() {
+                    Some('%') => {
+                        if emit {
+                            out.push('%');
+                        }
+                        continue;
+                    }
+                    Some('{') => {
+                        if emit {
+                            out.push_str("{{");
+                        }
+                        continue;
+                    }
+                    Some('}') => {
+                        if emit {
+                            out.push_str("}}");
+                        }
+                        continue;
+                    }
+                    Some('|') => {
+                        if emit {
+                            out.push('|');
+                        }
+                        continue;
+                    }
+                    Some(c) if c.is_ascii_alphabetic() => {
+                        modifier = Some(c);
+                        chars.next()
+                    }
+                    c => c,
+                };
+
+                let index = match operand {
+                    Some('[') => {
+                        let name: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                        *names.get(&name).ok_or_else(|| {
+                            format_translation_err!(None, "Unknown asm operand name `{}`", name)
+                        })?
+                    }
+                    Some(c) if c.is_ascii_digit() => {
+                        let mut digits = c.to_string();
+                        while let Some(&d) = chars.peek() {
+                            if !d.is_ascii_digit() {
+                                break;
+                            }
+                            digits.push(d);
+                            chars.next();
+                        }
+                        digits.parse::<usize>().unwrap()
+                    }
+                    _ => {
+                        return Err(format_translation_err!(
+                            None,
+                            "Unsupported asm template directive `%{}{}`",
+                            modifier.map_or(String::new(), |m| m.to_string()),
+                            operand.map_or(String::new(), |c| c.to_string()),
+                        ))
+                    }
+                };
+
+                let operand = operands.get(index).ok_or_else(|| {
+                    format_translation_err!(None, "asm operand {} out of range", index)
+                })?;
+                let reference = template_reference(arch, operand, modifier).ok_or_else(|| {
+                    format_translation_err!(
+                        None,
+                        "Unsupported modifier `{}` for asm operand {}",
+                        modifier.unwrap_or(' '),
+                        index,
+                    )
+                })?;
+                if emit {
+                    out.push_str(&reference);
+                }
+            }
+            c if emit => out.push(c),
+            _ => {}
+        }
+    }
+
+    Ok(out)
+}
+
+/// Build the template text that refers to `operand`.
+fn template_reference(
+    arch: AsmArch,
+    operand: &TemplateOperand,
+    modifier: Option<char>,
+) -> Option<String> {
+    match *operand {
+        TemplateOperand::Register { ref name, bytes } => arch.register_name(name, bytes, modifier),
+        TemplateOperand::Positional { index, ref place } => match (place, modifier) {
+            (AsmPlace::Class(class), Some(modifier)) => {
+                let modifier = arch.class_modifier(class, modifier)?;
+                Some(format!("{{{}:{}}}", index, modifier))
+            }
+            (AsmPlace::Class(_), None) => Some(format!("{{{}}}", index)),
+            (AsmPlace::Memory, None) => Some(match arch {
+                AsmArch::X86_64 => format!("({{{}}})", index),
+                AsmArch::Aarch64 => format!("[{{{}}}]", index),
+            }),
+            // AT&T syntax prefixes immediates with `$`, unless `%c` asks for
+            // the bare constant
+            (AsmPlace::Immediate, None) if arch == AsmArch::X86_64 => Some(format!("${{{}}}", index)),
+            (AsmPlace::Immediate, None) | (AsmPlace::Immediate, Some('c')) => {
+                Some(format!("{{{}}}", index))
+            }
+            _ => None,
+        },
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Convert an inline-assembly statement into an `asm!` invocation with
+    /// `in(reg)`/`out(reg)` operands. Tied operands become `inout` operands,
+    /// memory operands are passed by address and the `memory` and `cc`
+    /// clobbers map onto `options(...)`.
+    fn convert_asm_operands(
+        &self,
+        ctx: ExprContext,
+        span: Span,
+        arch: AsmArch,
+        is_volatile: bool,
+        template: &str,
+        inputs: &[AsmOperand],
+        outputs: &[AsmOperand],
+        clobbers: &[String],
+    ) -> Result<Vec<Stmt>, TranslationError> {
+        let tok = |kind| TokenTree::token(kind, DUMMY_SP);
+        let ident = |name: &str| tok(token::Ident(name.into_symbol(), false));
+        let expr = |expr: P<Expr>| tok(token::Interpolated(Rc::new(Nonterminal::NtExpr(expr))));
+        let place_tokens = |place: &AsmPlace| {
+            let spec = match place {
+                AsmPlace::Class(class) => ident(*class),
+                AsmPlace::Reg(reg) => expr(mk().lit_expr(mk().str_lit(reg))),
+                _ => ident("reg"),
+            };
+            vec![
+                tok(token::OpenDelim(DelimToken::Paren)),
+                spec,
+                tok(token::CloseDelim(DelimToken::Paren)),
+            ]
+        };
+
+        let parse = |operand: &AsmOperand| {
+            let constraint = AsmConstraint::parse(arch, &operand.constraints);
+            if !constraint.unsupported.is_empty() {
+                return Err(format_translation_err!(
+                    None,
+                    "Unsupported asm constraint `{}` in `{}`",
+                    constraint.unsupported,
+                    operand.constraints,
+                ));
+            }
+            Ok(constraint)
+        };
+        let output_constraints = outputs.iter().map(&parse).collect::<Result<Vec<_>, _>>()?;
+        let input_constraints = inputs.iter().map(&parse).collect::<Result<Vec<_>, _>>()?;
+
+        let mut tied_inputs = HashMap::new();
+        for (input_idx, constraint) in input_constraints.iter().enumerate() {
+            if let Some(output_idx) = constraint.tied_to {
+                if output_idx >= outputs.len() {
+                    return Err(format_translation_err!(
+                        None,
+                        "asm input {} is tied to missing output {}",
+                        input_idx,
+                        output_idx,
+                    ));
+                }
+                tied_inputs.insert(output_idx, input_idx);
+            }
+        }
+
+        let mut stmts = vec![];
+        let mut positional: Vec<Vec<TokenTree>> = vec![];
+        let mut explicit: Vec<Vec<TokenTree>> = vec![];
+        let mut template_operands = vec![];
+        let mut names = HashMap::new();
+
+        let writes_memory_clobber = clobbers.iter().any(|clobber| clobber == "memory");
+        let mut writes_memory = writes_memory_clobber;
+        let mut reads_memory = writes_memory_clobber;
+
+        let select_place = |operand: &AsmOperand,
+                            constraint: &AsmConstraint,
+                            is_output|
+         -> Result<(AsmPlace, Option<u32>), TranslationError> {
+            let bytes = self.asm_operand_bytes(operand.expression);
+            let place = constraint
+                .select_place(bytes.is_some(), is_output)
+                .ok_or_else(|| {
+                    format_translation_err!(
+                        None,
+                        "No asm! operand place for constraint `{}`",
+                        operand.constraints,
+                    )
+                })?;
+            match place {
+                AsmPlace::Reg(ref reg) if arch.is_reserved(reg) => Err(format_translation_err!(
+                    None,
+                    "Register `{}` cannot be used as an asm! operand",
+                    reg,
+                )),
+                // x86-64 general purpose registers only hold bytes in `reg_byte`
+                AsmPlace::Class("reg") | AsmPlace::Class("reg_abcd")
+                    if arch == AsmArch::X86_64 && bytes == Some(1) =>
+                {
+                    Ok((AsmPlace::Class("reg_byte"), bytes))
+                }
+                place => Ok((place, bytes)),
+            }
+        };
+
+        // Add an operand, returning how the template refers to it
+        let mut push_operand = |place: &AsmPlace, bytes, tokens: Vec<TokenTree>| match place {
+            AsmPlace::Reg(name) => {
+                explicit.push(tokens);
+                TemplateOperand::Register {
+                    name: name.clone(),
+                    bytes,
+                }
+            }
+            place => {
+                positional.push(tokens);
+                TemplateOperand::Positional {
+                    index: positional.len() - 1,
+                    place: place.clone(),
+                }
+            }
+        };
+
+        for (output_idx, (output, constraint)) in outputs.iter().zip(&output_constraints).enumerate() {
+            let (place, bytes) = select_place(output, constraint, true)?;
+
+            let mut out_expr = self.convert_expr(ctx.used(), output.expression)?;
+            stmts.append(out_expr.stmts_mut());
+            let out_expr = out_expr.into_value();
+
+            let mut tokens = vec![];
+            if place == AsmPlace::Memory {
+                if tied_inputs.contains_key(&output_idx) {
+                    return Err(TranslationError::generic(
+                        "asm inputs cannot be tied to memory outputs",
+                    ));
+                }
+                reads_memory = true;
+                writes_memory = true;
+                let ptr = mk().cast_expr(
+                    mk().mutbl().addr_of_expr(out_expr),
+                    mk().mutbl().ptr_ty(mk().infer_ty()),
+                );
+                tokens.push(ident("in"));
+                tokens.extend(place_tokens(&place));
+                tokens.push(expr(ptr));
+            } else if let Some(&input_idx) = tied_inputs.get(&output_idx) {
+                let mut in_expr = self.convert_expr(ctx.used(), inputs[input_idx].expression)?;
+                stmts.append(in_expr.stmts_mut());
+                tokens.push(ident("inout"));
+                tokens.extend(place_tokens(&place));
+                tokens.push(expr(in_expr.into_value()));
+                tokens.push(tok(token::FatArrow));
+                tokens.push(expr(out_expr));
+            } else {
+                let dir = if constraint.read_write {
+                    "inout"
+                } else if constraint.early_clobber {
+                    "out"
+                } else {
+                    "lateout"
+                };
+                tokens.push(ident(dir));
+                tokens.extend(place_tokens(&place));
+                tokens.push(expr(out_expr));
+            }
+
+            if let Some(ref name) = output.name {
+                names.insert(name.clone(), template_operands.len());
+            }
+            template_operands.push(push_operand(&place, bytes, tokens));
+        }
+
+        for (input, constraint) in inputs.iter().zip(&input_constraints) {
+            if let Some(name) = input.name.as_ref() {
+                names.insert(name.clone(), template_operands.len());
+            }
+
+            // Tied inputs were passed along with their outputs
+            if let Some(output_idx) = constraint.tied_to {
+                let operand = template_operands[output_idx].clone();
+                template_operands.push(operand);
+                continue;
+            }
+
+            let (place, bytes) = select_place(input, constraint, false)?;
+
+            let mut in_expr = self.convert_expr(ctx.used(), input.expression)?;
+            stmts.append(in_expr.stmts_mut());
+            let in_expr = in_expr.into_value();
+
+            let mut tokens = vec![];
+            match place {
+                AsmPlace::Memory => {
+                    reads_memory = true;
+                    let ptr = mk().cast_expr(
+                        mk().addr_of_expr(in_expr),
+                        mk().ptr_ty(mk().infer_ty()),
+                    );
+                    tokens.push(ident("in"));
+                    tokens.extend(place_tokens(&place));
+                    tokens.push(expr(ptr));
+                }
+                AsmPlace::Immediate => {
+                    tokens.push(ident("const"));
+                    tokens.push(expr(in_expr));
+                }
+                _ => {
+                    tokens.push(ident("in"));
+                    tokens.extend(place_tokens(&place));
+                    tokens.push(expr(in_expr));
+                }
+            }
+            template_operands.push(push_operand(&place, bytes, tokens));
+        }
+
+        let mut clobbers_flags = false;
+        for clobber in clobbers {
+            match clobber.trim_start_matches('%') {
+                "memory" => {}
+                "cc" => clobbers_flags = true,
+                // Clobbers that clang adds implicitly and `asm!` assumes anyway
+                "dirflag" | "fpsr" | "flags" => {}
+                reg if arch.is_reserved(reg) => {
+                    return Err(format_translation_err!(
+                        None,
+                        "Register `{}` cannot be clobbered by asm!",
+                        reg,
+                    ))
+                }
+                reg => explicit.push(vec![
+                    ident("lateout"),
+                    tok(token::OpenDelim(DelimToken::Paren)),
+                    expr(mk().lit_expr(mk().str_lit(reg))),
+                    tok(token::CloseDelim(DelimToken::Paren)),
+                    ident("_"),
+                ]),
+            }
+        }
+
+        let template = rewrite_asm_template(arch, template, &template_operands, &names)?;
+
+        let mut options = vec![];
+        // Non-volatile GCC asm with outputs may be removed or merged when
+        // its outputs are unused, which is what `pure` allows
+        if !is_volatile && !outputs.is_empty() && !writes_memory {
+            options.push("pure");
+        }
+        if !reads_memory {
+            options.push("nomem");
+        } else if !writes_memory {
+            options.push("readonly");
+        }
+        // GCC always assumes x86 asm clobbers the flags
+        if arch == AsmArch::Aarch64 && !clobbers_flags {
+            options.push("preserves_flags");
+        }
+        if arch == AsmArch::X86_64 {
+            options.push("att_syntax");
+        }
+
+        let mut tokens = vec![expr(mk().lit_expr(mk().str_lit(&template)))];
+        for operand in positional.into_iter().chain(explicit) {
+            tokens.push(tok(token::Comma));
+            tokens.extend(operand);
+        }
+        if !options.is_empty() {
+            tokens.push(tok(token::Comma));
+            tokens.push(ident("options"));
+            tokens.push(tok(token::OpenDelim(DelimToken::Paren)));
+            for (i, option) in options.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(tok(token::Comma));
+                }
+                tokens.push(ident(*option));
+            }
+            tokens.push(tok(token::CloseDelim(DelimToken::Paren)));
+        }
+
+        let mac = mk().mac(
+            vec!["asm"],
+            tokens.into_iter().collect::<TokenStream>(),
+            MacDelimiter::Parenthesis,
+        );
+        let mac = mk().mac_expr(mac);
+        stmts.push(mk().span(span).semi_stmt(mac));
+
+        Ok(stmts)
+    }
+
+    /// Size in bytes of a scalar asm operand, or `None` if the operand is
+    /// not a scalar.
+    fn asm_operand_bytes(&self, expr: CExprId) -> Option<u32> {
+        let ty = self.ast_context[expr].kind.get_type()?;
+        let bytes = match self.ast_context.resolve_type(ty).kind {
+            CTypeKind::Bool | CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar => 1,
+            CTypeKind::Short | CTypeKind::UShort => 2,
+            CTypeKind::Int | CTypeKind::UInt | CTypeKind::Float | CTypeKind::Enum(_) => 4,
+            CTypeKind::Long
+            | CTypeKind::ULong
+            | CTypeKind::LongLong
+            | CTypeKind::ULongLong
+            | CTypeKind::Double
+            | CTypeKind::Pointer(_) => 8,
+            CTypeKind::Int128 | CTypeKind::UInt128 => 16,
+            _ => return None,
+        };
+        Some(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn places(arch: AsmArch, constraints: &str) -> Vec<AsmPlace> {
+        AsmConstraint::parse(arch, constraints).places
+    }
+
+    #[test]
+    fn x86_64_constraints() {
+        let arch = AsmArch::X86_64;
+        assert_eq!(places(arch, "=r"), vec![AsmPlace::Class("reg")]);
+        assert_eq!(places(arch, "q"), vec![AsmPlace::Class("reg_abcd")]);
+        assert_eq!(places(arch, "x"), vec![AsmPlace::Class("xmm_reg")]);
+        assert_eq!(places(arch, "{ax}"), vec![AsmPlace::Reg("ax".into())]);
+        assert_eq!(places(arch, "S"), vec![AsmPlace::Reg("si".into())]);
+        assert_eq!(
+            places(arch, "imr"),
+            vec![AsmPlace::Immediate, AsmPlace::Memory, AsmPlace::Class("reg")]
+        );
+        assert_eq!(places(arch, "r|m"), vec![AsmPlace::Class("reg")]);
+        assert_eq!(AsmConstraint::parse(arch, "b").unsupported, "b");
+        assert_eq!(AsmConstraint::parse(arch, "t").unsupported, "t");
+
+        let constraint = AsmConstraint::parse(arch, "+&r");
+        assert!(constraint.read_write && constraint.early_clobber);
+        assert_eq!(AsmConstraint::parse(arch, "12").tied_to, Some(12));
+
+        let memory = AsmConstraint::parse(arch, "=*rm");
+        assert_eq!(memory.select_place(true, true), Some(AsmPlace::Class("reg")));
+        assert_eq!(memory.select_place(false, true), Some(AsmPlace::Memory));
+        let imm = AsmConstraint::parse(arch, "in");
+        assert_eq!(imm.select_place(true, false), Some(AsmPlace::Immediate));
+        assert_eq!(imm.select_place(true, true), None);
+    }
+
+    #[test]
+    fn aarch64_constraints() {
+        let arch = AsmArch::Aarch64;
+        assert_eq!(places(arch, "=r"), vec![AsmPlace::Class("reg")]);
+        assert_eq!(places(arch, "w"), vec![AsmPlace::Class("vreg")]);
+        assert_eq!(places(arch, "x"), vec![AsmPlace::Class("vreg_low16")]);
+        assert_eq!(places(arch, "Q"), vec![AsmPlace::Memory]);
+        assert_eq!(places(arch, "I"), vec![AsmPlace::Immediate]);
+        assert_eq!(places(arch, "{x0}"), vec![AsmPlace::Reg("x0".into())]);
+        assert_eq!(AsmConstraint::parse(arch, "q").unsupported, "q");
+        assert_eq!(AsmConstraint::parse(arch, "a").unsupported, "a");
+    }
+
+    #[test]
+    fn modifiers() {
+        let x86 = AsmArch::X86_64;
+        assert_eq!(x86.class_modifier("reg", 'k'), Some('e'));
+        assert_eq!(x86.class_modifier("reg", 'b'), Some('l'));
+        assert_eq!(x86.class_modifier("reg", 'q'), Some('r'));
+        assert_eq!(x86.class_modifier("xmm_reg", 't'), Some('y'));
+        assert_eq!(x86.class_modifier("reg_byte", 'k'), None);
+        assert_eq!(x86.register_name("ax", Some(4), None), Some("%eax".into()));
+        assert_eq!(x86.register_name("ax", Some(4), Some('h')), Some("%ah".into()));
+        assert_eq!(x86.register_name("di", Some(8), None), Some("%rdi".into()));
+        assert_eq!(x86.register_name("si", Some(8), Some('w')), Some("%si".into()));
+
+        let aarch64 = AsmArch::Aarch64;
+        assert_eq!(aarch64.class_modifier("reg", 'w'), Some('w'));
+        assert_eq!(aarch64.class_modifier("vreg", 'q'), Some('q'));
+        assert_eq!(aarch64.class_modifier("reg", 'q'), None);
+        assert_eq!(aarch64.register_name("x3", Some(4), None), Some("w3".into()));
+        assert_eq!(aarch64.register_name("x3", Some(4), Some('x')), Some("x3".into()));
+    }
+
+    #[test]
+    fn templates() {
+        let operands = vec![
+            TemplateOperand::Positional {
+                index: 0,
+                place: AsmPlace::Class("reg"),
+            },
+            TemplateOperand::Positional {
+                index: 1,
+                place: AsmPlace::Memory,
+            },
+            TemplateOperand::Positional {
+                index: 2,
+                place: AsmPlace::Immediate,
+            },
+            TemplateOperand::Register {
+                name: "cx".into(),
+                bytes: Some(4),
+            },
+        ];
+        let mut names = HashMap::new();
+        names.insert("dst".to_owned(), 0);
+
+        let rewrite = |arch, template| rewrite_asm_template(arch, template, &operands, &names);
+        assert_eq!(
+            rewrite(AsmArch::X86_64, "movl %1, %k[dst]\n\taddl %2, %0").unwrap(),
+            "movl ({1}), {0:e}\n\taddl ${2}, {0}"
+        );
+        assert_eq!(
+            rewrite(AsmArch::X86_64, "shll %%cl, %3; {bswap|bswap} %0; .long %c2").unwrap(),
+            "shll %cl, %ecx; bswap {0}; .long {2}"
+        );
+        assert_eq!(
+            rewrite(AsmArch::Aarch64, "ldr %w0, %1\n\tld1 {v0.16b}, %1").unwrap(),
+            "ldr {0:w}, [{1}]\n\tld1 {{v0.16b}}, [{1}]"
+        );
+        assert!(rewrite(AsmArch::X86_64, "mov %4, %0").is_err());
+        assert!(rewrite(AsmArch::X86_64, "mov %[src], %0").is_err());
+        assert!(rewrite(AsmArch::X86_64, "1: jmp 1b%=").is_err());
+    }
+}
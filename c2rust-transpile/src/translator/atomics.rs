@@ -12,21 +12,172 @@ impl<'c> Translation<'c> {
     }
 
     fn convert_memordering(&self, expr: CExprId) -> Option<Ordering> {
-        let memorder = &self.ast_context[expr];
-        match memorder.kind {
-            CExprKind::Literal(_, CLiteral::Integer(i, _)) => {
-                match i {
-                    0 => Some(Ordering::Relaxed),
-                    1 => Some(Ordering::Acquire),
-                    2 => Some(Ordering::Acquire),
-                    3 => Some(Ordering::Release),
-                    4 => Some(Ordering::AcqRel),
-                    5 => Some(Ordering::SeqCst),
-                    _ => None,
+        let value = match *self.ast_context.resolve_expr_value(expr) {
+            CExprKind::Literal(_, CLiteral::Integer(i, _)) => i,
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::EnumConstant { value: ConstIntExpr::U(i), .. } => i,
+                CDeclKind::EnumConstant { value: ConstIntExpr::I(i), .. } if i >= 0 => i as u64,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        MEMORY_ORDERINGS.get(value as usize).cloned()
+    }
+
+    /// Build a call to the atomic intrinsic selected by the memory ordering arguments `orders`
+    /// (and the `weak` flag, for compare-exchange). `intrinsic` names the intrinsic for each
+    /// choice of orderings, or returns `None` if that choice is invalid.
+    ///
+    /// When an ordering is not a constant, the intrinsic is selected at runtime by a `match` on
+    /// the C ordering values. Like GCC, we treat invalid orderings as sequentially consistent.
+    fn convert_atomic_intrinsic<F>(
+        &self,
+        ctx: ExprContext,
+        name: &str,
+        orders: &[CExprId],
+        weak: Option<CExprId>,
+        args: Vec<P<Expr>>,
+        intrinsic: F,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError>
+    where
+        F: Fn(&[Ordering], bool) -> Option<String>,
+    {
+        self.use_feature("core_intrinsics");
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let call = |intrinsic_name: &str, args: Vec<P<Expr>>| {
+            mk().call_expr(
+                mk().path_expr(vec!["", std_or_core, "intrinsics", intrinsic_name]),
+                args,
+            )
+        };
+
+        let static_orders: Vec<Option<Ordering>> =
+            orders.iter().map(|&x| self.convert_memordering(x)).collect();
+        let static_weak = match weak {
+            Some(weak_id) => self.convert_constant_bool(weak_id),
+            None => Some(false),
+        };
+
+        if let (Some(static_orders), Some(weak)) = (
+            static_orders.iter().cloned().collect::<Option<Vec<_>>>(),
+            static_weak,
+        ) {
+            let intrinsic_name = intrinsic(&static_orders[..], weak).ok_or_else(|| {
+                format_translation_err!(
+                    self.ast_context.display_loc(&self.ast_context[*orders.last().unwrap()].loc),
+                    "Invalid memory ordering for {}",
+                    name,
+                )
+            })?;
+            return Ok(WithStmts::new_val(call(&intrinsic_name, args)));
+        }
+
+        // The arguments must be evaluated exactly once, before we dispatch on the ordering
+        let mut stmts = vec![];
+        let args: Vec<P<Expr>> = args
+            .into_iter()
+            .map(|arg| match arg.kind {
+                ExprKind::Path(..) => arg,
+                _ => {
+                    let arg_name = self.renamer.borrow_mut().fresh();
+                    stmts.push(mk().local_stmt(P(mk().local(
+                        mk().ident_pat(&arg_name),
+                        None as Option<P<Ty>>,
+                        Some(arg),
+                    ))));
+                    mk().ident_expr(arg_name)
                 }
+            })
+            .collect();
+
+        // Collect the values to dispatch on, and every combination of their patterns
+        let mut scrutinees = vec![];
+        let mut weak_choices = vec![(static_weak.unwrap_or(false), None)];
+        if let (Some(weak_id), None) = (weak, static_weak) {
+            let (weak_stmts, weak_val) =
+                self.convert_condition(ctx, true, weak_id)?.discard_unsafe();
+            stmts.extend(weak_stmts);
+            scrutinees.push(weak_val);
+            weak_choices = vec![
+                (false, Some(mk().lit_pat(mk().lit_expr(mk().bool_lit(false))))),
+                (true, Some(mk().lit_pat(mk().lit_expr(mk().bool_lit(true))))),
+            ];
+        }
+        let mut order_choices: Vec<(Vec<Ordering>, Vec<P<Pat>>)> = vec![(vec![], vec![])];
+        for (&order_id, static_order) in orders.iter().zip(static_orders) {
+            order_choices = match static_order {
+                Some(order) => order_choices
+                    .into_iter()
+                    .map(|(mut orders, pats)| {
+                        orders.push(order);
+                        (orders, pats)
+                    })
+                    .collect(),
+                None => {
+                    let (order_stmts, order_val) =
+                        self.convert_expr(ctx.used(), order_id)?.discard_unsafe();
+                    stmts.extend(order_stmts);
+                    scrutinees.push(order_val);
+                    order_choices
+                        .into_iter()
+                        .flat_map(|(orders, pats)| {
+                            MEMORY_ORDERINGS.iter().enumerate().map(move |(i, &order)| {
+                                let mut orders = orders.clone();
+                                let mut pats = pats.clone();
+                                orders.push(order);
+                                pats.push(mk().lit_pat(mk().lit_expr(
+                                    mk().int_lit(i as u128, LitIntType::Unsuffixed),
+                                )));
+                                (orders, pats)
+                            })
+                        })
+                        .collect()
+                }
+            };
+        }
+
+        let fallback_orders = vec![Ordering::SeqCst; orders.len()];
+        let fallback = intrinsic(&fallback_orders[..], false)
+            .expect("Sequentially consistent atomics are always valid");
+
+        // Group the patterns by the intrinsic they select
+        let mut arms: IndexMap<String, Vec<P<Pat>>> = IndexMap::new();
+        for (weak, weak_pat) in &weak_choices {
+            for (orders, order_pats) in &order_choices {
+                let intrinsic_name = match intrinsic(&orders[..], *weak) {
+                    Some(intrinsic_name) if intrinsic_name != fallback => intrinsic_name,
+                    _ => continue,
+                };
+                let mut pats: Vec<P<Pat>> = weak_pat.iter().cloned().collect();
+                pats.extend(order_pats.iter().cloned());
+                let pat = if pats.len() == 1 {
+                    pats.pop().unwrap()
+                } else {
+                    mk().tuple_pat(pats)
+                };
+                arms.entry(intrinsic_name).or_insert(vec![]).push(pat);
             }
-            _ => None,
         }
+
+        let scrutinee = if scrutinees.len() == 1 {
+            scrutinees.pop().unwrap()
+        } else {
+            mk().tuple_expr(scrutinees)
+        };
+        let mut arms: Vec<Arm> = arms
+            .into_iter()
+            .map(|(intrinsic_name, mut pats)| {
+                let pat = if pats.len() == 1 {
+                    pats.pop().unwrap()
+                } else {
+                    mk().or_pat(pats)
+                };
+                mk().arm(pat, None as Option<P<Expr>>, call(&intrinsic_name, args.clone()))
+            })
+            .collect();
+        arms.push(mk().arm(mk().wild_pat(), None as Option<P<Expr>>, call(&fallback, args)));
+
+        Ok(WithStmts::new(stmts, mk().match_expr(scrutinee, arms)))
     }
 
     pub fn convert_atomic(
@@ -40,121 +191,106 @@ impl<'c> Translation<'c> {
         val2_id: Option<CExprId>,
         weak_id: Option<CExprId>,
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
-        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
         let ptr = self.convert_expr(ctx.used(), ptr_id)?;
-        let order = self.convert_memordering(order_id);
         let val1 = val1_id.map(|x| self.convert_expr(ctx.used(), x)).transpose()?;
-        let order_fail = order_fail_id.and_then(|x| self.convert_memordering(x));
         let val2 = val2_id.map(|x| self.convert_expr(ctx.used(), x)).transpose()?;
-        let weak = weak_id.and_then(|x| self.convert_constant_bool(x));
 
         match name {
-            "__atomic_load" | "__atomic_load_n" => {
+            "__atomic_load" | "__atomic_load_n" | "__c11_atomic_load" => {
                 ptr.and_then(|ptr| {
-                    let intrinsic_name = match order {
-                        None => unimplemented!("Dynamic memory consistency arguments are not yet supported"),
-                        Some(Ordering::SeqCst) => Some("atomic_load"),
-                        Some(Ordering::AcqRel) => None,
-                        Some(Ordering::Acquire) => Some("atomic_load_acq"),
-                        Some(Ordering::Release) => None,
-                        Some(Ordering::Relaxed) => Some("atomic_load_relaxed"),
-                        Some(_) => unreachable!("Did we not handle a case above??"),
-                    }.ok_or_else(|| format_translation_err!(
-                        self.ast_context.display_loc(&self.ast_context[order_id].loc),
-                        "Invalid memory ordering for __atomic_load",
-                    ))?;
-
-                    self.use_feature("core_intrinsics");
-
-                    let atomic_load =
-                        mk().path_expr(vec!["", std_or_core, "intrinsics", intrinsic_name]);
-                    let call = mk().call_expr(atomic_load, vec![ptr]);
+                    let call = self.convert_atomic_intrinsic(
+                        ctx,
+                        name,
+                        &[order_id],
+                        None,
+                        vec![ptr],
+                        |orders, _| match orders[0] {
+                            Ordering::SeqCst => Some("atomic_load".to_owned()),
+                            Ordering::Acquire => Some("atomic_load_acq".to_owned()),
+                            Ordering::Relaxed => Some("atomic_load_relaxed".to_owned()),
+                            _ => None,
+                        },
+                    )?;
+
                     if name == "__atomic_load" {
                         let ret = val1.expect("__atomic_load should have a ret argument");
                         ret.and_then(|ret| {
-                            let assignment = mk().assign_expr(
-                                mk().unary_expr(ast::UnOp::Deref, ret),
-                                call,
-                            );
+                            call.and_then(|call| {
+                                let assignment = mk().assign_expr(
+                                    mk().unary_expr(ast::UnOp::Deref, ret),
+                                    call,
+                                );
+                                self.convert_side_effects_expr(
+                                    ctx,
+                                    WithStmts::new_val(assignment),
+                                    "Builtin is not supposed to be used",
+                                )
+                            })
+                        })
+                    } else {
+                        call.and_then(|call| {
                             self.convert_side_effects_expr(
                                 ctx,
-                                WithStmts::new_val(assignment),
+                                WithStmts::new_val(call),
                                 "Builtin is not supposed to be used",
                             )
                         })
-                    } else {
-                        self.convert_side_effects_expr(
-                            ctx,
-                            WithStmts::new_val(call),
-                            "Builtin is not supposed to be used",
-                        )
                     }
                 })
             }
 
-            "__atomic_store" | "__atomic_store_n" => {
+            "__atomic_store" | "__atomic_store_n" | "__c11_atomic_store" => {
                 let val = val1.expect("__atomic_store must have a val argument");
                 ptr.and_then(|ptr| {
                     val.and_then(|val| {
-                        let intrinsic_name = match order {
-                            None => unimplemented!("Dynamic memory consistency arguments are not yet supported"),
-                            Some(Ordering::SeqCst) => Some("atomic_store"),
-                            Some(Ordering::AcqRel) => None,
-                            Some(Ordering::Acquire) => None,
-                            Some(Ordering::Release) => Some("atomic_store_rel"),
-                            Some(Ordering::Relaxed) => Some("atomic_store_relaxed"),
-                            Some(_) => unreachable!("Did we not handle a case above??"),
-                        }.ok_or_else(|| format_translation_err!(
-                            self.ast_context.display_loc(&self.ast_context[order_id].loc),
-                            "Invalid memory ordering for __atomic_store",
-                        ))?;
-
-                        self.use_feature("core_intrinsics");
-
-                        let atomic_store =
-                            mk().path_expr(vec!["", std_or_core, "intrinsics", intrinsic_name]);
                         let val = if name == "__atomic_store" {
                             mk().unary_expr(ast::UnOp::Deref, val)
                         } else {
                             val
                         };
-                        let call = mk().call_expr(atomic_store, vec![ptr, val]);
-                        self.convert_side_effects_expr(
+                        let call = self.convert_atomic_intrinsic(
                             ctx,
-                            WithStmts::new_val(call),
-                            "Builtin is not supposed to be used",
-                        )
+                            name,
+                            &[order_id],
+                            None,
+                            vec![ptr, val],
+                            |orders, _| match orders[0] {
+                                Ordering::SeqCst => Some("atomic_store".to_owned()),
+                                Ordering::Release => Some("atomic_store_rel".to_owned()),
+                                Ordering::Relaxed => Some("atomic_store_relaxed".to_owned()),
+                                _ => None,
+                            },
+                        )?;
+                        call.and_then(|call| {
+                            self.convert_side_effects_expr(
+                                ctx,
+                                WithStmts::new_val(call),
+                                "Builtin is not supposed to be used",
+                            )
+                        })
                     })
                 })
             }
 
-            "__atomic_exchange" | "__atomic_exchange_n" => {
+            "__atomic_exchange" | "__atomic_exchange_n" | "__c11_atomic_exchange" => {
                 let val = val1.expect("__atomic_store must have a val argument");
                 ptr.and_then(|ptr| {
                     val.and_then(|val| {
-                        let intrinsic_name = match order {
-                            None => unimplemented!("Dynamic memory consistency arguments are not yet supported"),
-                            Some(Ordering::SeqCst) => Some("atomic_xchg"),
-                            Some(Ordering::AcqRel) => Some("atomic_xchg_acqrel"),
-                            Some(Ordering::Acquire) => Some("atomic_xchg_acq"),
-                            Some(Ordering::Release) => Some("atomic_xchg_rel"),
-                            Some(Ordering::Relaxed) => Some("atomic_xchg_relaxed"),
-                            Some(_) => unreachable!("Did we not handle a case above??"),
-                        }.ok_or_else(|| format_translation_err!(
-                            self.ast_context.display_loc(&self.ast_context[order_id].loc),
-                            "Invalid memory ordering for __atomic_exchange",
-                        ))?;
-
-                        self.use_feature("core_intrinsics");
-
-                        let fn_path =
-                            mk().path_expr(vec!["", std_or_core, "intrinsics", intrinsic_name]);
                         let val = if name == "__atomic_exchange" {
                             mk().unary_expr(ast::UnOp::Deref, val)
                         } else {
                             val
                         };
-                        let call = mk().call_expr(fn_path, vec![ptr, val]);
+                        let call = self.convert_atomic_intrinsic(
+                            ctx,
+                            name,
+                            &[order_id],
+                            None,
+                            vec![ptr, val],
+                            |orders, _| {
+                                Some(format!("atomic_xchg{}", memordering_suffix(orders[0])))
+                            },
+                        )?;
                         if name == "__atomic_exchange" {
                             // LLVM stores the ret pointer in the order_fail slot
                             order_fail_id
@@ -162,132 +298,80 @@ impl<'c> Translation<'c> {
                                 .transpose()?
                                 .expect("__atomic_exchange must have a ret pointer argument")
                                 .and_then(|ret| {
-                                    let assignment = mk().assign_expr(
-                                        mk().unary_expr(ast::UnOp::Deref, ret),
-                                        call,
-                                    );
-                                    self.convert_side_effects_expr(
-                                        ctx,
-                                        WithStmts::new_val(assignment),
-                                        "Builtin is not supposed to be used",
-                                    )
+                                    call.and_then(|call| {
+                                        let assignment = mk().assign_expr(
+                                            mk().unary_expr(ast::UnOp::Deref, ret),
+                                            call,
+                                        );
+                                        self.convert_side_effects_expr(
+                                            ctx,
+                                            WithStmts::new_val(assignment),
+                                            "Builtin is not supposed to be used",
+                                        )
+                                    })
                                 })
                         } else {
-                            self.convert_side_effects_expr(
-                                ctx,
-                                WithStmts::new_val(call),
-                                "Builtin is not supposed to be used",
-                            )
+                            call.and_then(|call| {
+                                self.convert_side_effects_expr(
+                                    ctx,
+                                    WithStmts::new_val(call),
+                                    "Builtin is not supposed to be used",
+                                )
+                            })
                         }
                     })
                 })
             }
 
-            "__atomic_compare_exchange" | "__atomic_compare_exchange_n" => {
+            "__atomic_compare_exchange"
+            | "__atomic_compare_exchange_n"
+            | "__c11_atomic_compare_exchange_strong"
+            | "__c11_atomic_compare_exchange_weak" => {
                 let expected = val1.expect("__atomic_compare_exchange must have a expected argument");
                 let desired = val2.expect("__atomic_compare_exchange must have a desired argument");
+                let order_fail_id =
+                    order_fail_id.expect("__atomic_compare_exchange must have a failure ordering");
+                let always_weak = name == "__c11_atomic_compare_exchange_weak";
                 ptr.and_then(|ptr| {
                     expected.and_then(|expected| {
                         desired.and_then(|desired| {
-                            let intrinsic_name = match (weak, order, order_fail) {
-                                (None, _, _) | (_, None, _) | (_, _, None) => {
-                                    // We have to select which intrinsic to use at runtime
-                                    unimplemented!("Dynamic memory consistency arguments are not yet supported");
-                                }
-                                (_, _, Some(Ordering::Release)) | (_, _, Some(Ordering::AcqRel)) =>
-                                    None,
-
-                                (Some(false), Some(Ordering::SeqCst), Some(Ordering::SeqCst)) =>
-                                    Some("atomic_cxchg"),
-                                (Some(false), Some(Ordering::SeqCst), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchg_failacq"),
-                                (Some(false), Some(Ordering::SeqCst), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_failrelaxed"),
-                                (Some(false), Some(Ordering::SeqCst), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::AcqRel), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchg_acqrel"),
-                                (Some(false), Some(Ordering::AcqRel), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_acqrel_failrelaxed"),
-                                (Some(false), Some(Ordering::AcqRel), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::Release), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_rel"),
-                                (Some(false), Some(Ordering::Release), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::Acquire), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchg_acq"),
-                                (Some(false), Some(Ordering::Acquire), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_acq_failrelaxed"),
-                                (Some(false), Some(Ordering::Acquire), Some(_)) =>
-                                    None,
-                                (Some(false), Some(Ordering::Relaxed), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchg_relaxed"),
-                                (Some(false), Some(Ordering::Relaxed), Some(_)) =>
-                                    None,
-
-                                (Some(true), Some(Ordering::SeqCst), Some(Ordering::SeqCst)) =>
-                                    Some("atomic_cxchgweak"),
-                                (Some(true), Some(Ordering::SeqCst), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchgweak_failacq"),
-                                (Some(true), Some(Ordering::SeqCst), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_failrelaxed"),
-                                (Some(true), Some(Ordering::SeqCst), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::AcqRel), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchgweak_acqrel"),
-                                (Some(true), Some(Ordering::AcqRel), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_acqrel_failrelaxed"),
-                                (Some(true), Some(Ordering::AcqRel), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::Release), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_rel"),
-                                (Some(true), Some(Ordering::Release), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::Acquire), Some(Ordering::Acquire)) =>
-                                    Some("atomic_cxchgweak_acq"),
-                                (Some(true), Some(Ordering::Acquire), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_acq_failrelaxed"),
-                                (Some(true), Some(Ordering::Acquire), Some(_)) =>
-                                    None,
-                                (Some(true), Some(Ordering::Relaxed), Some(Ordering::Relaxed)) =>
-                                    Some("atomic_cxchgweak_relaxed"),
-                                (Some(true), Some(Ordering::Relaxed), Some(_)) =>
-                                    None,
-
-                                (Some(_), Some(_), Some(_)) => unreachable!("Did we not handle a case above??"),
-                            }.ok_or_else(|| format_translation_err!(
-                                self.ast_context.display_loc(&self.ast_context[order_fail_id.unwrap()].loc),
-                                "Invalid failure memory ordering",
-                            ))?;
-
-                            self.use_feature("core_intrinsics");
                             let expected = mk().unary_expr(ast::UnOp::Deref, expected);
-                            let desired = if name == "__atomic_compare_exchange_n" {
-                                desired
-                            } else {
+                            let desired = if name == "__atomic_compare_exchange" {
                                 mk().unary_expr(ast::UnOp::Deref, desired)
+                            } else {
+                                desired
                             };
 
-                            let atomic_cxchg =
-                                mk().path_expr(vec!["", std_or_core, "intrinsics", intrinsic_name]);
-                            let call = mk().call_expr(atomic_cxchg, vec![ptr, expected.clone(), desired]);
-                            let res_name = self.renamer.borrow_mut().fresh();
-                            let res_let = mk().local_stmt(P(mk().local(
-                                mk().ident_pat(&res_name),
-                                None as Option<P<Ty>>,
-                                Some(call),
-                            )));
-                            let assignment = mk().semi_stmt(mk().assign_expr(
-                                expected,
-                                mk().field_expr(mk().ident_expr(&res_name), "0"),
-                            ));
-                            let return_value = mk().field_expr(mk().ident_expr(&res_name), "1");
-                            self.convert_side_effects_expr(
+                            let call = self.convert_atomic_intrinsic(
                                 ctx,
-                                WithStmts::new(vec![res_let, assignment], return_value),
-                                "Builtin is not supposed to be used",
-                            )
+                                name,
+                                &[order_id, order_fail_id],
+                                weak_id,
+                                vec![ptr, expected.clone(), desired],
+                                |orders, weak| {
+                                    cxchg_intrinsic(weak || always_weak, orders[0], orders[1])
+                                        .map(String::from)
+                                },
+                            )?;
+
+                            call.and_then(|call| {
+                                let res_name = self.renamer.borrow_mut().fresh();
+                                let res_let = mk().local_stmt(P(mk().local(
+                                    mk().ident_pat(&res_name),
+                                    None as Option<P<Ty>>,
+                                    Some(call),
+                                )));
+                                let assignment = mk().semi_stmt(mk().assign_expr(
+                                    expected,
+                                    mk().field_expr(mk().ident_expr(&res_name), "0"),
+                                ));
+                                let return_value = mk().field_expr(mk().ident_expr(&res_name), "1");
+                                self.convert_side_effects_expr(
+                                    ctx,
+                                    WithStmts::new(vec![res_let, assignment], return_value),
+                                    "Builtin is not supposed to be used",
+                                )
+                            })
                         })
                     })
                 })
@@ -304,7 +388,12 @@ impl<'c> Translation<'c> {
             | "__atomic_fetch_and"
             | "__atomic_fetch_xor"
             | "__atomic_fetch_or"
-            | "__atomic_fetch_nand" => {
+            | "__atomic_fetch_nand"
+            | "__c11_atomic_fetch_add"
+            | "__c11_atomic_fetch_sub"
+            | "__c11_atomic_fetch_and"
+            | "__c11_atomic_fetch_xor"
+            | "__c11_atomic_fetch_or" => {
                 let intrinsic_name = if name.contains("_add") {
                     "atomic_xadd"
                 } else if name.contains("_sub") {
@@ -319,27 +408,15 @@ impl<'c> Translation<'c> {
                     "atomic_and"
                 };
 
-                let intrinsic_name = match order {
-                    None => {
-                        unimplemented!("Dynamic memory consistency arguments are not yet supported");
-                    }
-
-                    Some(Ordering::SeqCst) => format!("{}", intrinsic_name),
-                    Some(Ordering::AcqRel) => format!("{}_acqrel", intrinsic_name),
-                    Some(Ordering::Acquire) => format!("{}_acq", intrinsic_name),
-                    Some(Ordering::Release) => format!("{}_rel", intrinsic_name),
-                    Some(Ordering::Relaxed) => format!("{}_relaxed", intrinsic_name),
-
-                    _ => unreachable!("Unknown memory ordering"),
-                };
-
-                let fetch_first = name.starts_with("__atomic_fetch");
+                let fetch_first =
+                    name.starts_with("__atomic_fetch") || name.starts_with("__c11_atomic_fetch");
                 let val = val1.expect("__atomic arithmetic operations must have a val argument");
                 ptr.and_then(|ptr| {
                     val.and_then(|val| {
                         self.convert_atomic_op(
                             ctx,
-                            &intrinsic_name,
+                            intrinsic_name,
+                            &[order_id],
                             ptr,
                             val,
                             fetch_first,
@@ -377,27 +454,39 @@ impl<'c> Translation<'c> {
         )
     }
 
+    /// Translate an atomic read-modify-write operation. `func_name` is the sequentially
+    /// consistent intrinsic, and `orders` is the memory ordering argument, if the builtin has one.
     pub(crate) fn convert_atomic_op(
         &self,
         ctx: ExprContext,
         func_name: &str,
+        orders: &[CExprId],
         dst: P<Expr>,
         src: P<Expr>,
         fetch_first: bool,
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
-        self.use_feature("core_intrinsics");
-        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let intrinsic = |orders: &[Ordering], _| {
+            let order = orders.get(0).cloned().unwrap_or(Ordering::SeqCst);
+            Some(format!("{}{}", func_name, memordering_suffix(order)))
+        };
 
         // Emit `atomic_func(a0, a1) (op a1)?`
-        let atomic_func = mk().path_expr(vec!["", std_or_core, "intrinsics", func_name]);
-
         if fetch_first {
-            let call_expr = mk().call_expr(atomic_func, vec![dst, src]);
-            self.convert_side_effects_expr(
+            let call = self.convert_atomic_intrinsic(
                 ctx,
-                WithStmts::new_val(call_expr),
-                "Builtin is not supposed to be used",
-            )
+                func_name,
+                orders,
+                None,
+                vec![dst, src],
+                intrinsic,
+            )?;
+            call.and_then(|call| {
+                self.convert_side_effects_expr(
+                    ctx,
+                    WithStmts::new_val(call),
+                    "Builtin is not supposed to be used",
+                )
+            })
         } else {
             let (binary_op, is_nand) = if func_name.starts_with("atomic_xadd") {
                 (BinOpKind::Add, false)
@@ -432,10 +521,17 @@ impl<'c> Translation<'c> {
                 Some(src),
             )));
 
-            let call = mk().call_expr(
-                atomic_func,
+            let call = self.convert_atomic_intrinsic(
+                ctx,
+                func_name,
+                orders,
+                None,
                 vec![mk().ident_expr(&arg0_name), mk().ident_expr(&arg1_name)],
-            );
+                intrinsic,
+            )?;
+            let (call_stmts, call) = call.discard_unsafe();
+            let mut stmts = vec![arg0_let, arg1_let];
+            stmts.extend(call_stmts);
             let val = mk().binary_expr(binary_op, call, mk().ident_expr(arg1_name));
             let val = if is_nand {
                 // For nand, return `!(atomic_nand(arg0, arg1) & arg1)`
@@ -445,13 +541,158 @@ impl<'c> Translation<'c> {
             };
             self.convert_side_effects_expr(
                 ctx,
-                WithStmts::new(
-                    vec![arg0_let, arg1_let],
-                    val,
-                ),
+                WithStmts::new(stmts, val),
                 "Builtin is not supposed to be used",
             )
         }
     }
 
+    /// Translate an assignment to an `_Atomic` lvalue. Plain assignments become atomic stores and
+    /// `+=`, `-=`, `&=`, `|=` and `^=` on integers become atomic read-modify-writes. Other
+    /// compound assignments have no matching intrinsic, so we report an error rather than
+    /// silently translating them into a separate load and store.
+    ///
+    /// `fetch_first` selects the value of the expression: the value before the operation (for
+    /// post-increment and post-decrement) or after it.
+    pub(crate) fn convert_atomic_assignment(
+        &self,
+        ctx: ExprContext,
+        op: c_ast::BinOp,
+        lhs: CExprId,
+        lhs_type: CQualTypeId,
+        compute_type: CQualTypeId,
+        rhs_type: CQualTypeId,
+        rhs: WithStmts<P<Expr>>,
+        fetch_first: bool,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let lhs_loc = self.ast_context.display_loc(&self.ast_context[lhs].loc);
+        let intrinsic_name = match op {
+            c_ast::BinOp::Assign => None,
+            c_ast::BinOp::AssignAdd => Some("atomic_xadd"),
+            c_ast::BinOp::AssignSubtract => Some("atomic_xsub"),
+            c_ast::BinOp::AssignBitAnd => Some("atomic_and"),
+            c_ast::BinOp::AssignBitOr => Some("atomic_or"),
+            c_ast::BinOp::AssignBitXor => Some("atomic_xor"),
+            _ => {
+                return Err(format_translation_err!(
+                    lhs_loc,
+                    "Unsupported compound assignment {:?} to an _Atomic lvalue",
+                    op,
+                ))
+            }
+        };
+
+        let lhs_ctype = self.ast_context.resolve_type_id(lhs_type.ctype);
+        let lhs_kind = &self.ast_context[lhs_ctype].kind;
+        if intrinsic_name.is_some()
+            && (!lhs_kind.is_integral_type()
+                || lhs_kind.is_bool()
+                || self.ast_context.resolve_type_id(compute_type.ctype) != lhs_ctype)
+        {
+            return Err(format_translation_err!(
+                lhs_loc,
+                "Unsupported compound assignment {:?} to an _Atomic lvalue of this type",
+                op,
+            ));
+        }
+
+        let ty = self.convert_type(lhs_type.ctype)?;
+        let write = self.name_reference_write(ctx, lhs)?;
+        write.and_then(|write| {
+            rhs.and_then(|rhs| {
+                let rhs = if self.ast_context.resolve_type_id(rhs_type.ctype) == lhs_ctype {
+                    rhs
+                } else {
+                    mk().cast_expr(rhs, ty.clone())
+                };
+
+                let intrinsic_name = match intrinsic_name {
+                    Some(intrinsic_name) => intrinsic_name,
+                    None if ctx.is_unused() => {
+                        return Ok(WithStmts::new_val(self.volatile_write(&write, lhs_type, rhs)?));
+                    }
+                    None => {
+                        // The value of an assignment is the value stored
+                        let val_name = self.renamer.borrow_mut().fresh();
+                        let val_let = mk().local_stmt(P(mk().local(
+                            mk().ident_pat(&val_name),
+                            None as Option<P<Ty>>,
+                            Some(rhs),
+                        )));
+                        let store =
+                            self.volatile_write(&write, lhs_type, mk().ident_expr(&val_name))?;
+                        return Ok(WithStmts::new(
+                            vec![val_let, mk().semi_stmt(store)],
+                            mk().ident_expr(val_name),
+                        ));
+                    }
+                };
+
+                let ptr = match write.kind {
+                    ExprKind::Unary(ast::UnOp::Deref, ref e) => e.clone(),
+                    _ => mk().cast_expr(mk().mutbl().addr_of_expr(write), mk().mutbl().ptr_ty(ty)),
+                };
+                self.convert_atomic_op(
+                    ctx,
+                    intrinsic_name,
+                    &[],
+                    ptr,
+                    rhs,
+                    fetch_first || ctx.is_unused(),
+                )
+            })
+        })
+    }
+}
+
+/// Memory orderings indexed by their C values (`__ATOMIC_RELAXED` through `__ATOMIC_SEQ_CST`).
+/// Rust has no consume ordering, so we strengthen it to acquire.
+const MEMORY_ORDERINGS: [Ordering; 6] = [
+    Ordering::Relaxed,
+    Ordering::Acquire,
+    Ordering::Acquire,
+    Ordering::Release,
+    Ordering::AcqRel,
+    Ordering::SeqCst,
+];
+
+/// The suffix of the intrinsics for read-modify-write operations with this ordering
+fn memordering_suffix(order: Ordering) -> &'static str {
+    match order {
+        Ordering::SeqCst => "",
+        Ordering::AcqRel => "_acqrel",
+        Ordering::Acquire => "_acq",
+        Ordering::Release => "_rel",
+        Ordering::Relaxed => "_relaxed",
+        _ => unreachable!("Unknown memory ordering"),
+    }
+}
+
+/// The compare-exchange intrinsic for the given strength and success and failure orderings
+fn cxchg_intrinsic(weak: bool, order: Ordering, order_fail: Ordering) -> Option<&'static str> {
+    match (weak, order, order_fail) {
+        (_, _, Ordering::Release) | (_, _, Ordering::AcqRel) => None,
+
+        (false, Ordering::SeqCst, Ordering::SeqCst) => Some("atomic_cxchg"),
+        (false, Ordering::SeqCst, Ordering::Acquire) => Some("atomic_cxchg_failacq"),
+        (false, Ordering::SeqCst, Ordering::Relaxed) => Some("atomic_cxchg_failrelaxed"),
+        (false, Ordering::AcqRel, Ordering::Acquire) => Some("atomic_cxchg_acqrel"),
+        (false, Ordering::AcqRel, Ordering::Relaxed) => Some("atomic_cxchg_acqrel_failrelaxed"),
+        (false, Ordering::Release, Ordering::Relaxed) => Some("atomic_cxchg_rel"),
+        (false, Ordering::Acquire, Ordering::Acquire) => Some("atomic_cxchg_acq"),
+        (false, Ordering::Acquire, Ordering::Relaxed) => Some("atomic_cxchg_acq_failrelaxed"),
+        (false, Ordering::Relaxed, Ordering::Relaxed) => Some("atomic_cxchg_relaxed"),
+
+        (true, Ordering::SeqCst, Ordering::SeqCst) => Some("atomic_cxchgweak"),
+        (true, Ordering::SeqCst, Ordering::Acquire) => Some("atomic_cxchgweak_failacq"),
+        (true, Ordering::SeqCst, Ordering::Relaxed) => Some("atomic_cxchgweak_failrelaxed"),
+        (true, Ordering::AcqRel, Ordering::Acquire) => Some("atomic_cxchgweak_acqrel"),
+        (true, Ordering::AcqRel, Ordering::Relaxed) => Some("atomic_cxchgweak_acqrel_failrelaxed"),
+        (true, Ordering::Release, Ordering::Relaxed) => Some("atomic_cxchgweak_rel"),
+        (true, Ordering::Acquire, Ordering::Acquire) => Some("atomic_cxchgweak_acq"),
+        (true, Ordering::Acquire, Ordering::Relaxed) => Some("atomic_cxchgweak_acq_failrelaxed"),
+        (true, Ordering::Relaxed, Ordering::Relaxed) => Some("atomic_cxchgweak_relaxed"),
+
+        _ => None,
+    }
 }
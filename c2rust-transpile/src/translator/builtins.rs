@@ -39,7 +39,10 @@ impl<'c> Translation<'c> {
                 "f32",
                 "INFINITY",
             ]))),
-            "__builtin_huge_val" | "__builtin_huge_vall" => {
+            "__builtin_huge_vall" | "__builtin_infl" => {
+                Ok(WithStmts::new_val(self.long_double_const("INFINITY")))
+            }
+            "__builtin_huge_val" => {
                 Ok(WithStmts::new_val(mk().path_expr(vec![
                     "",
                     std_or_core,
@@ -53,7 +56,7 @@ impl<'c> Translation<'c> {
                 "f32",
                 "INFINITY",
             ]))),
-            "__builtin_inf" | "__builtin_infl" => Ok(WithStmts::new_val(mk().path_expr(vec![
+            "__builtin_inf" => Ok(WithStmts::new_val(mk().path_expr(vec![
                 "",
                 std_or_core,
                 "f64",
@@ -71,18 +74,10 @@ impl<'c> Translation<'c> {
                 "f64",
                 "NAN",
             ]))),
-            "__builtin_nanl" => {
-                self.extern_crates.borrow_mut().insert("f128");
-
-                Ok(WithStmts::new_val(mk().path_expr(vec![
-                    "f128",
-                    "f128",
-                    "NAN",
-                ])))
-            },
+            "__builtin_nanl" => Ok(WithStmts::new_val(self.long_double_const("NAN"))),
             "__builtin_signbit" | "__builtin_signbitf" | "__builtin_signbitl" => {
-                // Long doubles require the Float trait from num_traits to call this method
-                if builtin_name == "__builtin_signbitl" {
+                // f128 long doubles require the Float trait from num_traits to call this method
+                if builtin_name == "__builtin_signbitl" && self.long_double() == LongDouble::F128 {
                     self.items.borrow_mut()[&self.main_file].add_use(vec!["num_traits".into()], "Float");
                 }
 
@@ -133,6 +128,18 @@ impl<'c> Translation<'c> {
                 let val = self.convert_expr(ctx.used(), args[0])?;
                 Ok(val.map(|x| mk().method_call_expr(x, "abs", vec![] as Vec<P<Expr>>)))
             }
+            "__builtin_creal" | "__builtin_crealf" | "__builtin_creall" => {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| mk().field_expr(x, "re")))
+            }
+            "__builtin_cimag" | "__builtin_cimagf" | "__builtin_cimagl" => {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| mk().field_expr(x, "im")))
+            }
+            "__builtin_conj" | "__builtin_conjf" | "__builtin_conjl" => {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| mk().method_call_expr(x, "conj", vec![] as Vec<P<Expr>>)))
+            }
             "__builtin_flt_rounds" => {
                 // LLVM simply lowers this to the constant one which means
                 // that floats are rounded to the nearest number.
@@ -199,9 +206,10 @@ impl<'c> Translation<'c> {
                 self.convert_overflow_arith(ctx, "overflowing_mul", args)
             }
 
-            // Should be safe to always return 0 here.  "A return of 0 does not indicate that the
-            // value is *not* a constant, but merely that GCC cannot prove it is a constant with
-            // the specified value of the -O option. "
+            // Calls clang could fold are imported as their result, so this is only reached for
+            // the rest. Should be safe to return 0 here.  "A return of 0 does not indicate that
+            // the value is *not* a constant, but merely that GCC cannot prove it is a constant
+            // with the specified value of the -O option. "
             "__builtin_constant_p" => Ok(WithStmts::new_val(mk().lit_expr(mk().int_lit(0, "")))),
 
             "__builtin_object_size" => {
@@ -467,6 +475,7 @@ impl<'c> Translation<'c> {
                         self.convert_atomic_op(
                             ctx,
                             func_name,
+                            &[],
                             arg0,
                             arg1,
                             fetch_first,
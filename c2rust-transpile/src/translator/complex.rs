@@ -0,0 +1,202 @@
+//! This module provides translations of C `_Complex` values. Complex types are
+//! translated to `num_complex::Complex<T>`, which is `#[repr(C)]` and therefore
+//! has the same layout as the corresponding C type.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Return the element type of a complex type, or `None` if `ctype` does not
+    /// resolve to a complex type.
+    pub fn complex_element_type(&self, ctype: CTypeId) -> Option<CTypeId> {
+        match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Complex(elt) => Some(elt),
+            _ => None,
+        }
+    }
+
+    /// Construct a complex value from its real and imaginary parts. We use a
+    /// struct literal rather than `Complex::new` so that the result can also
+    /// be used in static initializers.
+    pub fn complex_expr(&self, re: P<Expr>, im: P<Expr>) -> P<Expr> {
+        self.extern_crates.borrow_mut().insert("num_complex");
+        mk().struct_expr(
+            mk().path(vec!["num_complex", "Complex"]),
+            vec![mk().field("re", re), mk().field("im", im)],
+        )
+    }
+
+    /// Construct the zero value of the element type `elt` of a complex number
+    fn complex_part_zero(&self, elt: CTypeId) -> P<Expr> {
+        match self.ast_context.resolve_type(elt).kind {
+            ref kind if self.is_long_double_struct(kind) => self.long_double_const("ZERO"),
+            ref kind if kind.is_floating_type() => mk().lit_expr(mk().float_unsuffixed_lit("0.")),
+            _ => mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed)),
+        }
+    }
+
+    /// Construct the complex zero value for complex numbers with element type `elt`
+    pub fn complex_zero(&self, elt: CTypeId) -> P<Expr> {
+        self.complex_expr(self.complex_part_zero(elt), self.complex_part_zero(elt))
+    }
+
+    /// Convert one of the complex `CastKind`s. `val` is the already translated
+    /// operand of type `source_ty`.
+    pub fn convert_complex_cast(
+        &self,
+        ctx: ExprContext,
+        kind: CastKind,
+        source_ty: CQualTypeId,
+        ty: CQualTypeId,
+        val: WithStmts<P<Expr>>,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        match kind {
+            CastKind::FloatingRealToComplex | CastKind::IntegralRealToComplex => {
+                let elt = self.complex_element_type(ty.ctype)
+                    .ok_or_else(|| format_err!("Cast to complex with non-complex target type"))?;
+                let re = self.complex_part_cast(source_ty.ctype, elt, val)?;
+                Ok(re.map(|re| self.complex_expr(re, self.complex_part_zero(elt))))
+            }
+
+            CastKind::FloatingComplexToReal | CastKind::IntegralComplexToReal => {
+                let elt = self.complex_element_type(source_ty.ctype)
+                    .ok_or_else(|| format_err!("Cast from complex with non-complex source type"))?;
+                let re = val.map(|x| mk().field_expr(x, "re"));
+                self.complex_part_cast(elt, ty.ctype, re)
+            }
+
+            CastKind::FloatingComplexCast
+            | CastKind::FloatingComplexToIntegralComplex
+            | CastKind::IntegralComplexToFloatingComplex
+            | CastKind::IntegralComplexCast => {
+                let source_elt = self.complex_element_type(source_ty.ctype)
+                    .ok_or_else(|| format_err!("Complex cast with non-complex source type"))?;
+                let target_elt = self.complex_element_type(ty.ctype)
+                    .ok_or_else(|| format_err!("Complex cast with non-complex target type"))?;
+
+                self.with_reusable_operand(ctx, val, |x| {
+                    let re = self.complex_part_cast(
+                        source_elt,
+                        target_elt,
+                        WithStmts::new_val(mk().field_expr(x.clone(), "re")),
+                    )?;
+                    let im = self.complex_part_cast(
+                        source_elt,
+                        target_elt,
+                        WithStmts::new_val(mk().field_expr(x, "im")),
+                    )?;
+                    re.and_then(|re| Ok(im.map(|im| self.complex_expr(re, im))))
+                })
+            }
+
+            CastKind::IntegralComplexToBoolean | CastKind::FloatingComplexToBoolean => {
+                let elt = self.complex_element_type(source_ty.ctype)
+                    .ok_or_else(|| format_err!("Complex to boolean cast with non-complex source type"))?;
+                let zero = self.complex_zero(elt);
+                Ok(val.map(|x| mk().binary_expr(BinOpKind::Ne, x, zero)))
+            }
+
+            _ => Err(format_err!("Not a complex cast: {:?}", kind).into()),
+        }
+    }
+
+    /// Translate the GNU `__real__` and `__imag__` operators. These are valid
+    /// lvalues on complex operands; on real operands `__real__` is the identity
+    /// and `__imag__` is zero.
+    pub fn convert_complex_part(
+        &self,
+        ctx: ExprContext,
+        part: c_ast::UnOp,
+        arg: CExprId,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let arg_ty = self.ast_context[arg]
+            .kind
+            .get_type()
+            .ok_or_else(|| format_err!("bad complex operand type"))?;
+        let field = match part {
+            c_ast::UnOp::Real => "re",
+            c_ast::UnOp::Imag => "im",
+            _ => return Err(format_err!("Not a complex part operator: {:?}", part).into()),
+        };
+
+        let val = self.convert_expr(ctx.used(), arg)?;
+        if self.complex_element_type(arg_ty).is_some() {
+            Ok(val.map(|x| mk().field_expr(x, field)))
+        } else if field == "re" {
+            Ok(val)
+        } else {
+            // The operand is still evaluated for its side effects
+            let mut zero = self.implicit_default_expr(arg_ty, ctx.is_static)?;
+            zero.prepend_stmts(val.into_stmts());
+            Ok(zero)
+        }
+    }
+
+    /// Complex values only compare equal to other complex values in Rust, so
+    /// promote a real operand of `==` or `!=` to a complex one.
+    pub fn complex_comparison_operands(
+        &self,
+        lhs_type: CQualTypeId,
+        rhs_type: CQualTypeId,
+        lhs: P<Expr>,
+        rhs: P<Expr>,
+    ) -> (P<Expr>, P<Expr>) {
+        let lhs_elt = self.complex_element_type(lhs_type.ctype);
+        let rhs_elt = self.complex_element_type(rhs_type.ctype);
+        match (lhs_elt, rhs_elt) {
+            (Some(elt), None) => (lhs, self.complex_expr(rhs, self.complex_part_zero(elt))),
+            (None, Some(elt)) => (self.complex_expr(lhs, self.complex_part_zero(elt)), rhs),
+            _ => (lhs, rhs),
+        }
+    }
+
+    /// Cast one component of a complex number, omitting the cast if the source
+    /// and target types are identical.
+    fn complex_part_cast(
+        &self,
+        source_ty: CTypeId,
+        target_ty: CTypeId,
+        val: WithStmts<P<Expr>>,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let source_kind = &self.ast_context.resolve_type(source_ty).kind;
+        let target_kind = &self.ast_context.resolve_type(target_ty).kind;
+        if source_kind == target_kind {
+            return Ok(val);
+        }
+        if self.is_long_double_struct(target_kind) {
+            return Ok(val.map(|x| self.cast_to_long_double(x)));
+        }
+        if self.is_long_double_struct(source_kind) {
+            return self.long_double_cast_to(val, target_kind);
+        }
+        let ty = self.convert_type(target_ty)?;
+        Ok(val.map(|x| mk().cast_expr(x, ty)))
+    }
+
+    /// Bind `val` to a temporary so that `f` can refer to it more than once.
+    /// Paths and static initializers (which cannot contain `let` statements and
+    /// have no side effects) are duplicated instead.
+    fn with_reusable_operand<F>(
+        &self,
+        ctx: ExprContext,
+        val: WithStmts<P<Expr>>,
+        f: F,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError>
+    where
+        F: FnOnce(P<Expr>) -> Result<WithStmts<P<Expr>>, TranslationError>,
+    {
+        val.and_then(|x| {
+            if ctx.is_static {
+                return f(x);
+            }
+            if let ExprKind::Path(..) = x.kind {
+                return f(x);
+            }
+
+            let name = self.renamer.borrow_mut().pick_name("c");
+            let local = mk().local(mk().ident_pat(name.clone()), None as Option<P<Ty>>, Some(x));
+            let mut res = f(mk().ident_expr(name))?;
+            res.prepend_stmts(vec![mk().local_stmt(P(local))]);
+            Ok(res)
+        })
+    }
+}
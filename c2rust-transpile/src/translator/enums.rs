@@ -0,0 +1,417 @@
+//! Translation of C enums into Rust `enum`s, used with `--enum-style=rust`.
+//!
+//! A Rust enum may only ever hold one of its variants, so we only translate a
+//! C enum this way when its values always come from its own constants and are
+//! otherwise only compared, switched on or copied around. All other enums keep
+//! the default translation into an integer type alias with free constants.
+//!
+//! Other translation units and C code outside the crate can't be checked, so
+//! an enum that they may share with us also stays an integer: one declared in
+//! a header, or used in the type of an externally visible function or
+//! variable, or of a struct or union field. This also keeps the translation of
+//! each header enum the same in every translation unit.
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+
+/// Value of an enum constant, widened so that signed and unsigned constants
+/// can be compared.
+fn constant_value(value: ConstIntExpr) -> i128 {
+    match value {
+        ConstIntExpr::I(v) => v as i128,
+        ConstIntExpr::U(v) => v as i128,
+    }
+}
+
+fn enum_of_type(ast_context: &TypedAstContext, ty: CTypeId) -> Option<CEnumId> {
+    match ast_context.resolve_type(ty).kind {
+        CTypeKind::Enum(enum_id) => Some(enum_id),
+        _ => None,
+    }
+}
+
+/// If `ty` is a pointer to an enum, return that enum.
+fn pointee_enum(ast_context: &TypedAstContext, ty: CTypeId) -> Option<CEnumId> {
+    match ast_context.resolve_type(ty).kind {
+        CTypeKind::Pointer(pointee) => enum_of_type(ast_context, pointee.ctype),
+        _ => None,
+    }
+}
+
+/// Find the enums that occur in `ty`, looking through pointers, arrays and
+/// function types but not into records.
+fn enums_in_type(ast_context: &TypedAstContext, ty: CTypeId) -> Vec<CEnumId> {
+    fn walk(ast_context: &TypedAstContext, ty: CTypeId, found: &mut Vec<CEnumId>) {
+        match ast_context.resolve_type(ty).kind {
+            CTypeKind::Enum(enum_id) => found.push(enum_id),
+            CTypeKind::Pointer(qty)
+            | CTypeKind::Reference(qty)
+            | CTypeKind::Attributed(qty, _)
+            | CTypeKind::BlockPointer(qty) => walk(ast_context, qty.ctype, found),
+            CTypeKind::ConstantArray(elem, _)
+            | CTypeKind::IncompleteArray(elem)
+            | CTypeKind::VariableArray(elem, _)
+            | CTypeKind::Decayed(elem) => walk(ast_context, elem, found),
+            CTypeKind::Function(ret, ref params, ..) => {
+                walk(ast_context, ret.ctype, found);
+                for param in params {
+                    walk(ast_context, param.ctype, found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut found = vec![];
+    walk(ast_context, ty, &mut found);
+    found
+}
+
+fn enum_of_expr(ast_context: &TypedAstContext, expr: CExprId) -> Option<CEnumId> {
+    ast_context[expr]
+        .kind
+        .get_type()
+        .and_then(|ty| enum_of_type(ast_context, ty))
+}
+
+fn enum_variants(ast_context: &TypedAstContext, enum_id: CEnumId) -> &[CEnumConstantId] {
+    match ast_context[enum_id].kind {
+        CDeclKind::Enum { ref variants, .. } => variants,
+        _ => panic!("{:?} does not point to an `enum` declaration", enum_id),
+    }
+}
+
+/// Find the constant of `enum_id` with the given value.
+fn find_variant(
+    ast_context: &TypedAstContext,
+    enum_id: CEnumId,
+    value: i128,
+) -> Option<CEnumConstantId> {
+    enum_variants(ast_context, enum_id)
+        .iter()
+        .cloned()
+        .find(|&variant_id| match ast_context[variant_id].kind {
+            CDeclKind::EnumConstant { value: v, .. } => constant_value(v) == value,
+            _ => false,
+        })
+}
+
+/// Check whether converting `expr` into the enum `enum_id` yields one of its
+/// variants. This mirrors the cases `Translation::enum_cast` translates
+/// without an `as` cast.
+fn converts_to_variant(ast_context: &TypedAstContext, enum_id: CEnumId, expr: CExprId) -> bool {
+    match ast_context[expr].kind {
+        CExprKind::DeclRef(_, decl_id, _) => {
+            enum_variants(ast_context, enum_id).contains(&decl_id)
+        }
+        CExprKind::Literal(_, CLiteral::Integer(i, _)) => {
+            find_variant(ast_context, enum_id, i as i64 as i128).is_some()
+        }
+        CExprKind::Unary(_, c_ast::UnOp::Negate, subexpr, _) => match ast_context[subexpr].kind {
+            CExprKind::Literal(_, CLiteral::Integer(i, _)) => {
+                find_variant(ast_context, enum_id, -(i as i64) as i128).is_some()
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Find the enums of the translation unit whose main file is `main_file` that
+/// can be translated into Rust enums. The reason each of the other enums has to
+/// keep the integer representation is logged.
+pub fn rust_enum_candidates(ast_context: &TypedAstContext, main_file: FileId) -> IndexSet<CEnumId> {
+    let mut candidates = IndexSet::new();
+    let mut rejected: IndexMap<CEnumId, &'static str> = IndexMap::new();
+    let mut reject = |enum_id: CEnumId, reason: &'static str| {
+        rejected.entry(enum_id).or_insert(reason);
+    };
+
+    for (&decl_id, decl) in ast_context.iter_decls() {
+        match decl.kind {
+            CDeclKind::Enum {
+                ref variants,
+                integral_type: Some(_),
+                ..
+            } => {
+                candidates.insert(decl_id);
+
+                if ast_context.file_id(decl).map_or(false, |id| id != main_file) {
+                    reject(
+                        decl_id,
+                        "it is declared in a header, which other translation units may translate differently",
+                    );
+                }
+
+                let mut values = variants
+                    .iter()
+                    .filter_map(|&variant_id| match ast_context[variant_id].kind {
+                        CDeclKind::EnumConstant { value, .. } => Some(constant_value(value)),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                if !values.contains(&0) {
+                    reject(decl_id, "it has no constant with value 0 to zero-initialize it with");
+                }
+                values.sort();
+                values.dedup();
+                if values.len() != variants.len() {
+                    reject(decl_id, "several of its constants have the same value");
+                }
+            }
+
+            CDeclKind::Field { typ, .. } => {
+                for enum_id in enums_in_type(ast_context, typ.ctype) {
+                    reject(enum_id, "it is used in the type of a struct or union field");
+                }
+            }
+
+            CDeclKind::Function {
+                is_global: true,
+                typ,
+                ..
+            } => {
+                for enum_id in enums_in_type(ast_context, typ) {
+                    reject(enum_id, "it is used in the signature of an externally visible function");
+                }
+            }
+
+            CDeclKind::Variable {
+                is_extern,
+                is_externally_visible,
+                typ,
+                ..
+            } if is_extern || is_externally_visible => {
+                for enum_id in enums_in_type(ast_context, typ.ctype) {
+                    reject(enum_id, "it is used in the type of an externally visible variable");
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    for (_, expr) in ast_context.iter_exprs() {
+        match expr.kind {
+            CExprKind::ImplicitCast(ty, subexpr, kind, _, _)
+            | CExprKind::ExplicitCast(ty, subexpr, kind, _, _) => {
+                let target = enum_of_type(ast_context, ty.ctype);
+                let source = enum_of_expr(ast_context, subexpr);
+                if let Some(enum_id) = target {
+                    if source != target && !converts_to_variant(ast_context, enum_id, subexpr) {
+                        reject(
+                            enum_id,
+                            "it is assigned values other than its own constants, e.g. bit-flags or the results of arithmetic",
+                        );
+                    }
+                }
+                if let CastKind::BitCast | CastKind::IntegralToPointer = kind {
+                    let target_pointee = pointee_enum(ast_context, ty.ctype);
+                    let source_pointee = ast_context[subexpr]
+                        .kind
+                        .get_type()
+                        .and_then(|ty| pointee_enum(ast_context, ty));
+                    if target_pointee != source_pointee {
+                        for enum_id in target_pointee.into_iter().chain(source_pointee) {
+                            reject(enum_id, "a pointer to it is cast to or from another pointer type");
+                        }
+                    }
+                }
+                if let Some(enum_id) = source {
+                    match kind {
+                        CastKind::IntegralCast
+                        | CastKind::LValueToRValue
+                        | CastKind::NoOp
+                        | CastKind::ToVoid => {}
+                        _ => reject(enum_id, "it is converted to a non-integral type"),
+                    }
+                }
+            }
+
+            CExprKind::Unary(_, op, subexpr, _) => match op {
+                c_ast::UnOp::AddressOf | c_ast::UnOp::Extension => {}
+                _ => {
+                    if let Some(enum_id) = enum_of_expr(ast_context, subexpr) {
+                        reject(enum_id, "it is used in arithmetic");
+                    }
+                }
+            },
+
+            CExprKind::Binary(_, op, lhs, rhs, _, _) => match op {
+                c_ast::BinOp::Assign | c_ast::BinOp::Comma => {}
+                _ => {
+                    for &operand in &[lhs, rhs] {
+                        if let Some(enum_id) = enum_of_expr(ast_context, operand) {
+                            reject(enum_id, "it is used in arithmetic");
+                        }
+                    }
+                }
+            },
+
+            CExprKind::Conditional(_, cond, _, _) | CExprKind::BinaryConditional(_, cond, _) => {
+                if let Some(enum_id) = enum_of_expr(ast_context, cond) {
+                    reject(enum_id, "it is used as a condition");
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    for (_, stmt) in ast_context.iter_stmts() {
+        match stmt.kind {
+            CStmtKind::If { scrutinee: cond, .. }
+            | CStmtKind::While { condition: cond, .. }
+            | CStmtKind::DoWhile { condition: cond, .. }
+            | CStmtKind::ForLoop { condition: Some(cond), .. } => {
+                if let Some(enum_id) = enum_of_expr(ast_context, cond) {
+                    reject(enum_id, "it is used as a condition");
+                }
+            }
+
+            // Every case of a switch on the enum must be one of its variants
+            CStmtKind::Switch { scrutinee, body } => {
+                if let Some((_, enum_id)) = switch_enum(ast_context, scrutinee) {
+                    let cases_are_variants = DFNodes::new(ast_context, SomeId::Stmt(body))
+                        .filter_map(SomeId::stmt)
+                        .all(|stmt_id| match ast_context[stmt_id].kind {
+                            CStmtKind::Case(_, _, value) => {
+                                find_variant(ast_context, enum_id, constant_value(value)).is_some()
+                            }
+                            _ => true,
+                        });
+                    if !cases_are_variants {
+                        reject(enum_id, "it is switched on with cases that are not its constants");
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    for (enum_id, reason) in rejected {
+        if candidates.remove(&enum_id) {
+            let name = ast_context[enum_id].kind.get_name().map_or("<anonymous>", String::as_str);
+            info!("Translating enum {} as integers because {}", name, reason);
+        }
+    }
+    candidates
+}
+
+/// If `scrutinee` switches on the value of an enum, possibly after integral
+/// promotion, return the unpromoted expression and the enum.
+fn switch_enum(ast_context: &TypedAstContext, scrutinee: CExprId) -> Option<(CExprId, CEnumId)> {
+    if let Some(enum_id) = enum_of_expr(ast_context, scrutinee) {
+        return Some((scrutinee, enum_id));
+    }
+    match ast_context[scrutinee].kind {
+        CExprKind::ImplicitCast(_, subexpr, CastKind::IntegralCast, _, _) => {
+            enum_of_expr(ast_context, subexpr).map(|enum_id| (subexpr, enum_id))
+        }
+        _ => None,
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Translate an enum whose usage allows it into a Rust enum.
+    pub fn convert_rust_enum(
+        &self,
+        span: Span,
+        enum_name: &str,
+        variants: &[CEnumConstantId],
+        integral_type: CQualTypeId,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let variants = variants
+            .iter()
+            .map(|&variant_id| {
+                let name = self
+                    .renamer
+                    .borrow()
+                    .get(&variant_id)
+                    .expect("Enum constant not named");
+                let value = match self.ast_context[variant_id].kind {
+                    CDeclKind::EnumConstant { value: ConstIntExpr::I(value), .. } => {
+                        signed_int_expr(value)
+                    }
+                    CDeclKind::EnumConstant { value: ConstIntExpr::U(value), .. } => {
+                        mk().lit_expr(mk().int_lit(value as u128, LitIntType::Unsuffixed))
+                    }
+                    _ => panic!("{:?} does not point to an enum constant", variant_id),
+                };
+                mk().unit_variant(name, Some(value))
+            })
+            .collect();
+
+        Ok(ConvertedDecl::Item(
+            mk().span(span)
+                .pub_()
+                .call_attr("derive", vec!["Copy", "Clone", "PartialEq", "Eq"])
+                .call_attr("repr", vec![self.enum_repr(integral_type.ctype)])
+                .enum_item(enum_name, variants),
+        ))
+    }
+
+    /// Pick the `repr` of a Rust enum with the given underlying C type.
+    fn enum_repr(&self, integral_type: CTypeId) -> &'static str {
+        match self.ast_context.resolve_type(integral_type).kind {
+            CTypeKind::Char | CTypeKind::SChar => "i8",
+            CTypeKind::UChar => "u8",
+            CTypeKind::Short => "i16",
+            CTypeKind::UShort => "u16",
+            CTypeKind::Int => "i32",
+            CTypeKind::UInt => "u32",
+            CTypeKind::Long | CTypeKind::LongLong => "i64",
+            CTypeKind::ULong | CTypeKind::ULongLong => "u64",
+            _ => "C",
+        }
+    }
+
+    /// If `decl_id` is a constant of an enum translated into a Rust enum,
+    /// return that enum.
+    pub fn rust_enum_of_constant(&self, decl_id: CDeclId) -> Option<CEnumId> {
+        match self.ast_context[decl_id].kind {
+            CDeclKind::EnumConstant { .. } => self
+                .ast_context
+                .parents
+                .get(&decl_id)
+                .cloned()
+                .filter(|enum_id| self.rust_enums.contains(enum_id)),
+            _ => None,
+        }
+    }
+
+    /// Path to the variant a constant of a Rust enum was translated into,
+    /// importing the enum if needed
+    pub fn rust_enum_variant_path(&self, enum_id: CEnumId, variant_id: CEnumConstantId) -> Vec<String> {
+        let enum_name = self
+            .type_converter
+            .borrow()
+            .resolve_decl_name(enum_id)
+            .expect("Enums should already be renamed");
+        let variant_name = self
+            .renamer
+            .borrow()
+            .get(&variant_id)
+            .expect("Enum constant not named");
+
+        if let Some(cur_file) = *self.cur_file.borrow() {
+            self.add_import(cur_file, enum_id, &enum_name);
+        }
+        vec![enum_name, variant_name]
+    }
+
+    /// If `scrutinee` switches on an enum translated into a Rust enum, return
+    /// the expression to match on instead and the enum.
+    pub fn rust_enum_switch(&self, scrutinee: CExprId) -> Option<(CExprId, CEnumId)> {
+        switch_enum(&self.ast_context, scrutinee)
+            .filter(|&(_, enum_id)| self.rust_enums.contains(&enum_id))
+    }
+
+    /// Pattern matching the variant of a Rust enum for the given `case` value
+    pub fn rust_enum_case_pat(&self, enum_id: CEnumId, value: ConstIntExpr) -> Option<P<Pat>> {
+        let variant_id = find_variant(&self.ast_context, enum_id, constant_value(value))?;
+        let path = self.rust_enum_variant_path(enum_id, variant_id);
+        Some(mk().qpath_pat(None, path))
+    }
+}
@@ -0,0 +1,154 @@
+//! This module provides assertions that translated structs and unions have the
+//! size, alignment and field offsets clang computed for them.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Build items checking that the Rust type `name` translated from the
+    /// record `decl_id` has the given size and alignment. These are `const`
+    /// items whose array types only match if the layout does, e.g.
+    /// `const _: [(); 8] = [(); ::std::mem::size_of::<foo>()];`.
+    ///
+    /// If `fields_name` is given, the offset of every field other than
+    /// bitfields is checked as well; it names the type holding the fields,
+    /// which is not `name` itself for structs split in two to apply
+    /// `align(N)`. Field addresses can't be computed in constants, so the
+    /// offsets are checked at runtime by `pub fn layout_offsets_<name>()`,
+    /// which is also a `#[test]` under `cfg(test)`. It borrows the fields of
+    /// a zeroed value, so it is skipped for packed records.
+    pub fn convert_layout_asserts(
+        &self,
+        span: Span,
+        decl_id: CRecordId,
+        name: &str,
+        fields_name: Option<&str>,
+        byte_size: u64,
+        alignment: u64,
+    ) -> Vec<P<Item>> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let usize_lit = |n: u64| mk().lit_expr(mk().int_lit(n.into(), LitIntType::Unsuffixed));
+        let mem_path = |func: &str, ty_name: &str| {
+            let path = vec![
+                mk().path_segment(""),
+                mk().path_segment(std_or_core),
+                mk().path_segment("mem"),
+                mk().path_segment_with_args(
+                    func,
+                    mk().angle_bracketed_args(vec![mk().path_ty(vec![ty_name])]),
+                ),
+            ];
+            mk().path_expr(path)
+        };
+        let mem_call = |func: &str, ty_name: &str| {
+            mk().call_expr(mem_path(func, ty_name), vec![] as Vec<P<Expr>>)
+        };
+        let unit_ty = || mk().tuple_ty(vec![] as Vec<P<Ty>>);
+        let assert_item = |actual: P<Expr>, expected: u64| {
+            let ty = mk().array_ty(unit_ty(), usize_lit(expected));
+            let init = mk().repeat_expr(mk().tuple_expr(vec![] as Vec<P<Expr>>), actual);
+            mk().span(span).const_item("_", ty, init)
+        };
+
+        let mut items = vec![
+            assert_item(mem_call("size_of", name), byte_size),
+            assert_item(mem_call("align_of", name), alignment),
+        ];
+
+        let fields_name = match fields_name {
+            Some(fields_name) => fields_name,
+            None => return items,
+        };
+        let fields = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                ..
+            } => fields,
+            _ => return items,
+        };
+
+        // Borrowing fields of packed records may create unaligned references
+        if self.ast_context.is_packed_struct_decl(decl_id) {
+            return items;
+        }
+
+        // let value = ::std::mem::zeroed::<fields_name>();
+        // let base = &value as *const fields_name as usize;
+        let usize_ty = || mk().path_ty(vec!["usize"]);
+        let zeroed = mk().call_expr(mem_path("zeroed", fields_name), vec![] as Vec<P<Expr>>);
+        let base_addr = mk().cast_expr(
+            mk().cast_expr(
+                mk().addr_of_expr(mk().ident_expr("value")),
+                mk().ptr_ty(mk().path_ty(vec![fields_name])),
+            ),
+            usize_ty(),
+        );
+        let mut stmts = vec![
+            mk().local_stmt(P(mk().local(mk().ident_pat("value"), None as Option<P<Ty>>, Some(zeroed)))),
+            mk().local_stmt(P(mk().local(mk().ident_pat("base"), None as Option<P<Ty>>, Some(base_addr)))),
+        ];
+
+        for &field_id in fields {
+            let bit_offset = match self.ast_context[field_id].kind {
+                // Bitfields are grouped into byte arrays without their names
+                CDeclKind::Field {
+                    bitfield_width: Some(_),
+                    ..
+                } => continue,
+                CDeclKind::Field {
+                    platform_bit_offset,
+                    ..
+                } => platform_bit_offset,
+                _ => continue,
+            };
+            let field_name = match self
+                .type_converter
+                .borrow()
+                .resolve_field_name(Some(decl_id), field_id)
+            {
+                Some(field_name) => field_name,
+                None => continue,
+            };
+
+            // assert_eq!(&value.field as *const _ as usize - base, offset);
+            let field_addr = mk().cast_expr(
+                mk().cast_expr(
+                    mk().addr_of_expr(mk().field_expr(mk().ident_expr("value"), field_name)),
+                    mk().ptr_ty(mk().infer_ty()),
+                ),
+                usize_ty(),
+            );
+            let offset = mk().binary_expr(BinOpKind::Sub, field_addr, mk().ident_expr("base"));
+            let tokens = vec![
+                TokenTree::token(
+                    token::Interpolated(Rc::new(Nonterminal::NtExpr(offset))),
+                    DUMMY_SP,
+                ),
+                TokenTree::token(token::Comma, DUMMY_SP),
+                TokenTree::token(
+                    token::Interpolated(Rc::new(Nonterminal::NtExpr(usize_lit(bit_offset / 8)))),
+                    DUMMY_SP,
+                ),
+            ];
+            stmts.push(mk().semi_stmt(mk().mac_expr(mk().mac(
+                vec!["assert_eq"],
+                tokens.into_iter().collect::<TokenStream>(),
+                MacDelimiter::Parenthesis,
+            ))));
+        }
+
+        let block = mk().block(vec![mk().expr_stmt(mk().block_expr(mk().unsafe_().block(stmts)))]);
+        items.push(
+            mk().span(span)
+                .pub_()
+                .call_attr("cfg_attr", vec!["test", "test"])
+                .call_attr("allow", vec!["dead_code", "non_snake_case"])
+                .fn_item(
+                    format!("layout_offsets_{}", name),
+                    mk().fn_decl(vec![], FunctionRetTy::Default(DUMMY_SP)),
+                    block,
+                ),
+        );
+
+        items
+    }
+}
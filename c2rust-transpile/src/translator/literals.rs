@@ -39,6 +39,10 @@ impl<'c> Translation<'c> {
             match self.ast_context[variant_id].kind {
                 CDeclKind::EnumConstant { value: v, .. } => {
                     if v == ConstIntExpr::I(value) || v == ConstIntExpr::U(value as u64) {
+                        if self.rust_enums.contains(&def_id) {
+                            return mk().path_expr(self.rust_enum_variant_path(def_id, variant_id));
+                        }
+
                         let name = self.renamer.borrow().get(&variant_id).unwrap();
 
                         // Import the enum variant if needed
@@ -104,7 +108,7 @@ impl<'c> Translation<'c> {
                 Ok(WithStmts::new_val(expr))
             }
 
-            CLiteral::Floating(val, ref c_str) => {
+            CLiteral::Floating(val, ref c_str, bits) => {
                 let mut bytes: Vec<u8> = vec![];
                 let str = if c_str.is_empty() {
                     dtoa::write(&mut bytes, val).unwrap();
@@ -113,14 +117,7 @@ impl<'c> Translation<'c> {
                     c_str.to_owned()
                 };
                 let val = match self.ast_context.resolve_type(ty.ctype).kind {
-                    CTypeKind::LongDouble => {
-                        self.extern_crates.borrow_mut().insert("f128");
-
-                        let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                        let args = vec![mk().ident_expr(str)];
-
-                        mk().call_expr(fn_path, args)
-                    }
+                    CTypeKind::LongDouble => self.long_double_literal(val, str, bits),
                     CTypeKind::Double => mk().lit_expr(mk().float_lit(str, FloatTy::F64)),
                     CTypeKind::Float => mk().lit_expr(mk().float_lit(str, FloatTy::F32)),
                     ref k => panic!("Unsupported floating point literal type {:?}", k),
@@ -225,7 +222,8 @@ impl<'c> Translation<'c> {
                     Ok(ids
                         .iter()
                         .map(|id| {
-                            self.convert_expr(ctx.used(), *id)?
+                            let val = self.convert_expr(ctx.used(), *id)?;
+                            self.coerce_expr_to(*id, ty, val)?
                                 .result_map(|x| {
                                     // Array literals require all of their elements to be
                                     // the correct type; they will not use implicit casts to
@@ -0,0 +1,259 @@
+//! This module provides translations of C `long double` values. Depending on
+//! `--long-double` they are translated to `f64`, to the x87 extended precision
+//! `c2rust_x87::f80` or to `f128::f128`. The latter two are structs, so their
+//! values have to be constructed and converted with method calls rather than
+//! literals and `as` casts.
+
+use super::*;
+
+/// Sign bit of the sign and exponent word of an x87 extended precision value
+const X87_SIGN_BIT: u16 = 0x8000;
+/// Exponent bias of x87 extended precision values
+const X87_BIAS: u32 = 16383;
+
+/// Widen a double to the bits of the x87 extended precision value with the same
+/// value, as mantissa and sign and exponent word.
+fn f64_to_x87_bits(x: f64) -> (u64, u16) {
+    let bits = x.to_bits();
+    let sign = if bits >> 63 != 0 { X87_SIGN_BIT } else { 0 };
+    let exp = ((bits >> 52) & 0x7ff) as u32;
+    let frac = bits & ((1 << 52) - 1);
+
+    match exp {
+        0 if frac == 0 => (0, sign),
+        // Subnormal doubles are normal as x87 values
+        0 => {
+            let lz = frac.leading_zeros();
+            (frac << lz, sign | (X87_BIAS + 63 - 1074 - lz) as u16)
+        }
+        0x7ff => (1 << 63 | frac << 11, sign | 0x7fff),
+        _ => (1 << 63 | frac << 11, sign | (exp + X87_BIAS - 1023) as u16),
+    }
+}
+
+/// Bits of the x87 extended precision value of an integer, which are always
+/// exact since the mantissa has 64 bits.
+fn u64_to_x87_bits(x: u64) -> (u64, u16) {
+    if x == 0 {
+        return (0, 0);
+    }
+    let lz = x.leading_zeros();
+    (x << lz, (X87_BIAS + 63 - lz) as u16)
+}
+
+impl<'c> Translation<'c> {
+    /// The Rust type `long double` is translated to
+    pub fn long_double(&self) -> LongDouble {
+        self.type_converter.borrow().long_double
+    }
+
+    /// Whether `kind` is a `long double` translated to a struct rather than a
+    /// primitive float type
+    pub fn is_long_double_struct(&self, kind: &CTypeKind) -> bool {
+        *kind == CTypeKind::LongDouble && self.long_double() != LongDouble::F64
+    }
+
+    /// Path to the associated item `name` of the translated `long double` type
+    fn long_double_item(&self, name: &'static str) -> Vec<&'static str> {
+        match self.long_double() {
+            LongDouble::F64 => {
+                let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+                vec!["", std_or_core, "f64", name]
+            }
+            LongDouble::X87 => {
+                self.extern_crates.borrow_mut().insert("c2rust_x87");
+                vec!["c2rust_x87", "f80", name]
+            }
+            LongDouble::F128 => {
+                self.extern_crates.borrow_mut().insert("f128");
+                vec!["f128", "f128", name]
+            }
+        }
+    }
+
+    /// Associated constant `name` of the translated `long double` type, such as
+    /// `NAN` or `INFINITY`
+    pub fn long_double_const(&self, name: &'static str) -> P<Expr> {
+        mk().path_expr(self.long_double_item(name))
+    }
+
+    /// Convert a primitive int or float into a `long double`
+    pub fn cast_to_long_double(&self, val: P<Expr>) -> P<Expr> {
+        match self.long_double() {
+            LongDouble::F64 => mk().cast_expr(val, mk().path_ty(vec!["f64"])),
+            _ => mk().call_expr(mk().path_expr(self.long_double_item("new")), vec![val]),
+        }
+    }
+
+    /// Translate a `long double` literal. `bits` are the exact bits of the
+    /// literal in the target's `long double` format, if they are wider than a
+    /// double.
+    pub fn long_double_literal(
+        &self,
+        val: f64,
+        spelling: String,
+        bits: Option<(u64, u64)>,
+    ) -> P<Expr> {
+        match self.long_double() {
+            LongDouble::F64 => mk().lit_expr(mk().float_lit(spelling, FloatTy::F64)),
+            LongDouble::X87 => {
+                let bits = match bits {
+                    Some((mantissa, sign_exp))
+                        if self.ast_context.long_double_format
+                            == LongDoubleFormat::X87LongDouble =>
+                    {
+                        (mantissa, sign_exp as u16)
+                    }
+                    _ => f64_to_x87_bits(val),
+                };
+                self.x87_from_bits(bits)
+            }
+            LongDouble::F128 => {
+                let fn_path = mk().path_expr(self.long_double_item("new"));
+                mk().call_expr(fn_path, vec![mk().ident_expr(spelling)])
+            }
+        }
+    }
+
+    /// Construct an x87 extended precision value from its bits, which is a
+    /// `const fn`
+    fn x87_from_bits(&self, (mantissa, sign_exp): (u64, u16)) -> P<Expr> {
+        let fn_path = mk().path_expr(self.long_double_item("from_bits"));
+        let args = vec![
+            mk().lit_expr(mk().int_lit(mantissa as u128, LitIntType::Unsuffixed)),
+            mk().lit_expr(mk().int_lit(sign_exp as u128, LitIntType::Unsuffixed)),
+        ];
+        mk().call_expr(fn_path, args)
+    }
+
+    /// Evaluate a `long double` expression built from literals with casts and
+    /// negations to its exact x87 extended precision bits.
+    fn x87_constant(&self, expr_id: CExprId) -> Option<(u64, u16)> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::Literal(ty, CLiteral::Floating(val, _, bits)) => {
+                let is_long_double =
+                    self.ast_context.resolve_type(ty.ctype).kind == CTypeKind::LongDouble;
+                match bits {
+                    Some((mantissa, sign_exp))
+                        if is_long_double
+                            && self.ast_context.long_double_format
+                                == LongDoubleFormat::X87LongDouble =>
+                    {
+                        Some((mantissa, sign_exp as u16))
+                    }
+                    _ => Some(f64_to_x87_bits(val)),
+                }
+            }
+            CExprKind::Literal(_, CLiteral::Integer(val, _)) => Some(u64_to_x87_bits(val)),
+            CExprKind::Unary(ty, UnOp::Negate, arg, _) => {
+                let kind = &self.ast_context.resolve_type(ty.ctype).kind;
+                if kind.is_floating_type() {
+                    self.x87_constant(arg)
+                        .map(|(mantissa, sign_exp)| (mantissa, sign_exp ^ X87_SIGN_BIT))
+                } else if kind.is_signed_integral_type() {
+                    // Integers have no negative zero
+                    self.x87_constant(arg).map(|(mantissa, sign_exp)| match mantissa {
+                        0 => (mantissa, sign_exp),
+                        _ => (mantissa, sign_exp ^ X87_SIGN_BIT),
+                    })
+                } else {
+                    None
+                }
+            }
+            // Only widening casts, which preserve the value
+            CExprKind::ImplicitCast(ty, arg, CastKind::IntegralToFloating, _, _)
+            | CExprKind::ExplicitCast(ty, arg, CastKind::IntegralToFloating, _, _)
+            | CExprKind::ImplicitCast(ty, arg, CastKind::FloatingCast, _, _)
+            | CExprKind::ExplicitCast(ty, arg, CastKind::FloatingCast, _, _)
+                if self.ast_context.resolve_type(ty.ctype).kind == CTypeKind::LongDouble =>
+            {
+                self.x87_constant(arg)
+            }
+            CExprKind::Paren(_, arg) => self.x87_constant(arg),
+            _ => None,
+        }
+    }
+
+    /// Translate a `long double` expression to a constant expression if it is
+    /// built from literals and the translated type allows it. Plain `f64`
+    /// expressions are constant anyway.
+    pub fn long_double_constant(&self, expr_id: CExprId) -> Option<P<Expr>> {
+        let ty = self.ast_context[expr_id].kind.get_type()?;
+        if self.long_double() != LongDouble::X87
+            || self.ast_context.resolve_type(ty).kind != CTypeKind::LongDouble
+        {
+            return None;
+        }
+        self.x87_constant(expr_id).map(|bits| self.x87_from_bits(bits))
+    }
+
+    /// Whether the `long double` subexpressions of a static initializer can't
+    /// be translated to constant expressions
+    pub fn long_double_initializer_is_uncompilable(&self, expr_id: CExprId) -> bool {
+        if self.long_double() == LongDouble::F64 {
+            return false;
+        }
+
+        DFExpr::new(&self.ast_context, expr_id.into()).any(|i| {
+            let expr_id = match i {
+                SomeId::Expr(expr_id) => expr_id,
+                _ => unreachable!("Found static initializer type other than expr"),
+            };
+            let is_long_double = self.ast_context[expr_id]
+                .kind
+                .get_type()
+                .map_or(false, |ty| {
+                    self.ast_context.resolve_type(ty).kind == CTypeKind::LongDouble
+                });
+
+            // The f128 crate doesn't currently provide a way to const
+            // initialize values, except for common mathematical constants
+            is_long_double
+                && (self.long_double() == LongDouble::F128 || self.x87_constant(expr_id).is_none())
+        })
+    }
+
+    /// Cast a `long double` struct to some other int or float type
+    pub fn long_double_cast_to(
+        &self,
+        val: WithStmts<P<Expr>>,
+        target_ty_ctype: &CTypeKind,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        self.extern_crates.borrow_mut().insert("num_traits");
+        self.items.borrow_mut()[&self.main_file].add_use(vec!["num_traits".into()], "ToPrimitive");
+
+        let to_method_name = match target_ty_ctype {
+            CTypeKind::Float => "to_f32",
+            CTypeKind::Double => "to_f64",
+            CTypeKind::Char => "to_i8",
+            CTypeKind::UChar => "to_u8",
+            CTypeKind::Short => "to_i16",
+            CTypeKind::UShort => "to_u16",
+            CTypeKind::Int => "to_i32",
+            CTypeKind::UInt => "to_u32",
+            CTypeKind::Long => "to_i64",
+            CTypeKind::ULong => "to_u64",
+            CTypeKind::LongLong => "to_i64",
+            CTypeKind::ULongLong => "to_u64",
+            CTypeKind::Int128 => "to_i128",
+            CTypeKind::UInt128 => "to_u128",
+            _ => {
+                return Err(format_err!(
+                    "Tried casting long double to unsupported type: {:?}",
+                    target_ty_ctype
+                )
+                .into())
+            }
+        };
+
+        Ok(val.map(|val| {
+            let to_call = mk().method_call_expr(val, to_method_name, Vec::<P<Expr>>::new());
+
+            mk().method_call_expr(
+                to_call,
+                "unwrap",
+                Vec::<P<Expr>>::new(),
+            )
+        }))
+    }
+}
@@ -0,0 +1,513 @@
+//! This module provides translations of function-like C macros. A macro whose
+//! replaced expansions all have the same signature is translated to an
+//! `#[inline]` function. Otherwise it is translated to a `macro_rules!` macro
+//! that binds its arguments to locals just as the function would, so that the
+//! same body can be used at different argument types. Expansions that cannot be
+//! replaced by a call are left inline: their arguments may have side effects,
+//! they may refer to local variables of the caller, or their translation may
+//! differ from the canonical one.
+
+use std::collections::HashSet;
+
+use super::*;
+
+/// How the expansions of a function-like macro are translated
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MacroFunctionStyle {
+    Function,
+    MacroRules,
+}
+
+/// A macro expansion that is replaced by a call
+#[derive(Debug)]
+struct MacroCall {
+    /// Expression passed for each macro parameter
+    arguments: Vec<CExprId>,
+    /// Type of the expression produced by the macro body, before any
+    /// conversions applied by the context of the expansion
+    body_type: CTypeId,
+    /// Global declarations referenced by the macro body
+    globals: Vec<CDeclId>,
+}
+
+/// The translation of a function-like macro
+#[derive(Debug)]
+pub struct MacroFunction {
+    style: MacroFunctionStyle,
+    is_unsafe: bool,
+    /// The `fn` or `macro_rules!` item defining the macro
+    item: P<Item>,
+    /// Types in the signature of the function
+    signature: Vec<CTypeId>,
+    /// Expansions that are replaced by a call
+    calls: IndexMap<CExprId, MacroCall>,
+}
+
+/// A single expansion of a function-like macro that could be replaced by a call
+struct MacroExpansion {
+    expansion: CExprId,
+    call: MacroCall,
+    param_types: Vec<CTypeId>,
+    translation: WithStmts<P<Expr>>,
+    /// The pretty-printed translation, to compare against other expansions
+    text: String,
+}
+
+impl MacroFunction {
+    pub fn item(&self) -> &P<Item> {
+        &self.item
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Get the translation of the function-like macro `decl_id`, translating
+    /// all of its expansions on first use. Returns `None` if none of the
+    /// expansions can be replaced by a call.
+    pub fn macro_function(
+        &self,
+        decl_id: CDeclId,
+    ) -> Result<Option<Rc<MacroFunction>>, TranslationError> {
+        if let Some(mac) = self.macro_functions.borrow().get(&decl_id) {
+            return Ok(mac.clone());
+        }
+
+        // Leave a placeholder so that failures are not retried
+        self.macro_functions.borrow_mut().insert(decl_id, None);
+        let mac = self.translate_macro_function(decl_id)?.map(Rc::new);
+        self.macro_functions.borrow_mut().insert(decl_id, mac.clone());
+        Ok(mac)
+    }
+
+    /// Replace the expansion `expr_id` of the function-like macro `decl_id`
+    /// with a call, if possible.
+    pub fn convert_macro_function_call(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        // Calls are not allowed in constant expressions
+        if ctx.is_static || ctx.is_const {
+            return Ok(None);
+        }
+
+        let mac = match self.macro_function(decl_id)? {
+            Some(mac) => mac,
+            None => return Ok(None),
+        };
+        let call = match mac.calls.get(&expr_id) {
+            Some(call) => call,
+            None => return Ok(None),
+        };
+        let name = self
+            .renamer
+            .borrow_mut()
+            .get(&decl_id)
+            .ok_or_else(|| format_err!("Macro name not declared"))?;
+
+        if let Some(cur_file) = *self.cur_file.borrow() {
+            match mac.style {
+                MacroFunctionStyle::Function => self.add_import(cur_file, decl_id, &name),
+                // The body of the macro is expanded in place, so it needs
+                // the globals it refers to.
+                MacroFunctionStyle::MacroRules => {
+                    for &global in &call.globals {
+                        let global_name = self.renamer.borrow_mut().get(&global);
+                        if let Some(global_name) = global_name {
+                            self.add_import(cur_file, global, &global_name);
+                        }
+                    }
+                }
+            }
+        }
+
+        let args = call
+            .arguments
+            .iter()
+            .map(|&arg| self.convert_expr(ctx.used(), arg))
+            .collect::<Result<WithStmts<Vec<P<Expr>>>, TranslationError>>()?;
+
+        let mut val = args.map(|args| match mac.style {
+            MacroFunctionStyle::Function => mk().call_expr(mk().path_expr(vec![name]), args),
+            MacroFunctionStyle::MacroRules => {
+                let mut tts = vec![];
+                for (i, arg) in args.into_iter().enumerate() {
+                    if i > 0 {
+                        tts.push(TokenTree::token(token::Comma, DUMMY_SP));
+                    }
+                    let arg = token::Interpolated(Rc::new(Nonterminal::NtExpr(arg)));
+                    tts.push(TokenTree::token(arg, DUMMY_SP));
+                }
+                mk().mac_expr(mk().mac(vec![name], tts, MacDelimiter::Parenthesis))
+            }
+        });
+        if mac.is_unsafe {
+            val.set_unsafe();
+        }
+
+        if ctx.is_unused() {
+            return self
+                .convert_side_effects_expr(ctx, val, "Function call expression is not supposed to be used")
+                .map(Some);
+        }
+
+        // Apply the conversions that the context of the expansion performs on
+        // the result of the macro body.
+        let expr_ty = self.ast_context[expr_id]
+            .kind
+            .get_qual_type()
+            .ok_or_else(|| format_err!("Invalid macro expansion type"))?;
+        if expr_ty.ctype == call.body_type {
+            Ok(Some(val))
+        } else {
+            self.convert_cast(ctx, CQualTypeId::new(call.body_type), expr_ty, val, None, None, None)
+                .map(Some)
+        }
+    }
+
+    /// Import the types in the signature of the function translated from the
+    /// macro `decl_id` into the module for `decl_file_id`.
+    pub fn import_macro_function_types(&self, decl_id: CDeclId, decl_file_id: FileId) {
+        if let Some(Some(mac)) = self.macro_functions.borrow().get(&decl_id) {
+            for &ty in &mac.signature {
+                self.import_type(ty, decl_file_id);
+            }
+        }
+    }
+
+    fn translate_macro_function(
+        &self,
+        decl_id: CDeclId,
+    ) -> Result<Option<MacroFunction>, TranslationError> {
+        let decl = &self.ast_context[decl_id];
+        let (name, parameters, replacements, arguments) = match decl.kind {
+            CDeclKind::MacroFunction {
+                ref name,
+                ref parameters,
+                ref replacements,
+                ref arguments,
+            } => (name, parameters, replacements, arguments),
+            _ => return Err(format_err!("Not a function-like macro: {:?}", decl_id).into()),
+        };
+        let rust_name = self
+            .renamer
+            .borrow_mut()
+            .get(&decl_id)
+            .ok_or_else(|| format_err!("Macro name not declared"))?;
+        trace!("Translating function-like macro {}", name);
+
+        // The macro body is translated out of the context of any expansion
+        let ctx = ExprContext {
+            used: true,
+            is_static: false,
+            is_const: false,
+            decay_ref: DecayRef::Default,
+            is_bitfield_write: false,
+            needs_address: false,
+            expecting_valistimpl: false,
+            ternary_needs_parens: false,
+            expanding_macro: Some(decl_id),
+        };
+
+        // Any macro we are in the middle of translating must not substitute
+        // its parameters into the expansions of this one, and the imports of
+        // a function belong to the module it is defined in.
+        let outer_params = self.macro_params.replace(HashMap::new());
+        let decl_file_id = self
+            .ast_context
+            .file_id(decl)
+            .filter(|file_id| *file_id != self.main_file);
+        let outer_file = if self.tcfg.reorganize_definitions {
+            self.cur_file.replace(decl_file_id)
+        } else {
+            *self.cur_file.borrow()
+        };
+
+        let result = self.with_scope(|| {
+            let param_names: Vec<String> = parameters
+                .iter()
+                .map(|param| self.renamer.borrow_mut().pick_name(param))
+                .collect();
+
+            let mut expansions = vec![];
+            for (&expansion, uses) in replacements.iter().zip(arguments) {
+                match self.translate_macro_expansion(ctx, expansion, uses, &param_names) {
+                    Ok(Some(expansion)) => expansions.push(expansion),
+                    Ok(None) => {}
+                    Err(e) => info!("Could not translate expansion of macro {}: {}", name, e),
+                }
+            }
+
+            self.macro_function_item(decl_id, &rust_name, &param_names, expansions)
+        });
+
+        *self.cur_file.borrow_mut() = outer_file;
+        self.macro_params.replace(outer_params);
+        result
+    }
+
+    /// Translate the body of a single expansion of a function-like macro,
+    /// substituting the macro parameters for the arguments. Returns `None` if
+    /// the expansion cannot be replaced by a call.
+    fn translate_macro_expansion(
+        &self,
+        ctx: ExprContext,
+        expansion: CExprId,
+        uses: &[Vec<CExprId>],
+        param_names: &[String],
+    ) -> Result<Option<MacroExpansion>, TranslationError> {
+        if uses.len() != param_names.len() {
+            return Ok(None);
+        }
+
+        // Strip conversions that the context of the expansion applies to the
+        // value of the macro body.
+        let mut body = expansion;
+        while let CExprKind::ImplicitCast(_, subexpr, _, _, _) = self.ast_context[body].kind {
+            if !self.ast_context[subexpr].kind.lrvalue().is_rvalue() {
+                break;
+            }
+            body = subexpr;
+        }
+        if !self.ast_context[body].kind.lrvalue().is_rvalue() {
+            return Ok(None);
+        }
+        let body_type = self.ast_context[body]
+            .kind
+            .get_type()
+            .ok_or_else(|| format_err!("Invalid macro body type"))?;
+
+        // Arguments are evaluated once, before the body, so they must not have
+        // side effects. Every use of a parameter must also agree on its type.
+        let mut arguments = vec![];
+        let mut param_types = vec![];
+        for param_uses in uses {
+            let arg = match param_uses.first() {
+                Some(&arg) => arg,
+                None => return Ok(None),
+            };
+            let arg_ty = self.ast_context[arg]
+                .kind
+                .get_type()
+                .ok_or_else(|| format_err!("Invalid macro argument type"))?;
+            for &param_use in param_uses {
+                let kind = &self.ast_context[param_use].kind;
+                let use_ty = kind
+                    .get_type()
+                    .ok_or_else(|| format_err!("Invalid macro argument type"))?;
+                if !kind.lrvalue().is_rvalue()
+                    || !self.ast_context.is_expr_pure(param_use)
+                    || self.ast_context.resolve_type(use_ty).kind
+                        != self.ast_context.resolve_type(arg_ty).kind
+                {
+                    return Ok(None);
+                }
+            }
+            arguments.push(arg);
+            param_types.push(arg_ty);
+        }
+
+        // The body must not depend on the function it was expanded in
+        let arg_nodes: HashSet<SomeId> = uses
+            .iter()
+            .flatten()
+            .flat_map(|&arg| DFExpr::new(&self.ast_context, arg.into()))
+            .collect();
+        let mut body_decls = HashSet::new();
+        let mut referenced = vec![];
+        for node in DFExpr::new(&self.ast_context, body.into()) {
+            if arg_nodes.contains(&node) {
+                continue;
+            }
+            match node {
+                SomeId::Expr(expr_id) => match self.ast_context[expr_id].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => referenced.push(decl_id),
+                    CExprKind::Predefined(..)
+                    | CExprKind::VAArg(..)
+                    | CExprKind::AddrLabel(..) => return Ok(None),
+                    _ => {}
+                },
+                SomeId::Stmt(stmt_id) => match self.ast_context[stmt_id].kind {
+                    CStmtKind::Return(..)
+                    | CStmtKind::Goto(..)
+                    | CStmtKind::IndirectGoto(..)
+                    | CStmtKind::Label(..)
+                    | CStmtKind::Break
+                    | CStmtKind::Continue => return Ok(None),
+                    CStmtKind::Decls(ref decls) => body_decls.extend(decls.iter().cloned()),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        let mut globals = vec![];
+        for decl_id in referenced {
+            if body_decls.contains(&decl_id) {
+                continue;
+            }
+            match self.ast_context[decl_id].kind {
+                CDeclKind::Variable { .. } if !self.ast_context.c_decls_top.contains(&decl_id) => {
+                    return Ok(None)
+                }
+                CDeclKind::Variable { .. }
+                | CDeclKind::Function { .. }
+                | CDeclKind::EnumConstant { .. } => globals.push(decl_id),
+                _ => {}
+            }
+        }
+
+        // Translate the body with each argument replaced by its parameter
+        {
+            let mut macro_params = self.macro_params.borrow_mut();
+            for (param_uses, param) in uses.iter().zip(param_names) {
+                for &param_use in param_uses {
+                    macro_params.insert(param_use, param.clone());
+                }
+            }
+        }
+        let translation = self.with_scope(|| self.convert_expr(ctx, body));
+        {
+            let mut macro_params = self.macro_params.borrow_mut();
+            for param_use in uses.iter().flatten() {
+                macro_params.remove(param_use);
+            }
+        }
+        let translation = translation?;
+
+        let mut text = pprust::expr_to_string(&translation.clone().to_expr());
+        if translation.is_unsafe() {
+            text.insert_str(0, "unsafe ");
+        }
+
+        Ok(Some(MacroExpansion {
+            expansion,
+            call: MacroCall {
+                arguments,
+                body_type,
+                globals,
+            },
+            param_types,
+            translation,
+            text,
+        }))
+    }
+
+    /// Build the definition of a function-like macro from the expansions that
+    /// share the most common translation. Returns `None` if there are no
+    /// such expansions.
+    fn macro_function_item(
+        &self,
+        decl_id: CDeclId,
+        name: &str,
+        param_names: &[String],
+        expansions: Vec<MacroExpansion>,
+    ) -> Result<Option<MacroFunction>, TranslationError> {
+        let mut counts: IndexMap<&str, usize> = IndexMap::new();
+        for expansion in &expansions {
+            *counts.entry(&expansion.text).or_insert(0) += 1;
+        }
+        // Prefer the first translation seen if there is a tie
+        let canonical = match counts.iter().rev().max_by_key(|&(_, count)| *count) {
+            Some((text, _)) => text.to_string(),
+            None => return Ok(None),
+        };
+
+        let mut expansions = expansions
+            .into_iter()
+            .filter(|expansion| expansion.text == canonical);
+        let first = expansions.next().unwrap();
+        let mut calls = IndexMap::new();
+        let mut same_signature = true;
+        for expansion in expansions {
+            same_signature &= self.same_resolved_type(expansion.call.body_type, first.call.body_type)
+                && expansion
+                    .param_types
+                    .iter()
+                    .zip(&first.param_types)
+                    .all(|(&ty1, &ty2)| self.same_resolved_type(ty1, ty2));
+            calls.insert(expansion.expansion, expansion.call);
+        }
+
+        let span = self.get_span(SomeId::Decl(decl_id)).unwrap_or(DUMMY_SP);
+        let is_unsafe = first.translation.is_unsafe();
+        let mut signature = vec![];
+        let (style, item) = if same_signature {
+            let mut args = vec![];
+            for (param, &ty) in param_names.iter().zip(&first.param_types) {
+                args.push(mk().arg(self.convert_type(ty)?, mk().ident_pat(param)));
+                signature.push(ty);
+            }
+            let ret = if let CTypeKind::Void = self.ast_context.resolve_type(first.call.body_type).kind {
+                FunctionRetTy::Default(DUMMY_SP)
+            } else {
+                signature.push(first.call.body_type);
+                FunctionRetTy::Ty(self.convert_type(first.call.body_type)?)
+            };
+            let decl = mk().fn_decl(args, ret);
+
+            let mut mk_ = mk().span(span).pub_().single_attr("inline");
+            if is_unsafe {
+                mk_ = mk_.unsafe_();
+            }
+            let item = mk_.fn_item(name, decl, first.translation.to_block());
+            (MacroFunctionStyle::Function, item)
+        } else {
+            let item = self.macro_rules_item(span, name, param_names, first.translation.to_expr());
+            (MacroFunctionStyle::MacroRules, item)
+        };
+        calls.insert(first.expansion, first.call);
+
+        Ok(Some(MacroFunction {
+            style,
+            is_unsafe,
+            item,
+            signature,
+            calls,
+        }))
+    }
+
+    /// Build `macro_rules! name { ($a:expr, ...) => { { let a = $a; ...; body } } }`
+    fn macro_rules_item(
+        &self,
+        span: Span,
+        name: &str,
+        param_names: &[String],
+        body: P<Expr>,
+    ) -> P<Item> {
+        let tok = |kind| TokenTree::token(kind, DUMMY_SP);
+        let ident = |name: &str| tok(token::Ident(name.into_symbol(), false));
+
+        let mut tts = vec![tok(token::OpenDelim(DelimToken::Paren))];
+        for (i, param) in param_names.iter().enumerate() {
+            if i > 0 {
+                tts.push(tok(token::Comma));
+            }
+            tts.push(tok(token::Dollar));
+            tts.push(ident(param));
+            tts.push(tok(token::Colon));
+            tts.push(ident("expr"));
+        }
+        tts.push(tok(token::CloseDelim(DelimToken::Paren)));
+        tts.push(tok(token::FatArrow));
+
+        tts.push(tok(token::OpenDelim(DelimToken::Brace)));
+        tts.push(tok(token::OpenDelim(DelimToken::Brace)));
+        for param in param_names {
+            tts.push(ident("let"));
+            tts.push(ident(param));
+            tts.push(tok(token::Eq));
+            tts.push(tok(token::Dollar));
+            tts.push(ident(param));
+            tts.push(tok(token::Semi));
+        }
+        tts.push(tok(token::Interpolated(Rc::new(Nonterminal::NtExpr(body)))));
+        tts.push(tok(token::CloseDelim(DelimToken::Brace)));
+        tts.push(tok(token::CloseDelim(DelimToken::Brace)));
+
+        mk().span(span).macro_def_item(name, tts)
+    }
+
+    fn same_resolved_type(&self, ty1: CTypeId, ty2: CTypeId) -> bool {
+        self.ast_context.resolve_type(ty1).kind == self.ast_context.resolve_type(ty2).kind
+    }
+}
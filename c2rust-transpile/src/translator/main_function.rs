@@ -4,7 +4,6 @@
 //! Rust.
 
 use super::*;
-use syntax::parse::token::{self, TokenKind};
 
 impl<'c> Translation<'c> {
     pub fn convert_main(&self, main_id: CDeclId) -> Result<P<Item>, TranslationError> {
@@ -25,8 +24,6 @@ impl<'c> Translation<'c> {
                 ))?,
             };
 
-            let decl = mk().fn_decl(vec![], FunctionRetTy::Default(DUMMY_SP));
-
             let main_fn_name = self
                 .renamer
                 .borrow()
@@ -35,8 +32,51 @@ impl<'c> Translation<'c> {
             let main_fn = mk().path_expr(vec![main_fn_name]);
 
             let exit_fn = mk().path_expr(vec!["", "std", "process", "exit"]);
-            let args_fn = mk().path_expr(vec!["", "std", "env", "args"]);
-            let vars_fn = mk().path_expr(vec!["", "std", "env", "vars"]);
+            let args_fn = mk().path_expr(vec!["", "std", "env", "args_os"]);
+            let vars_fn = mk().path_expr(vec!["", "std", "env", "vars_os"]);
+            // Arguments and environment variables are passed on as raw bytes
+            // on Unix, so that those which aren't valid UTF-8 survive. Other
+            // platforms have no such conversion, so they get lossy UTF-8.
+            let os_bytes = |name: &str, os_string: &str, mutable: bool| -> Vec<Stmt> {
+                let bytes_ty = || {
+                    mk().path_ty(vec![mk().path_segment_with_args(
+                        "Vec",
+                        mk().angle_bracketed_args(vec![mk().path_ty(vec!["u8"])]),
+                    )])
+                };
+                let pat = |builder: Builder| {
+                    if mutable {
+                        builder.mutbl().ident_pat(name)
+                    } else {
+                        builder.ident_pat(name)
+                    }
+                };
+                let into_vec = mk().call_expr(
+                    mk().path_expr(vec!["", "std", "os", "unix", "ffi", "OsStringExt", "into_vec"]),
+                    vec![mk().ident_expr(os_string)],
+                );
+                let lossy = mk().method_call_expr(
+                    mk().method_call_expr(
+                        mk().method_call_expr(
+                            mk().ident_expr(os_string),
+                            "to_string_lossy",
+                            vec![] as Vec<P<Expr>>,
+                        ),
+                        "into_owned",
+                        vec![] as Vec<P<Expr>>,
+                    ),
+                    "into_bytes",
+                    vec![] as Vec<P<Expr>>,
+                );
+                vec![
+                    mk().local_stmt(P(mk()
+                        .single_attr("cfg(unix)")
+                        .local(pat(mk()), Some(bytes_ty()), Some(into_vec)))),
+                    mk().local_stmt(P(mk()
+                        .single_attr("cfg(not(unix))")
+                        .local(pat(mk()), Some(bytes_ty()), Some(lossy)))),
+                ]
+            };
 
             let no_args: Vec<P<Expr>> = vec![];
 
@@ -45,6 +85,36 @@ impl<'c> Translation<'c> {
 
             let n = parameters.len();
 
+            // Check `main` has the right form
+            if n > 3 {
+                Err(format_err!(
+                    "Main function should have at most 3 parameters, not {}.",
+                    n
+                ))?;
+            };
+
+            let param_ty = |index: usize, param: &str| -> Result<P<Ty>, TranslationError> {
+                match self.ast_context.index(parameters[index]).kind {
+                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
+                    _ => Err(format_err!(
+                        "Cannot find type of '{}' argument in main function",
+                        param
+                    )
+                    .into()),
+                }
+            };
+
+            if n == 1 {
+                // Only `argc`, so the arguments only need counting
+
+                let argc = mk().method_call_expr(
+                    mk().call_expr(args_fn.clone(), no_args.clone()),
+                    "len",
+                    no_args.clone(),
+                );
+                main_args.push(mk().cast_expr(argc, param_ty(0, "argc")?));
+            }
+
             if n >= 2 {
                 // `argv` and `argc`
 
@@ -60,27 +130,29 @@ impl<'c> Translation<'c> {
                         mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![] as Vec<P<Expr>>),
                     ),
                 ))));
+                let mut arg_stmts = os_bytes("arg", "arg", false);
+                arg_stmts.push(mk().semi_stmt(mk().method_call_expr(
+                    mk().path_expr(vec!["args"]),
+                    "push",
+                    vec![mk().method_call_expr(
+                        mk().method_call_expr(
+                            mk().call_expr(
+                                mk().path_expr(vec!["", "std", "ffi", "CString", "new"]),
+                                vec![mk().path_expr(vec!["arg"])],
+                            ),
+                            "expect",
+                            vec![mk().lit_expr(
+                                mk().str_lit("Failed to convert argument into CString."),
+                            )],
+                        ),
+                        "into_raw",
+                        vec![] as Vec<P<Expr>>,
+                    )],
+                )));
                 stmts.push(mk().semi_stmt(mk().for_expr(
                     mk().ident_pat("arg"),
                     mk().call_expr(args_fn, vec![] as Vec<P<Expr>>),
-                    mk().block(vec![mk().semi_stmt(mk().method_call_expr(
-                        mk().path_expr(vec!["args"]),
-                        "push",
-                        vec![mk().method_call_expr(
-                            mk().method_call_expr(
-                                mk().call_expr(
-                                    mk().path_expr(vec!["", "std", "ffi", "CString", "new"]),
-                                    vec![mk().path_expr(vec!["arg"])],
-                                ),
-                                "expect",
-                                vec![mk().lit_expr(
-                                    mk().str_lit("Failed to convert argument into CString."),
-                                )],
-                            ),
-                            "into_raw",
-                            vec![] as Vec<P<Expr>>,
-                        )],
-                    ))]),
+                    mk().block(arg_stmts),
                     None as Option<Ident>,
                 )));
                 stmts.push(mk().semi_stmt(mk().method_call_expr(
@@ -92,18 +164,8 @@ impl<'c> Translation<'c> {
                     )],
                 )));
 
-                let argc_ty: P<Ty> = match self.ast_context.index(parameters[0]).kind {
-                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
-                    _ => Err(TranslationError::generic(
-                        "Cannot find type of 'argc' argument in main function",
-                    )),
-                }?;
-                let argv_ty: P<Ty> = match self.ast_context.index(parameters[1]).kind {
-                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
-                    _ => Err(TranslationError::generic(
-                        "Cannot find type of 'argv' argument in main function",
-                    )),
-                }?;
+                let argc_ty = param_ty(0, "argc")?;
+                let argv_ty = param_ty(1, "argv")?;
 
                 let args = mk().ident_expr("args");
                 let argc = mk().binary_expr(
@@ -132,50 +194,41 @@ impl<'c> Translation<'c> {
                         mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![] as Vec<P<Expr>>),
                     ),
                 ))));
-                let var_name_ident = mk().ident("var_name");
-                let var_value_ident = mk().ident("var_value");
+                // `name=value`
+                let mut var_stmts = os_bytes("var", "var_name", true);
+                var_stmts.extend(os_bytes("var_value", "var_value", false));
+                var_stmts.push(mk().semi_stmt(mk().method_call_expr(
+                    mk().path_expr(vec!["var"]),
+                    "push",
+                    vec![mk().lit_expr(mk().byte_lit(b'='))],
+                )));
+                var_stmts.push(mk().semi_stmt(mk().method_call_expr(
+                    mk().path_expr(vec!["var"]),
+                    "extend_from_slice",
+                    vec![mk().addr_of_expr(mk().path_expr(vec!["var_value"]))],
+                )));
+                var_stmts.push(mk().semi_stmt(mk().method_call_expr(
+                    mk().path_expr(vec!["vars"]),
+                    "push",
+                    vec![mk().method_call_expr(
+                        mk().method_call_expr(
+                            mk().call_expr(
+                                mk().path_expr(vec!["", "std", "ffi", "CString", "new"]),
+                                vec![mk().path_expr(vec!["var"])],
+                            ),
+                            "expect",
+                            vec![mk().lit_expr(
+                                mk().str_lit("Failed to convert environment variable into CString."),
+                            )],
+                        ),
+                        "into_raw",
+                        vec![] as Vec<P<Expr>>,
+                    )],
+                )));
                 stmts.push(mk().semi_stmt(mk().for_expr(
                     mk().tuple_pat(vec![mk().ident_pat("var_name"), mk().ident_pat("var_value")]),
                     mk().call_expr(vars_fn, vec![] as Vec<P<Expr>>),
-                    mk().block(vec![
-                        mk().local_stmt(P(mk().local(
-                            mk().ident_pat("var"),
-                            Some(mk().path_ty(vec!["String"])),
-                            Some(mk().mac_expr(mk().mac(
-                                vec!["format"],
-                                vec![
-                                    token::Interpolated(Rc::new(Nonterminal::NtExpr(mk().lit_expr(mk().str_lit("{}={}"))))),
-                                    token::Comma,
-                                    TokenKind::Ident(var_name_ident.name, var_name_ident.is_raw_guess()),
-                                    token::Comma,
-                                    TokenKind::Ident(var_value_ident.name, var_value_ident.is_raw_guess())
-                                ].into_iter()
-                                    .map(|tk| TokenTree::token(tk, DUMMY_SP))
-                                    .collect::<TokenStream>(),
-                                MacDelimiter::Parenthesis,
-                            )))
-                        ))),
-                        mk().semi_stmt(mk().method_call_expr(
-                            mk().path_expr(vec!["vars"]),
-                            "push",
-                            vec![
-                                mk().method_call_expr(
-                                    mk().method_call_expr(
-                                        mk().call_expr(
-                                            mk().path_expr(vec!["","std","ffi","CString","new"]),
-                                            vec![mk().path_expr(vec!["var"])],
-                                        ),
-                                        "expect",
-                                        vec![mk().lit_expr(
-                                            mk().str_lit("Failed to convert environment variable into CString.")
-                                        )],
-                                    ),
-                                    "into_raw",
-                                    vec![] as Vec<P<Expr>>,
-                                )
-                            ],
-                        ))
-                    ]),
+                    mk().block(var_stmts),
                     None as Option<Ident>,
                 )));
                 stmts.push(mk().semi_stmt(mk().method_call_expr(
@@ -187,27 +240,43 @@ impl<'c> Translation<'c> {
                     )],
                 )));
 
-                let envp_ty: P<Ty> = match self.ast_context.index(parameters[2]).kind {
-                    CDeclKind::Variable { ref typ, .. } => self.convert_type(typ.ctype),
-                    _ => Err(TranslationError::generic(
-                        "Cannot find type of 'envp' argument in main function",
-                    )),
-                }?;
+                let envp_ty = param_ty(2, "envp")?;
 
                 let envp = mk().method_call_expr(mk().ident_expr("vars"), "as_mut_ptr", no_args);
 
                 main_args.push(mk().cast_expr(envp, envp_ty));
             }
 
-            // Check `main` has the right form
-            if n != 0 && n != 2 && n != 3 {
-                Err(format_err!(
-                    "Main function should have 0, 2, or 3 parameters, not {}.",
-                    n
-                ))?;
-            };
+            let ret_ty = if self.tcfg.main_exit_code {
+                // Returning the exit code rather than calling `exit` lets
+                // destructors run
+                let exit_code = vec!["", "std", "process", "ExitCode"];
+                let call_main = mk().call_expr(main_fn, main_args);
+                let unsafe_block = mk().unsafe_().block(vec![mk().expr_stmt(call_main)]);
 
-            if let CTypeKind::Void = ret {
+                let exit_code_expr = if let CTypeKind::Void = ret {
+                    stmts.push(mk().semi_stmt(mk().block_expr(unsafe_block)));
+
+                    let mut success = exit_code.clone();
+                    success.push("SUCCESS");
+                    mk().path_expr(success)
+                } else {
+                    stmts.push(mk().local_stmt(P(mk().local(
+                        mk().ident_pat("status"),
+                        None as Option<P<Ty>>,
+                        Some(mk().block_expr(unsafe_block)),
+                    ))));
+
+                    // Only the low byte of the status reaches the parent process
+                    let mut from = exit_code.clone();
+                    from.push("from");
+                    let status = mk().cast_expr(mk().ident_expr("status"), mk().path_ty(vec!["u8"]));
+                    mk().call_expr(mk().path_expr(from), vec![status])
+                };
+                stmts.push(mk().expr_stmt(exit_code_expr));
+
+                FunctionRetTy::Ty(mk().path_ty(exit_code))
+            } else if let CTypeKind::Void = ret {
                 let call_main = mk().call_expr(main_fn, main_args);
                 let unsafe_block = mk().unsafe_().block(vec![mk().expr_stmt(call_main)]);
 
@@ -217,6 +286,8 @@ impl<'c> Translation<'c> {
                 let call_exit = mk().call_expr(exit_fn, vec![exit_arg]);
 
                 stmts.push(mk().semi_stmt(call_exit));
+
+                FunctionRetTy::Default(DUMMY_SP)
             } else {
                 let call_main = mk().cast_expr(
                     mk().call_expr(main_fn, main_args),
@@ -227,12 +298,19 @@ impl<'c> Translation<'c> {
                 let unsafe_block = mk().unsafe_().block(vec![mk().expr_stmt(call_exit)]);
 
                 stmts.push(mk().expr_stmt(mk().block_expr(unsafe_block)));
+
+                FunctionRetTy::Default(DUMMY_SP)
             };
 
+            let decl = mk().fn_decl(vec![], ret_ty);
             let block = mk().block(stmts);
-            let main_attributes = self.mk_cross_check(mk(), vec!["none"]);
-            let main_attributes = main_attributes.single_attr("main");
-            self.use_feature("main");
+            let mut main_attributes = self.mk_cross_check(mk(), vec!["none"]);
+            // `#[main]` is gone from the toolchains that have `ExitCode::from`,
+            // so such a `main` has to be the root of its crate
+            if !self.tcfg.main_exit_code {
+                main_attributes = main_attributes.single_attr("main");
+                self.use_feature("main");
+            }
             Ok(main_attributes.pub_().fn_item("main", decl, block))
         } else {
             Err(TranslationError::generic(
@@ -33,8 +33,9 @@ use crate::c_ast;
 use crate::c_ast::iterators::{DFExpr, SomeId};
 use crate::c_ast::*;
 use crate::cfg;
-use crate::convert_type::TypeConverter;
+use crate::convert_type::{x86_vector_type, TypeConverter};
 use crate::renamer::Renamer;
+use crate::report::{ReportRecord, UnitReport};
 use crate::with_stmts::WithStmts;
 use crate::TranspilerConfig;
 use c2rust_ast_exporter::clang_ast::LRValue;
@@ -43,10 +44,16 @@ mod assembly;
 mod atomics;
 mod builtins;
 mod comments;
+mod complex;
+mod enums;
+mod layout;
 mod literals;
+mod long_double;
+mod macros;
 mod main_function;
 mod named_references;
 mod operators;
+mod setjmp;
 mod simd;
 mod structs;
 mod variadic;
@@ -103,6 +110,65 @@ pub enum ReplaceMode {
     Extern,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnumStyle {
+    /// Translate enums into type aliases of their integral type, with a
+    /// `const` for each enum constant
+    Constants,
+    /// Translate enums into Rust `enum`s where their usage allows it
+    Rust,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SignedOverflow {
+    /// Use the plain Rust operators, which panic on overflow in debug builds
+    /// and wrap around in release builds
+    Plain,
+    /// Wrap around in all builds
+    Wrap,
+    /// Panic with the C source location of the overflowing operation
+    Checked,
+    /// Abort the process, like `-ftrapv`
+    Trap,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AsmStyle {
+    /// Emit the LLVM-style `asm!("..." : outputs : inputs : clobbers)`
+    /// syntax behind the `asm` feature gate
+    Llvm,
+    /// Rewrite GCC templates and constraints into `asm!` with
+    /// `in(reg)`/`out(reg)` operands and `options(...)`
+    Operands,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LongDouble {
+    /// Use `f64`, which is exact where `long double` is a plain `double`
+    F64,
+    /// Use the 80-bit x87 extended precision type from `c2rust-x87`
+    X87,
+    /// Use the IEEE quadruple precision type from the `f128` crate
+    F128,
+}
+
+impl LongDouble {
+    /// The default translation for the target's `long double` format. x87
+    /// extended precision is only the default on x86-64, since `c2rust-x87`
+    /// only has its layout; other x87 targets such as i686 use `f128`.
+    pub fn for_target(format: LongDoubleFormat, target_triple: &str) -> Self {
+        match format {
+            LongDoubleFormat::IEEEDoubleLongDouble => LongDouble::F64,
+            LongDoubleFormat::X87LongDouble if target_triple.starts_with("x86_64") => {
+                LongDouble::X87
+            }
+            LongDoubleFormat::X87LongDouble
+            | LongDoubleFormat::IEEEQuadLongDouble
+            | LongDoubleFormat::OtherLongDouble => LongDouble::F128,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct ExprContext {
     used: bool,
@@ -215,6 +281,8 @@ pub struct FunContext {
     va_list_arg_name: Option<String>,
     /// The va_list decls that are either `va_start`ed or `va_copy`ed.
     va_list_decl_ids: Option<IndexSet<CDeclId>>,
+    /// The C return type of the function, if it returns
+    ret_type: Option<CQualTypeId>,
 }
 
 impl FunContext {
@@ -223,13 +291,15 @@ impl FunContext {
             name: None,
             va_list_arg_name: None,
             va_list_decl_ids: None,
+            ret_type: None,
         }
     }
 
-    pub fn enter_new(&mut self, fn_name: &str) {
+    pub fn enter_new(&mut self, fn_name: &str, ret_type: Option<CQualTypeId>) {
         self.name = Some(fn_name.to_string());
         self.va_list_arg_name = None;
         self.va_list_decl_ids = None;
+        self.ret_type = ret_type;
     }
 
     pub fn get_name(&self) -> &str {
@@ -258,6 +328,20 @@ pub struct Translation<'c> {
     function_context: RefCell<FunContext>,
     potential_flexible_array_members: RefCell<IndexSet<CDeclId>>,
     macro_types: RefCell<IndexMap<CDeclId, CTypeId>>,
+    macro_functions: RefCell<IndexMap<CDeclId, Option<Rc<macros::MacroFunction>>>>,
+    // While translating the body of a function-like macro, maps the
+    // expressions substituted for its parameters to the parameter names
+    macro_params: RefCell<HashMap<CExprId, String>>,
+    // macro_rules! definitions, which must precede all of their uses
+    macro_items: RefCell<Vec<P<Item>>>,
+    // Integer tags standing in for the addresses of labels used as values
+    label_tags: RefCell<HashMap<CLabelId, u64>>,
+    // Declarations that were skipped or replaced, for `--report`
+    report: RefCell<UnitReport>,
+    // Enums translated into Rust enums rather than integer types
+    rust_enums: IndexSet<CEnumId>,
+    // Whether functions may be unwound through by a translated `longjmp`
+    may_longjmp: bool,
 
     // Comment support
     pub comment_context: CommentContext, // Incoming comments
@@ -497,7 +581,7 @@ pub fn translate(
     ast_context: TypedAstContext,
     tcfg: &TranspilerConfig,
     main_file: PathBuf,
-) -> (String, PragmaVec, CrateSet) {
+) -> (String, PragmaVec, CrateSet, UnitReport) {
     let mut t = Translation::new(ast_context, tcfg, main_file.as_path());
     let ctx = ExprContext {
         used: true,
@@ -529,7 +613,16 @@ pub fn translate(
 
     // Headers often pull in declarations that are unused;
     // we simplify the translator output by omitting those.
-    t.ast_context.prune_unused_decls();
+    if tcfg.bindings_only {
+        t.ast_context.prune_to_bindings();
+    } else {
+        t.ast_context.prune_unused_decls();
+    }
+
+    if tcfg.enum_style == EnumStyle::Rust {
+        t.rust_enums = enums::rust_enum_candidates(&t.ast_context, t.main_file);
+    }
+    t.may_longjmp = setjmp::uses_jmp(&t.ast_context);
 
     enum Name<'a> {
         VarName(&'a str),
@@ -545,9 +638,12 @@ pub fn translate(
         }
     }
 
-    // Used for testing; so that we don't overlap with C function names
+    // Used for testing; so that we don't overlap with C function names. Bindings
+    // have to refer to the C symbols themselves, so they are never prefixed.
     if let Some(ref prefix) = t.tcfg.prefix_function_names {
-        prefix_names(&mut t, prefix);
+        if !t.tcfg.bindings_only {
+            prefix_names(&mut t, prefix);
+        }
     }
 
     // `with_globals` sets up a thread-local variable required by the syntax crate.
@@ -637,6 +733,9 @@ pub fn translate(
                     Name::VarName(ident)
                 }
                 CDeclKind::MacroObject { ref name, .. } => Name::VarName(name),
+                CDeclKind::MacroFunction { ref name, .. } if t.tcfg.translate_fn_macros => {
+                    Name::VarName(name)
+                }
                 _ => Name::NoName,
             };
             match decl_name {
@@ -663,6 +762,7 @@ pub fn translate(
                 if t.tcfg.reorganize_definitions {
                     *t.cur_file.borrow_mut() = decl_file_id;
                 }
+                t.report.borrow_mut().add_declaration();
                 match t.convert_decl(ctx, decl_id) {
                     Ok(ConvertedDecl::Item(item)) => {
                         t.insert_item(item, decl);
@@ -677,6 +777,7 @@ pub fn translate(
                     }
                     Ok(ConvertedDecl::NoItem) => {}
                     Err(e) => {
+                        t.report_decl(decl_id, &e, false);
                         let ref k = t.ast_context.get_decl(&decl_id).map(|x| &x.kind);
                         let msg = format!("Skipping declaration {:?} due to error: {}", k, e);
                         translate_failure(&t.tcfg, &msg);
@@ -718,6 +819,8 @@ pub fn translate(
                 CDeclKind::Function { is_implicit, .. } => !is_implicit,
                 CDeclKind::Variable { .. } => true,
                 CDeclKind::MacroObject { .. } => tcfg.translate_const_macros,
+                CDeclKind::MacroFunction { .. } => tcfg.translate_fn_macros,
+                CDeclKind::StaticAssert { .. } => true,
                 _ => false,
             };
             if needs_export {
@@ -730,6 +833,7 @@ pub fn translate(
                 {
                     *t.cur_file.borrow_mut() = decl_file_id;
                 }
+                t.report.borrow_mut().add_declaration();
                 match t.convert_decl(ctx, *top_id) {
                     Ok(ConvertedDecl::Item(item)) => {
                         t.insert_item(item, decl);
@@ -744,6 +848,7 @@ pub fn translate(
                     }
                     Ok(ConvertedDecl::NoItem) => {}
                     Err(e) => {
+                        t.report_decl(*top_id, &e, false);
                         let ref decl = t.ast_context.get_decl(top_id);
                         let msg = match decl {
                             Some(decl) => {
@@ -772,10 +877,12 @@ pub fn translate(
         }
 
         // Add the main entry point
-        if let Some(main_id) = t.ast_context.c_main {
+        if let Some(main_id) = t.ast_context.c_main.filter(|_| !tcfg.bindings_only) {
+            t.report.borrow_mut().add_declaration();
             match t.convert_main(main_id) {
                 Ok(item) => t.items.borrow_mut()[&t.main_file].add_item(item),
                 Err(e) => {
+                    t.report_decl(main_id, &e, false);
                     let msg = format!("Failed to translate main: {}", e);
                     translate_failure(&t.tcfg, &msg)
                 }
@@ -793,6 +900,7 @@ pub fn translate(
 
         let pragmas = t.get_pragmas();
         let crates = t.extern_crates.borrow().clone();
+        let report = t.report.replace(UnitReport::new(main_file.clone())).finish();
 
         let mut mod_items: Vec<P<Item>> = Vec::new();
 
@@ -864,6 +972,12 @@ pub fn translate(
         let translation = pprust::to_string_with_comments(comments, |s| {
             print_header(s, &t, t.tcfg.is_binary(main_file.as_path()));
 
+            // macro_rules! are textually scoped, so they must come before
+            // every module that may use them
+            for macro_item in t.macro_items.borrow().iter() {
+                s.print_item(macro_item);
+            }
+
             for mod_item in mod_items {
                 s.print_item(&*mod_item);
             }
@@ -893,7 +1007,7 @@ pub fn translate(
 
             s.print_remaining_comments();
         });
-        (translation, pragmas, crates)
+        (translation, pragmas, crates, report)
     })
 }
 
@@ -1086,6 +1200,12 @@ impl<'c> Translation<'c> {
         if tcfg.translate_valist {
             type_converter.translate_valist = true
         }
+        type_converter.long_double = tcfg
+            .long_double
+            .unwrap_or_else(|| {
+                LongDouble::for_target(ast_context.long_double_format, &ast_context.target_triple)
+            });
+        type_converter.fixed_width_ints = tcfg.fixed_width_ints;
 
         let main_file = ast_context.find_file_id(main_file).unwrap_or(0);
         let items = indexmap!{main_file => ItemStore::new()};
@@ -1112,6 +1232,13 @@ impl<'c> Translation<'c> {
             function_context: RefCell::new(FunContext::new()),
             potential_flexible_array_members: RefCell::new(IndexSet::new()),
             macro_types: RefCell::new(IndexMap::new()),
+            macro_functions: RefCell::new(IndexMap::new()),
+            macro_params: RefCell::new(HashMap::new()),
+            macro_items: RefCell::new(Vec::new()),
+            label_tags: RefCell::new(HashMap::new()),
+            report: RefCell::new(UnitReport::new(main_file.to_owned())),
+            rust_enums: IndexSet::new(),
+            may_longjmp: false,
             comment_context,
             comment_store: RefCell::new(CommentStore::new()),
             spans: HashMap::new(),
@@ -1132,6 +1259,24 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Record in the translation report that `decl_id` could not be
+    /// translated, or was replaced with an extern declaration.
+    fn report_decl(&self, decl_id: CDeclId, error: &TranslationError, replaced_with_extern: bool) {
+        let decl = match self.ast_context.get_decl(&decl_id) {
+            Some(decl) => decl,
+            None => return,
+        };
+        let decl_loc = self.ast_context.display_loc(&decl.loc);
+        let record = ReportRecord::new(
+            error.loc().or(decl_loc.as_ref()),
+            decl.kind.get_name().cloned(),
+            (&decl.kind).into(),
+            error,
+            replaced_with_extern,
+        );
+        self.report.borrow_mut().add_record(record);
+    }
+
     /// Called when translation makes use of a language feature that will require a feature-gate.
     pub fn use_feature(&self, feature: &'static str) {
         self.features.borrow_mut().insert(feature);
@@ -1170,6 +1315,24 @@ impl<'c> Translation<'c> {
         self.panic_or_err_helper(msg, self.tcfg.panic_on_translator_failure)
     }
 
+    /// Number the address-taken labels of a function body, starting from 1 so that a null
+    /// label pointer never matches a real label.
+    pub fn assign_label_tags(&self, labels: &IndexSet<CLabelId>) {
+        let mut label_tags = self.label_tags.borrow_mut();
+        for (i, &label) in labels.iter().enumerate() {
+            label_tags.insert(label, i as u64 + 1);
+        }
+    }
+
+    /// Get the integer tag standing in for the address of a label
+    pub fn label_tag(&self, label: CLabelId) -> Result<u64, TranslationError> {
+        self.label_tags
+            .borrow()
+            .get(&label)
+            .cloned()
+            .ok_or_else(|| format_err!("Address of label {:?} outside of its function", label).into())
+    }
+
     pub fn panic(&self, msg: &str) -> P<Expr> {
         self.panic_or_err_helper(msg, true)
     }
@@ -1238,7 +1401,7 @@ impl<'c> Translation<'c> {
 
     /// The purpose of this function is to decide on whether or not a static initializer's
     /// translation is able to be compiled as a valid rust static initializer
-    fn static_initializer_is_uncompilable(&self, expr_id: Option<CExprId>, qtype: CQualTypeId) -> bool {
+    fn static_initializer_is_uncompilable(&self, expr_id: Option<CExprId>) -> bool {
         use crate::c_ast::BinOp::{Add, Divide, Modulus, Multiply, Subtract};
         use crate::c_ast::CastKind::{IntegralToPointer, PointerToIntegral};
         use crate::c_ast::UnOp::{AddressOf, Negate};
@@ -1248,9 +1411,7 @@ impl<'c> Translation<'c> {
             None => return false,
         };
 
-        // The f128 crate doesn't currently provide a way to const initialize
-        // values, except for common mathematical constants
-        if let CTypeKind::LongDouble = self.ast_context[qtype.ctype].kind {
+        if self.long_double_initializer_is_uncompilable(expr_id) {
             return true;
         }
 
@@ -1453,6 +1614,7 @@ impl<'c> Translation<'c> {
                 manual_alignment,
                 max_field_alignment,
                 platform_byte_size,
+                platform_alignment,
                 ..
             } => {
                 let name = self
@@ -1544,6 +1706,7 @@ impl<'c> Translation<'c> {
                         .struct_item(inner_name.clone(), field_entries, false);
 
                     // https://github.com/rust-lang/rust/issues/33626
+                    let outer_name = name.clone();
                     let outer_ty = mk().path_ty(vec![name.clone()]);
                     let outer_reprs = vec![
                         simple_metaitem("C"),
@@ -1572,23 +1735,47 @@ impl<'c> Translation<'c> {
                         .call_attr("allow", vec!["dead_code", "non_upper_case_globals"])
                         .const_item(padding_name, padding_ty, padding_value);
 
-                    let structs = vec![outer_struct, inner_struct, padding_const];
+                    let mut structs = vec![outer_struct, inner_struct, padding_const];
+                    if self.tcfg.emit_layout_asserts {
+                        structs.extend(self.convert_layout_asserts(
+                            s,
+                            decl_id,
+                            &outer_name,
+                            Some(&inner_name),
+                            platform_byte_size,
+                            platform_alignment,
+                        ));
+                    }
                     Ok(ConvertedDecl::Items(structs))
                 } else {
                     assert!(!self.ast_context.has_inner_struct_decl(decl_id));
                     let repr_attr = mk().meta_item(vec!["repr"], MetaItemKind::List(reprs));
-                    Ok(ConvertedDecl::Item(
-                        mk().span(s)
-                            .pub_()
-                            .call_attr("derive", derives)
-                            .meta_item_attr(AttrStyle::Outer, repr_attr)
-                            .struct_item(name, field_entries, false),
-                    ))
+                    let struct_item = mk().span(s)
+                        .pub_()
+                        .call_attr("derive", derives)
+                        .meta_item_attr(AttrStyle::Outer, repr_attr)
+                        .struct_item(name.clone(), field_entries, false);
+                    if self.tcfg.emit_layout_asserts {
+                        let mut items = vec![struct_item];
+                        items.extend(self.convert_layout_asserts(
+                            s,
+                            decl_id,
+                            &name,
+                            Some(&name),
+                            platform_byte_size,
+                            platform_alignment,
+                        ));
+                        Ok(ConvertedDecl::Items(items))
+                    } else {
+                        Ok(ConvertedDecl::Item(struct_item))
+                    }
                 }
             }
 
             CDeclKind::Union {
                 fields: Some(ref fields),
+                platform_byte_size,
+                platform_alignment,
                 ..
             } => {
                 let name = self
@@ -1617,23 +1804,35 @@ impl<'c> Translation<'c> {
                     }
                 }
 
-                Ok(if field_syns.is_empty() {
+                let union_item = if field_syns.is_empty() {
                     // Empty unions are a GNU extension, but Rust doesn't allow empty unions.
-                    ConvertedDecl::Item(
-                        mk().span(s)
-                            .pub_()
-                            .call_attr("derive", vec!["Copy", "Clone"])
-                            .call_attr("repr", vec!["C"])
-                            .struct_item(name, vec![], false),
-                    )
+                    mk().span(s)
+                        .pub_()
+                        .call_attr("derive", vec!["Copy", "Clone"])
+                        .call_attr("repr", vec!["C"])
+                        .struct_item(name.clone(), vec![], false)
                 } else {
-                    ConvertedDecl::Item(
-                        mk().span(s)
-                            .pub_()
-                            .call_attr("derive", vec!["Copy", "Clone"])
-                            .call_attr("repr", vec!["C"])
-                            .union_item(name, field_syns),
-                    )
+                    mk().span(s)
+                        .pub_()
+                        .call_attr("derive", vec!["Copy", "Clone"])
+                        .call_attr("repr", vec!["C"])
+                        .union_item(name.clone(), field_syns)
+                };
+
+                Ok(if self.tcfg.emit_layout_asserts {
+                    // All union fields are at offset 0
+                    let mut items = vec![union_item];
+                    items.extend(self.convert_layout_asserts(
+                        s,
+                        decl_id,
+                        &name,
+                        None,
+                        platform_byte_size,
+                        platform_alignment,
+                    ));
+                    ConvertedDecl::Items(items)
+                } else {
+                    ConvertedDecl::Item(union_item)
                 })
             }
 
@@ -1643,6 +1842,7 @@ impl<'c> Translation<'c> {
 
             CDeclKind::Enum {
                 integral_type: Some(integral_type),
+                ref variants,
                 ..
             } => {
                 let enum_name = &self
@@ -1650,12 +1850,20 @@ impl<'c> Translation<'c> {
                     .borrow()
                     .resolve_decl_name(decl_id)
                     .expect("Enums should already be renamed");
+                if self.rust_enums.contains(&decl_id) {
+                    return self.convert_rust_enum(s, enum_name, variants, integral_type);
+                }
                 let ty = self.convert_type(integral_type.ctype)?;
                 Ok(ConvertedDecl::Item(
                     mk().span(s).pub_().type_item(enum_name, ty),
                 ))
             }
 
+            // Constants of Rust enums become their variants
+            CDeclKind::EnumConstant { .. } if self.rust_enum_of_constant(decl_id).is_some() => {
+                Ok(ConvertedDecl::NoItem)
+            }
+
             CDeclKind::EnumConstant { value, .. } => {
                 let name = self
                     .renamer
@@ -1736,16 +1944,32 @@ impl<'c> Translation<'c> {
 
                 let is_main = self.ast_context.c_main == Some(decl_id);
 
+                if self.tcfg.bindings_only {
+                    // Only functions with external linkage can be bound to, and the
+                    // program's entry point stays with the C code
+                    if !is_global || is_inline || is_main {
+                        return Ok(ConvertedDecl::NoItem);
+                    }
+                    return self.convert_function(
+                        ctx, s, is_global, false, is_main, is_var, is_extern,
+                        new_name, name, &args, ret, None, attrs,
+                    );
+                }
+
                 let converted_function = self.convert_function(
                     ctx, s, is_global, is_inline, is_main, is_var, is_extern,
                     new_name, name, &args, ret, body, attrs,
                 );
 
                 converted_function.or_else(|e| match self.tcfg.replace_unsupported_decls {
-                    ReplaceMode::Extern if body.is_none() => self.convert_function(
-                        ctx, s, is_global, false, is_main, is_var, is_extern,
-                        new_name, name, &args, ret, None, attrs,
-                    ),
+                    ReplaceMode::Extern if body.is_none() => {
+                        let replacement = self.convert_function(
+                            ctx, s, is_global, false, is_main, is_var, is_extern,
+                            new_name, name, &args, ret, None, attrs,
+                        )?;
+                        self.report_decl(decl_id, &e, true);
+                        Ok(replacement)
+                    }
                     _ => Err(e),
                 })
             }
@@ -1761,6 +1985,11 @@ impl<'c> Translation<'c> {
                     return Ok(ConvertedDecl::NoItem);
                 }
 
+                // Uses of fixed-width integer typedefs refer to the Rust type
+                if self.type_converter.borrow().fixed_width_int(&self.ast_context, decl_id).is_some() {
+                    return Ok(ConvertedDecl::NoItem);
+                }
+
                 // We can't typedef to std::ffi::VaList, since the typedef won't
                 // have explicit lifetime params which VaList
                 // requires. Temporarily disable translation of valist to Rust
@@ -1777,24 +2006,25 @@ impl<'c> Translation<'c> {
                 ))
             }
 
-            // Externally-visible variable without initializer (definition elsewhere)
+            // Externally-visible variable without initializer (definition elsewhere). When only
+            // emitting bindings, definitions are left to the C code and declared the same way.
             CDeclKind::Variable {
                 is_externally_visible: true,
                 has_static_duration,
                 has_thread_duration,
-                is_defn: false,
+                is_defn,
                 ref ident,
                 initializer,
                 typ,
                 ref attrs,
                 ..
-            } => {
+            } if !is_defn || self.tcfg.bindings_only => {
                 assert!(
                     has_static_duration || has_thread_duration,
                     "An extern variable must be static or thread-local"
                 );
                 assert!(
-                    initializer.is_none(),
+                    is_defn || initializer.is_none(),
                     "An extern variable that isn't a definition can't have an initializer"
                 );
 
@@ -1836,6 +2066,15 @@ impl<'c> Translation<'c> {
                 ))
             }
 
+            // Variables internal to the C code have no bindings
+            CDeclKind::Variable {
+                has_static_duration,
+                has_thread_duration,
+                ..
+            } if self.tcfg.bindings_only && (has_static_duration || has_thread_duration) => {
+                Ok(ConvertedDecl::NoItem)
+            }
+
             // Static-storage or thread-local variable with initializer (definition here)
             CDeclKind::Variable {
                 has_static_duration,
@@ -1859,7 +2098,7 @@ impl<'c> Translation<'c> {
 
                 // Collect problematic static initializers and offload them to sections for the linker
                 // to initialize for us
-                let (ty, init) = if self.static_initializer_is_uncompilable(initializer, typ) {
+                let (ty, init) = if self.static_initializer_is_uncompilable(initializer) {
                     // Note: We don't pass has_static_duration through here. Extracted initializers
                     // are run outside of the static initializer.
                     let (ty, _, init) =
@@ -1972,12 +2211,101 @@ impl<'c> Translation<'c> {
                 }
             }
 
+            CDeclKind::MacroFunction { ref name, .. } => {
+                match self.macro_function(decl_id) {
+                    Ok(Some(mac)) => Ok(ConvertedDecl::Item(mac.item().clone())),
+                    Ok(None) => Ok(ConvertedDecl::NoItem),
+                    Err(e) => {
+                        info!("Could not translate macro {}: {}", name, e);
+                        Ok(ConvertedDecl::NoItem)
+                    }
+                }
+            }
+
+            CDeclKind::StaticAssert {
+                assert_expr,
+                ref message,
+            } => Ok(ConvertedDecl::Item(self.convert_static_assert(
+                ctx,
+                s,
+                assert_expr,
+                message.as_ref().map(String::as_str),
+            ))),
+
             // Do not translate non-canonical decls. They will be translated at
             // their canonical declaration.
             CDeclKind::NonCanonicalDecl { .. } => Ok(ConvertedDecl::NoItem),
         }
     }
 
+    /// Translate a `_Static_assert` into `const _: [(); 0 - !(...) as usize] = [];`,
+    /// whose array length underflows if the condition is false. Clang has
+    /// already checked the assertion, so a condition that doesn't translate to a
+    /// constant expression is only kept as a comment. The message, if any, is
+    /// kept as a comment too.
+    fn convert_static_assert(
+        &self,
+        ctx: ExprContext,
+        span: Span,
+        assert_expr: CExprId,
+        message: Option<&str>,
+    ) -> P<Item> {
+        let cond = if self.static_initializer_is_uncompilable(Some(assert_expr)) {
+            None
+        } else {
+            self.convert_condition(ctx.set_const(true), true, assert_expr)
+                .ok()
+                .filter(|cond| !cond.is_unsafe())
+                .and_then(WithStmts::to_pure_expr)
+        };
+
+        let comment = match (&cond, message) {
+            (Some(_), Some(message)) => Some(format!("// _Static_assert: {:?}", message)),
+            (Some(_), None) => None,
+            (None, Some(message)) => Some(format!(
+                "// _Static_assert without a constant Rust translation: {:?}",
+                message
+            )),
+            (None, None) => Some(String::from(
+                "// _Static_assert without a constant Rust translation",
+            )),
+        };
+        // Keep the comments of the C declaration, if any
+        let span = match comment {
+            Some(comment) => {
+                let pos = if span == DUMMY_SP { None } else { Some(span.lo()) };
+                self.comment_store
+                    .borrow_mut()
+                    .extend_existing_comments(&[comment], pos, CommentStyle::Isolated)
+                    .map(pos_to_span)
+                    .unwrap_or(span)
+            }
+            None => span,
+        };
+
+        let unit_ty = mk().tuple_ty(vec![] as Vec<P<Ty>>);
+        match cond {
+            Some(cond) => {
+                let failed = mk().cast_expr(
+                    mk().unary_expr("!", mk().paren_expr(cond)),
+                    mk().path_ty(vec!["usize"]),
+                );
+                let len = mk().binary_expr(
+                    BinOpKind::Sub,
+                    mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed)),
+                    failed,
+                );
+                let ty = mk().array_ty(unit_ty, len);
+                let empty = mk().array_expr(vec![] as Vec<P<Expr>>);
+                mk().span(span).const_item("_", ty, empty)
+            }
+            None => {
+                let unit = mk().tuple_expr(vec![] as Vec<P<Expr>>);
+                mk().span(span).const_item("_", unit_ty, unit)
+            }
+        }
+    }
+
     fn canonical_macro_replacement(
         &self,
         ctx: ExprContext,
@@ -2041,7 +2369,7 @@ impl<'c> Translation<'c> {
         body: Option<CStmtId>,
         attrs: &IndexSet<c_ast::Attribute>,
     ) -> Result<ConvertedDecl, TranslationError> {
-        self.function_context.borrow_mut().enter_new(name);
+        self.function_context.borrow_mut().enter_new(name, return_type);
 
         self.with_scope(|| {
             let mut args: Vec<Param> = vec![];
@@ -2194,6 +2522,11 @@ impl<'c> Translation<'c> {
                     // specifies internal linkage in all other cases due to name mangling by rustc.
                 }
 
+                if self.may_longjmp && !is_main {
+                    self.use_feature("unwind_attributes");
+                    mk_ = mk_.call_attr("unwind", vec!["allowed"]);
+                }
+
                 Ok(ConvertedDecl::Item(
                     mk_.span(span).unsafe_().fn_item(new_name, decl, block),
                 ))
@@ -2216,6 +2549,11 @@ impl<'c> Translation<'c> {
                     };
                 }
 
+                if self.may_longjmp {
+                    self.use_feature("unwind_attributes");
+                    mk_ = mk_.call_attr("unwind", vec!["allowed"]);
+                }
+
                 let function_decl = mk_.fn_foreign_item(new_name, decl);
 
                 Ok(ConvertedDecl::ForeignItem(function_decl))
@@ -2427,7 +2765,7 @@ impl<'c> Translation<'c> {
                 typ,
                 ..
             } => {
-                if self.static_initializer_is_uncompilable(initializer, typ) {
+                if self.static_initializer_is_uncompilable(initializer) {
                     let ident2 = self
                         .renamer
                         .borrow_mut()
@@ -2711,7 +3049,9 @@ impl<'c> Translation<'c> {
         TranslationError,
     > {
         let init = match initializer {
-            Some(x) => self.convert_expr(ctx.used(), x),
+            Some(x) => self
+                .convert_expr(ctx.used(), x)
+                .and_then(|init| self.coerce_expr_to(x, typ.ctype, init)),
             None => self.implicit_default_expr(typ.ctype, ctx.is_static),
         };
 
@@ -2742,9 +3082,13 @@ impl<'c> Translation<'c> {
         if let Some(cur_file) = *self.cur_file.borrow() {
             self.import_type(type_id, cur_file);
         }
-        self.type_converter
+        let ty = self.type_converter
+            .borrow_mut()
+            .convert(&self.ast_context, type_id)?;
+        self.extern_crates
             .borrow_mut()
-            .convert(&self.ast_context, type_id)
+            .extend(self.type_converter.borrow().extern_crates_used());
+        Ok(ty)
     }
 
     /// Construct an expression for a NULL at any type, including forward declarations,
@@ -2773,7 +3117,7 @@ impl<'c> Translation<'c> {
         Ok(mk().cast_expr(zero, ty))
     }
 
-    /// Write to a `lhs` that is volatile
+    /// Write to a `lhs` that is volatile or atomic
     pub fn volatile_write(
         &self,
         lhs: &P<Expr>,
@@ -2801,14 +3145,17 @@ impl<'c> Translation<'c> {
             }
         };
         let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let write_fn = if lhs_type.qualifiers.is_atomic {
+            self.use_feature("core_intrinsics");
+            vec!["", std_or_core, "intrinsics", "atomic_store"]
+        } else {
+            vec!["", std_or_core, "ptr", "write_volatile"]
+        };
 
-        Ok(mk().call_expr(
-            mk().path_expr(vec!["", std_or_core, "ptr", "write_volatile"]),
-            vec![addr_lhs, rhs],
-        ))
+        Ok(mk().call_expr(mk().path_expr(write_fn), vec![addr_lhs, rhs]))
     }
 
-    /// Read from a `lhs` that is volatile
+    /// Read from a `lhs` that is volatile or atomic
     pub fn volatile_read(
         &self,
         lhs: &P<Expr>,
@@ -2839,13 +3186,19 @@ impl<'c> Translation<'c> {
         // We explicitly annotate the type of pointer we're reading from
         // in order to avoid omitted bit-casts to const from causing the
         // wrong type to be inferred via the result of the pointer.
+        let (module, read_fn) = if lhs_type.qualifiers.is_atomic {
+            self.use_feature("core_intrinsics");
+            ("intrinsics", "atomic_load")
+        } else {
+            ("ptr", "read_volatile")
+        };
         let mut path_parts: Vec<PathSegment> = vec![];
-        for elt in vec!["", std_or_core, "ptr"] {
+        for elt in vec!["", std_or_core, module] {
             path_parts.push(mk().path_segment(elt))
         }
         let elt_ty = self.convert_type(lhs_type.ctype)?;
         let ty_params = mk().angle_bracketed_args(vec![elt_ty]);
-        let elt = mk().path_segment_with_args("read_volatile", ty_params);
+        let elt = mk().path_segment_with_args(read_fn, ty_params);
         path_parts.push(elt);
 
         let read_volatile_expr = mk().path_expr(path_parts);
@@ -3019,6 +3372,16 @@ impl<'c> Translation<'c> {
     /// `stmts` field of the output and it is expected that the `val` field of the output will be
     /// ignored.
     pub fn convert_expr(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        self.convert_expr_inner(ctx, expr_id).map_err(|e| {
+            e.add_node("CExprKind", (&self.ast_context[expr_id].kind).into())
+        })
+    }
+
+    fn convert_expr_inner(
         &self,
         mut ctx: ExprContext,
         expr_id: CExprId,
@@ -3030,12 +3393,22 @@ impl<'c> Translation<'c> {
 
         trace!("Converting expr {:?}: {:?}", expr_id, self.ast_context[expr_id]);
 
-        if self.tcfg.translate_const_macros {
+        if let Some(param) = self.macro_params.borrow().get(&expr_id) {
+            return Ok(WithStmts::new_val(mk().ident_expr(param)));
+        }
+
+        if self.tcfg.translate_const_macros || self.tcfg.translate_fn_macros {
             if let Some(converted) = self.convert_macro_expansion(ctx, expr_id)? {
                 return Ok(converted);
             }
         }
 
+        if ctx.is_static || ctx.is_const {
+            if let Some(val) = self.long_double_constant(expr_id) {
+                return Ok(WithStmts::new_val(val));
+            }
+        }
+
         match *expr_kind {
             CExprKind::DesignatedInitExpr(..) => {
                 Err(TranslationError::generic("Unexpected designated init expr"))
@@ -3043,14 +3416,19 @@ impl<'c> Translation<'c> {
             CExprKind::BadExpr => Err(TranslationError::generic(
                 "convert_expr: expression kind not supported",
             )),
-            CExprKind::ShuffleVector(_, ref child_expr_ids) => self
-                .convert_shuffle_vector(ctx, child_expr_ids)
-                .map_err(|e| {
-                    TranslationError::new(self.ast_context.display_loc(src_loc), e.context(TranslationErrorKind::OldLLVMSimd))
-                }),
-            CExprKind::ConvertVector(..) => {
-                Err(TranslationError::generic("convert vector not supported"))
+            CExprKind::ShuffleVector(ty, ref child_expr_ids) => {
+                if let Some(indices) = self.generic_shuffle_indices(child_expr_ids) {
+                    self.convert_generic_shuffle_vector(ctx, ty, child_expr_ids, &indices)
+                        .map_err(|e| e.add_loc(self.ast_context.display_loc(src_loc)))
+                } else {
+                    self.convert_shuffle_vector(ctx, child_expr_ids).map_err(|e| {
+                        TranslationError::new(self.ast_context.display_loc(src_loc), e.context(TranslationErrorKind::OldLLVMSimd))
+                    })
+                }
             }
+            CExprKind::ConvertVector(ty, ref child_expr_ids) => self
+                .convert_convert_vector(ctx, ty, child_expr_ids)
+                .map_err(|e| e.add_loc(self.ast_context.display_loc(src_loc))),
 
             CExprKind::UnaryType(_ty, kind, opt_expr, arg_ty) => {
                 let result = match kind {
@@ -3097,8 +3475,10 @@ impl<'c> Translation<'c> {
                     .get(&decl_id)
                     .ok_or_else(|| format_err!("name not declared: '{}'", varname))?;
 
+                let rust_enum = self.rust_enum_of_constant(decl_id);
+
                 // Import the referenced global decl into our submodule
-                if self.tcfg.reorganize_definitions {
+                if self.tcfg.reorganize_definitions && rust_enum.is_none() {
                     if let Some(cur_file) = self.cur_file.borrow().as_ref() {
                         self.add_import(*cur_file, decl_id, &rustname);
                         // match decl {
@@ -3108,11 +3488,16 @@ impl<'c> Translation<'c> {
                     }
                 }
 
-                let mut val = mk().path_expr(vec![rustname]);
+                let mut val = match rust_enum {
+                    Some(enum_id) => mk().path_expr(self.rust_enum_variant_path(enum_id, decl_id)),
+                    None => mk().path_expr(vec![rustname]),
+                };
 
-                // If the variable is volatile and used as something that isn't an LValue, this
-                // constitutes a volatile read.
-                if lrvalue.is_rvalue() && qual_ty.qualifiers.is_volatile {
+                // If the variable is volatile or atomic and used as something that isn't an
+                // LValue, this constitutes a volatile or atomic read.
+                if lrvalue.is_rvalue()
+                    && (qual_ty.qualifiers.is_volatile || qual_ty.qualifiers.is_atomic)
+                {
                     val = self.volatile_read(&val, qual_ty)?;
                 }
 
@@ -3267,7 +3652,7 @@ impl<'c> Translation<'c> {
                 self.convert_unary_operator(ctx, op, type_id, arg, lrvalue)
             }
 
-            CExprKind::Conditional(_, cond, lhs, rhs) => {
+            CExprKind::Conditional(ty, cond, lhs, rhs) => {
                 if ctx.is_const {
                     return Err(format_translation_err!(
                         self.ast_context.display_loc(src_loc),
@@ -3276,8 +3661,10 @@ impl<'c> Translation<'c> {
                 }
                 let cond = self.convert_condition(ctx, true, cond)?;
 
-                let lhs = self.convert_expr(ctx, lhs)?;
-                let rhs = self.convert_expr(ctx, rhs)?;
+                let lhs_val = self.convert_expr(ctx, lhs)?;
+                let lhs = self.coerce_expr_to(lhs, ty.ctype, lhs_val)?;
+                let rhs_val = self.convert_expr(ctx, rhs)?;
+                let rhs = self.coerce_expr_to(rhs, ty.ctype, rhs_val)?;
 
                 if ctx.is_unused() {
                     let is_unsafe = lhs.is_unsafe() || rhs.is_unsafe();
@@ -3344,7 +3731,7 @@ impl<'c> Translation<'c> {
                 .convert_binary_expr(ctx, type_id, op, lhs, rhs, opt_lhs_type_id, opt_res_type_id)
                 .map_err(|e| e.add_loc(self.ast_context.display_loc(src_loc))),
 
-            CExprKind::ArraySubscript(_, ref lhs, ref rhs, _) => {
+            CExprKind::ArraySubscript(qual_ty, ref lhs, ref rhs, lrvalue) => {
                 let lhs_node = &self.ast_context.index(*lhs).kind;
                 let rhs_node = &self.ast_context.index(*rhs).kind;
 
@@ -3378,7 +3765,7 @@ impl<'c> Translation<'c> {
                 }
 
                 let rhs = self.convert_expr(ctx.used(), *rhs)?;
-                rhs.and_then(|rhs| {
+                let val = rhs.and_then(|rhs| {
                     let simple_index_array = if ctx.needs_address() {
                         // We can't necessarily index into an array if we're using
                         // that element to compute an address.
@@ -3459,7 +3846,14 @@ impl<'c> Translation<'c> {
                             Ok(pointer_offset(lhs, rhs, mul, false, true))
                         })
                     }
-                })
+                })?;
+
+                // Reading an `_Atomic` element is an atomic load
+                if lrvalue.is_rvalue() && qual_ty.qualifiers.is_atomic {
+                    val.result_map(|val| self.volatile_read(&val, qual_ty))
+                } else {
+                    Ok(val)
+                }
             }
 
             CExprKind::Call(call_expr_ty, func, ref args) => {
@@ -3471,6 +3865,10 @@ impl<'c> Translation<'c> {
                     Some(CTypeKind::Function(_, _, is_variadic, _, _)) => *is_variadic,
                     _ => false,
                 };
+                let param_tys = match fn_ty {
+                    Some(CTypeKind::Function(_, params, _, _, _)) => params.clone(),
+                    _ => vec![],
+                };
                 let func = match self.ast_context[func].kind {
                     // Direct function call
                     CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _)
@@ -3481,6 +3879,9 @@ impl<'c> Translation<'c> {
                             _ => false,
                         } =>
                     {
+                        if let Some(jmp) = self.convert_jmp_call(ctx, expr_id, args)? {
+                            return Ok(jmp);
+                        }
                         self.convert_expr(ctx.used(), fexp)?
                     }
 
@@ -3542,7 +3943,17 @@ impl<'c> Translation<'c> {
                     // We want to decay refs only when function is variadic
                     ctx.decay_ref = DecayRef::from(is_variadic);
 
-                    let args = self.convert_exprs(ctx.used(), args)?;
+                    let args = args
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, &arg)| {
+                            let val = self.convert_expr(ctx.used(), arg)?;
+                            match param_tys.get(idx) {
+                                Some(param_ty) => self.coerce_expr_to(arg, param_ty.ctype, val),
+                                None => Ok(val),
+                            }
+                        })
+                        .collect::<Result<WithStmts<Vec<_>>, TranslationError>>()?;
 
                     let res: Result<_, TranslationError> = Ok(
                         args.map(|args| mk().call_expr(func, args))
@@ -3557,7 +3968,7 @@ impl<'c> Translation<'c> {
                 )
             }
 
-            CExprKind::Member(_, expr, decl, kind, _) => {
+            CExprKind::Member(qual_ty, expr, decl, kind, lrvalue) => {
                 if ctx.is_unused() {
                     self.convert_expr(ctx, expr)
                 } else {
@@ -3615,6 +4026,11 @@ impl<'c> Translation<'c> {
                         val = val.map(|v| mk().field_expr(v, field_name));
                     };
 
+                    // Reading an `_Atomic` field is an atomic load
+                    if lrvalue.is_rvalue() && qual_ty.qualifiers.is_atomic {
+                        val = val.result_map(|v| self.volatile_read(&v, qual_ty))?;
+                    }
+
                     Ok(val)
                 }
             }
@@ -3640,14 +4056,15 @@ impl<'c> Translation<'c> {
             CExprKind::VAArg(ty, val_id) => self.convert_vaarg(ctx, ty, val_id),
 
             CExprKind::Choose(_, _cond, lhs, rhs, is_cond_true) => {
+                // Clang already evaluated the condition, so only the chosen
+                // expression is translated. The other one is often only valid
+                // for other argument types, e.g. in type-generic macros.
                 let chosen_expr = if is_cond_true {
                     self.convert_expr(ctx, lhs)?
                 } else {
                     self.convert_expr(ctx, rhs)?
                 };
 
-                // TODO: Support compile-time choice between lhs and rhs based on cond.
-
                 // From Clang Expr.h
                 // ChooseExpr - GNU builtin-in function __builtin_choose_expr.
                 // This AST node is similar to the conditional operator (?:) in C, with
@@ -3665,6 +4082,15 @@ impl<'c> Translation<'c> {
             CExprKind::Atomic{ref name, ptr, order, val1, order_fail, val2, weak, ..} => {
                 self.convert_atomic(ctx, name, ptr, order, val1, order_fail, val2, weak)
             }
+
+            CExprKind::AddrLabel(ty, label) => {
+                // Label addresses are only ever compared against the tags
+                // dispatched on by `goto *ptr`, so an integer suffices.
+                let tag = self.label_tag(label)?;
+                let ty = self.convert_type(ty.ctype)?;
+                let val = mk().lit_expr(mk().int_lit(tag as u128, LitIntType::Unsuffixed));
+                Ok(WithStmts::new_val(mk().cast_expr(val, ty)))
+            }
         }
     }
 
@@ -3672,14 +4098,28 @@ impl<'c> Translation<'c> {
                                -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
         if let Some(macs) = self.ast_context.macro_expansions.get(&expr_id) {
             // Find the first macro after the macro we're currently
-            // expanding, if any.
-            if let Some(macro_id) = macs
+            // expanding, if any, starting from the outermost expansion.
+            for macro_id in macs
                 .splitn(2, |macro_id| ctx.expanding_macro(macro_id))
                 .last()
                 .unwrap()
-                .first()
             {
                 trace!("  found macro expansion: {:?}", macro_id);
+                // Function-like macros that can't be called here fall back
+                // to the macros they expand to.
+                match self.ast_context[*macro_id].kind {
+                    CDeclKind::MacroFunction { .. } => {
+                        if self.tcfg.translate_fn_macros {
+                            if let Some(call) = self.convert_macro_function_call(ctx, *macro_id, expr_id)? {
+                                return Ok(Some(call));
+                            }
+                        }
+                        continue;
+                    }
+                    _ if !self.tcfg.translate_const_macros => continue,
+                    _ => {}
+                }
+
                 // Ensure that we've converted this macro and that it has a
                 // valid definition
                 if let ConvertedDecl::NoItem = self.convert_decl(ctx, *macro_id)? {
@@ -3819,6 +4259,96 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Whether `ctype` is translated to `usize` or `isize` because it is, or is
+    /// a typedef of, a fixed-width integer typedef such as `size_t`
+    fn is_pointer_sized_typedef(&self, mut ctype: CTypeId) -> bool {
+        loop {
+            match self.ast_context[ctype].kind {
+                CTypeKind::Typedef(decl_id) => {
+                    let fixed_width = self
+                        .type_converter
+                        .borrow()
+                        .fixed_width_int(&self.ast_context, decl_id);
+                    if let Some(rust_ty) = fixed_width {
+                        return rust_ty == "usize" || rust_ty == "isize";
+                    }
+                    match self.ast_context[decl_id].kind {
+                        CDeclKind::Typedef { typ, .. } => ctype = typ.ctype,
+                        _ => return false,
+                    }
+                }
+                CTypeKind::Elaborated(ty) | CTypeKind::Paren(ty) | CTypeKind::TypeOf(ty) => {
+                    ctype = ty
+                }
+                CTypeKind::Attributed(qty, _) => ctype = qty.ctype,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Whether clang considers `source` and `target` the same type, but only
+    /// one of them is translated to `usize` or `isize` by `--fixed-width-ints`,
+    /// either directly or as the pointee of pointers such as `size_t *` and
+    /// `unsigned long *`.
+    fn fixed_width_mismatch(&self, source: CTypeId, target: CTypeId) -> bool {
+        let source_kind = &self.ast_context.resolve_type(source).kind;
+        let target_kind = &self.ast_context.resolve_type(target).kind;
+        match (source_kind, target_kind) {
+            (&CTypeKind::Pointer(source), &CTypeKind::Pointer(target)) => {
+                self.fixed_width_mismatch(source.ctype, target.ctype)
+            }
+            _ if source_kind.is_integral_type() && target_kind.is_integral_type() => {
+                self.is_pointer_sized_typedef(source) != self.is_pointer_sized_typedef(target)
+            }
+            _ => false,
+        }
+    }
+
+    /// Cast `val` from C type `source` to `target` if clang considers them the
+    /// same type, so it didn't insert a cast, but `fixed_width_mismatch` finds
+    /// they are translated differently. Rust treats `usize` and `isize` as
+    /// distinct from the `libc` types of the same width.
+    pub fn coerce_fixed_width_int(
+        &self,
+        source: CTypeId,
+        target: CTypeId,
+        val: P<Expr>,
+    ) -> Result<P<Expr>, TranslationError> {
+        if !self.tcfg.fixed_width_ints || !self.fixed_width_mismatch(source, target) {
+            return Ok(val);
+        }
+        Ok(mk().cast_expr(val, self.convert_type(target)?))
+    }
+
+    /// Apply `coerce_fixed_width_int` to the translation of the C expression
+    /// `expr_id` used as a value of type `target`
+    pub fn coerce_expr_to(
+        &self,
+        expr_id: CExprId,
+        target: CTypeId,
+        val: WithStmts<P<Expr>>,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        match self.ast_context[expr_id].kind.get_type() {
+            Some(source) => val.result_map(|val| self.coerce_fixed_width_int(source, target, val)),
+            None => Ok(val),
+        }
+    }
+
+    /// Translate the value returned by a `return` statement of the current
+    /// function
+    pub fn convert_return_value(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let val = self.convert_expr(ctx.used(), expr_id)?;
+        let ret_type = self.function_context.borrow().ret_type;
+        match ret_type {
+            Some(ret_type) => self.coerce_expr_to(expr_id, ret_type.ctype, val),
+            None => Ok(val),
+        }
+    }
+
     fn convert_cast(
         &self,
         ctx: ExprContext,
@@ -3833,7 +4363,9 @@ impl<'c> Translation<'c> {
         let target_ty_kind = &self.ast_context.resolve_type(ty.ctype).kind;
 
         if source_ty_kind == target_ty_kind {
-            return Ok(val);
+            return val.result_map(|val| {
+                self.coerce_fixed_width_int(source_ty.ctype, ty.ctype, val)
+            });
         }
 
         let kind = kind.unwrap_or_else(|| {
@@ -3879,7 +4411,38 @@ impl<'c> Translation<'c> {
                 (CTypeKind::Pointer(..), CTypeKind::Pointer(..))
                     => CastKind::BitCast,
 
-                // Ignoring Complex casts for now
+                (&CTypeKind::Complex(src_elt), &CTypeKind::Complex(tgt_elt)) => {
+                    let src_float = self.ast_context.resolve_type(src_elt).kind.is_floating_type();
+                    let tgt_float = self.ast_context.resolve_type(tgt_elt).kind.is_floating_type();
+                    match (src_float, tgt_float) {
+                        (true, true) => CastKind::FloatingComplexCast,
+                        (true, false) => CastKind::FloatingComplexToIntegralComplex,
+                        (false, true) => CastKind::IntegralComplexToFloatingComplex,
+                        (false, false) => CastKind::IntegralComplexCast,
+                    }
+                }
+
+                (&CTypeKind::Complex(src_elt), CTypeKind::Bool) => {
+                    if self.ast_context.resolve_type(src_elt).kind.is_floating_type() {
+                        CastKind::FloatingComplexToBoolean
+                    } else {
+                        CastKind::IntegralComplexToBoolean
+                    }
+                }
+
+                (&CTypeKind::Complex(src_elt), _) => {
+                    if self.ast_context.resolve_type(src_elt).kind.is_floating_type() {
+                        CastKind::FloatingComplexToReal
+                    } else {
+                        CastKind::IntegralComplexToReal
+                    }
+                }
+
+                (_, &CTypeKind::Complex(_)) if source_ty_kind.is_floating_type()
+                    => CastKind::FloatingRealToComplex,
+
+                (_, &CTypeKind::Complex(_)) if source_ty_kind.is_integral_type()
+                    => CastKind::IntegralRealToComplex,
 
                 _ => {
                     warn!(
@@ -3910,6 +4473,24 @@ impl<'c> Translation<'c> {
                             x,
                             self.tcfg.emit_no_std,
                         )))
+                    } else if self.ast_context.resolve_type(ty.ctype).kind.is_vector()
+                        && self.ast_context.resolve_type(source_ty.ctype).kind.is_vector()
+                    {
+                        // Vectors of the same size reinterpret each other's bits
+                        let source_ty = self.convert_type(source_ty.ctype)?;
+                        let target_ty = self.convert_type(ty.ctype)?;
+                        if pprust::ty_to_string(&source_ty) == pprust::ty_to_string(&target_ty) {
+                            return Ok(WithStmts::new_val(x));
+                        }
+                        if ctx.is_static || ctx.is_const {
+                            self.use_feature("const_transmute");
+                        }
+                        Ok(WithStmts::new_unsafe_val(transmute_expr(
+                            source_ty,
+                            target_ty,
+                            x,
+                            self.tcfg.emit_no_std,
+                        )))
                     } else {
                         // Normal case
                         let target_ty = self.convert_type(ty.ctype)?;
@@ -3944,13 +4525,10 @@ impl<'c> Translation<'c> {
                 let source_ty_ctype_id = source_ty.ctype;
 
                 let source_ty = self.convert_type(source_ty_ctype_id)?;
-                if let CTypeKind::LongDouble = target_ty_ctype {
-                    self.extern_crates.borrow_mut().insert("f128");
-
-                    let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                    Ok(val.map(|val| mk().call_expr(fn_path, vec![val])))
-                } else if let CTypeKind::LongDouble = self.ast_context[source_ty_ctype_id].kind {
-                    self.f128_cast_to(val, target_ty_ctype)
+                if self.is_long_double_struct(target_ty_ctype) {
+                    Ok(val.map(|val| self.cast_to_long_double(val)))
+                } else if self.is_long_double_struct(&self.ast_context[source_ty_ctype_id].kind) {
+                    self.long_double_cast_to(val, target_ty_ctype)
                 } else if let &CTypeKind::Enum(enum_decl_id) = target_ty_ctype {
                     // Casts targeting `enum` types...
                     let expr = expr.ok_or_else(|| format_err!("Casts to enums require a C ExprId"))?;
@@ -3964,6 +4542,14 @@ impl<'c> Translation<'c> {
                                 self.use_feature("const_transmute");
                             }
                             Ok(WithStmts::new_unsafe_val(transmute_expr(source_ty, target_ty, x, self.tcfg.emit_no_std)))
+                        } else if self.tcfg.fixed_width_ints
+                            && kind == CastKind::IntegralCast
+                            && pprust::ty_to_string(&source_ty) == pprust::ty_to_string(&target_ty)
+                        {
+                            // Typedefs of distinct C types, e.g. `ssize_t` and
+                            // `ptrdiff_t` on some 32-bit targets, may both
+                            // translate to the same fixed-width type
+                            Ok(WithStmts::new_val(x))
                         } else {
                             Ok(WithStmts::new_val(mk().cast_expr(x, target_ty)))
                         }
@@ -4089,9 +4675,10 @@ impl<'c> Translation<'c> {
             | CastKind::IntegralRealToComplex
             | CastKind::IntegralComplexCast
             | CastKind::IntegralComplexToFloatingComplex
-            | CastKind::IntegralComplexToBoolean => Err(TranslationError::generic(
-                "TODO casts with complex numbers not supported",
-            )),
+            | CastKind::FloatingComplexToBoolean
+            | CastKind::IntegralComplexToBoolean => {
+                self.convert_complex_cast(ctx, kind, source_ty, ty, val)
+            }
 
             CastKind::VectorSplat => Err(TranslationError::generic(
                 "TODO vector splat casts not supported",
@@ -4099,50 +4686,6 @@ impl<'c> Translation<'c> {
         }
     }
 
-    /// Cast a f128 to some other int or float type
-    fn f128_cast_to(
-        &self,
-        val: WithStmts<P<Expr>>,
-        target_ty_ctype: &CTypeKind,
-    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
-        self.extern_crates.borrow_mut().insert("num_traits");
-        self.items.borrow_mut()[&self.main_file].add_use(vec!["num_traits".into()], "ToPrimitive");
-
-        let to_method_name = match target_ty_ctype {
-            CTypeKind::Float => "to_f32",
-            CTypeKind::Double => "to_f64",
-            CTypeKind::Char => "to_i8",
-            CTypeKind::UChar => "to_u8",
-            CTypeKind::Short => "to_i16",
-            CTypeKind::UShort => "to_u16",
-            CTypeKind::Int => "to_i32",
-            CTypeKind::UInt => "to_u32",
-            CTypeKind::Long => "to_i64",
-            CTypeKind::ULong => "to_u64",
-            CTypeKind::LongLong => "to_i64",
-            CTypeKind::ULongLong => "to_u64",
-            CTypeKind::Int128 => "to_i128",
-            CTypeKind::UInt128 => "to_u128",
-            _ => {
-                return Err(format_err!(
-                    "Tried casting long double to unsupported type: {:?}",
-                    target_ty_ctype
-                )
-                .into())
-            }
-        };
-
-        Ok(val.map(|val| {
-            let to_call = mk().method_call_expr(val, to_method_name, Vec::<P<Expr>>::new());
-
-            mk().method_call_expr(
-                to_call,
-                "unwrap",
-                Vec::<P<Expr>>::new(),
-            )
-        }))
-    }
-
     /// This handles translating casts when the target type in an `enum` type.
     ///
     /// When translating variable references to `EnumConstant`'s, we always insert casts to the
@@ -4212,9 +4755,13 @@ impl<'c> Translation<'c> {
             Ok(WithStmts::new_val(mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed))))
         } else if resolved_ty.is_floating_type() {
             match self.ast_context[ty_id].kind {
-                CTypeKind::LongDouble => Ok(WithStmts::new_val(mk().path_expr(vec!["f128", "f128", "ZERO"]))),
+                ref kind if self.is_long_double_struct(kind) => {
+                    Ok(WithStmts::new_val(self.long_double_const("ZERO")))
+                }
                 _ => Ok(WithStmts::new_val(mk().lit_expr(mk().float_unsuffixed_lit("0.")))),
             }
+        } else if let &CTypeKind::Complex(elt) = resolved_ty {
+            Ok(WithStmts::new_val(self.complex_zero(elt)))
         } else if let &CTypeKind::Pointer(_) = resolved_ty {
             self.null_ptr(resolved_ty_id, is_static)
                 .map(WithStmts::new_val)
@@ -4388,6 +4935,13 @@ impl<'c> Translation<'c> {
             } else {
                 mk().unary_expr(ast::UnOp::Not, val)
             }
+        } else if let CTypeKind::Complex(elt) = *ty {
+            let zero = self.complex_zero(elt);
+            if target {
+                mk().binary_expr(BinOpKind::Ne, val, zero)
+            } else {
+                mk().binary_expr(BinOpKind::Eq, val, zero)
+            }
         } else {
             // One simplification we can make at the cost of inspecting `val` more closely: if `val`
             // is already in the form `(x <op> y) as <ty>` where `<op>` is a Rust operator
@@ -4450,6 +5004,11 @@ impl<'c> Translation<'c> {
     /// If we're trying to organize item definitions into submodules, add them to a module
     /// scoped "namespace" if we have a path available, otherwise add it to the global "namespace"
     fn insert_item(&self, mut item: P<Item>, decl: &CDecl) {
+        if let ItemKind::MacroDef(..) = item.kind {
+            self.macro_items.borrow_mut().push(item);
+            return;
+        }
+
         let decl_file_id = self.ast_context.file_id(decl);
 
         if self.tcfg.reorganize_definitions {
@@ -4530,6 +5089,9 @@ impl<'c> Translation<'c> {
             | ULongLong | Int128 | UInt128 | Half | Float | Double | LongDouble => {}
             // Bool uses the bool type, so no dependency on libc
             Bool => {}
+            // Fixed-width integer typedefs are primitive types
+            Typedef(decl_id)
+                if self.type_converter.borrow().fixed_width_int(&self.ast_context, decl_id).is_some() => {}
             Paren(ctype)
             | Decayed(ctype)
             | IncompleteArray(ctype)
@@ -4572,8 +5134,11 @@ impl<'c> Translation<'c> {
                     self.import_type(param_id.ctype, decl_file_id);
                 }
             }
-            Vector(..) => {
-                // Handled in `import_simd_typedef`
+            Vector(CQualTypeId { ctype, .. }, len) => {
+                match x86_vector_type(&self.ast_context, ctype, len) {
+                    Some(name) => self.import_simd_type(decl_file_id, name),
+                    None => self.import_type(ctype, decl_file_id),
+                }
             }
             TypeOfExpr(_) | BuiltinFn => {}
         }
@@ -4629,6 +5194,10 @@ impl<'c> Translation<'c> {
                 }
             }
 
+            CDeclKind::MacroFunction { .. } => {
+                self.import_macro_function_types(decl_id, decl_file_id)
+            }
+
             CDeclKind::Function { .. } => {
                 // TODO: We may need to explicitly skip SIMD functions here when getting types for
                 // a fn definition in a header since SIMD headers define functions but we're using imports
@@ -4640,6 +5209,21 @@ impl<'c> Translation<'c> {
                 is_externally_visible: false,
                 ..
             } if has_static_duration || has_thread_duration => {}
+
+            CDeclKind::StaticAssert { assert_expr, .. } => {
+                // Types named by `sizeof`, casts and the like in the condition
+                for i in DFExpr::new(&self.ast_context, assert_expr.into()) {
+                    if let SomeId::Expr(expr_id) = i {
+                        let kind = &self.ast_context[expr_id].kind;
+                        if let CExprKind::UnaryType(_, _, _, arg_ty) = *kind {
+                            self.import_type(arg_ty.ctype, decl_file_id);
+                        }
+                        if let Some(ty) = kind.get_type() {
+                            self.import_type(ty, decl_file_id);
+                        }
+                    }
+                }
+            }
             ref e => unimplemented!("{:?}", e),
         }
     }
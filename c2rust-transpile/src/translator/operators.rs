@@ -149,8 +149,28 @@ impl<'c> Translation<'c> {
                         }
                     }
 
+                    // Operands of arithmetic have the result type, and those
+                    // of comparisons the same type
+                    let operand_ty = match op {
+                        c_ast::BinOp::ShiftLeft | c_ast::BinOp::ShiftRight => None,
+                        c_ast::BinOp::Less
+                        | c_ast::BinOp::Greater
+                        | c_ast::BinOp::LessEqual
+                        | c_ast::BinOp::GreaterEqual
+                        | c_ast::BinOp::EqualEqual
+                        | c_ast::BinOp::NotEqual => Some(lhs_type_id.ctype),
+                        _ => Some(type_id.ctype),
+                    };
+
                     self.convert_expr(ctx, lhs)?.and_then(|lhs_val| {
                         self.convert_expr(rhs_ctx, rhs)?.result_map(|rhs_val| {
+                            let (lhs_val, rhs_val) = match operand_ty {
+                                Some(operand_ty) => (
+                                    self.coerce_fixed_width_int(lhs_type_id.ctype, operand_ty, lhs_val)?,
+                                    self.coerce_fixed_width_int(rhs_type_id.ctype, operand_ty, rhs_val)?,
+                                ),
+                                None => (lhs_val, rhs_val),
+                            };
                             let expr_ids = Some((lhs, rhs));
                             self.convert_binary_operator(
                                 ctx,
@@ -162,6 +182,7 @@ impl<'c> Translation<'c> {
                                 lhs_val,
                                 rhs_val,
                                 expr_ids,
+                                lhs_loc,
                             )
                         })
                     })
@@ -182,6 +203,7 @@ impl<'c> Translation<'c> {
         compute_res_ty: Option<CQualTypeId>,
         lhs_ty: CQualTypeId,
         rhs_ty: CQualTypeId,
+        loc: &Option<SrcSpan>,
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
         let compute_lhs_ty = compute_lhs_ty.unwrap();
         let compute_res_ty = compute_res_ty.unwrap();
@@ -195,13 +217,12 @@ impl<'c> Translation<'c> {
             let lhs_type = self.convert_type(compute_lhs_ty.ctype)?;
 
             // We can't simply as-cast into a non primitive like f128
-            let lhs = if *resolved_computed_kind == CTypeKind::LongDouble {
-                self.extern_crates.borrow_mut().insert("f128");
-
-                let fn_path = mk().path_expr(vec!["f128", "f128", "from"]);
-                let args = vec![read];
-
-                mk().call_expr(fn_path, args)
+            let lhs = if self.is_long_double_struct(resolved_computed_kind) {
+                self.cast_to_long_double(read)
+            } else if let CTypeKind::Complex(_) = resolved_computed_kind {
+                // Complex values have to be constructed rather than as-cast
+                self.convert_cast(ctx, lhs_ty, compute_lhs_ty, WithStmts::new_val(read), None, None, None)?
+                    .to_expr()
             } else {
                 mk().cast_expr(read, lhs_type.clone())
             };
@@ -216,6 +237,7 @@ impl<'c> Translation<'c> {
                 lhs,
                 rhs,
                 None,
+                loc,
             )?;
 
             let is_enum_result = self.ast_context[self.ast_context.resolve_type_id(lhs_ty.ctype)]
@@ -227,11 +249,14 @@ impl<'c> Translation<'c> {
                 WithStmts::new_unsafe_val(transmute_expr(lhs_type, result_type, val, self.tcfg.emit_no_std))
             } else {
                 // We can't as-cast from a non primitive like f128 back to the result_type
-                if *resolved_computed_kind == CTypeKind::LongDouble {
+                if self.is_long_double_struct(resolved_computed_kind) {
                     let resolved_lhs_kind = &self.ast_context.resolve_type(lhs_ty.ctype).kind;
                     let val = WithStmts::new_val(val);
 
-                    self.f128_cast_to(val, resolved_lhs_kind)?
+                    self.long_double_cast_to(val, resolved_lhs_kind)?
+                } else if let CTypeKind::Complex(_) = resolved_computed_kind {
+                    let val = WithStmts::new_val(val);
+                    self.convert_cast(ctx, compute_res_ty, lhs_ty, val, None, None, None)?
                 } else {
                     WithStmts::new_val(mk().cast_expr(val, result_type))
                 }
@@ -317,6 +342,19 @@ impl<'c> Translation<'c> {
             return self.convert_bitfield_assignment_op_with_rhs(ctx, op, lhs, rhs_expr, *field_id);
         }
 
+        if initial_lhs_type_id.qualifiers.is_atomic {
+            return self.convert_atomic_assignment(
+                ctx,
+                op,
+                lhs,
+                initial_lhs_type_id,
+                compute_lhs_type_id,
+                rhs_type_id,
+                rhs_translation,
+                false,
+            );
+        }
+
         let is_volatile = initial_lhs_type_id.qualifiers.is_volatile;
         let is_volatile_compound_assign = op.underlying_assignment().is_some() && is_volatile;
 
@@ -339,12 +377,20 @@ impl<'c> Translation<'c> {
             | c_ast::BinOp::AssignModulus => compute_type_kind.is_unsigned_integral_type(),
             _ => false,
         };
+        let checks_overflow = match op {
+            c_ast::BinOp::AssignAdd | c_ast::BinOp::AssignSubtract | c_ast::BinOp::AssignMultiply => {
+                self.checks_signed_overflow(ctx, compute_lhs_type_id.ctype)
+            }
+            _ => false,
+        };
+        let lhs_loc = &self.ast_context[lhs].loc;
 
         let lhs_translation = if initial_lhs_type_id.ctype != compute_lhs_type_id.ctype
             || ctx.is_used()
             || pointer_lhs.is_some()
             || is_volatile_compound_assign
             || is_unsigned_arith
+            || checks_overflow
         {
             self.name_reference_write_read(ctx, lhs)?
         } else {
@@ -355,7 +401,25 @@ impl<'c> Translation<'c> {
                 ))
         };
 
+        // The right-hand side is combined with the left-hand side as is if
+        // that doesn't need a cast to the computation type
+        let rhs_target_ty = match op {
+            c_ast::BinOp::AssignShiftLeft | c_ast::BinOp::AssignShiftRight => None,
+            c_ast::BinOp::Assign => Some(initial_lhs_type_id.ctype),
+            _ if is_volatile || is_unsigned_arith || checks_overflow => Some(compute_lhs_type_id.ctype),
+            _ if self.ast_context.resolve_type_id(compute_lhs_type_id.ctype)
+                == self.ast_context.resolve_type_id(initial_lhs_type_id.ctype) =>
+            {
+                Some(initial_lhs_type_id.ctype)
+            }
+            _ => Some(compute_lhs_type_id.ctype),
+        };
+
         rhs_translation.and_then(|rhs| {
+            let rhs = match rhs_target_ty {
+                Some(target_ty) => self.coerce_fixed_width_int(rhs_type_id.ctype, target_ty, rhs)?,
+                None => rhs,
+            };
             lhs_translation.and_then(|(write, read)| {
                 // Assignment expression itself
                 let assign_stmt = match op {
@@ -368,7 +432,7 @@ impl<'c> Translation<'c> {
                     }
 
                     // Anything volatile needs to be desugared into explicit reads and writes
-                    op if is_volatile || is_unsigned_arith => {
+                    op if is_volatile || is_unsigned_arith || checks_overflow => {
                         let mut is_unsafe = false;
                         let op = op
                             .underlying_assignment()
@@ -385,6 +449,7 @@ impl<'c> Translation<'c> {
                                 read.clone(),
                                 rhs,
                                 None,
+                                lhs_loc,
                             )?
                         } else {
                             let lhs_type = self.convert_type(compute_type.unwrap().ctype)?;
@@ -401,6 +466,7 @@ impl<'c> Translation<'c> {
                                 lhs,
                                 rhs,
                                 None,
+                                lhs_loc,
                             )?;
 
                             let is_enum_result = self.ast_context
@@ -453,6 +519,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignSubtract => self.convert_assignment_operator_aux(
                         ctx,
@@ -465,6 +532,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignMultiply => self.convert_assignment_operator_aux(
                         ctx,
@@ -477,6 +545,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignDivide => self.convert_assignment_operator_aux(
                         ctx,
@@ -489,6 +558,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignModulus => self.convert_assignment_operator_aux(
                         ctx,
@@ -501,6 +571,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignBitXor => self.convert_assignment_operator_aux(
                         ctx,
@@ -513,6 +584,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignShiftLeft => self.convert_assignment_operator_aux(
                         ctx,
@@ -525,6 +597,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignShiftRight => self.convert_assignment_operator_aux(
                         ctx,
@@ -537,6 +610,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignBitOr => self.convert_assignment_operator_aux(
                         ctx,
@@ -549,6 +623,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
                     c_ast::BinOp::AssignBitAnd => self.convert_assignment_operator_aux(
                         ctx,
@@ -561,6 +636,7 @@ impl<'c> Translation<'c> {
                         result_type,
                         qtype,
                         rhs_type_id,
+                        lhs_loc,
                     )?,
 
                     _ => panic!("Cannot convert non-assignment operator"),
@@ -589,6 +665,7 @@ impl<'c> Translation<'c> {
         lhs: P<Expr>,
         rhs: P<Expr>,
         lhs_rhs_ids: Option<(CExprId, CExprId)>,
+        loc: &Option<SrcSpan>,
     ) -> Result<P<Expr>, TranslationError> {
         let is_unsigned_integral_type = self
             .ast_context
@@ -596,7 +673,21 @@ impl<'c> Translation<'c> {
             .kind
             .is_unsigned_integral_type();
 
+        let (lhs, rhs) = match op {
+            c_ast::BinOp::EqualEqual | c_ast::BinOp::NotEqual => {
+                self.complex_comparison_operands(lhs_type, rhs_type, lhs, rhs)
+            }
+            _ => (lhs, rhs),
+        };
+
         match op {
+            c_ast::BinOp::Add | c_ast::BinOp::Subtract | c_ast::BinOp::Multiply
+                if self.checks_signed_overflow(ctx, ctype)
+                    && self.checks_signed_overflow(ctx, lhs_type.ctype) =>
+            {
+                Ok(self.convert_signed_arithmetic(BinOpKind::from(op), ctype, lhs, rhs, loc))
+            }
+
             c_ast::BinOp::Add => self.convert_addition(ctx, lhs_type, rhs_type, lhs, rhs),
             c_ast::BinOp::Subtract => self.convert_subtraction(ctx, ty, lhs_type, rhs_type, lhs, rhs),
 
@@ -766,6 +857,80 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Whether signed `+`, `-` and `*` on `ctype` are translated according to
+    /// `--signed-overflow` rather than with the plain Rust operators.
+    fn checks_signed_overflow(&self, ctx: ExprContext, ctype: CTypeId) -> bool {
+        // Overflow in a constant expression or static initializer is already
+        // a compile error
+        self.tcfg.signed_overflow != SignedOverflow::Plain
+            && !ctx.is_const
+            && !ctx.is_static
+            && self.ast_context.resolve_type(ctype).kind.is_signed_integral_type()
+    }
+
+    /// Translate signed integer arithmetic according to `--signed-overflow`.
+    fn convert_signed_arithmetic(
+        &self,
+        op: BinOpKind,
+        ctype: CTypeId,
+        lhs: P<Expr>,
+        rhs: P<Expr>,
+        loc: &Option<SrcSpan>,
+    ) -> P<Expr> {
+        let method = match op {
+            BinOpKind::Add => "add",
+            BinOpKind::Sub => "sub",
+            BinOpKind::Mul => "mul",
+            _ => panic!("No overflow checking for {:?}", op),
+        };
+
+        // C computes in at least `int`, so increments of narrower types
+        // can only overflow in the conversion back, which wraps around
+        let narrow = match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Char | CTypeKind::SChar | CTypeKind::Short => true,
+            _ => false,
+        };
+
+        let mode = if narrow { SignedOverflow::Wrap } else { self.tcfg.signed_overflow };
+        match mode {
+            SignedOverflow::Plain => mk().binary_expr(op, lhs, rhs),
+            SignedOverflow::Wrap => {
+                let method = format!("wrapping_{}", method);
+                mk().method_call_expr(lhs, &*method, vec![rhs])
+            }
+            SignedOverflow::Checked => {
+                let method = format!("checked_{}", method);
+                let checked = mk().method_call_expr(lhs, &*method, vec![rhs]);
+                let loc = self.ast_context.display_loc(loc).and_then(|loc| {
+                    let file = loc.file()?.file_name()?.to_string_lossy().into_owned();
+                    Some(format!("{}:{}", file, loc.span().begin_line))
+                });
+                let msg = match loc {
+                    Some(loc) => format!("signed overflow at {}", loc),
+                    None => "signed overflow".to_owned(),
+                };
+                mk().method_call_expr(checked, "expect", vec![mk().lit_expr(mk().str_lit(msg))])
+            }
+            SignedOverflow::Trap => {
+                let method = format!("checked_{}", method);
+                let checked = mk().method_call_expr(lhs, &*method, vec![rhs]);
+                let abort = if self.tcfg.emit_no_std {
+                    self.use_feature("core_intrinsics");
+                    mk().path_expr(vec!["", "core", "intrinsics", "abort"])
+                } else {
+                    mk().path_expr(vec!["", "std", "process", "abort"])
+                };
+                let closure = mk().closure_expr(
+                    CaptureBy::Ref,
+                    Movability::Movable,
+                    mk().fn_decl(vec![], FunctionRetTy::Default(DUMMY_SP)),
+                    mk().call_expr(abort, vec![] as Vec<P<Expr>>),
+                );
+                mk().method_call_expr(checked, "unwrap_or_else", vec![closure])
+            }
+        }
+    }
+
     fn convert_pre_increment(
         &self,
         ctx: ExprContext,
@@ -782,14 +947,7 @@ impl<'c> Translation<'c> {
             // TODO: If rust gets f16 support:
             // CTypeKind::Half |
             CTypeKind::Float | CTypeKind::Double => mk().lit_expr(mk().float_unsuffixed_lit("1.")),
-            CTypeKind::LongDouble => {
-                self.extern_crates.borrow_mut().insert("f128");
-
-                let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                let args = vec![mk().ident_expr("1.")];
-
-                mk().call_expr(fn_path, args)
-            }
+            CTypeKind::LongDouble => self.long_double_literal(1., "1.".to_owned(), None),
             _ => mk().lit_expr(mk().int_lit(1, LitIntType::Unsuffixed)),
         };
         let arg_type = self.ast_context[arg]
@@ -827,6 +985,16 @@ impl<'c> Translation<'c> {
             .get_qual_type()
             .ok_or_else(|| format_err!("bad post inc type"))?;
 
+        if ty.qualifiers.is_atomic {
+            let op = if up {
+                c_ast::BinOp::AssignAdd
+            } else {
+                c_ast::BinOp::AssignSubtract
+            };
+            let one = WithStmts::new_val(mk().lit_expr(mk().int_lit(1, LitIntType::Unsuffixed)));
+            return self.convert_atomic_assignment(ctx, op, arg, ty, ty, ty, one, true);
+        }
+
         self.name_reference_write_read(ctx, arg)?
             .and_then(|(write, read)| {
                 let val_name = self.renamer.borrow_mut().fresh();
@@ -840,14 +1008,7 @@ impl<'c> Translation<'c> {
                     // TODO: If rust gets f16 support:
                     // CTypeKind::Half |
                     CTypeKind::Float | CTypeKind::Double => mk().lit_expr(mk().float_unsuffixed_lit("1.")),
-                    CTypeKind::LongDouble => {
-                        self.extern_crates.borrow_mut().insert("f128");
-
-                        let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                        let args = vec![mk().ident_expr("1.")];
-
-                        mk().call_expr(fn_path, args)
-                    }
+                    CTypeKind::LongDouble => self.long_double_literal(1., "1.".to_owned(), None),
                     _ => mk().lit_expr(mk().int_lit(1, LitIntType::Unsuffixed)),
                 };
 
@@ -878,6 +1039,10 @@ impl<'c> Translation<'c> {
                             }
                             let m = if up { "wrapping_add" } else { "wrapping_sub" };
                             mk().method_call_expr(read.clone(), m, vec![one])
+                        } else if self.checks_signed_overflow(ctx, ty.ctype) {
+                            let k = if up { BinOpKind::Add } else { BinOpKind::Sub };
+                            let loc = &self.ast_context[arg].loc;
+                            self.convert_signed_arithmetic(k, ty.ctype, read.clone(), one, loc)
                         } else {
                             let k = if up { BinOpKind::Add } else { BinOpKind::Sub };
                             mk().binary_expr(k, read.clone(), one)
@@ -998,9 +1163,11 @@ impl<'c> Translation<'c> {
                                 } else {
                                     let mut val = mk().unary_expr(ast::UnOp::Deref, val);
 
-                                    // If the type on the other side of the pointer we are dereferencing is volatile and
-                                    // this whole expression is not an LValue, we should make this a volatile read
-                                    if lrvalue.is_rvalue() && cqual_type.qualifiers.is_volatile {
+                                    // If the type on the other side of the pointer we are dereferencing is volatile
+                                    // or atomic and this whole expression is not an LValue, we should make this a
+                                    // volatile or atomic read
+                                    let qualifiers = cqual_type.qualifiers;
+                                    if lrvalue.is_rvalue() && (qualifiers.is_volatile || qualifiers.is_atomic) {
                                         val = self.volatile_read(&val, cqual_type)?
                                     }
                                     Ok(val)
@@ -1025,6 +1192,10 @@ impl<'c> Translation<'c> {
                     Ok(val.map(neg_expr))
                 }
             }
+            // GNU C uses `~` on complex operands to denote complex conjugation
+            c_ast::UnOp::Complement if self.complex_element_type(ctype).is_some() => Ok(self
+                .convert_expr(ctx.used(), arg)?
+                .map(|a| mk().method_call_expr(a, "conj", vec![] as Vec<P<Expr>>))),
             c_ast::UnOp::Complement => Ok(self
                 .convert_expr(ctx.used(), arg)?
                 .map(|a| mk().unary_expr(ast::UnOp::Not, a))),
@@ -1037,7 +1208,8 @@ impl<'c> Translation<'c> {
                 let arg = self.convert_expr(ctx, arg)?;
                 Ok(arg)
             }
-            c_ast::UnOp::Real | c_ast::UnOp::Imag | c_ast::UnOp::Coawait => {
+            c_ast::UnOp::Real | c_ast::UnOp::Imag => self.convert_complex_part(ctx, name, arg),
+            c_ast::UnOp::Coawait => {
                 panic!("Unsupported extension operator")
             }
         }
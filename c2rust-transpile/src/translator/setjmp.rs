@@ -0,0 +1,364 @@
+//! Translation of `setjmp`/`longjmp` into unwinding
+//!
+//! A `longjmp(env, val)` becomes a panic whose payload is the address of `env` along with `val`.
+//! The only supported use of `setjmp` is as the guard of an `if` statement:
+//!
+//! ```c
+//! if (setjmp(env) == 0) { body } else { handler }
+//! ```
+//!
+//! `body` runs in a closure under `catch_unwind`, and `handler` runs if `body` was left by a
+//! `longjmp` to `env`. Any other panic continues unwinding.
+//!
+//! Translated functions are `extern "C"`, and unwinding out of an `extern "C"` function is
+//! undefined behavior unless it is marked `#[unwind(allowed)]`. In a translation unit that uses
+//! `setjmp` or `longjmp`, every function, including the imported ones it may call, is marked that
+//! way. A `longjmp` that unwinds through a function from a translation unit that uses neither is
+//! still undefined behavior.
+
+use super::*;
+use std::collections::HashSet;
+
+const SETJMP_FUNCTIONS: &[&str] = &[
+    "setjmp",
+    "_setjmp",
+    "sigsetjmp",
+    "__sigsetjmp",
+    "__builtin_setjmp",
+];
+
+const LONGJMP_FUNCTIONS: &[&str] = &["longjmp", "_longjmp", "siglongjmp", "__builtin_longjmp"];
+
+/// An `if` statement guarded by a call to `setjmp`
+#[derive(Debug, Copy, Clone)]
+pub struct SetjmpIf {
+    /// The `jmp_buf` passed to `setjmp`
+    env: CExprId,
+    /// Runs when `setjmp` returns 0
+    body: Option<CStmtId>,
+    /// Runs when `setjmp` returns through a `longjmp`
+    pub handler: Option<CStmtId>,
+}
+
+/// Check whether the translation unit calls `setjmp` or `longjmp`, in which case its functions may
+/// be unwound through. Unused declarations must already have been pruned.
+pub fn uses_jmp(ast_context: &TypedAstContext) -> bool {
+    ast_context.iter_decls().any(|(_, decl)| match decl.kind {
+        CDeclKind::Function {
+            ref name,
+            body: None,
+            ..
+        } => SETJMP_FUNCTIONS.contains(&name.as_str()) || LONGJMP_FUNCTIONS.contains(&name.as_str()),
+        _ => false,
+    })
+}
+
+impl<'c> Translation<'c> {
+    /// Get the name of the external function called by `expr_id`, if it is a direct call
+    fn called_extern_function(&self, expr_id: CExprId) -> Option<&str> {
+        let func = match self.ast_context[expr_id].kind {
+            CExprKind::Call(_, func, _) => func,
+            _ => return None,
+        };
+        let fexp = match self.ast_context[func].kind {
+            CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _)
+            | CExprKind::ImplicitCast(_, fexp, CastKind::BuiltinFnToFnPtr, _, _) => fexp,
+            _ => return None,
+        };
+        match self.ast_context[fexp].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    body: None,
+                    ..
+                } => Some(name.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Look through implicit casts and parentheses
+    fn strip_implicit_casts(&self, expr_id: CExprId) -> CExprId {
+        match self.ast_context[expr_id].kind {
+            CExprKind::ImplicitCast(_, e, _, _, _) | CExprKind::Paren(_, e) => {
+                self.strip_implicit_casts(e)
+            }
+            _ => expr_id,
+        }
+    }
+
+    /// If `expr_id` is a call to `setjmp`, get the `jmp_buf` it is passed
+    fn setjmp_env(&self, expr_id: CExprId) -> Option<CExprId> {
+        let expr_id = self.strip_implicit_casts(expr_id);
+        match self.called_extern_function(expr_id) {
+            Some(name) if SETJMP_FUNCTIONS.contains(&name) => {}
+            _ => return None,
+        }
+        match self.ast_context[expr_id].kind {
+            CExprKind::Call(_, _, ref args) if !args.is_empty() => Some(args[0]),
+            _ => None,
+        }
+    }
+
+    /// Recognize `if (setjmp(env) == 0)`, `if (!setjmp(env))`, `if (setjmp(env) != 0)` and
+    /// `if (setjmp(env))`.
+    pub fn match_setjmp_if(
+        &self,
+        scrutinee: CExprId,
+        true_variant: CStmtId,
+        false_variant: Option<CStmtId>,
+    ) -> Option<SetjmpIf> {
+        let is_zero = |expr_id: CExprId| {
+            let expr_id = self.strip_implicit_casts(expr_id);
+            match self.ast_context[expr_id].kind {
+                CExprKind::Literal(_, CLiteral::Integer(0, _)) => true,
+                _ => false,
+            }
+        };
+
+        let scrutinee = self.strip_implicit_casts(scrutinee);
+        let (env, on_zero) = match self.ast_context[scrutinee].kind {
+            CExprKind::Binary(_, op @ c_ast::BinOp::EqualEqual, lhs, rhs, _, _)
+            | CExprKind::Binary(_, op @ c_ast::BinOp::NotEqual, lhs, rhs, _, _) => {
+                let env = if is_zero(rhs) {
+                    self.setjmp_env(lhs)
+                } else if is_zero(lhs) {
+                    self.setjmp_env(rhs)
+                } else {
+                    None
+                };
+                (env?, op == c_ast::BinOp::EqualEqual)
+            }
+            CExprKind::Unary(_, c_ast::UnOp::Not, arg, _) => (self.setjmp_env(arg)?, true),
+            _ => (self.setjmp_env(scrutinee)?, false),
+        };
+
+        let (body, handler) = if on_zero {
+            (Some(true_variant), false_variant)
+        } else {
+            (false_variant, Some(true_variant))
+        };
+        Some(SetjmpIf { env, body, handler })
+    }
+
+    /// Check that control can only leave the body of a `setjmp` by falling off its end or by
+    /// a `longjmp`, since the body is translated into a closure.
+    fn check_setjmp_body(&self, body: CStmtId) -> Result<(), TranslationError> {
+        let mut in_loop = HashSet::new();
+        let mut in_switch = HashSet::new();
+        for node in DFExpr::new(&self.ast_context, body.into()) {
+            if let SomeId::Stmt(stmt_id) = node {
+                match self.ast_context[stmt_id].kind {
+                    CStmtKind::While { body, .. }
+                    | CStmtKind::DoWhile { body, .. }
+                    | CStmtKind::ForLoop { body, .. } => in_loop.extend(
+                        DFExpr::new(&self.ast_context, body.into()).flat_map(SomeId::stmt),
+                    ),
+                    CStmtKind::Switch { body, .. } => in_switch.extend(
+                        DFExpr::new(&self.ast_context, body.into()).flat_map(SomeId::stmt),
+                    ),
+                    _ => {}
+                }
+            }
+        }
+
+        for stmt_id in DFExpr::new(&self.ast_context, body.into()).flat_map(SomeId::stmt) {
+            let escape = match self.ast_context[stmt_id].kind {
+                CStmtKind::Return(..) => Some("`return`"),
+                CStmtKind::Goto(..) | CStmtKind::IndirectGoto(..) => Some("`goto`"),
+                CStmtKind::Label(..) => Some("a label"),
+                CStmtKind::Break if !in_loop.contains(&stmt_id) && !in_switch.contains(&stmt_id) => {
+                    Some("`break`")
+                }
+                CStmtKind::Continue if !in_loop.contains(&stmt_id) => Some("`continue`"),
+                CStmtKind::Case(..) | CStmtKind::Default(..) if !in_switch.contains(&stmt_id) => {
+                    Some("a `case` label")
+                }
+                _ => None,
+            };
+            if let Some(escape) = escape {
+                return Err(format_translation_err!(
+                    self.ast_context.display_loc(&self.ast_context[stmt_id].loc),
+                    "Cannot translate {} inside the body of a `setjmp`; the body may only be left \
+                     by falling off its end or by `longjmp`",
+                    escape,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Translate the guard of a `setjmp` `if` statement, running its body. The resulting value is
+    /// true if the body was left through a `longjmp`.
+    pub fn convert_setjmp_if(
+        &self,
+        ctx: ExprContext,
+        scrutinee: CExprId,
+        setjmp: &SetjmpIf,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let src_loc = &self.ast_context[scrutinee].loc;
+        if self.tcfg.emit_no_std {
+            return Err(format_translation_err!(
+                self.ast_context.display_loc(src_loc),
+                "Cannot translate `setjmp` without `std`, which is needed to catch a `longjmp`",
+            ));
+        }
+        if !self.ast_context.is_expr_pure(setjmp.env) {
+            return Err(format_translation_err!(
+                self.ast_context.display_loc(src_loc),
+                "Cannot translate `setjmp` on a `jmp_buf` expression with side effects",
+            ));
+        }
+
+        let body = match setjmp.body {
+            Some(body) => {
+                self.check_setjmp_body(body)?;
+                self.convert_function_body(
+                    ctx,
+                    "<setjmp>",
+                    &[body],
+                    cfg::ImplicitReturnType::Void,
+                )?
+            }
+            None => {
+                return Err(format_translation_err!(
+                    self.ast_context.display_loc(src_loc),
+                    "Cannot translate `setjmp` unless the code it protects is the branch taken \
+                     when it returns 0",
+                ))
+            }
+        };
+
+        let env = self.convert_expr(ctx.used(), setjmp.env)?;
+        Ok(env.map(|env| {
+            let closure = mk().closure_expr(
+                CaptureBy::Ref,
+                Movability::Movable,
+                mk().fn_decl(vec![], FunctionRetTy::Default(DUMMY_SP)),
+                mk().block_expr(mk().block(body)),
+            );
+            let catch_unwind = mk().call_expr(
+                mk().path_expr(vec!["", "std", "panic", "catch_unwind"]),
+                vec![mk().call_expr(
+                    mk().path_expr(vec!["", "std", "panic", "AssertUnwindSafe"]),
+                    vec![closure],
+                )],
+            );
+
+            // Only catch `longjmp`s to this `jmp_buf`; anything else keeps unwinding
+            let resume_unwind = |payload: &str| {
+                mk().call_expr(
+                    mk().path_expr(vec!["", "std", "panic", "resume_unwind"]),
+                    vec![mk().ident_expr(payload)],
+                )
+            };
+            let payload_ty = mk().tuple_ty(vec![
+                mk().path_ty(vec!["usize"]),
+                mk().path_ty(vec!["libc", "c_int"]),
+            ]);
+            let downcast = mk().method_call_expr(
+                mk().ident_expr("payload"),
+                mk().path_segment_with_args(
+                    "downcast",
+                    mk().angle_bracketed_args(vec![payload_ty]),
+                ),
+                Vec::<P<Expr>>::new(),
+            );
+            let is_env = mk().binary_expr(
+                BinOpKind::Eq,
+                mk().field_expr(mk().ident_expr("jmp"), "0"),
+                mk().cast_expr(env, mk().path_ty(vec!["usize"])),
+            );
+            let longjmp = mk().match_expr(
+                downcast,
+                vec![
+                    mk().arm(
+                        mk().tuple_struct_pat(vec!["Ok"], vec![mk().ident_pat("jmp")]),
+                        Some(is_env),
+                        mk().lit_expr(mk().bool_lit(true)),
+                    ),
+                    mk().arm(
+                        mk().tuple_struct_pat(vec!["Ok"], vec![mk().ident_pat("jmp")]),
+                        None,
+                        resume_unwind("jmp"),
+                    ),
+                    mk().arm(
+                        mk().tuple_struct_pat(vec!["Err"], vec![mk().ident_pat("payload")]),
+                        None,
+                        resume_unwind("payload"),
+                    ),
+                ],
+            );
+
+            mk().match_expr(
+                catch_unwind,
+                vec![
+                    mk().arm(
+                        mk().tuple_struct_pat(vec!["Ok"], vec![mk().tuple_pat(Vec::<P<Pat>>::new())]),
+                        None,
+                        mk().lit_expr(mk().bool_lit(false)),
+                    ),
+                    mk().arm(
+                        mk().tuple_struct_pat(vec!["Err"], vec![mk().ident_pat("payload")]),
+                        None,
+                        longjmp,
+                    ),
+                ],
+            )
+        }))
+    }
+
+    /// Translate a direct call to `setjmp` or `longjmp` that is not part of a `setjmp` `if`
+    /// statement. Returns `None` for calls to any other function.
+    pub fn convert_jmp_call(
+        &self,
+        ctx: ExprContext,
+        call_id: CExprId,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        let src_loc = &self.ast_context[call_id].loc;
+        match self.called_extern_function(call_id) {
+            Some(name) if SETJMP_FUNCTIONS.contains(&name) => Err(format_translation_err!(
+                self.ast_context.display_loc(src_loc),
+                "Cannot translate `{}` outside of `if ({}(env) == 0) {{ ... }} else {{ ... }}`",
+                name,
+                name,
+            )),
+            Some(name) if LONGJMP_FUNCTIONS.contains(&name) => {
+                if self.tcfg.emit_no_std {
+                    return Err(format_translation_err!(
+                        self.ast_context.display_loc(src_loc),
+                        "Cannot translate `{}` without `std`, which is needed to unwind",
+                        name,
+                    ));
+                }
+                if args.len() != 2 {
+                    return Err(format_translation_err!(
+                        self.ast_context.display_loc(src_loc),
+                        "Expected 2 arguments to `{}`",
+                        name,
+                    ));
+                }
+
+                let env = self.convert_expr(ctx.used(), args[0])?;
+                let val = self.convert_expr(ctx.used(), args[1])?;
+                let payload = env.and_then(|env| -> Result<_, TranslationError> {
+                    Ok(val.map(|val| {
+                        mk().tuple_expr(vec![
+                            mk().cast_expr(env, mk().path_ty(vec!["usize"])),
+                            mk().cast_expr(val, mk().path_ty(vec!["libc", "c_int"])),
+                        ])
+                    }))
+                })?;
+                Ok(Some(payload.map(|payload| {
+                    mk().call_expr(
+                        mk().path_expr(vec!["", "std", "panic", "resume_unwind"]),
+                        vec![mk().call_expr(mk().path_expr(vec!["Box", "new"]), vec![payload])],
+                    )
+                })))
+            }
+            _ => Ok(None),
+        }
+    }
+}
@@ -6,7 +6,7 @@ use super::*;
 use crate::c_ast::BinOp::{Add, BitAnd, ShiftRight};
 use crate::c_ast::CExprKind::{Binary, Call, Conditional, ExplicitCast, ImplicitCast, Literal};
 use crate::c_ast::CLiteral::Integer;
-use crate::c_ast::CTypeKind::{Char, Double, Float, Int, LongLong, Short};
+use crate::c_ast::CTypeKind::{Char, Double, Float, Int, LongLong, Short, UInt};
 use crate::c_ast::CastKind::{BitCast, IntegralCast};
 
 /// As of rustc 1.29, rust is known to be missing some SIMD functions.
@@ -75,29 +75,7 @@ impl<'c> Translation<'c> {
         match name {
             // Public API SIMD typedefs:
             "__m128i" | "__m128" | "__m128d" | "__m64" | "__m256" | "__m256d" | "__m256i" => {
-                // __m64 is still behind a feature gate
-                if name == "__m64" {
-                    self.use_feature("stdsimd");
-                }
-
-                let item_store = &mut self.items.borrow_mut()[&self.cur_file()];
-
-                let x86_attr = mk().call_attr("cfg", vec!["target_arch = \"x86\""]).pub_();
-                let x86_64_attr = mk()
-                    .call_attr("cfg", vec!["target_arch = \"x86_64\""])
-                    .pub_();
-                let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" }.to_string();
-
-                item_store.add_use_with_attr(
-                    vec![std_or_core.clone(), "arch".into(), "x86".into()],
-                    name,
-                    x86_attr,
-                );
-                item_store.add_use_with_attr(
-                    vec![std_or_core, "arch".into(), "x86_64".into()],
-                    name,
-                    x86_64_attr,
-                );
+                self.import_simd_type(self.cur_file(), name);
 
                 true
             }
@@ -139,6 +117,34 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Import one of the x86 SIMD types from `core::arch` into the given file.
+    pub fn import_simd_type(&self, file_id: FileId, name: &str) {
+        // __m64 is still behind a feature gate
+        if name == "__m64" {
+            self.use_feature("stdsimd");
+        }
+
+        let mut items = self.items.borrow_mut();
+        let item_store = items.entry(file_id).or_insert(ItemStore::new());
+
+        let x86_attr = mk().call_attr("cfg", vec!["target_arch = \"x86\""]).pub_();
+        let x86_64_attr = mk()
+            .call_attr("cfg", vec!["target_arch = \"x86_64\""])
+            .pub_();
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" }.to_string();
+
+        item_store.add_use_with_attr(
+            vec![std_or_core.clone(), "arch".into(), "x86".into()],
+            name,
+            x86_attr,
+        );
+        item_store.add_use_with_attr(
+            vec![std_or_core, "arch".into(), "x86_64".into()],
+            name,
+            x86_64_attr,
+        );
+    }
+
     /// Determine if a particular function name is an SIMD primitive. If so an appropriate
     /// use statement is generated, `true` is returned, and no further processing will need to be done.
     pub fn import_simd_function(&self, name: &str) -> Result<bool, TranslationError> {
@@ -246,24 +252,27 @@ impl<'c> Translation<'c> {
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
         // NOTE: This is only for x86/_64, and so support for other architectures
         // might need some sort of disambiguation to be exported
-        let (fn_name, bytes) = match (&self.ast_context[ctype].kind, len) {
-            (Float, 4) => ("_mm_setzero_ps", 16),
-            (Float, 8) => ("_mm256_setzero_ps", 32),
-            (Double, 2) => ("_mm_setzero_pd", 16),
-            (Double, 4) => ("_mm256_setzero_pd", 32),
-            (Char, 16) | (Int, 4) | (LongLong, 2) => ("_mm_setzero_si128", 16),
-            (Char, 32) | (Int, 8) | (LongLong, 4) => ("_mm256_setzero_si256", 32),
-            (Char, 8) | (Int, 2) | (LongLong, 1) => {
+        let (fn_name, bytes) = match x86_vector_type(&self.ast_context, ctype, len) {
+            Some("__m128") => ("_mm_setzero_ps", 16),
+            Some("__m256") => ("_mm256_setzero_ps", 32),
+            Some("__m128d") => ("_mm_setzero_pd", 16),
+            Some("__m256d") => ("_mm256_setzero_pd", 32),
+            Some("__m128i") => ("_mm_setzero_si128", 16),
+            Some("__m256i") => ("_mm256_setzero_si256", 32),
+            Some("__m64") => {
                 // __m64 is still unstable as of rust 1.29
                 self.use_feature("stdsimd");
 
                 ("_mm_setzero_si64", 8)
             }
-            (kind, len) => Err(format_err!(
-                "Unsupported vector default initializer: {:?} x {}",
-                kind,
-                len
-            ))?,
+            Some(name) => Err(format_err!("Unsupported vector default initializer: {}", name))?,
+            // Vectors without a matching SIMD type are plain arrays
+            None => {
+                let len = mk().lit_expr(mk().int_lit(len as u128, ""));
+                return Ok(self
+                    .implicit_default_expr(ctype, is_static)?
+                    .map(|zero| mk().repeat_expr(zero, len)));
+            }
         };
 
         if is_static {
@@ -297,9 +306,12 @@ impl<'c> Translation<'c> {
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
         let param_translation = self.convert_exprs(ctx, ids)?;
         param_translation.and_then(|mut params| {
-            // When used in a static, we cannot call the standard functions since they
-            // are not const and so we are forced to transmute
-            let call = if ctx.is_static {
+            let call = if x86_vector_type(&self.ast_context, ctype, len).is_none() {
+                // Vectors without a matching SIMD type are plain arrays
+                mk().array_expr(params)
+            } else if ctx.is_static {
+                // When used in a static, we cannot call the standard functions since they
+                // are not const and so we are forced to transmute
                 let tuple = mk().tuple_expr(params);
                 let transmute = transmute_expr(
                     mk().infer_ty(),
@@ -313,33 +325,38 @@ impl<'c> Translation<'c> {
                 transmute
             } else {
                 let fn_call_name = match (&self.ast_context[ctype].kind, len) {
-                    (Float, 4) => "_mm_setr_ps",
-                    (Float, 8) => "_mm256_setr_ps",
-                    (Double, 2) => "_mm_setr_pd",
-                    (Double, 4) => "_mm256_setr_pd",
-                    (LongLong, 2) => "_mm_set_epi64x",
-                    (LongLong, 4) => "_mm256_setr_epi64x",
-                    (Char, 8) => "_mm_setr_pi8",
-                    (Char, 16) => "_mm_setr_epi8",
-                    (Char, 32) => "_mm256_setr_epi8",
-                    (Int, 2) => "_mm_setr_pi32",
-                    (Int, 4) => "_mm_setr_epi32",
-                    (Int, 8) => "_mm256_setr_epi32",
-                    (Short, 4) => "_mm_setr_pi16",
-                    (Short, 8) => "_mm_setr_epi16",
-                    (Short, 16) => "_mm256_setr_epi16",
-                    ref e => Err(format_err!("Unknown vector init list: {:?}", e))?,
+                    (Float, 4) => Some("_mm_setr_ps"),
+                    (Float, 8) => Some("_mm256_setr_ps"),
+                    (Double, 2) => Some("_mm_setr_pd"),
+                    (Double, 4) => Some("_mm256_setr_pd"),
+                    (LongLong, 2) => Some("_mm_set_epi64x"),
+                    (LongLong, 4) => Some("_mm256_setr_epi64x"),
+                    (Char, 8) => Some("_mm_setr_pi8"),
+                    (Char, 16) => Some("_mm_setr_epi8"),
+                    (Char, 32) => Some("_mm256_setr_epi8"),
+                    (Int, 2) => Some("_mm_setr_pi32"),
+                    (Int, 4) => Some("_mm_setr_epi32"),
+                    (Int, 8) => Some("_mm256_setr_epi32"),
+                    (Short, 4) => Some("_mm_setr_pi16"),
+                    (Short, 8) => Some("_mm_setr_epi16"),
+                    (Short, 16) => Some("_mm256_setr_epi16"),
+                    _ => None,
                 };
 
-                self.import_simd_function(fn_call_name)?;
+                match fn_call_name {
+                    Some(fn_call_name) => {
+                        self.import_simd_function(fn_call_name)?;
 
-                // rust is missing support for _mm_setr_epi64x, so we have to use
-                // the reverse arguments for _mm_set_epi64x
-                if fn_call_name == "_mm_set_epi64x" {
-                    params.reverse();
-                }
+                        // rust is missing support for _mm_setr_epi64x, so we have to use
+                        // the reverse arguments for _mm_set_epi64x
+                        if fn_call_name == "_mm_set_epi64x" {
+                            params.reverse();
+                        }
 
-                mk().call_expr(mk().ident_expr(fn_call_name), params)
+                        mk().call_expr(mk().ident_expr(fn_call_name), params)
+                    }
+                    None => self.array_to_vector(ctype, len, mk().array_expr(params))?,
+                }
             };
 
             if ctx.is_used() {
@@ -467,6 +484,285 @@ impl<'c> Translation<'c> {
         })
     }
 
+    /// Extract the lane indices of a `__builtin_shufflevector` call written against
+    /// the generic vector extensions, where every index is an integer literal and
+    /// `-1` marks a lane whose value is undefined. Shuffles produced by the x86
+    /// header macros compute their indices from a mask and yield `None`.
+    pub fn generic_shuffle_indices(&self, child_expr_ids: &[CExprId]) -> Option<Vec<i64>> {
+        fn index(ast_context: &TypedAstContext, expr_id: CExprId) -> Option<i64> {
+            match ast_context[expr_id].kind {
+                Literal(_, Integer(i, _)) => Some(i as i64),
+                ImplicitCast(_, expr_id, IntegralCast, _, _) | CExprKind::Paren(_, expr_id) => {
+                    index(ast_context, expr_id)
+                }
+                CExprKind::Unary(_, c_ast::UnOp::Negate, expr_id, _) => {
+                    index(ast_context, expr_id).map(|i| -i)
+                }
+                _ => None,
+            }
+        }
+
+        if child_expr_ids.len() < 3 {
+            return None;
+        }
+
+        child_expr_ids[2..]
+            .iter()
+            .map(|&expr_id| index(&self.ast_context, expr_id))
+            .collect()
+    }
+
+    /// Convert a `__builtin_shufflevector` call with constant lane indices (see
+    /// `generic_shuffle_indices`). Shuffles matching an SSE shuffle instruction use
+    /// the corresponding intrinsic; any other shuffle is built lane by lane from
+    /// arrays holding the input vectors.
+    pub fn convert_generic_shuffle_vector(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        child_expr_ids: &[CExprId],
+        indices: &[i64],
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        if ctx.is_static || ctx.is_const {
+            return Err(format_err!(
+                "Shuffle vectors are not supported in static or constant initializers"
+            ).into());
+        }
+
+        let (elt, len) = self.vector_type(self.ast_context[child_expr_ids[0]].kind.get_type())?;
+        let (res_elt, res_len) = self.vector_type(Some(ty.ctype))?;
+        let len_i64 = len as i64;
+        if let Some(&i) = indices.iter().find(|&&i| i < -1 || i >= 2 * len_i64) {
+            return Err(format_err!("Shuffle vector index {} out of range", i).into());
+        }
+
+        // Each lane is a two-bit index into one of the inputs of an SSE shuffle
+        let imm = |lanes: &[i64], offset: i64| {
+            lanes.iter().enumerate().fold(0, |acc, (lane, &i)| {
+                acc | ((if i < 0 { 0 } else { i - offset }) << (2 * lane))
+            })
+        };
+        let from = |lanes: &[i64], lo: i64| {
+            lanes.iter().all(|&i| i == -1 || (lo..lo + len_i64).contains(&i))
+        };
+        let elt_kind = &self.ast_context.resolve_type(elt).kind;
+        let intrinsic = match (elt_kind, len, res_len) {
+            (Float, 4, 4) if from(&indices[..2], 0) && from(&indices[2..], 4) => {
+                Some(("_mm_shuffle_ps", true, imm(&indices[..2], 0) | imm(&indices[2..], 4) << 4))
+            }
+            (Double, 2, 2) if from(&indices[..1], 0) && from(&indices[1..], 2) => {
+                let lane = |i: i64, offset: i64| if i < 0 { 0 } else { i - offset };
+                Some(("_mm_shuffle_pd", true, lane(indices[0], 0) | lane(indices[1], 2) << 1))
+            }
+            (Int, 4, 4) | (UInt, 4, 4) if from(indices, 0) => {
+                Some(("_mm_shuffle_epi32", false, imm(indices, 0)))
+            }
+            (Int, 4, 4) | (UInt, 4, 4) if from(indices, 4) => {
+                Some(("_mm_shuffle_epi32", false, imm(indices, 4)))
+            }
+            _ => None,
+        };
+
+        let params = self.convert_exprs(ctx.used(), &child_expr_ids[..2])?;
+        let mut val = params.and_then(|params| -> Result<_, TranslationError> {
+            let mut params = params.into_iter();
+            let first = params.next().ok_or("Missing first param in convert_generic_shuffle_vector")?;
+            let second = params.next().ok_or("Missing second param in convert_generic_shuffle_vector")?;
+
+            match intrinsic {
+                Some((fn_name, binary, imm)) => {
+                    self.import_simd_function(fn_name)?;
+
+                    // `_mm_shuffle_epi32` selects from a single vector
+                    let mut args = vec![];
+                    if binary || indices.iter().all(|&i| i < len_i64) {
+                        args.push(first);
+                    } else {
+                        args.push(second);
+                    }
+                    if binary {
+                        args.push(second);
+                    }
+                    args.push(mk().lit_expr(mk().int_lit(imm as u128, "")));
+
+                    Ok(WithStmts::new_val(mk().call_expr(mk().ident_expr(fn_name), args)))
+                }
+                None => {
+                    let mut stmts = vec![];
+                    let mut inputs = vec![];
+                    for param in vec![first, second] {
+                        let name = self.renamer.borrow_mut().fresh();
+                        let array = self.vector_to_array(elt, len, param)?;
+                        stmts.push(mk().local_stmt(P(mk().local(
+                            mk().ident_pat(&name),
+                            None as Option<P<Ty>>,
+                            Some(array),
+                        ))));
+                        inputs.push(name);
+                    }
+
+                    let lanes = indices
+                        .iter()
+                        .map(|&i| -> Result<P<Expr>, TranslationError> {
+                            if i < 0 {
+                                Ok(self.implicit_default_expr(res_elt, false)?.to_expr())
+                            } else {
+                                let input = &inputs[(i / len_i64) as usize];
+                                let lane = mk().lit_expr(mk().int_lit((i % len_i64) as u128, ""));
+                                Ok(mk().index_expr(mk().ident_expr(input), lane))
+                            }
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let vector = self.array_to_vector(res_elt, res_len, mk().array_expr(lanes))?;
+
+                    let mut val = WithStmts::new(stmts, vector);
+                    val.set_unsafe();
+                    Ok(val)
+                }
+            }
+        })?;
+
+        if ctx.is_unused() {
+            let is_unsafe = val.is_unsafe();
+            let (mut stmts, call) = val.discard_unsafe();
+            stmts.push(mk().expr_stmt(call));
+            val = WithStmts::new(stmts, self.panic_or_err("No value for unused shuffle vector return"));
+            val.merge_unsafe(is_unsafe);
+        }
+
+        Ok(val)
+    }
+
+    /// Convert a `__builtin_convertvector` call, which converts each lane of a vector
+    /// to the element type of the result as if by a cast. Conversions between
+    /// 32-bit integers and floats use the SSE2 intrinsics; any other conversion
+    /// is done lane by lane through arrays.
+    pub fn convert_convert_vector(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        child_expr_ids: &[CExprId],
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        if ctx.is_static || ctx.is_const {
+            return Err(format_err!(
+                "Vector conversions are not supported in static or constant initializers"
+            ).into());
+        }
+
+        let src_id = *child_expr_ids.first().ok_or("Missing convert vector argument")?;
+        let (src_elt, len) = self.vector_type(self.ast_context[src_id].kind.get_type())?;
+        let (dst_elt, dst_len) = self.vector_type(Some(ty.ctype))?;
+        if len != dst_len {
+            return Err(format_err!(
+                "Vector conversion from {} to {} lanes",
+                len,
+                dst_len
+            ).into());
+        }
+
+        let src_simd_ty = x86_vector_type(&self.ast_context, src_elt, len);
+        let dst_simd_ty = x86_vector_type(&self.ast_context, dst_elt, len);
+        let intrinsic = match (
+            &self.ast_context.resolve_type(src_elt).kind,
+            &self.ast_context.resolve_type(dst_elt).kind,
+            len,
+        ) {
+            (Int, Float, 4) => Some("_mm_cvtepi32_ps"),
+            (Float, Int, 4) => Some("_mm_cvttps_epi32"),
+            _ => None,
+        };
+
+        let mut val = self.convert_expr(ctx.used(), src_id)?.and_then(|src| -> Result<_, TranslationError> {
+            // Integer vectors whose lanes only differ in signedness share a SIMD type,
+            // and the conversion leaves the bits of each lane unchanged
+            if src_simd_ty.is_some() && src_simd_ty == dst_simd_ty {
+                return Ok(WithStmts::new_val(src));
+            }
+
+            if let Some(fn_name) = intrinsic {
+                self.import_simd_function(fn_name)?;
+                return Ok(WithStmts::new_val(mk().call_expr(mk().ident_expr(fn_name), vec![src])));
+            }
+
+            let name = self.renamer.borrow_mut().fresh();
+            let array = self.vector_to_array(src_elt, len, src)?;
+            let array_let = mk().local_stmt(P(mk().local(
+                mk().ident_pat(&name),
+                None as Option<P<Ty>>,
+                Some(array),
+            )));
+
+            let dst_ty = self.convert_type(dst_elt)?;
+            let lanes: Vec<P<Expr>> = (0..len)
+                .map(|i| {
+                    let lane = mk().lit_expr(mk().int_lit(i as u128, ""));
+                    mk().cast_expr(mk().index_expr(mk().ident_expr(&name), lane), dst_ty.clone())
+                })
+                .collect();
+            let vector = self.array_to_vector(dst_elt, len, mk().array_expr(lanes))?;
+
+            let mut val = WithStmts::new(vec![array_let], vector);
+            val.set_unsafe();
+            Ok(val)
+        })?;
+
+        if ctx.is_unused() {
+            let is_unsafe = val.is_unsafe();
+            let (mut stmts, call) = val.discard_unsafe();
+            stmts.push(mk().expr_stmt(call));
+            val = WithStmts::new(stmts, self.panic_or_err("No value for unused convert vector return"));
+            val.merge_unsafe(is_unsafe);
+        }
+
+        Ok(val)
+    }
+
+    /// Element type and length of the given vector type.
+    fn vector_type(&self, ctype: Option<CTypeId>) -> Result<(CTypeId, usize), TranslationError> {
+        let ctype = ctype.ok_or("Missing vector type")?;
+        match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Vector(CQualTypeId { ctype, .. }, len) => Ok((ctype, len)),
+            ref kind => Err(format_err!("Expected a vector type, found {:?}", kind))?,
+        }
+    }
+
+    /// Reinterpret a vector of `len` elements of type `elt` as an array of its lanes.
+    fn vector_to_array(
+        &self,
+        elt: CTypeId,
+        len: usize,
+        vector: P<Expr>,
+    ) -> Result<P<Expr>, TranslationError> {
+        match x86_vector_type(&self.ast_context, elt, len) {
+            Some(name) => {
+                self.import_simd_type(self.cur_file(), name);
+                let elt_ty = self.convert_type(elt)?;
+                let array_ty = mk().array_ty(elt_ty, mk().lit_expr(mk().int_lit(len as u128, "")));
+                Ok(transmute_expr(mk().path_ty(vec![name]), array_ty, vector, self.tcfg.emit_no_std))
+            }
+            // Vectors without a matching SIMD type are already arrays
+            None => Ok(vector),
+        }
+    }
+
+    /// Reinterpret an array of `len` elements of type `elt` as the corresponding vector.
+    fn array_to_vector(
+        &self,
+        elt: CTypeId,
+        len: usize,
+        array: P<Expr>,
+    ) -> Result<P<Expr>, TranslationError> {
+        match x86_vector_type(&self.ast_context, elt, len) {
+            Some(name) => {
+                self.import_simd_type(self.cur_file(), name);
+                let elt_ty = self.convert_type(elt)?;
+                let array_ty = mk().array_ty(elt_ty, mk().lit_expr(mk().int_lit(len as u128, "")));
+                Ok(transmute_expr(array_ty, mk().path_ty(vec![name]), array, self.tcfg.emit_no_std))
+            }
+            None => Ok(array),
+        }
+    }
+
     /// Vectors tend to have casts to and from internal types. This is problematic for shuffle vectors
     /// in particular which are usually macros ontop of a builtin call. Although one of these casts
     /// is likely redundant (external type), the other is not (internal type). We remove both of the
@@ -556,7 +852,11 @@ impl<'c> Translation<'c> {
         use self::CastKind::BuiltinFnToFnPtr;
 
         match self.ast_context[expr_id].kind {
-            CExprKind::ShuffleVector(..) => is_explicit && kind == CastKind::BitCast,
+            CExprKind::ShuffleVector(_, ref child_expr_ids) => {
+                is_explicit
+                    && kind == CastKind::BitCast
+                    && self.generic_shuffle_indices(child_expr_ids).is_none()
+            }
             CExprKind::Call(_, fn_id, _) => {
                 let fn_expr = &self.ast_context[fn_id].kind;
 
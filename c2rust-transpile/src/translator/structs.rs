@@ -504,8 +504,9 @@ impl<'a> Translation<'a> {
                     let field = init.map(|init| mk().field(field_name, init));
                     fields.push(field);
                 }
-                Both(field_id, (field_name, _, bitfield_width, use_inner_type)) => {
-                    let mut expr = self.convert_expr(ctx.used(), *field_id)?;
+                Both(field_id, (field_name, ty, bitfield_width, use_inner_type)) => {
+                    let expr = self.convert_expr(ctx.used(), *field_id)?;
+                    let mut expr = self.coerce_expr_to(*field_id, ty.ctype, expr)?;
 
                     if !expr.is_pure() {
                         return Err(TranslationError::generic(
@@ -0,0 +1,541 @@
+//! Software implementation of the x87 80-bit extended precision format,
+//! which C compilers use for `long double` on x86 and x86-64. Code translated
+//! with `--long-double=x87` stores `long double` values as `f80`.
+//!
+//! Arithmetic is correctly rounded to the 64-bit significand, like x87 code
+//! running with the default precision control.
+
+#![no_std]
+#![allow(non_camel_case_types)]
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use num_traits::ToPrimitive;
+
+const BIAS: i32 = 16383;
+const MAX_EXP: i32 = 0x7fff;
+const SIGN_BIT: u16 = 0x8000;
+const INT_BIT: u64 = 1 << 63;
+
+/// Extra low bits kept while aligning the operands of an addition
+const GUARD_BITS: i32 = 62;
+
+/// An x87 extended precision float, with the layout of `long double` on
+/// x86-64: the 64-bit significand, then the sign and 15-bit exponent,
+/// padded to 16 bytes. This does not match `long double` on 32-bit x86,
+/// which is 12 bytes aligned to 4, so the translator only uses `f80` for
+/// x86-64 targets.
+#[repr(C, align(16))]
+#[derive(Copy, Clone, Default)]
+pub struct f80 {
+    /// Significand, including the explicit integer bit
+    mantissa: u64,
+    /// Sign bit followed by the biased exponent
+    sign_exp: u16,
+}
+
+enum Class {
+    Nan,
+    Inf(bool),
+    Zero(bool),
+    /// `(-1)^sign * sig * 2^exp`
+    Finite(bool, i32, u128),
+}
+
+impl f80 {
+    pub const ZERO: f80 = f80::from_bits(0, 0);
+    pub const ONE: f80 = f80::from_bits(INT_BIT, BIAS as u16);
+    pub const INFINITY: f80 = f80::from_bits(INT_BIT, MAX_EXP as u16);
+    pub const NEG_INFINITY: f80 = f80::from_bits(INT_BIT, SIGN_BIT | MAX_EXP as u16);
+    pub const NAN: f80 = f80::from_bits(INT_BIT | INT_BIT >> 1, MAX_EXP as u16);
+
+    /// Construct a value from its significand and its sign and exponent word
+    pub const fn from_bits(mantissa: u64, sign_exp: u16) -> f80 {
+        f80 { mantissa, sign_exp }
+    }
+
+    /// The significand and the sign and exponent word of this value
+    pub fn to_bits(self) -> (u64, u16) {
+        (self.mantissa, self.sign_exp)
+    }
+
+    pub fn new<T: Into<f80>>(x: T) -> f80 {
+        x.into()
+    }
+
+    pub fn is_nan(self) -> bool {
+        match self.class() {
+            Class::Nan => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_sign_negative(self) -> bool {
+        self.sign_exp & SIGN_BIT != 0
+    }
+
+    pub fn is_sign_positive(self) -> bool {
+        !self.is_sign_negative()
+    }
+
+    /// Convert to an `i128`, truncating towards zero
+    pub fn to_i128(&self) -> Option<i128> {
+        let (sign, mag) = self.to_int()?;
+        if sign {
+            if mag <= 1 << 127 {
+                Some(mag.wrapping_neg() as i128)
+            } else {
+                None
+            }
+        } else if mag < 1 << 127 {
+            Some(mag as i128)
+        } else {
+            None
+        }
+    }
+
+    /// Convert to a `u128`, truncating towards zero
+    pub fn to_u128(&self) -> Option<u128> {
+        match self.to_int()? {
+            (true, mag) if mag != 0 => None,
+            (_, mag) => Some(mag),
+        }
+    }
+
+    fn zero(sign: bool) -> f80 {
+        f80::from_bits(0, if sign { SIGN_BIT } else { 0 })
+    }
+
+    fn infinity(sign: bool) -> f80 {
+        if sign {
+            f80::NEG_INFINITY
+        } else {
+            f80::INFINITY
+        }
+    }
+
+    fn class(self) -> Class {
+        let sign = self.is_sign_negative();
+        let exp = (self.sign_exp & !SIGN_BIT) as i32;
+        if exp == MAX_EXP {
+            if self.mantissa << 1 == 0 {
+                Class::Inf(sign)
+            } else {
+                Class::Nan
+            }
+        } else if self.mantissa == 0 {
+            Class::Zero(sign)
+        } else {
+            // Denormals share the exponent of the smallest normal numbers
+            Class::Finite(sign, exp.max(1) - BIAS - 63, self.mantissa as u128)
+        }
+    }
+
+    /// Round `(-1)^sign * sig * 2^exp` to the nearest `f80`
+    fn from_parts(sign: bool, exp: i32, sig: u128) -> f80 {
+        if sig == 0 {
+            return f80::zero(sign);
+        }
+        let (exp, sig) = round(sig, exp, 64, BIAS, MAX_EXP);
+        if exp == MAX_EXP {
+            f80::infinity(sign)
+        } else {
+            let sign = if sign { SIGN_BIT } else { 0 };
+            f80::from_bits(sig as u64, sign | exp as u16)
+        }
+    }
+
+    /// Round to an IEEE binary format with `digits` significant bits,
+    /// returning the biased exponent and the significand without its
+    /// leading bit
+    fn to_ieee(self, digits: u32, bias: i32, max_exp: i32) -> (i32, u64) {
+        match self.class() {
+            Class::Nan => (max_exp, 1 << (digits - 2)),
+            Class::Inf(_) => (max_exp, 0),
+            Class::Zero(_) => (0, 0),
+            Class::Finite(_, exp, sig) => {
+                let (exp, sig) = round(sig, exp, digits, bias, max_exp);
+                (exp, sig as u64 & ((1 << (digits - 1)) - 1))
+            }
+        }
+    }
+
+    /// Truncate towards zero, giving the sign and magnitude of the result.
+    /// Returns `None` for NaNs, infinities and magnitudes too large for a
+    /// `u128`.
+    fn to_int(self) -> Option<(bool, u128)> {
+        match self.class() {
+            Class::Nan | Class::Inf(_) => None,
+            Class::Zero(sign) => Some((sign, 0)),
+            Class::Finite(sign, exp, sig) => {
+                let mag = if exp >= 0 {
+                    if exp > sig.leading_zeros() as i32 {
+                        return None;
+                    }
+                    sig << exp
+                } else if exp <= -128 {
+                    0
+                } else {
+                    sig >> -exp
+                };
+                Some((sign, mag))
+            }
+        }
+    }
+}
+
+/// Round `sig * 2^exp`, which must be nonzero, to nearest, ties to even, in a
+/// binary format with `digits` significant bits and exponent bias `bias`.
+/// Returns the biased exponent, which is `max_exp` on overflow, and the
+/// significand including its leading bit.
+fn round(sig: u128, exp: i32, digits: u32, bias: i32, max_exp: i32) -> (i32, u128) {
+    let msb = 127 - sig.leading_zeros() as i32;
+    // Subnormal results have the exponent of the smallest normal numbers
+    let mut biased = (exp + msb + bias).max(1);
+    let shift = biased - bias - (digits as i32 - 1) - exp;
+    let mut sig = if shift > 0 {
+        shift_right_round(sig, shift as u32)
+    } else {
+        sig << (-shift) as u32
+    };
+
+    // Rounding up may carry into a new leading bit
+    if sig >> digits != 0 {
+        sig >>= 1;
+        biased += 1;
+    }
+    if sig >> (digits - 1) == 0 {
+        biased = 0;
+    }
+    if biased >= max_exp {
+        (max_exp, 0)
+    } else {
+        (biased, sig)
+    }
+}
+
+/// Shift `sig` right by `shift` bits, rounding to nearest, ties to even
+fn shift_right_round(sig: u128, shift: u32) -> u128 {
+    if shift > 128 {
+        return 0;
+    }
+    let (quot, rem, half) = if shift == 128 {
+        (0, sig, 1 << 127)
+    } else {
+        (sig >> shift, sig & ((1u128 << shift) - 1), 1u128 << (shift - 1))
+    };
+    if rem > half || (rem == half && quot & 1 == 1) {
+        quot + 1
+    } else {
+        quot
+    }
+}
+
+/// Shift `sig` right by `shift` bits, setting the lowest bit if any of the
+/// bits shifted out were set
+fn shift_right_sticky(sig: u128, shift: u32) -> u128 {
+    if shift == 0 {
+        sig
+    } else if shift >= 128 {
+        (sig != 0) as u128
+    } else {
+        (sig >> shift) | (sig & ((1u128 << shift) - 1) != 0) as u128
+    }
+}
+
+impl Add for f80 {
+    type Output = f80;
+
+    fn add(self, other: f80) -> f80 {
+        match (self.class(), other.class()) {
+            (Class::Nan, _) | (_, Class::Nan) => f80::NAN,
+            (Class::Inf(a), Class::Inf(b)) if a != b => f80::NAN,
+            (Class::Inf(_), _) => self,
+            (_, Class::Inf(_)) => other,
+            (Class::Zero(a), Class::Zero(b)) => f80::zero(a && b),
+            (Class::Zero(_), _) => other,
+            (_, Class::Zero(_)) => self,
+            (Class::Finite(sign_a, exp_a, sig_a), Class::Finite(sign_b, exp_b, sig_b)) => {
+                // Align both operands with the larger one, shifted left to
+                // make room for the bits of the smaller one
+                let max_exp = exp_a.max(exp_b);
+                let sig_a = shift_right_sticky(sig_a << GUARD_BITS, (max_exp - exp_a) as u32);
+                let sig_b = shift_right_sticky(sig_b << GUARD_BITS, (max_exp - exp_b) as u32);
+                let exp = max_exp - GUARD_BITS;
+                let (sign, sig) = if sign_a == sign_b {
+                    (sign_a, sig_a + sig_b)
+                } else if sig_a >= sig_b {
+                    (sign_a, sig_a - sig_b)
+                } else {
+                    (sign_b, sig_b - sig_a)
+                };
+                // Exact cancellation gives positive zero
+                f80::from_parts(sign && sig != 0, exp, sig)
+            }
+        }
+    }
+}
+
+impl Sub for f80 {
+    type Output = f80;
+
+    fn sub(self, other: f80) -> f80 {
+        self + -other
+    }
+}
+
+impl Mul for f80 {
+    type Output = f80;
+
+    fn mul(self, other: f80) -> f80 {
+        let sign = self.is_sign_negative() != other.is_sign_negative();
+        match (self.class(), other.class()) {
+            (Class::Nan, _) | (_, Class::Nan) => f80::NAN,
+            (Class::Inf(_), Class::Zero(_)) | (Class::Zero(_), Class::Inf(_)) => f80::NAN,
+            (Class::Inf(_), _) | (_, Class::Inf(_)) => f80::infinity(sign),
+            (Class::Zero(_), _) | (_, Class::Zero(_)) => f80::zero(sign),
+            (Class::Finite(_, exp_a, sig_a), Class::Finite(_, exp_b, sig_b)) => {
+                // Both significands are below 2^64, so the product is exact
+                f80::from_parts(sign, exp_a + exp_b, sig_a * sig_b)
+            }
+        }
+    }
+}
+
+impl Div for f80 {
+    type Output = f80;
+
+    fn div(self, other: f80) -> f80 {
+        let sign = self.is_sign_negative() != other.is_sign_negative();
+        match (self.class(), other.class()) {
+            (Class::Nan, _) | (_, Class::Nan) => f80::NAN,
+            (Class::Inf(_), Class::Inf(_)) | (Class::Zero(_), Class::Zero(_)) => f80::NAN,
+            (Class::Inf(_), _) | (_, Class::Zero(_)) => f80::infinity(sign),
+            (_, Class::Inf(_)) | (Class::Zero(_), _) => f80::zero(sign),
+            (Class::Finite(_, exp_a, sig_a), Class::Finite(_, exp_b, sig_b)) => {
+                // Normalize both significands to 64 bits, so that the
+                // quotient below has 64 or 65 bits
+                let norm_a = sig_a.leading_zeros() - 64;
+                let norm_b = sig_b.leading_zeros() - 64;
+                let (sig_a, sig_b) = (sig_a << norm_a, sig_b << norm_b);
+                let exp = exp_a - norm_a as i32 - exp_b + norm_b as i32;
+
+                let quot = (sig_a << 64) / sig_b;
+                let rem = (sig_a << 64) % sig_b;
+                // Two more quotient bits for rounding, and whether anything
+                // is left below them
+                let low = (rem << 64) / sig_b;
+                let sticky = low & ((1 << 62) - 1) != 0 || (rem << 64) % sig_b != 0;
+                let sig = (quot << 2) | (low >> 62) | sticky as u128;
+                f80::from_parts(sign, exp - 66, sig)
+            }
+        }
+    }
+}
+
+impl Neg for f80 {
+    type Output = f80;
+
+    fn neg(self) -> f80 {
+        f80::from_bits(self.mantissa, self.sign_exp ^ SIGN_BIT)
+    }
+}
+
+macro_rules! impl_assign_op {
+    ($($trait:ident $method:ident $op:tt),*) => {$(
+        impl $trait for f80 {
+            fn $method(&mut self, other: f80) {
+                *self = *self $op other;
+            }
+        }
+    )*}
+}
+
+impl_assign_op! {
+    AddAssign add_assign +,
+    SubAssign sub_assign -,
+    MulAssign mul_assign *,
+    DivAssign div_assign /
+}
+
+impl PartialEq for f80 {
+    fn eq(&self, other: &f80) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for f80 {
+    fn partial_cmp(&self, other: &f80) -> Option<Ordering> {
+        let magnitude = |x: &f80| match x.class() {
+            Class::Nan => None,
+            Class::Zero(_) => Some((0, 0)),
+            _ => Some((x.sign_exp & !SIGN_BIT, x.mantissa)),
+        };
+        let (a, b) = (magnitude(self)?, magnitude(other)?);
+        if a == (0, 0) && b == (0, 0) {
+            return Some(Ordering::Equal);
+        }
+        Some(match (self.is_sign_negative(), other.is_sign_negative()) {
+            (false, false) => a.cmp(&b),
+            (true, true) => b.cmp(&a),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        })
+    }
+}
+
+impl From<f64> for f80 {
+    fn from(x: f64) -> f80 {
+        let bits = x.to_bits();
+        let sign = bits >> 63 != 0;
+        let exp = ((bits >> 52) & 0x7ff) as i32;
+        let frac = bits & ((1 << 52) - 1);
+        match exp {
+            0x7ff if frac == 0 => f80::infinity(sign),
+            0x7ff => {
+                let sign = if sign { SIGN_BIT } else { 0 };
+                f80::from_bits(f80::NAN.mantissa | frac << 11, sign | MAX_EXP as u16)
+            }
+            0 => f80::from_parts(sign, 1 - 1075, frac as u128),
+            _ => f80::from_parts(sign, exp - 1075, (frac | 1 << 52) as u128),
+        }
+    }
+}
+
+impl From<f32> for f80 {
+    fn from(x: f32) -> f80 {
+        f80::from(x as f64)
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($ty:ty)*) => {$(
+        impl From<$ty> for f80 {
+            #[allow(unused_comparisons)]
+            fn from(x: $ty) -> f80 {
+                let mag = if x < 0 {
+                    (x as i128 as u128).wrapping_neg()
+                } else {
+                    x as u128
+                };
+                f80::from_parts(x < 0, 0, mag)
+            }
+        }
+    )*}
+}
+
+impl_from_int!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+impl ToPrimitive for f80 {
+    fn to_i64(&self) -> Option<i64> {
+        let x = self.to_i128()?;
+        if x >= i64::min_value() as i128 && x <= i64::max_value() as i128 {
+            Some(x as i64)
+        } else {
+            None
+        }
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        let x = self.to_u128()?;
+        if x <= u64::max_value() as u128 {
+            Some(x as u64)
+        } else {
+            None
+        }
+    }
+
+    fn to_f32(&self) -> Option<f32> {
+        let (exp, frac) = self.to_ieee(24, 127, 0xff);
+        let sign = self.is_sign_negative() as u32;
+        Some(f32::from_bits(sign << 31 | (exp as u32) << 23 | frac as u32))
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        let (exp, frac) = self.to_ieee(53, 1023, 0x7ff);
+        let sign = self.is_sign_negative() as u64;
+        Some(f64::from_bits(sign << 63 | (exp as u64) << 52 | frac))
+    }
+}
+
+impl fmt::Debug for f80 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f64().unwrap(), f)
+    }
+}
+
+impl fmt::Display for f80 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f64().unwrap(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn division_rounds() {
+        assert_eq!(
+            (f80::new(1) / f80::new(3)).to_bits(),
+            (0xaaaa_aaaa_aaaa_aaab, 0x3ffd)
+        );
+        assert_eq!(
+            (f80::new(1) / f80::new(10)).to_bits(),
+            (0xcccc_cccc_cccc_cccd, 0x3ffb)
+        );
+    }
+
+    #[test]
+    fn addition_rounds_to_even() {
+        let tiny = f80::from_bits(INT_BIT, (BIAS - 64) as u16);
+        assert_eq!(f80::ONE + tiny, f80::ONE);
+        assert_eq!(f80::ONE + tiny + tiny, f80::ONE);
+        assert_eq!((f80::ONE + (tiny + tiny)).to_bits(), (INT_BIT | 1, BIAS as u16));
+        assert_eq!(f80::ONE - f80::ONE, f80::ZERO);
+        assert!((f80::ONE - f80::ONE).is_sign_positive());
+    }
+
+    #[test]
+    fn denormals() {
+        let half = f80::new(0.5);
+        assert_eq!((f80::from_bits(1, 0) * half).to_bits(), (0, 0));
+        assert_eq!((f80::from_bits(3, 0) * half).to_bits(), (2, 0));
+        assert_eq!((f80::from_bits(INT_BIT, 1) * half).to_bits(), (INT_BIT >> 1, 0));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(f80::new(0.1f64).to_f64(), Some(0.1));
+        assert_eq!(f80::new(-0.0f64).to_f64().map(f64::to_bits), Some((-0.0f64).to_bits()));
+        assert_eq!(f80::new(core::f64::MIN_POSITIVE / 4.0).to_f64(), Some(core::f64::MIN_POSITIVE / 4.0));
+        assert_eq!(f80::new(u64::max_value()).to_u64(), Some(u64::max_value()));
+        assert_eq!((f80::new(u64::max_value()) + f80::ONE).to_u64(), None);
+        assert_eq!(f80::new(i64::min_value()).to_i64(), Some(i64::min_value()));
+        assert_eq!(f80::new(-2.5).to_i32(), Some(-2));
+        assert_eq!(f80::new(-0.5).to_u32(), Some(0));
+        assert_eq!(f80::INFINITY.to_i64(), None);
+    }
+
+    #[test]
+    fn range() {
+        // 2^1000 squared overflows an f64 but not an f80
+        let big = f80::from_bits(INT_BIT, (BIAS + 1000) as u16);
+        assert_eq!((big * big).to_f64(), Some(core::f64::INFINITY));
+        assert_eq!(big * big / big, big);
+        assert_eq!(f80::from_bits(u64::max_value(), 0x7ffe) * f80::new(2), f80::INFINITY);
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(f80::NAN != f80::NAN);
+        assert_eq!(f80::new(-0.0), f80::ZERO);
+        assert!(f80::new(-1) < f80::new(-0.5));
+        assert!(f80::new(2) > f80::ONE);
+        assert!(f80::NEG_INFINITY < f80::new(i64::min_value()));
+        assert!(f80::from_bits(1, 0) > f80::ZERO);
+        assert_eq!(f80::NAN.partial_cmp(&f80::ONE), None);
+    }
+}
@@ -8,9 +8,15 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use c2rust_transpile::{Diagnostic, ReplaceMode, TranspilerConfig};
+use c2rust_transpile::{
+    AsmStyle, Configuration, Diagnostic, EnumStyle, LongDouble, ReplaceMode, ReportFormat,
+    SignedOverflow, TranspilerConfig,
+};
 
 fn main() {
+    // With `--jobs`, clang runs in child processes of this executable
+    c2rust_transpile::run_exporter_worker();
+
     let yaml = load_yaml!("../transpile.yaml");
     let matches = App::from_yaml(yaml).get_matches();
 
@@ -80,6 +86,11 @@ fn main() {
         // option. Defaulting to enabled now, can add an option to disable if
         // needed.
         translate_asm: true,
+        asm_style: match matches.value_of("asm-style") {
+            Some("llvm") => AsmStyle::Llvm,
+            Some("operands") => AsmStyle::Operands,
+            _ => panic!("Invalid asm style"),
+        },
 
         // We used to guard varargs with a command-line option before nightly
         // support landed. We may still want to disable this option to target
@@ -87,6 +98,7 @@ fn main() {
         translate_valist: true,
 
         translate_const_macros: matches.is_present("translate-const-macros"),
+        translate_fn_macros: matches.is_present("translate-fn-macros"),
         disable_refactoring: matches.is_present("disable-refactoring"),
 
         use_c_loop_info: !matches.is_present("ignore-c-loop-info"),
@@ -110,9 +122,45 @@ fn main() {
             }
         },
         replace_unsupported_decls: ReplaceMode::Extern,
+        enum_style: match matches.value_of("enum-style") {
+            Some("constants") => EnumStyle::Constants,
+            Some("rust") => EnumStyle::Rust,
+            _ => panic!("Invalid enum style"),
+        },
+        signed_overflow: match matches.value_of("signed-overflow") {
+            Some("plain") => SignedOverflow::Plain,
+            Some("wrap") => SignedOverflow::Wrap,
+            Some("checked") => SignedOverflow::Checked,
+            Some("trap") => SignedOverflow::Trap,
+            _ => panic!("Invalid signed overflow mode"),
+        },
+        long_double: match matches.value_of("long-double") {
+            Some("f64") => Some(LongDouble::F64),
+            Some("x87") => Some(LongDouble::X87),
+            Some("f128") => Some(LongDouble::F128),
+            None => None,
+            _ => panic!("Invalid long double type"),
+        },
+        fixed_width_ints: matches.is_present("fixed-width-ints"),
         emit_no_std: matches.is_present("emit-no-std"),
+        emit_layout_asserts: matches.is_present("emit-layout-asserts"),
+        bindings_only: matches.is_present("bindings-only"),
+        main_exit_code: matches.is_present("main-exit-code"),
         enabled_warnings,
         log_level,
+        ast_cache_dir: matches.value_of("ast-cache-dir").map(PathBuf::from),
+        jobs: value_t!(matches, "jobs", usize).unwrap_or_else(|e| e.exit()),
+        report_file: matches.value_of("report").map(PathBuf::from),
+        report_format: match matches.value_of("report-format") {
+            Some("json") => ReportFormat::Json,
+            Some("sarif") => ReportFormat::Sarif,
+            _ => panic!("Invalid report format"),
+        },
+        configurations: matches
+            .values_of("configuration")
+            .unwrap_or_else(|| Values::default())
+            .map(|s| Configuration::from_str(s).unwrap_or_else(|e| panic!("{}", e)))
+            .collect(),
     };
     // binaries imply emit-build-files
     if !tcfg.binaries.is_empty() {
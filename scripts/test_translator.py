@@ -30,7 +30,7 @@ from rust_file import (
     RustMod,
     RustVisibility,
 )
-from typing import Generator, List, Optional, Set, Iterable
+from typing import Dict, Generator, List, Optional, Set, Iterable
 
 # Tools we will need
 clang = get_cmd_or_die("clang")
@@ -42,9 +42,12 @@ cargo = get_cmd_or_die("cargo")
 
 # Intermediate files
 intermediate_files = [
-    'cc_db', 'c_obj', 'c_lib', 'rust_src',
+    'cc_db', 'c_obj', 'c_lib', 'rust_src', 'rust_bin',
 ]
 
+# Arguments passed to translated main functions; the last one is not UTF-8
+main_binary_args = ["first", "\udcff\udcfe"]
+
 
 class TestOutcome(Enum):
     Success = "successes"
@@ -71,8 +74,26 @@ class CFile:
         self.disable_incremental_relooper = "disable_incremental_relooper" in flags
         self.disallow_current_block = "disallow_current_block" in flags
         self.translate_const_macros = "translate_const_macros" in flags
+        self.translate_fn_macros = "translate_fn_macros" in flags
         self.reorganize_definitions = "reorganize_definitions" in flags
         self.emit_build_files = "emit_build_files" in flags
+        self.rust_enums = "rust_enums" in flags
+        self.emit_layout_asserts = "emit_layout_asserts" in flags
+        self.bindings_only = "bindings_only" in flags
+        self.fixed_width_ints = "fixed_width_ints" in flags
+        self.asm_style_operands = "asm_style_operands" in flags
+        self.main_binary = "main_binary" in flags
+        self.main_exit_code = "main_exit_code" in flags
+        self.exit_status = int(next((flag[12:] for flag in flags
+                                     if flag.startswith("exit_status_")), 0))
+        self.signed_overflow = next((flag[16:] for flag in flags
+                                     if flag.startswith("signed_overflow_")), None)
+        self.long_double = next((flag[12:] for flag in flags
+                                 if flag.startswith("long_double_")), None)
+        # Flags are unordered, so configurations are passed in sorted order and
+        # the first one in that order is enabled by default
+        self.configurations = sorted(flag[14:] for flag in flags
+                                     if flag.startswith("configuration_"))
 
     def translate(self, cc_db, extra_args: List[str] = []) -> RustFile:
         extensionless_file, _ = os.path.splitext(self.path)
@@ -98,10 +119,30 @@ class CFile:
             args.append("--fail-on-multiple")
         if self.translate_const_macros:
             args.append("--translate-const-macros")
+        if self.translate_fn_macros:
+            args.append("--translate-fn-macros")
         if self.reorganize_definitions:
             args.append("--reorganize-definitions")
         if self.emit_build_files:
             args.append("--emit-build-files")
+        if self.rust_enums:
+            args.append("--enum-style=rust")
+        if self.emit_layout_asserts:
+            args.append("--emit-layout-asserts")
+        if self.bindings_only:
+            args.append("--bindings-only")
+        if self.fixed_width_ints:
+            args.append("--fixed-width-ints")
+        if self.asm_style_operands:
+            args.append("--asm-style=operands")
+        if self.main_exit_code:
+            args.append("--main-exit-code")
+        if self.signed_overflow:
+            args.append("--signed-overflow=" + self.signed_overflow)
+        if self.long_double:
+            args.append("--long-double=" + self.long_double)
+        for configuration in self.configurations:
+            args.append("--configuration=" + configuration)
 
         if self.logLevel == 'DEBUG':
             args.append("--log-level=debug")
@@ -215,6 +256,7 @@ class TestDirectory:
             "c_obj": [],
             "c_lib": [],
             "cc_db": [],
+            "rust_bin": [],
         }
 
         for entry in os.listdir(self.full_path_src):
@@ -315,6 +357,8 @@ class TestDirectory:
         any_tests = any(test_fn for test_file in self.rs_test_files
                         for test_fn in test_file.test_functions)
 
+        any_tests = any_tests or any(c_file.main_binary for c_file in self.c_files)
+
         if not any_tests:
             description = "No tests were found..."
             logging.debug("%s:", self.name)
@@ -358,9 +402,9 @@ class TestDirectory:
             "stdsimd",
             "const_transmute",
             "nll",
-            "custom_attribute",
             "linkage",
         ])
+        main_binaries = []
 
         # .c -> .rs
         for c_file in self.c_files:
@@ -392,12 +436,24 @@ class TestDirectory:
                 self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-lib.rs")
                 self.generated_files["rust_src"].append(self.full_path + "/src/rust-toolchain")
 
+            # Translated main functions are crate roots of their own
+            if c_file.main_binary:
+                main_binaries.append((c_file, translated_rust_file))
+                continue
+
             _, rust_file_short = os.path.split(translated_rust_file.path)
             extensionless_rust_file, _ = os.path.splitext(rust_file_short)
 
             rust_file_builder.add_mod(RustMod(extensionless_rust_file,
                                               RustVisibility.Public))
 
+        # Directories that pin their own, newer toolchain, or whose generated
+        # build files do, can't enable features that have since been removed
+        toolchain_env = self._generated_toolchain_env()
+        if not toolchain_env and \
+                not os.path.exists(os.path.join(self.full_path, "rust-toolchain")):
+            rust_file_builder.add_features(["custom_attribute"])
+
         match_arms = []
         rustc_extra_args = ["-C", "target-cpu=native"]
 
@@ -457,7 +513,7 @@ class TestDirectory:
         self.generated_files["rust_src"].append(main_file)
 
         # Try and build test binary
-        with pb.local.cwd(self.full_path):
+        with pb.local.cwd(self.full_path), pb.local.env(**toolchain_env):
             args = ["build"]
 
             if c.BUILD_TYPE == 'release':
@@ -476,6 +532,9 @@ class TestDirectory:
 
             return outcomes
 
+        for c_file, rust_file in main_binaries:
+            outcomes.append(self._run_main_binary(c_file, rust_file, toolchain_env))
+
         for test_file in self.rs_test_files:
             if not test_file.pass_expected:
                 continue
@@ -489,7 +548,7 @@ class TestDirectory:
                 if c.BUILD_TYPE == 'release':
                     args.append('--release')
 
-                with pb.local.cwd(self.full_path):
+                with pb.local.cwd(self.full_path), pb.local.env(**toolchain_env):
                     retcode, stdout, stderr = cargo[args].run(retcode=None)
 
                 logging.debug("stdout:%s\n", stdout)
@@ -527,6 +586,61 @@ class TestDirectory:
             self.print_status(Colors.OKBLUE, "N/A", display_text)
         return outcomes
 
+    def _generated_toolchain_env(self) -> Dict[str, str]:
+        """
+        Environment selecting the toolchain pinned by the `rust-toolchain` file
+        generated for C files translated with `emit_build_files`, if it isn't
+        the default one
+        """
+        toolchain_path = os.path.join(self.full_path, "src", "rust-toolchain")
+        if not any(c_file.emit_build_files for c_file in self.c_files) or \
+                not os.path.exists(toolchain_path):
+            return {}
+
+        with open(toolchain_path) as toolchain_file:
+            toolchain = toolchain_file.read().strip()
+        with open(os.path.join(c.ROOT_DIR, "rust-toolchain")) as default_file:
+            if toolchain == default_file.read().strip():
+                return {}
+        return {"RUSTUP_TOOLCHAIN": toolchain}
+
+    def _run_main_binary(self, c_file: CFile, rust_file: RustFile,
+                         toolchain_env: Dict[str, str]) -> TestOutcome:
+        """
+        Build the translation of a C file with a main function as a binary
+        and check the exit status it returns for `main_binary_args`, with a
+        non-UTF-8 environment variable set.
+        """
+        _, file_name = os.path.split(rust_file.path)
+        deps_dir = os.path.join(self.full_path, "target", c.BUILD_TYPE, "deps")
+
+        try:
+            # Build in the test directory so that its toolchain is used
+            with pb.local.cwd(self.full_path), pb.local.env(**toolchain_env):
+                binary = rust_file.compile(CrateType.Binary, save_output=True,
+                                           extra_args=["-L", "dependency=" + deps_dir])
+        except NonZeroReturn as exception:
+            self.print_status(Colors.FAIL, "FAILED", "compile " + file_name)
+            sys.stdout.write('\n')
+            sys.stdout.write(str(exception))
+            return TestOutcome.UnexpectedFailure
+
+        self.generated_files["rust_bin"].append(str(binary.executable))
+
+        with pb.local.env(C2RUST_MAIN_TEST="\udcff"):
+            retcode, _, stderr = binary[main_binary_args].run(retcode=None)
+
+        if retcode != c_file.exit_status:
+            self.print_status(Colors.FAIL, "FAILED",
+                              "run {}: exit status {}".format(file_name, retcode))
+            sys.stdout.write('\n')
+            sys.stdout.write(stderr)
+            return TestOutcome.UnexpectedFailure
+
+        self.print_status(Colors.OKGREEN, "OK", "    run " + file_name)
+        sys.stdout.write('\n')
+        return TestOutcome.Success
+
     def cleanup(self) -> None:
         if "all" in self.keep:
             return
@@ -562,7 +676,7 @@ def readable_directory(directory: str) -> str:
 
 def get_testdirectories(
         directory: str, files: str,
-        keep: List[str], test_longdoubles: bool,
+        keep: List[str], test_longdoubles: bool, test_asm: bool,
         logLevel: str) -> Generator[TestDirectory, None, None]:
     for entry in os.listdir(directory):
         path = os.path.abspath(os.path.join(directory, entry))
@@ -570,6 +684,8 @@ def get_testdirectories(
         if os.path.isdir(path):
             if path.endswith("longdouble") and not test_longdoubles:
                 continue
+            if path.endswith("asm") and not test_asm:
+                continue
 
             yield TestDirectory(path, files, keep, logLevel)
 
@@ -601,12 +717,17 @@ def main() -> None:
         default=False, action="store_true",
         help="Enables testing of long double translation which requires gcc headers",
     )
+    parser.add_argument(
+        '--test-asm', dest='test_asm',
+        default=False, action="store_true",
+        help="Enables testing of asm! operand translation which requires a newer nightly",
+    )
     c.add_args(parser)
 
     args = parser.parse_args()
     c.update_args(args)
     test_directories = get_testdirectories(args.directory, args.regex_files,
-                                           args.keep, args.test_longdoubles,
+                                           args.keep, args.test_longdoubles, args.test_asm,
                                            args.logLevel)
     setup_logging(args.logLevel)
 
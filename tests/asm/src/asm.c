@@ -0,0 +1,38 @@
+//! asm_style_operands
+
+// Only x86-64 is exercised here; the `--test-asm` runs are x86-64 only.
+
+static int add(int a, int b) {
+    int out;
+    __asm__("addl %2, %0" : "=r"(out) : "0"(a), "r"(b));
+    return out;
+}
+
+static unsigned increment(unsigned x) {
+    __asm__("incl %0" : "+r"(x));
+    return x;
+}
+
+static void store(int *p, int v) {
+    __asm__ volatile("movl %1, %0" : "=m"(*p) : "r"(v) : "memory");
+}
+
+static unsigned long shift(unsigned long x, unsigned long n) {
+    __asm__("shlq %%cl, %0" : "+r"(x) : "c"(n) : "cc");
+    return x;
+}
+
+void entry(const unsigned buffer_size, int buffer[const]) {
+    if (buffer_size < 6) {
+        return;
+    }
+
+    int i = 0;
+
+    buffer[i++] = add(2, 3);
+    buffer[i++] = add(-7, 4);
+    buffer[i++] = increment(41);
+    store(&buffer[i++], 1234);
+    buffer[i++] = (int) shift(3, 4);
+    buffer[i++] = (int) (shift(1, 40) >> 32);
+}
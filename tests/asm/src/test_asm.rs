@@ -0,0 +1,30 @@
+//! feature_asm
+
+extern crate libc;
+
+use asm::rust_entry;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn entry(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 6;
+
+pub fn test_asm_operands() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [5, -3, 42, 1234, 48, 256];
+
+    unsafe {
+        entry(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_entry(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    for index in 0..BUFFER_SIZE {
+        assert_eq!(buffer[index], rust_buffer[index]);
+        assert_eq!(buffer[index], expected_buffer[index]);
+    }
+}
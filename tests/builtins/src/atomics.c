@@ -66,3 +66,85 @@ void new_atomics(const unsigned buffer_size, int buffer[const])
     __atomic_store_n(&x, 0, __ATOMIC_RELAXED);
     buffer[i++] = x;
 }
+
+void dynamic_atomics(const unsigned buffer_size, int buffer[const], int order, int order_fail, int weak)
+{
+    int i = 0, x = 34;
+    buffer[i++] = __atomic_fetch_add(&x, 55, order);    buffer[i++] = x;
+    buffer[i++] = __atomic_sub_fetch(&x, 17, order);    buffer[i++] = x;
+    buffer[i++] = __atomic_exchange_n(&x, 33, order);   buffer[i++] = x;
+    buffer[i++] = __atomic_load_n(&x, order_fail);
+    __atomic_store_n(&x, 77, __ATOMIC_SEQ_CST);
+    buffer[i++] = x;
+
+    for (int x = 70; x < 80; x++) {
+        int expected = 77;
+        int ok = 0;
+        do {
+            ok = __atomic_compare_exchange_n(&x, &expected, 137, weak, order, order_fail);
+        } while (weak && !ok && expected == 77);
+        buffer[i++] = ok;
+        buffer[i++] = expected;
+        buffer[i++] = x;
+    }
+}
+
+void c11_atomics(const unsigned buffer_size, int buffer[const], int order, int order_fail)
+{
+    int i = 0;
+    _Atomic int x = 34;
+    __c11_atomic_store(&x, 55, order);
+    buffer[i++] = __c11_atomic_load(&x, order_fail);
+    buffer[i++] = __c11_atomic_exchange(&x, 33, order);
+    buffer[i++] = __c11_atomic_fetch_add(&x, 17, order);
+    buffer[i++] = __c11_atomic_load(&x, __ATOMIC_SEQ_CST);
+
+    int expected = 50;
+    buffer[i++] = __c11_atomic_compare_exchange_strong(&x, &expected, 77, order, order_fail);
+    buffer[i++] = expected;
+    buffer[i++] = __c11_atomic_compare_exchange_strong(&x, &expected, 77, order, order_fail);
+    buffer[i++] = expected;
+    buffer[i++] = __c11_atomic_load(&x, order_fail);
+
+    expected = 77;
+    int ok = 0;
+    do {
+        ok = __c11_atomic_compare_exchange_weak(&x, &expected, 137, order, order_fail);
+    } while (!ok && expected == 77);
+    buffer[i++] = ok;
+    buffer[i++] = expected;
+    buffer[i++] = __c11_atomic_load(&x, order_fail);
+}
+
+void atomic_increments(_Atomic int *counter, int n)
+{
+    for (int i = 0; i < n; i++) {
+        (*counter)++;
+    }
+}
+
+struct atomic_fields {
+    _Atomic unsigned flags;
+    _Atomic long values[2];
+};
+
+void atomic_accesses(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+    _Atomic int x = 34;
+    struct atomic_fields fields = { 0 };
+
+    buffer[i++] = x++;           buffer[i++] = x;
+    buffer[i++] = ++x;           buffer[i++] = x--;
+    buffer[i++] = --x;           buffer[i++] = x += 55;
+    buffer[i++] = x -= 17;       buffer[i++] = x;
+    buffer[i++] = x = 7;         buffer[i++] = x;
+
+    fields.flags |= 0xF0;        buffer[i++] = fields.flags;
+    buffer[i++] = fields.flags &= 0x3C;
+    buffer[i++] = fields.flags ^= 0xFF;
+    fields.values[1] = x;
+    fields.values[1] += 100;     buffer[i++] = fields.values[1];
+    buffer[i++] = fields.values[0]++;
+    buffer[i++] = fields.values[0];
+}
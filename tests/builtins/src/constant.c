@@ -0,0 +1,28 @@
+#include <stddef.h>
+
+struct pair {
+    int a;
+    char b;
+};
+
+_Static_assert(sizeof(struct pair) == 8, "pair is padded to two ints");
+_Static_assert(offsetof(struct pair, b) == sizeof(int), "b {follows} a");
+// Unsigned arithmetic is not translated in constant expressions
+_Static_assert(sizeof(int) + 1 == 5, "int has four bytes");
+
+#define IS_CONST(x) __builtin_constant_p(x)
+#define SIZE_CLASS(x)                                         \
+    __builtin_choose_expr(sizeof(x) == sizeof(char), 1,      \
+        __builtin_choose_expr(sizeof(x) == sizeof(int), 4, 8))
+
+int constant_p(int x) {
+    _Static_assert(IS_CONST(42), "literals are constant");
+    return IS_CONST(42) * 100 + IS_CONST(x) * 10 + IS_CONST(sizeof(x));
+}
+
+int choose(void) {
+    char c = 0;
+    int i = 0;
+    long l = 0;
+    return SIZE_CLASS(c) * 100 + SIZE_CLASS(i) * 10 + SIZE_CLASS(l);
+}
@@ -1,7 +1,8 @@
 //! feature_core_intrinsics, extern_crate_core
 extern crate libc;
 
-use atomics::{rust_atomics_entry, rust_new_atomics};
+use atomics::{rust_atomic_accesses, rust_atomic_increments, rust_atomics_entry, rust_c11_atomics};
+use atomics::{rust_dynamic_atomics, rust_new_atomics};
 use mem_x_fns::rust_mem_x;
 use math::{rust_ffs, rust_ffsl, rust_ffsll};
 use self::libc::{c_int, c_uint, c_char, c_long, c_longlong};
@@ -13,6 +14,12 @@ extern "C" {
     #[no_mangle]
     fn new_atomics(_: c_uint, _: *mut c_int);
     #[no_mangle]
+    fn dynamic_atomics(_: c_uint, _: *mut c_int, _: c_int, _: c_int, _: c_int);
+    #[no_mangle]
+    fn c11_atomics(_: c_uint, _: *mut c_int, _: c_int, _: c_int);
+    #[no_mangle]
+    fn atomic_accesses(_: c_uint, _: *mut c_int);
+    #[no_mangle]
     fn mem_x(_: *const c_char, _: *mut c_char);
     #[no_mangle]
     fn ffs(_: c_int) -> c_int;
@@ -54,6 +61,88 @@ pub fn test_new_atomics() {
     }
 }
 
+pub fn test_dynamic_atomics() {
+    // (success, failure) pairs of `__ATOMIC_*` values, including an invalid failure ordering
+    let orders = [(0, 0), (2, 2), (3, 0), (4, 2), (5, 5), (5, 3)];
+
+    for &(order, order_fail) in &orders {
+        for weak in 0..2 {
+            let mut buffer = [0; BUFFER_SIZE];
+            let mut rust_buffer = [0; BUFFER_SIZE];
+
+            unsafe {
+                dynamic_atomics(BUFFER_SIZE as u32, buffer.as_mut_ptr(), order, order_fail, weak);
+                rust_dynamic_atomics(
+                    BUFFER_SIZE as u32,
+                    rust_buffer.as_mut_ptr(),
+                    order,
+                    order_fail,
+                    weak,
+                );
+            }
+
+            for index in 0..BUFFER_SIZE {
+                assert_eq!(buffer[index], rust_buffer[index]);
+            }
+        }
+    }
+}
+
+pub fn test_c11_atomics() {
+    // (success, failure) pairs of `__ATOMIC_*` values
+    let orders = [(0, 0), (2, 2), (3, 0), (4, 2), (5, 5)];
+
+    for &(order, order_fail) in &orders {
+        let mut buffer = [0; BUFFER_SIZE];
+        let mut rust_buffer = [0; BUFFER_SIZE];
+
+        unsafe {
+            c11_atomics(BUFFER_SIZE as u32, buffer.as_mut_ptr(), order, order_fail);
+            rust_c11_atomics(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr(), order, order_fail);
+        }
+
+        assert_eq!(&buffer[..12], &[55, 55, 33, 50, 1, 50, 0, 77, 77, 1, 77, 137]);
+        for index in 0..BUFFER_SIZE {
+            assert_eq!(buffer[index], rust_buffer[index]);
+        }
+    }
+}
+
+pub fn test_atomic_accesses() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+
+    unsafe {
+        atomic_accesses(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_atomic_accesses(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    for index in 0..BUFFER_SIZE {
+        assert_eq!(buffer[index], rust_buffer[index]);
+    }
+}
+
+pub fn test_atomic_increments() {
+    // Increments of an `_Atomic int` must not lose updates when they race
+    const THREADS: usize = 4;
+    const INCREMENTS: c_int = 100_000;
+
+    let mut counter: c_int = 0;
+    let counter_addr = &mut counter as *mut c_int as usize;
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            ::std::thread::spawn(move || unsafe {
+                rust_atomic_increments(counter_addr as *mut c_int, INCREMENTS);
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(counter, THREADS as c_int * INCREMENTS);
+}
+
 pub fn test_mem_fns() {
     let const_string = "I am ten!\0";
     let mut buffer = [0; BUFFER_SIZE2];
@@ -0,0 +1,35 @@
+extern crate libc;
+
+use constant::{rust_choose, rust_constant_p};
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn constant_p(_: c_int) -> c_int;
+    #[no_mangle]
+    fn choose() -> c_int;
+}
+
+pub fn test_constant_p() {
+    unsafe {
+        assert_eq!(constant_p(7), rust_constant_p(7));
+    }
+}
+
+pub fn test_choose_expr() {
+    unsafe {
+        assert_eq!(choose(), rust_choose());
+    }
+}
+
+pub fn test_static_asserts() {
+    let src = include_str!("constant.rs");
+
+    assert!(src.contains("const _: [(); 0 - !("));
+    assert!(src.contains("as usize] = [];"));
+    assert!(src.contains("// _Static_assert: \"pair is padded to two ints\""));
+    assert!(src.contains("// _Static_assert: \"b {follows} a\""));
+    assert!(src.contains("// _Static_assert: \"literals are constant\""));
+    assert!(src.contains("// _Static_assert without a constant Rust translation: \"int has four bytes\""));
+}
@@ -0,0 +1,37 @@
+#include <complex.h>
+
+double _Complex make_complex(double re, double im) {
+    double _Complex z;
+    __real__ z = re;
+    __imag__ z = im;
+    return z;
+}
+
+double _Complex complex_arith(double _Complex a, double _Complex b) {
+    double _Complex c = a * b;
+    c += a;
+    c -= b / a;
+    c = c * 2.0;
+    return ~c;
+}
+
+float _Complex complex_casts(double _Complex a, int x) {
+    int _Complex ic = (int _Complex) a;
+    double _Complex from_real = x;
+    float _Complex f = a + from_real;
+    __imag__ f += __real__ ic;
+    return f;
+}
+
+double complex_parts(double _Complex a) {
+    return creal(a) * 10.0 + cimag(a) + __builtin_creal(conj(a));
+}
+
+int complex_compare(double _Complex a, double _Complex b) {
+    int res = 0;
+    if (a == b) res += 1;
+    if (a != 0.0) res += 2;
+    if (a) res += 4;
+    if (!b) res += 8;
+    return res;
+}
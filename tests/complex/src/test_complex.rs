@@ -0,0 +1,62 @@
+//! extern_crate_num_complex
+
+extern crate libc;
+extern crate num_complex;
+
+use complex::{rust_complex_arith, rust_complex_casts, rust_complex_compare, rust_complex_parts, rust_make_complex};
+use self::libc::{c_double, c_int};
+use self::num_complex::Complex;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn make_complex(_: c_double, _: c_double) -> Complex<c_double>;
+    #[no_mangle]
+    fn complex_arith(_: Complex<c_double>, _: Complex<c_double>) -> Complex<c_double>;
+    #[no_mangle]
+    fn complex_casts(_: Complex<c_double>, _: c_int) -> Complex<f32>;
+    #[no_mangle]
+    fn complex_parts(_: Complex<c_double>) -> c_double;
+    #[no_mangle]
+    fn complex_compare(_: Complex<c_double>, _: Complex<c_double>) -> c_int;
+}
+
+pub fn test_make_complex() {
+    unsafe {
+        assert_eq!(make_complex(1.5, -2.0), Complex::new(1.5, -2.0));
+        assert_eq!(rust_make_complex(1.5, -2.0), Complex::new(1.5, -2.0));
+    }
+}
+
+pub fn test_complex_arith() {
+    let a = Complex::new(1.0, 2.0);
+    let b = Complex::new(-3.0, 0.5);
+    unsafe {
+        assert_eq!(complex_arith(a, b), rust_complex_arith(a, b));
+    }
+}
+
+pub fn test_complex_casts() {
+    let a = Complex::new(2.75, -1.25);
+    unsafe {
+        assert_eq!(complex_casts(a, 3), rust_complex_casts(a, 3));
+    }
+}
+
+pub fn test_complex_parts() {
+    let a = Complex::new(4.0, 7.0);
+    unsafe {
+        assert_eq!(complex_parts(a), 51.0);
+        assert_eq!(rust_complex_parts(a), 51.0);
+    }
+}
+
+pub fn test_complex_compare() {
+    let a = Complex::new(1.0, 1.0);
+    let zero = Complex::new(0.0, 0.0);
+    unsafe {
+        assert_eq!(complex_compare(a, a), rust_complex_compare(a, a));
+        assert_eq!(complex_compare(a, zero), rust_complex_compare(a, zero));
+        assert_eq!(complex_compare(zero, a), rust_complex_compare(zero, a));
+    }
+}
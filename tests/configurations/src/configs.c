@@ -0,0 +1,23 @@
+//! configuration_small=-DSMALL, configuration_wide=-DWIDE, emit_build_files
+
+// Translated in the `small` and `wide` configurations; libtest.a is built
+// without either define and matches `small`, which is enabled by default
+#ifdef WIDE
+#define LIMIT 4096
+#else
+#define LIMIT 16
+#endif
+
+int shared(int x) {
+    return x * 2;
+}
+
+#ifdef WIDE
+int wide_only(void) {
+    return 1;
+}
+#endif
+
+int limit(void) {
+    return LIMIT;
+}
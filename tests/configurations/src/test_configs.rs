@@ -0,0 +1,41 @@
+extern crate libc;
+
+use configs::{rust_limit, rust_shared};
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn shared(_: c_int) -> c_int;
+
+    #[no_mangle]
+    fn limit() -> c_int;
+}
+
+pub fn test_default_configuration() {
+    unsafe {
+        assert_eq!(shared(21), rust_shared(21));
+        assert_eq!(limit(), rust_limit());
+        assert_eq!(rust_limit(), 16);
+    }
+}
+
+pub fn test_merged_items() {
+    let src = include_str!("configs.rs");
+
+    // Items that are the same in both configurations are emitted once
+    assert_eq!(src.matches("fn rust_shared(").count(), 1);
+    assert_eq!(src.matches("fn rust_limit(").count(), 2);
+    assert!(src.contains("#[cfg(feature = \"small\")]"));
+
+    // The item only in `wide` is guarded by that feature alone
+    let before = &src[..src.find("fn rust_wide_only(").unwrap()];
+    let guard = &before[before.rfind("#[cfg(").unwrap()..];
+    assert!(guard.starts_with("#[cfg(feature = \"wide\")]"));
+}
+
+pub fn test_generated_features() {
+    let cargo_toml = include_str!("Cargo.toml");
+
+    assert!(cargo_toml.contains("[features]\ndefault = [\"small\"]\nsmall = []\nwide = []\n"));
+}
@@ -0,0 +1,45 @@
+//! rust_enums
+
+// Only ever assigned its own constants, so it becomes a Rust enum
+enum Direction { North, East, South, West };
+
+// Used as bit-flags, so it stays an integer type
+enum Perms { Read = 1, Write = 2, Exec = 4 };
+
+// Other translation units can pass it to `color_code`, so it stays an integer
+// type to keep the C ABI
+enum Color { Red, Green };
+
+// Stored in a struct field, so it stays an integer type
+enum Shape { Circle, Square };
+
+struct Cell { enum Shape shape; };
+
+int color_code(enum Color c) { return c == Green; }
+
+int cell_is_square(const struct Cell *cell) { return cell->shape == Square; }
+
+static enum Direction turn(enum Direction d) {
+  switch (d) {
+  case North: return East;
+  case East: return South;
+  case South: return West;
+  default: return North;
+  }
+}
+
+void entry6(const unsigned buffer_size, int buffer[]) {
+  if (buffer_size < 6) { return; }
+
+  enum Direction d = North;
+  for (int i = 0; i < 4; i++) {
+    buffer[i] = d;
+    d = turn(d);
+  }
+
+  // Comparisons go through the underlying integer values
+  buffer[4] = d == North && d != West;
+
+  enum Perms p = Read | Exec;
+  buffer[5] = p;
+}
@@ -0,0 +1,42 @@
+extern crate libc;
+
+use rust_enums::{rust_entry6, Color, Direction, Exec, Green, Perms, Shape, Square};
+
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn entry6(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE6: usize = 6;
+
+pub fn test_rust_enum() {
+    let d: Direction = Direction::South;
+    assert_eq!(d as u32, 2);
+    assert!(Direction::West == Direction::West);
+
+    let p: Perms = Exec;
+    assert_eq!(p, 4);
+
+    let c: Color = Green;
+    assert_eq!(c, 1);
+
+    let s: Shape = Square;
+    assert_eq!(s, 1);
+}
+
+pub fn test_buffer6() {
+    let mut buffer = [0; BUFFER_SIZE6];
+    let mut rust_buffer = [0; BUFFER_SIZE6];
+    let expected_buffer = [0, 1, 2, 3, 1, 5];
+
+    unsafe {
+        entry6(BUFFER_SIZE6 as u32, buffer.as_mut_ptr());
+        rust_entry6(BUFFER_SIZE6 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,34 @@
+//! allow_current_block
+
+enum { OP_INC, OP_DOUBLE, OP_DEC, OP_HALT };
+
+int run_bytecode(const unsigned char *code) {
+    static void *dispatch[] = { &&do_inc, &&do_double, &&do_dec, &&do_halt };
+    int acc = 0;
+
+#define NEXT goto *dispatch[*code++]
+    NEXT;
+
+do_inc:
+    acc += 1;
+    NEXT;
+do_double:
+    acc *= 2;
+    NEXT;
+do_dec:
+    acc -= 1;
+    NEXT;
+do_halt:
+    return acc;
+#undef NEXT
+}
+
+int pick_label(int which) {
+    void *target = which ? &&second : &&first;
+    goto *target;
+
+first:
+    return 10;
+second:
+    return 20;
+}
@@ -0,0 +1,33 @@
+extern crate libc;
+
+use computed_goto::{rust_pick_label, rust_run_bytecode};
+use libc::{c_int, c_uchar};
+
+#[link(name = "test")]
+extern "C" {
+    fn run_bytecode(code: *const c_uchar) -> c_int;
+
+    fn pick_label(which: c_int) -> c_int;
+}
+
+pub fn test_run_bytecode() {
+    let programs: [&[c_uchar]; 3] = [
+        &[3],
+        &[0, 1, 1, 2, 3],
+        &[0, 0, 0, 1, 2, 1, 0, 3],
+    ];
+
+    for program in programs.iter() {
+        let expected = unsafe { run_bytecode(program.as_ptr()) };
+        let actual = unsafe { rust_run_bytecode(program.as_ptr()) };
+        assert_eq!(expected, actual);
+    }
+}
+
+pub fn test_pick_label() {
+    for which in 0..2 {
+        let expected = unsafe { pick_label(which) };
+        let actual = unsafe { rust_pick_label(which) };
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,51 @@
+//! fixed_width_ints
+
+#include <stddef.h>
+#include <stdint.h>
+
+struct buffer {
+    size_t len;
+    uint8_t data[16];
+};
+
+uint32_t mix(uint32_t a, uint16_t b, int8_t c) {
+    return a * 31 + b - c;
+}
+
+size_t element_bytes(size_t count) {
+    // `sizeof` has type `unsigned long` rather than `size_t`
+    unsigned long size = sizeof(int32_t);
+    size_t total = count * sizeof(int64_t);
+    total += size;
+    return count > 4 ? total : size;
+}
+
+unsigned long buffer_len(const struct buffer *buf) {
+    return buf->len;
+}
+
+ptrdiff_t distance(const uint8_t *from, const uint8_t *to) {
+    return to - from;
+}
+
+size_t fill_buffer(uint8_t value) {
+    struct buffer buf = { sizeof(buf.data) };
+    for (size_t i = 0; i < buf.len; i++) {
+        buf.data[i] = value + i;
+    }
+    return buffer_len(&buf) + buf.data[15];
+}
+
+static void count_bytes(size_t *total, const uint8_t *data, size_t len) {
+    for (size_t i = 0; i < len; i++) {
+        *total += data[i] != 0;
+    }
+}
+
+unsigned long nonzero_bytes(const uint8_t *data, unsigned long len) {
+    // `unsigned long *` is passed where clang sees the same `size_t *`
+    unsigned long n = 0;
+    count_bytes(&n, data, len);
+    size_t *p = &n;
+    return *p;
+}
@@ -0,0 +1,21 @@
+//! signed_overflow_wrap
+
+int overflow_add(int a, int b) {
+    return a + b;
+}
+
+int overflow_mul(int a, int b) {
+    return a * b;
+}
+
+long overflow_sub_long(long a, long b) {
+    return a - b;
+}
+
+int overflow_accumulate(int a, int b) {
+    int x = a;
+    x += b;
+    x *= 2;
+    x++;
+    return x;
+}
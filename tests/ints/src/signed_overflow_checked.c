@@ -0,0 +1,30 @@
+//! signed_overflow_checked
+
+// signed_overflow_trap.c translates these again with `--signed-overflow=trap`
+#ifndef NAME
+#define NAME(f) checked_##f
+#endif
+
+static int NAME(scaled) = 6 * 7 + 1;
+
+int NAME(static)(void) {
+    return NAME(scaled);
+}
+
+int NAME(arith)(int a, int b) {
+    int x = a * b;
+    x -= b;
+    x++;
+    return x + a;
+}
+
+signed char NAME(narrow_increment)(signed char c) {
+    c++;
+    c += 1;
+    return c;
+}
+
+short NAME(narrow_decrement)(short s) {
+    s--;
+    return s;
+}
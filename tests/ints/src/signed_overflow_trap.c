@@ -0,0 +1,4 @@
+//! signed_overflow_trap
+
+#define NAME(f) trap_##f
+#include "signed_overflow_checked.c"
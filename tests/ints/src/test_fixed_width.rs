@@ -0,0 +1,39 @@
+use fixed_width::{
+    rust_distance, rust_element_bytes, rust_fill_buffer, rust_mix, rust_nonzero_bytes,
+};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn mix(_: u32, _: u16, _: i8) -> u32;
+    #[no_mangle]
+    fn element_bytes(_: usize) -> usize;
+    #[no_mangle]
+    fn distance(_: *const u8, _: *const u8) -> isize;
+    #[no_mangle]
+    fn fill_buffer(_: u8) -> usize;
+    #[no_mangle]
+    fn nonzero_bytes(_: *const u8, _: u64) -> u64;
+}
+
+pub fn test_fixed_width_types() {
+    let bytes = [0u8; 8];
+    let (from, to) = (bytes.as_ptr(), unsafe { bytes.as_ptr().add(5) });
+    let data = [1u8, 0, 2, 3, 0, 0, 4, 0];
+
+    unsafe {
+        assert_eq!(mix(7, 3, -2), rust_mix(7u32, 3u16, -2i8));
+        assert_eq!(element_bytes(2), rust_element_bytes(2usize));
+        assert_eq!(element_bytes(8), rust_element_bytes(8usize));
+        assert_eq!(distance(from, to), rust_distance(from, to));
+        assert_eq!(fill_buffer(10), rust_fill_buffer(10u8));
+        assert_eq!(nonzero_bytes(data.as_ptr(), 8), rust_nonzero_bytes(data.as_ptr(), 8));
+    }
+}
+
+pub fn test_no_libc_aliases() {
+    let src = include_str!("fixed_width.rs");
+    assert!(!src.contains("pub type uint32_t"));
+    assert!(!src.contains("pub type size_t"));
+    assert!(src.contains("pub len: usize"));
+}
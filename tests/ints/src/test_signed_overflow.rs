@@ -0,0 +1,37 @@
+extern crate libc;
+
+use signed_overflow::{
+    rust_overflow_accumulate, rust_overflow_add, rust_overflow_mul, rust_overflow_sub_long,
+};
+use self::libc::{c_int, c_long};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn overflow_add(_: c_int, _: c_int) -> c_int;
+    #[no_mangle]
+    fn overflow_mul(_: c_int, _: c_int) -> c_int;
+    #[no_mangle]
+    fn overflow_sub_long(_: c_long, _: c_long) -> c_long;
+    #[no_mangle]
+    fn overflow_accumulate(_: c_int, _: c_int) -> c_int;
+}
+
+pub fn test_no_overflow() {
+    unsafe {
+        assert_eq!(overflow_add(40, 2), rust_overflow_add(40, 2));
+        assert_eq!(overflow_mul(-6, 7), rust_overflow_mul(-6, 7));
+        assert_eq!(overflow_sub_long(1, 3), rust_overflow_sub_long(1, 3));
+        assert_eq!(overflow_accumulate(10, 5), rust_overflow_accumulate(10, 5));
+    }
+}
+
+// Signed overflow is undefined in C, so only the translation is checked
+pub fn test_wrapping_overflow() {
+    unsafe {
+        assert_eq!(rust_overflow_add(c_int::max_value(), 1), c_int::min_value());
+        assert_eq!(rust_overflow_mul(0x10000, 0x10000), 0);
+        assert_eq!(rust_overflow_sub_long(c_long::min_value(), 1), c_long::max_value());
+        assert_eq!(rust_overflow_accumulate(c_int::max_value(), 0), -1);
+    }
+}
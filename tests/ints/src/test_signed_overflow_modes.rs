@@ -0,0 +1,87 @@
+extern crate libc;
+
+use signed_overflow_checked::{
+    rust_checked_arith, rust_checked_narrow_decrement, rust_checked_narrow_increment, rust_checked_static,
+};
+use signed_overflow_trap::{
+    rust_trap_arith, rust_trap_narrow_decrement, rust_trap_narrow_increment, rust_trap_static,
+};
+use self::libc::{c_int, c_schar, c_short};
+use std::env;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Output};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn checked_static() -> c_int;
+    #[no_mangle]
+    fn checked_arith(_: c_int, _: c_int) -> c_int;
+    #[no_mangle]
+    fn checked_narrow_increment(_: c_schar) -> c_schar;
+}
+
+pub fn test_no_overflow() {
+    unsafe {
+        assert_eq!(checked_static(), rust_checked_static());
+        assert_eq!(checked_static(), rust_trap_static());
+        assert_eq!(checked_arith(-6, 7), rust_checked_arith(-6, 7));
+        assert_eq!(checked_arith(-6, 7), rust_trap_arith(-6, 7));
+        assert_eq!(checked_arith(1000, 1000), rust_checked_arith(1000, 1000));
+        assert_eq!(checked_arith(1000, 1000), rust_trap_arith(1000, 1000));
+    }
+}
+
+// Narrow increments only overflow in the conversion back from `int`, so
+// they wrap instead of panicking or aborting
+pub fn test_narrow_wraps() {
+    unsafe {
+        assert_eq!(checked_narrow_increment(126), rust_checked_narrow_increment(126));
+        assert_eq!(checked_narrow_increment(126), rust_trap_narrow_increment(126));
+        assert_eq!(rust_checked_narrow_increment(c_schar::max_value()), c_schar::min_value() + 1);
+        assert_eq!(rust_trap_narrow_increment(c_schar::max_value()), c_schar::min_value() + 1);
+        assert_eq!(rust_checked_narrow_decrement(c_short::min_value()), c_short::max_value());
+        assert_eq!(rust_trap_narrow_decrement(c_short::min_value()), c_short::max_value());
+    }
+}
+
+// xfail
+pub fn test_checked_overflow() {
+    unsafe {
+        rust_checked_arith(c_int::max_value(), 2);
+    }
+}
+
+// xfail
+pub fn test_trap_overflow() {
+    unsafe {
+        rust_trap_arith(c_int::max_value(), 2);
+    }
+}
+
+/// Run one of the overflowing tests above in a child process
+fn run_overflow_test(name: &str) -> Output {
+    Command::new(env::current_exe().unwrap())
+        .arg(format!("test_signed_overflow_modes::{}", name))
+        .output()
+        .unwrap()
+}
+
+pub fn test_checked_overflow_message() {
+    let output = run_overflow_test("test_checked_overflow");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(101));
+    assert!(
+        stderr.contains("signed overflow at signed_overflow_checked.c:15"),
+        "unexpected panic: {}",
+        stderr
+    );
+}
+
+pub fn test_trap_overflow_aborts() {
+    let output = run_overflow_test("test_trap_overflow");
+
+    assert_eq!(output.status.signal(), Some(libc::SIGABRT));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("panicked"));
+}
@@ -0,0 +1,28 @@
+//! bindings_only, translate_const_macros
+
+#include <stddef.h>
+
+#define SCALE_LIMIT 100
+
+typedef struct point {
+    int x;
+    int y;
+} point;
+
+enum axis { AXIS_X, AXIS_Y };
+
+int scale_calls = 0;
+
+static int clamp(int value) {
+    return value > SCALE_LIMIT ? SCALE_LIMIT : value;
+}
+
+void scale_point(point *p, int factor) {
+    scale_calls++;
+    p->x = clamp(p->x * factor);
+    p->y = clamp(p->y * factor);
+}
+
+int point_coord(const point *p, enum axis axis) {
+    return axis == AXIS_X ? p->x : p->y;
+}
@@ -0,0 +1,27 @@
+use bindings::{point, point_coord, scale_calls, scale_point, AXIS_X, AXIS_Y, SCALE_LIMIT};
+
+// The bindings module only declares the C functions, so these calls go
+// straight to the C implementations
+pub fn test_bindings() {
+    let mut p = point { x: 3, y: 40 };
+
+    unsafe {
+        scale_point(&mut p, 2);
+        assert_eq!(scale_calls, 1);
+        assert_eq!(point_coord(&p, AXIS_X), 6);
+        assert_eq!(point_coord(&p, AXIS_Y), 80);
+
+        scale_point(&mut p, 2);
+        assert_eq!(scale_calls, 2);
+        assert_eq!(point_coord(&p, AXIS_Y), SCALE_LIMIT);
+    }
+}
+
+pub fn test_bindings_source() {
+    let src = include_str!("bindings.rs");
+
+    // Definitions stay in C and internal functions get no bindings
+    assert!(!src.contains("fn clamp"));
+    assert!(!src.contains("unsafe extern \"C\" fn"));
+    assert!(src.contains("fn scale_point(p: *mut point, factor: libc::c_int);"));
+}
@@ -1,3 +1,5 @@
+//! long_double_f128
+
 long double long_double_ops(long double *a) {
     long double b = 1.3L;
     double rounder = 0.0;
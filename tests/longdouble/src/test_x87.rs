@@ -0,0 +1,94 @@
+//! extern_crate_c2rust_x87, extern_crate_num_traits
+
+extern crate libc;
+
+use c2rust_x87::f80;
+use x87::{rust_x87_arith, rust_x87_convert, rust_x87_from_ints, rust_x87_ops};
+use x87::{rust_x87_neg, rust_x87_tenth, rust_x87_third};
+
+use self::libc::{c_double, c_longlong, c_uint, c_ulonglong};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn x87_arith(out: *mut f80, a: *const f80, b: *const f80);
+
+    #[no_mangle]
+    fn x87_ops(a: *mut f80);
+
+    #[no_mangle]
+    fn x87_convert(a: *const f80, d: *mut c_double, i: *mut c_longlong, u: *mut c_uint);
+
+    #[no_mangle]
+    fn x87_from_ints(out: *mut f80, i: c_longlong, u: c_ulonglong, d: c_double);
+
+    static x87_tenth: f80;
+    static x87_neg: f80;
+    static x87_third: f80;
+}
+
+const INPUTS: [(f64, f64); 4] = [(1.7, 0.3), (-2.5, 1e-300), (1e300, 3.0), (0.0, -7.0)];
+
+pub fn test_x87_arith() {
+    for &(a, b) in INPUTS.iter() {
+        let (a, b) = (f80::new(a), f80::new(b));
+        let mut out = [f80::ZERO; 5];
+        let mut rust_out = [f80::ZERO; 5];
+
+        unsafe {
+            x87_arith(out.as_mut_ptr(), &a, &b);
+            rust_x87_arith(rust_out.as_mut_ptr(), &a, &b);
+        }
+
+        for (x, y) in out.iter().zip(rust_out.iter()) {
+            assert_eq!(x.to_bits(), y.to_bits());
+        }
+    }
+}
+
+pub fn test_x87_ops() {
+    for &(a, _) in INPUTS.iter() {
+        let mut val = f80::new(a);
+        let mut rust_val = f80::new(a);
+
+        unsafe {
+            x87_ops(&mut val);
+            rust_x87_ops(&mut rust_val);
+        }
+
+        assert_eq!(val.to_bits(), rust_val.to_bits());
+    }
+}
+
+pub fn test_x87_conversions() {
+    let a = f80::new(123456.75f64) / f80::new(3);
+    let (mut d, mut i, mut u) = (0., 0, 0);
+    let (mut rust_d, mut rust_i, mut rust_u) = (0., 0, 0);
+
+    unsafe {
+        x87_convert(&a, &mut d, &mut i, &mut u);
+        rust_x87_convert(&a, &mut rust_d, &mut rust_i, &mut rust_u);
+    }
+
+    assert_eq!((d, i, u), (rust_d, rust_i, rust_u));
+
+    let mut out = [f80::ZERO; 3];
+    let mut rust_out = [f80::ZERO; 3];
+
+    unsafe {
+        x87_from_ints(out.as_mut_ptr(), -(1 << 62) - 1, u64::max_value(), 1e-310);
+        rust_x87_from_ints(rust_out.as_mut_ptr(), -(1 << 62) - 1, u64::max_value(), 1e-310);
+    }
+
+    for (x, y) in out.iter().zip(rust_out.iter()) {
+        assert_eq!(x.to_bits(), y.to_bits());
+    }
+}
+
+pub fn test_x87_globals() {
+    unsafe {
+        assert_eq!(x87_tenth.to_bits(), rust_x87_tenth.to_bits());
+        assert_eq!(x87_neg.to_bits(), rust_x87_neg.to_bits());
+        assert_eq!(x87_third.to_bits(), rust_x87_third.to_bits());
+    }
+}
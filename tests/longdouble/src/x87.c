@@ -0,0 +1,38 @@
+//! long_double_x87
+
+void x87_arith(long double *out, const long double *a, const long double *b) {
+    out[0] = *a + *b;
+    out[1] = *a - *b;
+    out[2] = *a * *b;
+    out[3] = *a / *b;
+    out[4] = -*a;
+}
+
+void x87_ops(long double *a) {
+    long double b = 1.3L;
+
+    (*a)++;
+    ++b;
+    *a *= b;
+    *a /= 3;
+    *a -= 0.1L;
+    --*a;
+    b += *a;
+    *a = b--;
+}
+
+void x87_convert(const long double *a, double *d, long long *i, unsigned *u) {
+    *d = *a;
+    *i = *a;
+    *u = *a;
+}
+
+void x87_from_ints(long double *out, long long i, unsigned long long u, double d) {
+    out[0] = i;
+    out[1] = u;
+    out[2] = d;
+}
+
+long double x87_tenth = 0.1L;
+long double x87_neg = -2;
+const long double x87_third = 1.0L / 3;
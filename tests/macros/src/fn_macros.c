@@ -0,0 +1,35 @@
+//! translate_fn_macros
+
+// Only ever expanded with int arguments, so this becomes a function
+#define AREA(w, h) ((w) * (h))
+
+// Expanded with different argument types, so this becomes a macro_rules!
+#define MIN(a, b) ((a) < (b) ? (a) : (b))
+
+// Expanded with an argument with side effects, so this stays inline
+#define SQUARE(x) ((x) * (x))
+
+int int_area(int w, int h) {
+  return AREA(w, h);
+}
+
+int rect_areas(int w, int h) {
+  return AREA(w + 1, h) + AREA(w, h + 1);
+}
+
+int min_int(int x, int y) {
+  return MIN(x, y);
+}
+
+double min_double(double x, double y) {
+  return MIN(x, y);
+}
+
+static int next(int *counter) {
+  return ++*counter;
+}
+
+int square_side_effects(int x) {
+  int y = SQUARE(next(&x));
+  return y + x;
+}
@@ -0,0 +1,29 @@
+extern crate libc;
+
+use fn_macros::{rust_int_area, rust_rect_areas, rust_min_int, rust_min_double};
+use fn_macros::rust_square_side_effects;
+use self::libc::{c_double, c_int};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn int_area(_: c_int, _: c_int) -> c_int;
+    #[no_mangle]
+    fn rect_areas(_: c_int, _: c_int) -> c_int;
+    #[no_mangle]
+    fn min_int(_: c_int, _: c_int) -> c_int;
+    #[no_mangle]
+    fn min_double(_: c_double, _: c_double) -> c_double;
+    #[no_mangle]
+    fn square_side_effects(_: c_int) -> c_int;
+}
+
+pub fn test_fn_macros() {
+    unsafe {
+        assert_eq!(rust_int_area(3, 4), int_area(3, 4));
+        assert_eq!(rust_rect_areas(3, 4), rect_areas(3, 4));
+        assert_eq!(rust_min_int(3, -4), min_int(3, -4));
+        assert_eq!(rust_min_double(0.5, 1.5), min_double(0.5, 1.5));
+        assert_eq!(rust_square_side_effects(3), square_side_effects(3));
+    }
+}
@@ -0,0 +1,6 @@
+//! main_binary
+
+// Only counting the arguments must not need them to be valid UTF-8
+int main(int argc) {
+    return argc == 3 ? 0 : 1;
+}
@@ -0,0 +1,24 @@
+//! main_binary
+
+#include <stdlib.h>
+#include <string.h>
+
+// Run with the arguments `first` and the bytes 0xff 0xfe, which are not
+// UTF-8, and with C2RUST_MAIN_TEST set to the byte 0xff
+int main(int argc, char *argv[], char *envp[]) {
+    if (argc != 3 || argv[3] != NULL) {
+        return 1;
+    }
+    if (strcmp(argv[1], "first") != 0) {
+        return 2;
+    }
+    if (strcmp(argv[2], "\xff\xfe") != 0) {
+        return 3;
+    }
+    for (char **var = envp; *var != NULL; var++) {
+        if (strcmp(*var, "C2RUST_MAIN_TEST=\xff") == 0) {
+            return 0;
+        }
+    }
+    return 4;
+}
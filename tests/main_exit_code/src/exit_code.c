@@ -0,0 +1,11 @@
+//! main_binary, main_exit_code, emit_build_files, exit_status_42
+
+#include <string.h>
+
+int main(int argc, char *argv[]) {
+    if (argc != 3 || strcmp(argv[2], "\xff\xfe") != 0) {
+        return 1;
+    }
+    // Only the low byte of the status reaches the parent process
+    return 256 + 42;
+}
@@ -0,0 +1,21 @@
+#include <setjmp.h>
+
+static jmp_buf error_env;
+
+static int checked_div(int a, int b) {
+    if (b == 0) {
+        longjmp(error_env, 1);
+    }
+    return a / b;
+}
+
+int safe_div(int a, int b, int *result) {
+    int ok = 0;
+    if (setjmp(error_env) == 0) {
+        *result = checked_div(a, b);
+        ok = 1;
+    } else {
+        *result = -1;
+    }
+    return ok;
+}
@@ -0,0 +1,22 @@
+extern crate libc;
+
+use setjmp::rust_safe_div;
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn safe_div(_: c_int, _: c_int, _: *mut c_int) -> c_int;
+}
+
+pub fn test_setjmp() {
+    for &(a, b) in &[(7, 2), (9, 3), (5, 0), (0, 0)] {
+        let mut result = 0;
+        let mut rust_result = 0;
+        let ok = unsafe { safe_div(a, b, &mut result) };
+        let rust_ok = unsafe { rust_safe_div(a, b, &mut rust_result) };
+
+        assert_eq!(ok, rust_ok);
+        assert_eq!(result, rust_result);
+    }
+}
@@ -0,0 +1,143 @@
+#include <string.h>
+
+// Vectors using clang's generic vector extensions rather than the x86 headers.
+// Those with the layout of an x86 SIMD type are translated to it, the others
+// (v2sf, v4qu) are translated to arrays.
+typedef int v2si __attribute__((vector_size(8)));
+typedef float v2sf __attribute__((vector_size(8)));
+typedef double v2df __attribute__((vector_size(16)));
+typedef unsigned char v4qu __attribute__((vector_size(4)));
+typedef float v4sf __attribute__((vector_size(16)));
+typedef int v4si __attribute__((vector_size(16)));
+typedef short v8hi __attribute__((vector_size(16)));
+typedef int v8si __attribute__((vector_size(32)));
+typedef float v8sf __attribute__((vector_size(32)));
+typedef signed char v16qi __attribute__((vector_size(16)));
+typedef unsigned char v16qu __attribute__((vector_size(16)));
+
+void shuffle_2(const int *ints, const double *doubles, const float *floats,
+               int *int_out, double *double_out, float *float_out) {
+    v2si a, b;
+    memcpy(&a, ints, sizeof(a));
+    memcpy(&b, ints + 2, sizeof(b));
+    v2si r = __builtin_shufflevector(a, b, 3, 0);
+    memcpy(int_out, &r, sizeof(r));
+
+    v2df c, d;
+    memcpy(&c, doubles, sizeof(c));
+    memcpy(&d, doubles + 2, sizeof(d));
+    v2df s = __builtin_shufflevector(c, d, 1, 2);
+    memcpy(double_out, &s, sizeof(s));
+
+    v2sf e, f;
+    memcpy(&e, floats, sizeof(e));
+    memcpy(&f, floats + 2, sizeof(f));
+    v2sf t = __builtin_shufflevector(e, f, 1, 2);
+    memcpy(float_out, &t, sizeof(t));
+}
+
+void shuffle_4(const int *ints, const float *floats, const unsigned char *bytes,
+               int *int_out, float *float_out, unsigned char *byte_out) {
+    v4si a, b;
+    memcpy(&a, ints, sizeof(a));
+    memcpy(&b, ints + 4, sizeof(b));
+    v4si r1 = __builtin_shufflevector(a, b, 3, 2, 1, 0);
+    v4si r2 = __builtin_shufflevector(a, b, 7, 7, 4, 5);
+    v8si r3 = __builtin_shufflevector(a, b, 0, 4, 1, 5, 2, 6, 3, 7);
+    memcpy(int_out, &r1, sizeof(r1));
+    memcpy(int_out + 4, &r2, sizeof(r2));
+    memcpy(int_out + 8, &r3, sizeof(r3));
+
+    v4sf c, d;
+    memcpy(&c, floats, sizeof(c));
+    memcpy(&d, floats + 4, sizeof(d));
+    v4sf s1 = __builtin_shufflevector(c, d, 2, 0, 7, 5);
+    v4sf s2 = __builtin_shufflevector(c, d, 0, 4, 1, 5);
+    memcpy(float_out, &s1, sizeof(s1));
+    memcpy(float_out + 4, &s2, sizeof(s2));
+
+    v4qu e, f;
+    memcpy(&e, bytes, sizeof(e));
+    memcpy(&f, bytes + 4, sizeof(f));
+    v4qu t = __builtin_shufflevector(e, f, 3, 2, 5, 4);
+    memcpy(byte_out, &t, sizeof(t));
+}
+
+void shuffle_8(const short *shorts, const float *floats,
+               short *short_out, float *float_out) {
+    v8hi a, b;
+    memcpy(&a, shorts, sizeof(a));
+    memcpy(&b, shorts + 8, sizeof(b));
+    v8hi r1 = __builtin_shufflevector(a, b, 7, 6, 5, 4, 3, 2, 1, 0);
+    v8hi r2 = __builtin_shufflevector(a, b, 0, 8, 2, 10, 4, 12, 6, 14);
+    memcpy(short_out, &r1, sizeof(r1));
+    memcpy(short_out + 8, &r2, sizeof(r2));
+
+    v8sf c;
+    memcpy(&c, floats, sizeof(c));
+    v4sf s = __builtin_shufflevector(c, c, 1, 3, 5, 7);
+    memcpy(float_out, &s, sizeof(s));
+}
+
+void shuffle_16(const signed char *bytes, signed char *byte_out) {
+    v16qi a, b;
+    memcpy(&a, bytes, sizeof(a));
+    memcpy(&b, bytes + 16, sizeof(b));
+    v16qi r = __builtin_shufflevector(a, b, 0, 16, 1, 17, 2, 18, 3, 19,
+                                      4, 20, 5, 21, 6, 22, 7, 23);
+    memcpy(byte_out, &r, sizeof(r));
+}
+
+void convert_2(const int *ints, const float *floats,
+               double *double_out, int *int_out) {
+    v2si a;
+    memcpy(&a, ints, sizeof(a));
+    v2df r = __builtin_convertvector(a, v2df);
+    memcpy(double_out, &r, sizeof(r));
+
+    v2sf b;
+    memcpy(&b, floats, sizeof(b));
+    v2si s = __builtin_convertvector(b, v2si);
+    memcpy(int_out, &s, sizeof(s));
+}
+
+void convert_4(const int *ints, const float *floats, const unsigned char *bytes,
+               float *float_out, int *int_out) {
+    v4si a;
+    memcpy(&a, ints, sizeof(a));
+    v4sf r = __builtin_convertvector(a, v4sf);
+    memcpy(float_out, &r, sizeof(r));
+
+    v4sf b;
+    memcpy(&b, floats, sizeof(b));
+    v4si s = __builtin_convertvector(b, v4si);
+    memcpy(int_out, &s, sizeof(s));
+
+    v4qu c;
+    memcpy(&c, bytes, sizeof(c));
+    v4sf t = __builtin_convertvector(c, v4sf);
+    memcpy(float_out + 4, &t, sizeof(t));
+}
+
+void convert_8(const short *shorts, const int *ints,
+               float *float_out, short *short_out) {
+    v8hi a;
+    memcpy(&a, shorts, sizeof(a));
+    v8sf r = __builtin_convertvector(a, v8sf);
+    memcpy(float_out, &r, sizeof(r));
+
+    v8si b;
+    memcpy(&b, ints, sizeof(b));
+    v8hi s = __builtin_convertvector(b, v8hi);
+    memcpy(short_out, &s, sizeof(s));
+}
+
+void convert_16(const signed char *bytes, unsigned char *byte_out, float *float_out) {
+    v16qi a;
+    memcpy(&a, bytes, sizeof(a));
+    v16qu r = __builtin_convertvector(a, v16qu);
+    memcpy(byte_out, &r, sizeof(r));
+
+    v4sf s = __builtin_convertvector(__builtin_shufflevector(a, a, 0, 5, 10, 15), v4sf);
+    memcpy(float_out, &s, sizeof(s));
+}
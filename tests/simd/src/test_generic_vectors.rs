@@ -0,0 +1,159 @@
+extern crate libc;
+
+use generic_vectors::{
+    rust_convert_16, rust_convert_2, rust_convert_4, rust_convert_8, rust_shuffle_16,
+    rust_shuffle_2, rust_shuffle_4, rust_shuffle_8,
+};
+use self::libc::{c_double, c_float, c_int, c_schar, c_short, c_uchar};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn shuffle_2(_: *const c_int, _: *const c_double, _: *const c_float, _: *mut c_int, _: *mut c_double, _: *mut c_float);
+    #[no_mangle]
+    fn shuffle_4(_: *const c_int, _: *const c_float, _: *const c_uchar, _: *mut c_int, _: *mut c_float, _: *mut c_uchar);
+    #[no_mangle]
+    fn shuffle_8(_: *const c_short, _: *const c_float, _: *mut c_short, _: *mut c_float);
+    #[no_mangle]
+    fn shuffle_16(_: *const c_schar, _: *mut c_schar);
+    #[no_mangle]
+    fn convert_2(_: *const c_int, _: *const c_float, _: *mut c_double, _: *mut c_int);
+    #[no_mangle]
+    fn convert_4(_: *const c_int, _: *const c_float, _: *const c_uchar, _: *mut c_float, _: *mut c_int);
+    #[no_mangle]
+    fn convert_8(_: *const c_short, _: *const c_int, _: *mut c_float, _: *mut c_short);
+    #[no_mangle]
+    fn convert_16(_: *const c_schar, _: *mut c_uchar, _: *mut c_float);
+}
+
+const INTS: [c_int; 8] = [1, -2, 3, -4, 5, 70000, -70000, 8];
+const FLOATS: [c_float; 8] = [0.5, -1.5, 2.75, -3.25, 1e9, -7.9, 6.1, 100.0];
+const DOUBLES: [c_double; 4] = [1.25, -2.5, 3.75, -5.0];
+const SHORTS: [c_short; 16] = [1, -2, 3, -4, 5, -6, 7, -8, 900, -1000, 1100, -1200, 1300, -1400, 1500, -1600];
+const BYTES: [c_uchar; 8] = [0, 1, 127, 128, 200, 255, 17, 42];
+const SIGNED_BYTES: [c_schar; 32] = [
+    0, 1, -1, 2, -2, 3, -3, 4, -4, 5, -5, 6, -6, 7, -7, 127,
+    -128, 100, -100, 50, -50, 25, -25, 12, -12, 6, -6, 3, -3, 1, -1, 0,
+];
+
+pub fn test_shuffle_2() {
+    let (mut ints, mut doubles, mut floats) = ([0; 2], [0.; 2], [0.; 2]);
+    let (mut rust_ints, mut rust_doubles, mut rust_floats) = ([0; 2], [0.; 2], [0.; 2]);
+
+    unsafe {
+        shuffle_2(INTS.as_ptr(), DOUBLES.as_ptr(), FLOATS.as_ptr(), ints.as_mut_ptr(), doubles.as_mut_ptr(), floats.as_mut_ptr());
+        rust_shuffle_2(INTS.as_ptr(), DOUBLES.as_ptr(), FLOATS.as_ptr(), rust_ints.as_mut_ptr(), rust_doubles.as_mut_ptr(), rust_floats.as_mut_ptr());
+    }
+
+    assert_eq!(ints, [-4, 1]);
+    assert_eq!(ints, rust_ints);
+    assert_eq!(doubles, [-2.5, 3.75]);
+    assert_eq!(doubles, rust_doubles);
+    assert_eq!(floats, [-1.5, 2.75]);
+    assert_eq!(floats, rust_floats);
+}
+
+pub fn test_shuffle_4() {
+    let (mut ints, mut floats, mut bytes) = ([0; 16], [0.; 8], [0; 4]);
+    let (mut rust_ints, mut rust_floats, mut rust_bytes) = ([0; 16], [0.; 8], [0; 4]);
+
+    unsafe {
+        shuffle_4(INTS.as_ptr(), FLOATS.as_ptr(), BYTES.as_ptr(), ints.as_mut_ptr(), floats.as_mut_ptr(), bytes.as_mut_ptr());
+        rust_shuffle_4(INTS.as_ptr(), FLOATS.as_ptr(), BYTES.as_ptr(), rust_ints.as_mut_ptr(), rust_floats.as_mut_ptr(), rust_bytes.as_mut_ptr());
+    }
+
+    assert_eq!(ints, [-4, 3, -2, 1, 8, 8, 5, 70000, 1, 5, -2, 70000, 3, -70000, -4, 8]);
+    assert_eq!(ints, rust_ints);
+    assert_eq!(floats, [2.75, 0.5, 100.0, -7.9, 0.5, 1e9, -1.5, -7.9]);
+    assert_eq!(floats, rust_floats);
+    assert_eq!(bytes, [128, 127, 255, 200]);
+    assert_eq!(bytes, rust_bytes);
+}
+
+pub fn test_shuffle_8() {
+    let (mut shorts, mut floats) = ([0; 16], [0.; 4]);
+    let (mut rust_shorts, mut rust_floats) = ([0; 16], [0.; 4]);
+
+    unsafe {
+        shuffle_8(SHORTS.as_ptr(), FLOATS.as_ptr(), shorts.as_mut_ptr(), floats.as_mut_ptr());
+        rust_shuffle_8(SHORTS.as_ptr(), FLOATS.as_ptr(), rust_shorts.as_mut_ptr(), rust_floats.as_mut_ptr());
+    }
+
+    assert_eq!(shorts, [-8, 7, -6, 5, -4, 3, -2, 1, 1, 900, 3, 1100, 5, 1300, 7, 1500]);
+    assert_eq!(shorts, rust_shorts);
+    assert_eq!(floats, [-1.5, -3.25, -7.9, 100.0]);
+    assert_eq!(floats, rust_floats);
+}
+
+pub fn test_shuffle_16() {
+    let mut bytes = [0; 16];
+    let mut rust_bytes = [0; 16];
+
+    unsafe {
+        shuffle_16(SIGNED_BYTES.as_ptr(), bytes.as_mut_ptr());
+        rust_shuffle_16(SIGNED_BYTES.as_ptr(), rust_bytes.as_mut_ptr());
+    }
+
+    assert_eq!(bytes, [0, -128, 1, 100, -1, -100, 2, 50, -2, -50, 3, 25, -3, -25, 4, 12]);
+    assert_eq!(bytes, rust_bytes);
+}
+
+pub fn test_convert_2() {
+    let (mut doubles, mut ints) = ([0.; 2], [0; 2]);
+    let (mut rust_doubles, mut rust_ints) = ([0.; 2], [0; 2]);
+
+    unsafe {
+        convert_2(INTS.as_ptr(), FLOATS.as_ptr(), doubles.as_mut_ptr(), ints.as_mut_ptr());
+        rust_convert_2(INTS.as_ptr(), FLOATS.as_ptr(), rust_doubles.as_mut_ptr(), rust_ints.as_mut_ptr());
+    }
+
+    assert_eq!(doubles, [1.0, -2.0]);
+    assert_eq!(doubles, rust_doubles);
+    assert_eq!(ints, [0, -1]);
+    assert_eq!(ints, rust_ints);
+}
+
+pub fn test_convert_4() {
+    let (mut floats, mut ints) = ([0.; 8], [0; 4]);
+    let (mut rust_floats, mut rust_ints) = ([0.; 8], [0; 4]);
+
+    unsafe {
+        convert_4(INTS.as_ptr(), FLOATS.as_ptr(), BYTES.as_ptr(), floats.as_mut_ptr(), ints.as_mut_ptr());
+        rust_convert_4(INTS.as_ptr(), FLOATS.as_ptr(), BYTES.as_ptr(), rust_floats.as_mut_ptr(), rust_ints.as_mut_ptr());
+    }
+
+    assert_eq!(floats, [1.0, -2.0, 3.0, -4.0, 0.0, 1.0, 127.0, 128.0]);
+    assert_eq!(floats, rust_floats);
+    assert_eq!(ints, [0, -1, 2, -3]);
+    assert_eq!(ints, rust_ints);
+}
+
+pub fn test_convert_8() {
+    let (mut floats, mut shorts) = ([0.; 8], [0; 8]);
+    let (mut rust_floats, mut rust_shorts) = ([0.; 8], [0; 8]);
+
+    unsafe {
+        convert_8(SHORTS.as_ptr(), INTS.as_ptr(), floats.as_mut_ptr(), shorts.as_mut_ptr());
+        rust_convert_8(SHORTS.as_ptr(), INTS.as_ptr(), rust_floats.as_mut_ptr(), rust_shorts.as_mut_ptr());
+    }
+
+    assert_eq!(floats, [1.0, -2.0, 3.0, -4.0, 5.0, -6.0, 7.0, -8.0]);
+    assert_eq!(floats, rust_floats);
+    assert_eq!(shorts, [1, -2, 3, -4, 5, 70000u32 as c_short, -70000i32 as c_short, 8]);
+    assert_eq!(shorts, rust_shorts);
+}
+
+pub fn test_convert_16() {
+    let (mut bytes, mut floats) = ([0; 16], [0.; 4]);
+    let (mut rust_bytes, mut rust_floats) = ([0; 16], [0.; 4]);
+
+    unsafe {
+        convert_16(SIGNED_BYTES.as_ptr(), bytes.as_mut_ptr(), floats.as_mut_ptr());
+        rust_convert_16(SIGNED_BYTES.as_ptr(), rust_bytes.as_mut_ptr(), rust_floats.as_mut_ptr());
+    }
+
+    assert_eq!(bytes, [0, 1, 255, 2, 254, 3, 253, 4, 252, 5, 251, 6, 250, 7, 249, 127]);
+    assert_eq!(bytes, rust_bytes);
+    assert_eq!(floats, [0.0, 3.0, -5.0, 127.0]);
+    assert_eq!(floats, rust_floats);
+}
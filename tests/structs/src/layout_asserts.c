@@ -0,0 +1,55 @@
+//! emit_layout_asserts
+
+#include <stddef.h>
+
+struct plain {
+    char c;
+    int i;
+    double d;
+};
+
+struct __attribute__((packed)) packed {
+    char c;
+    int i;
+    short s;
+};
+
+struct aligned {
+    char c;
+    int i;
+} __attribute__((aligned(16)));
+
+struct with_bitfields {
+    unsigned a: 3;
+    unsigned b: 10;
+    char c;
+    unsigned long d: 40;
+};
+
+struct nested {
+    struct packed p;
+    struct aligned a;
+    char tail;
+};
+
+union mixed {
+    char c;
+    double d;
+    struct packed p;
+};
+
+void layout_sizes(size_t out[]) {
+    out[0] = sizeof(struct plain);
+    out[1] = sizeof(struct packed);
+    out[2] = sizeof(struct aligned);
+    out[3] = sizeof(struct with_bitfields);
+    out[4] = sizeof(struct nested);
+    out[5] = sizeof(union mixed);
+}
+
+void layout_offsets(size_t out[]) {
+    out[0] = offsetof(struct plain, i);
+    out[1] = offsetof(struct plain, d);
+    out[2] = offsetof(struct nested, a);
+    out[3] = offsetof(struct nested, tail);
+}
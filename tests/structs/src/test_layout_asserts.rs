@@ -0,0 +1,66 @@
+extern crate libc;
+
+use layout_asserts::{aligned, mixed, nested, packed, plain, rust_layout_sizes, with_bitfields};
+use layout_asserts::{layout_offsets_aligned, layout_offsets_nested, layout_offsets_plain};
+use layout_asserts::layout_offsets_with_bitfields;
+use self::libc::size_t;
+use std::mem::{size_of, zeroed};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn layout_sizes(_: *mut size_t);
+
+    #[no_mangle]
+    fn layout_offsets(_: *mut size_t);
+}
+
+// The layouts themselves are checked by the assertions emitted with
+// `--emit-layout-asserts` when the translated module compiles
+pub fn test_layout_sizes() {
+    let mut sizes = [0; 6];
+    let mut rust_sizes = [0; 6];
+
+    unsafe {
+        layout_sizes(sizes.as_mut_ptr());
+        rust_layout_sizes(rust_sizes.as_mut_ptr());
+    }
+
+    assert_eq!(sizes, rust_sizes);
+    assert_eq!(sizes, [
+        size_of::<plain>(),
+        size_of::<packed>(),
+        size_of::<aligned>(),
+        size_of::<with_bitfields>(),
+        size_of::<nested>(),
+        size_of::<mixed>(),
+    ]);
+}
+
+pub fn test_layout_offsets() {
+    let mut offsets = [0; 4];
+
+    unsafe {
+        layout_offsets(offsets.as_mut_ptr());
+    }
+
+    let p: plain = unsafe { zeroed() };
+    let n: nested = unsafe { zeroed() };
+    let p_base = &p as *const plain as usize;
+    let n_base = &n as *const nested as usize;
+
+    assert_eq!(offsets, [
+        &p.i as *const _ as usize - p_base,
+        &p.d as *const _ as usize - p_base,
+        &n.a as *const _ as usize - n_base,
+        &n.tail as *const _ as usize - n_base,
+    ]);
+}
+
+// Each of these asserts every field offset clang computed for the record
+pub fn test_generated_offset_checks() {
+    layout_offsets_plain();
+    layout_offsets_aligned();
+    layout_offsets_with_bitfields();
+    layout_offsets_nested();
+}